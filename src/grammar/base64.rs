@@ -0,0 +1,103 @@
+//! Base 64 encoding (RFC 4648, section 4), as used by structured field byte sequences and by
+//! `Basic` authentication.
+//!
+//! Only the standard alphabet is supported, not the URL- and filename-safe one.
+
+const ALPHABET: &'static [u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Whether a character is in the base 64 alphabet (excluding the `=` padding character).
+#[inline]
+pub fn is_base64_char(o: u8) -> bool {
+    (o >= b'A' && o <= b'Z') || (o >= b'a' && o <= b'z') || (o >= b'0' && o <= b'9') ||
+    o == b'+' || o == b'/'
+}
+
+fn value(o: u8) -> u32 {
+    match o {
+        b'A'...b'Z' => (o - b'A') as u32,
+        b'a'...b'z' => (o - b'a') as u32 + 26,
+        b'0'...b'9' => (o - b'0') as u32 + 52,
+        b'+' => 62,
+        b'/' => 63,
+        _ => unreachable!(),
+    }
+}
+
+/// Encode bytes as base 64, with padding.
+pub fn encode(raw: &[u8]) -> String {
+    let mut out = String::with_capacity((raw.len() + 2) / 3 * 4);
+    for chunk in raw.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &c)| n | (c as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode base 64.
+///
+/// Padding is optional, but if present must be correct. Returns `None` for anything else that
+/// isn’t base 64, including whitespace and a final character with bits set beyond the end of the
+/// data (so that each byte string has only one encoding, ignoring padding).
+pub fn decode(raw: &[u8]) -> Option<Vec<u8>> {
+    let data = match raw.iter().position(|&c| c == b'=') {
+        Some(padding) => {
+            if raw.len() % 4 != 0 || raw.len() - padding > 2 ||
+                    !raw[padding..].iter().all(|&c| c == b'=') {
+                return None;
+            }
+            &raw[..padding]
+        },
+        None => raw,
+    };
+    if data.len() % 4 == 1 || !data.iter().all(|&c| is_base64_char(c)) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    for chunk in data.chunks(4) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &c)| n | value(c) << (18 - 6 * i));
+        let bytes = chunk.len() - 1;
+        if n & (0xffffff >> (8 * bytes)) != 0 {
+            return None;
+        }
+        for i in 0..bytes {
+            out.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode, decode};
+
+    #[test]
+    fn test_rfc_4648_vectors() {
+        for &(raw, encoded) in &[(&b""[..], ""), (b"f", "Zg=="), (b"fo", "Zm8="),
+                                 (b"foo", "Zm9v"), (b"foob", "Zm9vYg=="), (b"fooba", "Zm9vYmE="),
+                                 (b"foobar", "Zm9vYmFy")] {
+            assert_eq!(encode(raw), encoded);
+            assert_eq!(decode(encoded.as_bytes()), Some(raw.to_vec()));
+        }
+        assert_eq!(encode(b"\xff\xfe\x00"), "//4A");
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(b"Zg"), Some(b"f".to_vec()));
+        assert_eq!(decode(b"Zm8"), Some(b"fo".to_vec()));
+        assert_eq!(decode(b"Zm9v Yg=="), None);
+        assert_eq!(decode(b"Zm9vYg="), None);
+        assert_eq!(decode(b"Zm9vY==="), None);
+        assert_eq!(decode(b"Zm9vY"), None);
+        assert_eq!(decode(b"Zg=a"), None);
+        assert_eq!(decode(b"Zh=="), None);
+        assert_eq!(decode(b"Zm9-"), None);
+    }
+}
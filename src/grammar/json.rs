@@ -0,0 +1,448 @@
+//! A minimal JSON (RFC 8259) reader and writer, for the headers whose values are JSON, such as
+//! `NEL` and `Report-To`.
+//!
+//! This is not intended to be a general-purpose JSON library: it parses into a simple tree and
+//! makes no effort to be fast.
+
+use std::char;
+use std::fmt;
+use std::str;
+
+/// A JSON value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// `null`.
+    Null,
+    /// `true` or `false`.
+    Bool(bool),
+    /// A number. As in JavaScript, all numbers are double-precision floating-point.
+    Number(f64),
+    /// A string.
+    String(String),
+    /// An array.
+    Array(Vec<Value>),
+    /// An object, with its members in the order in which they appeared.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// If the value is an object, get the value of the first member with the given name.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref members) => {
+                members.iter().find(|&&(ref n, _)| n == name).map(|&(_, ref value)| value)
+            },
+            _ => None,
+        }
+    }
+
+    /// If the value is a string, get it.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// If the value is a boolean, get it.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// If the value is a number, get it.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// If the value is a non-negative integral number that fits, get it as a `u64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Number(n) if n >= 0.0 && n.fract() == 0.0 && n < 18446744073709551616.0 => {
+                Some(n as u64)
+            },
+            _ => None,
+        }
+    }
+
+    /// If the value is an array, get its elements.
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match *self {
+            Value::Array(ref elements) => Some(elements),
+            _ => None,
+        }
+    }
+}
+
+/// The deepest nesting of arrays and objects that will be parsed.
+///
+/// The parser is recursive, so without a limit a header of nothing but `[` could overflow the
+/// stack. Real report configurations nest only a few levels deep.
+pub const MAX_DEPTH: usize = 64;
+
+/// Parse a single JSON value, with optional surrounding whitespace.
+pub fn parse(raw: &[u8]) -> Option<Value> {
+    match parse_prefix(raw) {
+        Some((value, rest)) if skip_whitespace(rest).is_empty() => Some(value),
+        _ => None,
+    }
+}
+
+/// Parse a JSON value off the start of a slice, returning it and the remainder of the slice.
+///
+/// Leading whitespace is skipped, but trailing whitespace is not. Arrays and objects nested more
+/// than `MAX_DEPTH` deep are rejected.
+pub fn parse_prefix(raw: &[u8]) -> Option<(Value, &[u8])> {
+    parse_value(raw, 0)
+}
+
+fn parse_value(raw: &[u8], depth: usize) -> Option<(Value, &[u8])> {
+    let raw = skip_whitespace(raw);
+    match raw.first() {
+        Some(&b'n') => literal(raw, b"null", Value::Null),
+        Some(&b't') => literal(raw, b"true", Value::Bool(true)),
+        Some(&b'f') => literal(raw, b"false", Value::Bool(false)),
+        Some(&b'"') => parse_string(raw).map(|(s, rest)| (Value::String(s), rest)),
+        Some(&b'[') | Some(&b'{') if depth == MAX_DEPTH => None,
+        Some(&b'[') => parse_array(&raw[1..], depth + 1),
+        Some(&b'{') => parse_object(&raw[1..], depth + 1),
+        Some(&c) if c == b'-' || (c >= b'0' && c <= b'9') => parse_number(raw),
+        _ => None,
+    }
+}
+
+fn skip_whitespace(raw: &[u8]) -> &[u8] {
+    let start = raw.iter().position(|&c| c != b' ' && c != b'\t' && c != b'\n' && c != b'\r')
+                          .unwrap_or(raw.len());
+    &raw[start..]
+}
+
+fn literal<'a>(raw: &'a [u8], name: &[u8], value: Value) -> Option<(Value, &'a [u8])> {
+    if raw.starts_with(name) {
+        Some((value, &raw[name.len()..]))
+    } else {
+        None
+    }
+}
+
+fn parse_number(raw: &[u8]) -> Option<(Value, &[u8])> {
+    // number = [ minus ] int [ frac ] [ exp ]
+    fn digits(raw: &[u8], i: usize) -> usize {
+        raw[i..].iter().position(|&c| c < b'0' || c > b'9').map_or(raw.len(), |n| i + n)
+    }
+    let mut i = 0;
+    if raw.get(i) == Some(&b'-') {
+        i += 1;
+    }
+    match raw.get(i) {
+        Some(&b'0') => i += 1,
+        Some(&c) if c >= b'1' && c <= b'9' => i = digits(raw, i),
+        _ => return None,
+    }
+    if raw.get(i) == Some(&b'.') {
+        let end = digits(raw, i + 1);
+        if end == i + 1 {
+            return None;
+        }
+        i = end;
+    }
+    if raw.get(i) == Some(&b'e') || raw.get(i) == Some(&b'E') {
+        i += 1;
+        if raw.get(i) == Some(&b'+') || raw.get(i) == Some(&b'-') {
+            i += 1;
+        }
+        let end = digits(raw, i);
+        if end == i {
+            return None;
+        }
+        i = end;
+    }
+    // All ASCII, so this can’t fail.
+    let s = unsafe { str::from_utf8_unchecked(&raw[..i]) };
+    s.parse().ok().map(|n| (Value::Number(n), &raw[i..]))
+}
+
+fn hex4(raw: &[u8]) -> Option<u32> {
+    if raw.len() < 4 {
+        return None;
+    }
+    let mut n = 0;
+    for &c in &raw[..4] {
+        n = n * 16 + match c {
+            b'0'...b'9' => c - b'0',
+            b'a'...b'f' => c - b'a' + 10,
+            b'A'...b'F' => c - b'A' + 10,
+            _ => return None,
+        } as u32;
+    }
+    Some(n)
+}
+
+fn parse_string(raw: &[u8]) -> Option<(String, &[u8])> {
+    debug_assert_eq!(raw[0], b'"');
+    let mut out = Vec::new();
+    let mut i = 1;
+    loop {
+        match raw.get(i) {
+            None => return None,
+            Some(&b'"') => break,
+            Some(&b'\\') => {
+                let c = match raw.get(i + 1) {
+                    Some(&b'"') => '"',
+                    Some(&b'\\') => '\\',
+                    Some(&b'/') => '/',
+                    Some(&b'b') => '\x08',
+                    Some(&b'f') => '\x0c',
+                    Some(&b'n') => '\n',
+                    Some(&b'r') => '\r',
+                    Some(&b't') => '\t',
+                    Some(&b'u') => {
+                        let high = match hex4(&raw[i + 2..]) {
+                            Some(high) => high,
+                            None => return None,
+                        };
+                        let c = if high >= 0xd800 && high < 0xdc00 {
+                            // A UTF-16 surrogate pair, which must be written as two escapes.
+                            if raw.get(i + 6) != Some(&b'\\') || raw.get(i + 7) != Some(&b'u') {
+                                return None;
+                            }
+                            let low = match hex4(&raw[i + 8..]) {
+                                Some(low) if low >= 0xdc00 && low < 0xe000 => low,
+                                _ => return None,
+                            };
+                            i += 6;
+                            char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+                        } else {
+                            char::from_u32(high)
+                        };
+                        i += 4;
+                        match c {
+                            Some(c) => c,
+                            None => return None,
+                        }
+                    },
+                    _ => return None,
+                };
+                let mut buf = String::new();
+                buf.push(c);
+                out.extend(buf.bytes());
+                i += 2;
+            },
+            Some(&c) if c < 0x20 => return None,
+            Some(&c) => {
+                out.push(c);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8(out).ok().map(|s| (s, &raw[i + 1..]))
+}
+
+fn parse_array(mut raw: &[u8], depth: usize) -> Option<(Value, &[u8])> {
+    let mut elements = vec![];
+    raw = skip_whitespace(raw);
+    if raw.first() == Some(&b']') {
+        return Some((Value::Array(elements), &raw[1..]));
+    }
+    loop {
+        let (element, rest) = match parse_value(raw, depth) {
+            Some(x) => x,
+            None => return None,
+        };
+        elements.push(element);
+        raw = skip_whitespace(rest);
+        match raw.first() {
+            Some(&b',') => raw = &raw[1..],
+            Some(&b']') => return Some((Value::Array(elements), &raw[1..])),
+            _ => return None,
+        }
+    }
+}
+
+fn parse_object(mut raw: &[u8], depth: usize) -> Option<(Value, &[u8])> {
+    let mut members = vec![];
+    raw = skip_whitespace(raw);
+    if raw.first() == Some(&b'}') {
+        return Some((Value::Object(members), &raw[1..]));
+    }
+    loop {
+        raw = skip_whitespace(raw);
+        if raw.first() != Some(&b'"') {
+            return None;
+        }
+        let (name, rest) = match parse_string(raw) {
+            Some(x) => x,
+            None => return None,
+        };
+        raw = skip_whitespace(rest);
+        if raw.first() != Some(&b':') {
+            return None;
+        }
+        let (value, rest) = match parse_value(&raw[1..], depth) {
+            Some(x) => x,
+            None => return None,
+        };
+        members.push((name, value));
+        raw = skip_whitespace(rest);
+        match raw.first() {
+            Some(&b',') => raw = &raw[1..],
+            Some(&b'}') => return Some((Value::Object(members), &raw[1..])),
+            _ => return None,
+        }
+    }
+}
+
+/// Write a string as a JSON string literal.
+///
+/// Everything outside printable ASCII is escaped, so that the output is suitable for use in a
+/// header field value.
+pub fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    try!(f.write_str("\""));
+    for c in s.chars() {
+        match c {
+            '"' => try!(f.write_str("\\\"")),
+            '\\' => try!(f.write_str("\\\\")),
+            ' '...'~' => try!(write!(f, "{}", c)),
+            _ => {
+                let mut buf = [0; 2];
+                let len = encode_utf16(c, &mut buf);
+                for unit in &buf[..len] {
+                    try!(write!(f, "\\u{:04x}", unit));
+                }
+            },
+        }
+    }
+    f.write_str("\"")
+}
+
+fn encode_utf16(c: char, buf: &mut [u16; 2]) -> usize {
+    let c = c as u32;
+    if c < 0x10000 {
+        buf[0] = c as u16;
+        1
+    } else {
+        let c = c - 0x10000;
+        buf[0] = 0xd800 | (c >> 10) as u16;
+        buf[1] = 0xdc00 | (c & 0x3ff) as u16;
+        2
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(ref s) => write_string(f, s),
+            Value::Array(ref elements) => {
+                try!(f.write_str("["));
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        try!(f.write_str(", "));
+                    }
+                    try!(write!(f, "{}", element));
+                }
+                f.write_str("]")
+            },
+            Value::Object(ref members) => {
+                try!(f.write_str("{"));
+                for (i, &(ref name, ref value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        try!(f.write_str(", "));
+                    }
+                    try!(write_string(f, name));
+                    try!(write!(f, ": {}", value));
+                }
+                f.write_str("}")
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use super::{parse, parse_prefix, Value, MAX_DEPTH};
+
+    fn s(s: &str) -> Value {
+        Value::String(s.to_owned())
+    }
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(parse(b"null"), Some(Value::Null));
+        assert_eq!(parse(b" true "), Some(Value::Bool(true)));
+        assert_eq!(parse(b"false"), Some(Value::Bool(false)));
+        assert_eq!(parse(b"0"), Some(Value::Number(0.0)));
+        assert_eq!(parse(b"-12.5e1"), Some(Value::Number(-125.0)));
+        assert_eq!(parse(b"2592000"), Some(Value::Number(2592000.0)));
+        assert_eq!(parse(b"\"a\\\"b\\u00e9\\ud83d\\ude00\""), Some(s("a\"bé\u{1f600}")));
+        assert_eq!(parse(b"01"), None);
+        assert_eq!(parse(b"1."), None);
+        assert_eq!(parse(b"nul"), None);
+        assert_eq!(parse(b"\"a"), None);
+        assert_eq!(parse(b"\"a\nb\""), None);
+        assert_eq!(parse(b"\"\\ud83d\""), None);
+    }
+
+    #[test]
+    fn test_compounds() {
+        assert_eq!(parse(b"[]"), Some(Value::Array(vec![])));
+        assert_eq!(parse(b"{}"), Some(Value::Object(vec![])));
+        let value = parse(b"{\"a\": [1, \"x\", {\"b\": null}], \"c\" : true}").unwrap();
+        assert_eq!(value, Value::Object(vec![
+            ("a".to_owned(), Value::Array(vec![
+                Value::Number(1.0),
+                s("x"),
+                Value::Object(vec![("b".to_owned(), Value::Null)]),
+            ])),
+            ("c".to_owned(), Value::Bool(true)),
+        ]));
+        assert_eq!(value.get("c").and_then(Value::as_bool), Some(true));
+        assert_eq!(value.get("d"), None);
+        assert_eq!(parse(b"[1,]"), None);
+        assert_eq!(parse(b"{\"a\" 1}"), None);
+        assert_eq!(parse(b"{\"a\": 1} x"), None);
+        assert_eq!(parse_prefix(b"{\"a\": 1}, {}"),
+                   Some((Value::Object(vec![("a".to_owned(), Value::Number(1.0))]), &b", {}"[..])));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        fn nested(depth: usize) -> Vec<u8> {
+            let mut raw: Vec<u8> = iter::repeat(b'[').take(depth).collect();
+            raw.extend(iter::repeat(b']').take(depth));
+            raw
+        }
+        assert!(parse(&nested(MAX_DEPTH)).is_some());
+        assert_eq!(parse(&nested(MAX_DEPTH + 1)), None);
+        // Objects count too.
+        let mut raw = b"{\"a\": ".to_vec();
+        raw.extend(nested(MAX_DEPTH));
+        raw.push(b'}');
+        assert_eq!(parse(&raw), None);
+        // Deep enough to overflow the stack, were there no limit.
+        assert_eq!(parse(&nested(1_000_000)), None);
+    }
+
+    #[test]
+    fn test_as_u64() {
+        assert_eq!(Value::Number(86400.0).as_u64(), Some(86400));
+        assert_eq!(Value::Number(-1.0).as_u64(), None);
+        assert_eq!(Value::Number(0.5).as_u64(), None);
+        assert_eq!(s("1").as_u64(), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let value = parse(b"{\"a\":[1,2.5,\"\\u00e9\\\"\"],\"b\":null}").unwrap();
+        assert_eq!(value.to_string(), "{\"a\": [1, 2.5, \"\\u00e9\\\"\"], \"b\": null}");
+        assert_eq!(parse(value.to_string().as_bytes()), Some(value));
+    }
+}
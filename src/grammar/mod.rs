@@ -3,3 +3,83 @@
 //! These are mostly implementation details that you shouldn’t need to worry about.
 
 pub mod token;
+pub mod quoted_string;
+pub mod parameters;
+pub mod json;
+pub mod base64;
+
+/// Whether a character is optional whitespace (`OWS`, being `*( SP / HTAB )`).
+#[inline]
+pub fn is_ows(o: u8) -> bool {
+    o == b' ' || o == b'\t'
+}
+
+/// Strip leading and trailing optional whitespace from a slice.
+pub fn trim_ows(raw: &[u8]) -> &[u8] {
+    match raw.iter().position(|&c| !is_ows(c)) {
+        Some(start) => {
+            let end = raw.iter().rposition(|&c| !is_ows(c)).unwrap();
+            &raw[start..end + 1]
+        },
+        None => &raw[..0],
+    }
+}
+
+/// Strip leading optional whitespace from a slice.
+pub fn trim_ows_start(raw: &[u8]) -> &[u8] {
+    match raw.iter().position(|&c| !is_ows(c)) {
+        Some(start) => &raw[start..],
+        None => &raw[..0],
+    }
+}
+
+/// Whether a character is a `field-vchar` (`VCHAR / obs-text`).
+#[inline]
+pub fn is_field_vchar(o: u8) -> bool {
+    o >= 0x21 && o != 0x7f
+}
+
+/// Whether a slice is a valid field value, being `*field-content`:
+///
+/// ```abnf
+/// field-content = field-vchar [ 1*( SP / HTAB ) field-vchar ]
+/// ```
+///
+/// That is, visible characters and obs-text with interior whitespace only. Control characters,
+/// and in particular CR and LF, are not permitted.
+pub fn is_field_content(raw: &[u8]) -> bool {
+    raw.iter().all(|&c| is_field_vchar(c) || is_ows(c)) &&
+        raw.first().map_or(true, |&c| !is_ows(c)) &&
+        raw.last().map_or(true, |&c| !is_ows(c))
+}
+
+/// Decode bytes as ISO-8859-1, in which every byte is the character of the same code point.
+///
+/// This is how obs-text in header values from legacy software is usually meant to be read.
+pub fn decode_latin1(raw: &[u8]) -> String {
+    raw.iter().map(|&c| c as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_field_content, decode_latin1};
+
+    #[test]
+    fn test_field_content() {
+        assert!(is_field_content(b""));
+        assert!(is_field_content(b"Apache/2.4.1 (Unix)"));
+        assert!(is_field_content(b"a\t\xe9"));
+        assert!(!is_field_content(b" a"));
+        assert!(!is_field_content(b"a "));
+        assert!(!is_field_content(b"a\r\nb"));
+        assert!(!is_field_content(b"a\nb"));
+        assert!(!is_field_content(b"a\x00b"));
+        assert!(!is_field_content(b"a\x7fb"));
+    }
+
+    #[test]
+    fn test_decode_latin1() {
+        assert_eq!(decode_latin1(b"caf\xe9"), "caf\u{e9}");
+        assert_eq!(decode_latin1(b"\xc3\xa9\xff"), "\u{c3}\u{a9}\u{ff}");
+    }
+}
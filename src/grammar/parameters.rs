@@ -0,0 +1,100 @@
+//! Things pertaining to the `parameter` grammar rule, as used by media types and others.
+//!
+//! RFC 7231 grammar:
+//!
+//! ```abnf
+//! parameters     = *( OWS ";" OWS parameter )
+//! parameter      = token "=" ( token / quoted-string )
+//! ```
+
+use std::str;
+
+use super::{is_ows, trim_ows_start};
+use super::token::is_tchar;
+use super::quoted_string;
+
+/// Split a token off the start of a slice, returning the token and the remainder.
+///
+/// The token may be empty.
+#[inline]
+pub fn split_token(raw: &[u8]) -> (&[u8], &[u8]) {
+    let end = raw.iter().position(|&c| !is_tchar(c)).unwrap_or(raw.len());
+    (&raw[..end], &raw[end..])
+}
+
+/// Parse a sequence of `;`-prefixed parameters.
+///
+/// Parameter names and values are stored exactly as they appeared; in particular, quoted-string
+/// values retain their quotes. (Use `grammar::quoted_string::unquote` if that is not desired.)
+///
+/// An empty parameter (e.g. from a trailing `;`) is tolerated and skipped.
+///
+/// Returns `None` if the parameters are malformed, or if any value is not valid UTF-8 (which can
+/// only happen with obs-text inside a quoted-string).
+pub fn parse(mut raw: &[u8]) -> Option<Vec<(String, String)>> {
+    let mut parameters = vec![];
+    loop {
+        raw = trim_ows_start(raw);
+        if raw.is_empty() {
+            return Some(parameters);
+        }
+        if raw[0] != b';' {
+            return None;
+        }
+        raw = trim_ows_start(&raw[1..]);
+        if raw.is_empty() || raw[0] == b';' {
+            continue;
+        }
+        let (name, rest) = split_token(raw);
+        if name.is_empty() || rest.first() != Some(&b'=') {
+            return None;
+        }
+        let rest = &rest[1..];
+        let (value, rest) = if rest.first() == Some(&b'"') {
+            match quoted_string::split(rest) {
+                Some(split) => split,
+                None => return None,
+            }
+        } else {
+            let (value, rest) = split_token(rest);
+            if value.is_empty() {
+                return None;
+            }
+            (value, rest)
+        };
+        if !rest.is_empty() && !is_ows(rest[0]) && rest[0] != b';' {
+            return None;
+        }
+        let value = match str::from_utf8(value) {
+            Ok(value) => value.to_owned(),
+            Err(_) => return None,
+        };
+        // A token is ASCII, so that can’t fail.
+        let name = unsafe { str::from_utf8_unchecked(name) }.to_owned();
+        parameters.push((name, value));
+        raw = rest;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    fn p(name: &str, value: &str) -> (String, String) {
+        (name.to_owned(), value.to_owned())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(b""), Some(vec![]));
+        assert_eq!(parse(b";a=1"), Some(vec![p("a", "1")]));
+        assert_eq!(parse(b" ; a=1 ;B=\"x; y\""), Some(vec![p("a", "1"), p("B", "\"x; y\"")]));
+        assert_eq!(parse(b"; a=1;"), Some(vec![p("a", "1")]));
+        assert_eq!(parse(b"a=1"), None);
+        assert_eq!(parse(b"; a"), None);
+        assert_eq!(parse(b"; a="), None);
+        assert_eq!(parse(b"; a=\"1"), None);
+        assert_eq!(parse(b"; a=1 2"), None);
+        assert_eq!(parse(b"; a=1\"2\""), None);
+    }
+}
@@ -0,0 +1,118 @@
+//! Things pertaining to the RFC 7230 `quoted-string` grammar rule.
+//!
+//! RFC 7230 grammar:
+//!
+//! ```abnf
+//! quoted-string  = DQUOTE *( qdtext / quoted-pair ) DQUOTE
+//! qdtext         = HTAB / SP / %x21 / %x23-5B / %x5D-7E / obs-text
+//! obs-text       = %x80-FF
+//! quoted-pair    = "\" ( HTAB / SP / VCHAR / obs-text )
+//! ```
+
+/// qdtext: a character which may appear unescaped inside a quoted-string.
+#[inline]
+pub fn is_qdtext(o: u8) -> bool {
+    o == b'\t' || o == b' ' || o == b'!' || (o >= 0x23 && o <= 0x5b) || (o >= 0x5d && o <= 0x7e) ||
+    o >= 0x80
+}
+
+/// Whether a character may be the second character of a quoted-pair.
+#[inline]
+pub fn is_quoted_pair_char(o: u8) -> bool {
+    o == b'\t' || o == b' ' || (o >= 0x21 && o <= 0x7e) || o >= 0x80
+}
+
+/// Split a quoted-string off the start of a slice.
+///
+/// Returns the quoted-string (including its quotes) and the remainder of the slice, or `None` if
+/// the slice does not start with a complete and valid quoted-string.
+pub fn split(raw: &[u8]) -> Option<(&[u8], &[u8])> {
+    if raw.first() != Some(&b'"') {
+        return None;
+    }
+    let mut i = 1;
+    while i < raw.len() {
+        match raw[i] {
+            b'"' => return Some((&raw[..i + 1], &raw[i + 1..])),
+            b'\\' if i + 1 < raw.len() && is_quoted_pair_char(raw[i + 1]) => i += 2,
+            c if is_qdtext(c) => i += 1,
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Unquote a quoted-string, resolving any quoted-pairs.
+///
+/// The whole slice must be a single quoted-string; `None` is returned otherwise.
+pub fn unquote(raw: &[u8]) -> Option<Vec<u8>> {
+    match split(raw) {
+        Some((quoted, rest)) if rest.is_empty() => {
+            let mut out = Vec::with_capacity(quoted.len() - 2);
+            let mut iter = quoted[1..quoted.len() - 1].iter();
+            while let Some(&c) = iter.next() {
+                if c == b'\\' {
+                    // split has already verified that quoted-pairs are complete.
+                    out.push(*iter.next().unwrap());
+                } else {
+                    out.push(c);
+                }
+            }
+            Some(out)
+        },
+        _ => None,
+    }
+}
+
+/// Produce a quoted-string containing the given value, escaping `"` and `\` as quoted-pairs.
+///
+/// Returns `None` if the value contains characters which cannot be represented in a
+/// quoted-string at all (control characters other than HTAB, and DEL).
+pub fn quote(value: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(value.len() + 2);
+    out.push(b'"');
+    for &c in value {
+        if c == b'"' || c == b'\\' {
+            out.push(b'\\');
+            out.push(c);
+        } else if is_qdtext(c) {
+            out.push(c);
+        } else {
+            return None;
+        }
+    }
+    out.push(b'"');
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split, unquote, quote};
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split(b"\"foo\"; bar"), Some((&b"\"foo\""[..], &b"; bar"[..])));
+        assert_eq!(split(b"\"f\\\"o\"o"), Some((&b"\"f\\\"o\""[..], &b"o"[..])));
+        assert_eq!(split(b"foo"), None);
+        assert_eq!(split(b"\"foo"), None);
+        assert_eq!(split(b"\"foo\\\""), None);
+        assert_eq!(split(b"\"fo\no\""), None);
+    }
+
+    #[test]
+    fn test_unquote() {
+        assert_eq!(unquote(b"\"foo bar\""), Some(b"foo bar".to_vec()));
+        assert_eq!(unquote(b"\"\""), Some(vec![]));
+        assert_eq!(unquote(b"\"a\\\"b\\\\c\""), Some(b"a\"b\\c".to_vec()));
+        assert_eq!(unquote(b"\"foo\" "), None);
+        assert_eq!(unquote(b"foo"), None);
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote(b"foo bar"), Some(b"\"foo bar\"".to_vec()));
+        assert_eq!(quote(b"a\"b\\c"), Some(b"\"a\\\"b\\\\c\"".to_vec()));
+        assert_eq!(quote(b"a\nb"), None);
+        assert_eq!(unquote(&quote(b"a\"b\\c").unwrap()), Some(b"a\"b\\c".to_vec()));
+    }
+}
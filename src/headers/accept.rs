@@ -0,0 +1,381 @@
+//! The `Accept` header (RFC 7231, section 5.3.2), quality values (RFC 7231, section 5.3.1), and
+//! choosing the best representation to respond with.
+
+use std::ascii::AsciiExt;
+use std::fmt;
+
+use super::{ToHeader, Header, Headers};
+use super::content_type::{MediaType, unquoted};
+
+/// A quality value (“qvalue”), the relative weight of a preference, in thousandths.
+///
+/// RFC 7231 grammar:
+///
+/// ```abnf
+/// weight = OWS ";" OWS "q=" qvalue
+/// qvalue = ( "0" [ "." 0*3DIGIT ] )
+///        / ( "1" [ "." 0*3("0") ] )
+/// ```
+///
+/// 0 means “not acceptable”; the default, where no weight is given, is 1.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Quality(u16);
+
+impl Quality {
+    /// Construct a quality value from thousandths. Returns `None` if it is greater than 1000.
+    pub fn from_thousandths(thousandths: u16) -> Option<Quality> {
+        if thousandths <= 1000 {
+            Some(Quality(thousandths))
+        } else {
+            None
+        }
+    }
+
+    /// The quality value, in thousandths.
+    pub fn thousandths(&self) -> u16 {
+        self.0
+    }
+}
+
+/// The default quality value, 1.
+impl Default for Quality {
+    fn default() -> Quality {
+        Quality(1000)
+    }
+}
+
+impl ToHeader for Quality {
+    fn parse(raw: &[u8]) -> Option<Quality> {
+        let (integer, fraction) = match raw.iter().position(|&c| c == b'.') {
+            Some(dot) => (&raw[..dot], &raw[dot + 1..]),
+            None => (raw, &b""[..]),
+        };
+        if fraction.len() > 3 || !fraction.iter().all(|&c| c >= b'0' && c <= b'9') {
+            return None;
+        }
+        let thousandths = fraction.iter().chain(b"000".iter()).take(3)
+                                  .fold(0, |n, &c| n * 10 + (c - b'0') as u16);
+        match integer {
+            b"0" => Some(Quality(thousandths)),
+            b"1" if thousandths == 0 => Some(Quality(1000)),
+            _ => None,
+        }
+    }
+}
+
+/// Writes as few digits as possible: `1`, `0`, `0.5`, `0.25`, `0.125`.
+impl Header for Quality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            1000 => f.write_str("1"),
+            0 => f.write_str("0"),
+            n if n % 100 == 0 => write!(f, "0.{}", n / 100),
+            n if n % 10 == 0 => write!(f, "0.{:02}", n / 10),
+            n => write!(f, "0.{:03}", n),
+        }
+    }
+}
+
+/// A media range with its weight, one item of an `Accept` header.
+///
+/// RFC 7231 grammar:
+///
+/// ```abnf
+/// Accept        = #( media-range [ accept-params ] )
+/// media-range   = ( "*/*"
+///                 / ( type "/" "*" )
+///                 / ( type "/" subtype )
+///                 ) *( OWS ";" OWS parameter )
+/// accept-params = weight *( accept-ext )
+/// accept-ext    = OWS ";" OWS token [ "=" ( token / quoted-string ) ]
+/// ```
+///
+/// The `q` parameter separates the media range’s own parameters from the extensions.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MediaRange {
+    /// The media range, which may have `*` as its subtype, or as both type and subtype.
+    pub range: MediaType,
+    /// The weight.
+    pub quality: Quality,
+    /// Any parameters after the weight, as they were received. (Valueless extensions, which RFC
+    /// 7231 permits, are not supported.)
+    pub extensions: Vec<(String, String)>,
+}
+
+impl MediaRange {
+    /// Construct a media range with the default weight and no parameters.
+    pub fn new(type_: &str, subtype: &str) -> MediaRange {
+        MediaRange {
+            range: MediaType::new(type_, subtype),
+            quality: Quality::default(),
+            extensions: vec![],
+        }
+    }
+
+    /// Whether the media range includes the media type given.
+    ///
+    /// Besides matching the type and subtype, every parameter of the range must be present in the
+    /// media type with the same value; parameter names are case-insensitive, and quoted values
+    /// are compared unquoted.
+    pub fn matches(&self, media_type: &MediaType) -> bool {
+        (self.range.type_ == "*" || self.range.type_ == media_type.type_) &&
+        (self.range.subtype == "*" || self.range.subtype == media_type.subtype) &&
+        self.range.parameters.iter().all(|&(ref name, ref value)| {
+            media_type.parameter(name).map_or(false, |other| {
+                unquoted(other) == unquoted(value)
+            })
+        })
+    }
+
+    /// How specific the range is, for RFC 7231’s rule that the most specific matching range
+    /// applies: a type beats `*`, a subtype beats `*`, and more parameters beat fewer.
+    fn specificity(&self) -> (bool, bool, usize) {
+        (self.range.type_ != "*", self.range.subtype != "*", self.range.parameters.len())
+    }
+}
+
+impl ToHeader for MediaRange {
+    fn parse(raw: &[u8]) -> Option<MediaRange> {
+        let mut range = match MediaType::parse(raw) {
+            Some(range) => range,
+            None => return None,
+        };
+        if range.type_ == "*" && range.subtype != "*" {
+            return None;
+        }
+        let q = range.parameters.iter().position(|&(ref name, _)| name.eq_ignore_ascii_case("q"));
+        let (quality, extensions) = match q {
+            Some(q) => {
+                let mut extensions = range.parameters.split_off(q);
+                let quality = match Quality::parse(extensions[0].1.as_bytes()) {
+                    Some(quality) => quality,
+                    None => return None,
+                };
+                let _ = extensions.remove(0);
+                (quality, extensions)
+            },
+            None => (Quality::default(), vec![]),
+        };
+        Some(MediaRange { range: range, quality: quality, extensions: extensions })
+    }
+}
+
+impl Header for MediaRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(self.range.fmt(f));
+        if self.quality != Quality::default() || !self.extensions.is_empty() {
+            try!(f.write_str("; q="));
+            try!(self.quality.fmt(f));
+        }
+        for &(ref name, ref value) in &self.extensions {
+            try!(write!(f, "; {}={}", name, value));
+        }
+        Ok(())
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_list_header_marker! {
+    /// The `Accept` header: the media types which the user agent will accept in the response.
+    pub ACCEPT: MediaRange = "accept"
+}
+
+/// The media ranges of an `Accept` header, for content negotiation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Accept {
+    ranges: Vec<MediaRange>,
+}
+
+impl Accept {
+    /// Construct a set of media ranges.
+    pub fn new<I: IntoIterator<Item = MediaRange>>(ranges: I) -> Accept {
+        Accept { ranges: ranges.into_iter().collect() }
+    }
+
+    /// Read the media ranges from a message’s `Accept` header.
+    ///
+    /// If there is no `Accept` header, there will be no ranges, which means that anything goes.
+    pub fn from_headers(headers: &Headers) -> Accept {
+        Accept::new(headers.get(ACCEPT).into_owned())
+    }
+
+    /// The media ranges, in the order they were given.
+    pub fn ranges(&self) -> &[MediaRange] {
+        &self.ranges
+    }
+
+    /// The most specific range which matches the media type given, if any.
+    fn most_specific_match(&self, media_type: &MediaType) -> Option<&MediaRange> {
+        let mut best: Option<&MediaRange> = None;
+        for range in self.ranges.iter().filter(|range| range.matches(media_type)) {
+            match best {
+                Some(best) if best.specificity() >= range.specificity() => (),
+                _ => best = Some(range),
+            }
+        }
+        best
+    }
+
+    /// The quality of the media type given: that of the most specific matching range, 0 if none
+    /// matches, or 1 if there are no ranges at all.
+    pub fn quality(&self, media_type: &MediaType) -> Quality {
+        if self.ranges.is_empty() {
+            return Quality::default();
+        }
+        self.most_specific_match(media_type).map_or(Quality(0), |range| range.quality)
+    }
+
+    /// Whether the media type given is acceptable at all: a matching range exists and its quality
+    /// is not 0. If there are no ranges at all, as when there is no `Accept` header, everything
+    /// is acceptable.
+    ///
+    /// If none of the representations a resource has are acceptable, the response may be
+    /// `406 Not Acceptable` (RFC 7231, section 6.5.6).
+    pub fn is_acceptable(&self, media: &MediaType) -> bool {
+        self.quality(media) != Quality(0)
+    }
+
+    /// Choose the best of the media types offered, or `None` if none are acceptable.
+    ///
+    /// The highest quality wins. Of media types with equal quality, the one matched by the more
+    /// specific range wins, so that when `text/html;level=1` and `text/html` are both acceptable,
+    /// the offer of `text/html;level=1` is preferred if `Accept` names it; failing that, the
+    /// first offered wins, so offer media types in order of your own preference.
+    pub fn best_match<'a>(&self, offered: &'a [MediaType]) -> Option<&'a MediaType> {
+        let mut best: Option<(&MediaType, (Quality, Option<(bool, bool, usize)>))> = None;
+        for media_type in offered {
+            let rank = (self.quality(media_type),
+                        self.most_specific_match(media_type).map(|range| range.specificity()));
+            if rank.0 == Quality(0) {
+                continue;
+            }
+            match best {
+                Some((_, ref best_rank)) if *best_rank >= rank => (),
+                _ => best = Some((media_type, rank)),
+            }
+        }
+        best.map(|(media_type, _)| media_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use headers::content_type::MediaType;
+    use super::{Accept, MediaRange, Quality};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    fn mt(raw: &str) -> MediaType {
+        MediaType::parse(raw.as_bytes()).unwrap()
+    }
+
+    fn from_raw(raw: &[u8]) -> Accept {
+        Accept::from_headers(&Headers::parse_block(&[&b"Accept: "[..], raw, b"\r\n"].concat())
+                                  .unwrap())
+    }
+
+    #[test]
+    fn test_quality() {
+        eq(b"1", Quality::default());
+        eq(b"0", Quality::from_thousandths(0).unwrap());
+        eq(b"0.5", Quality::from_thousandths(500).unwrap());
+        eq(b"0.25", Quality::from_thousandths(250).unwrap());
+        eq(b"0.125", Quality::from_thousandths(125).unwrap());
+        eq(b"0.001", Quality::from_thousandths(1).unwrap());
+        assert_eq!(Quality::parse(b"1.000"), Quality::from_thousandths(1000));
+        assert_eq!(Quality::parse(b"0.50"), Quality::from_thousandths(500));
+        assert_eq!(Quality::parse(b"0."), Quality::from_thousandths(0));
+        assert_eq!(Quality::from_thousandths(1001), None);
+        bad::<Quality>(b"1.001");
+        bad::<Quality>(b"0.1234");
+        bad::<Quality>(b"2");
+        bad::<Quality>(b".5");
+        bad::<Quality>(b"");
+    }
+
+    #[test]
+    fn test_media_range() {
+        eq(b"text/html", MediaRange::new("text", "html"));
+        eq(b"*/*; q=0.1", MediaRange {
+            quality: Quality::from_thousandths(100).unwrap(),
+            .. MediaRange::new("*", "*")
+        });
+        eq(b"text/html; level=1; q=0.5; foo=bar", MediaRange {
+            range: mt("text/html;level=1"),
+            quality: Quality::from_thousandths(500).unwrap(),
+            extensions: vec![("foo".to_owned(), "bar".to_owned())],
+        });
+        bad::<MediaRange>(b"*/html");
+        bad::<MediaRange>(b"text/html; q=2");
+        bad::<MediaRange>(b"text/html; q=\"1\"");
+    }
+
+    #[test]
+    fn test_matches() {
+        let html = mt("text/html");
+        let html_level = mt("text/html; level=1");
+        assert!(MediaRange::new("*", "*").matches(&html));
+        assert!(MediaRange::new("text", "*").matches(&html));
+        assert!(!MediaRange::new("image", "*").matches(&html));
+        assert!(MediaRange::new("text", "html").matches(&html_level));
+        let range = MediaRange::parse(b"text/html;LEVEL=\"1\"").unwrap();
+        assert!(range.matches(&html_level));
+        assert!(!range.matches(&html));
+        assert!(!range.matches(&mt("text/html; level=2")));
+    }
+
+    #[test]
+    fn test_rfc_7231_example() {
+        let accept = from_raw(b"text/*;q=0.3, text/html;q=0.7, text/html;level=1, \
+                              text/html;level=2;q=0.4, */*;q=0.5");
+        let q = |raw: &str| accept.quality(&mt(raw)).thousandths();
+        assert_eq!(q("text/html;level=1"), 1000);
+        assert_eq!(q("text/html"), 700);
+        assert_eq!(q("text/plain"), 300);
+        assert_eq!(q("image/jpeg"), 500);
+        assert_eq!(q("text/html;level=2"), 400);
+        assert_eq!(q("text/html;level=3"), 700);
+    }
+
+    #[test]
+    fn test_best_match() {
+        let offered = [mt("text/html"), mt("text/html;level=1")];
+        let accept = from_raw(b"text/html, text/html;level=1");
+        assert_eq!(accept.best_match(&offered), Some(&offered[1]));
+        let accept = from_raw(b"text/html;level=1;q=0.5, text/html");
+        assert_eq!(accept.best_match(&offered), Some(&offered[0]));
+
+        let offered = [mt("application/json"), mt("text/html")];
+        assert_eq!(from_raw(b"text/html, */*;q=0.1").best_match(&offered), Some(&offered[1]));
+        assert_eq!(from_raw(b"*/*").best_match(&offered), Some(&offered[0]));
+        assert_eq!(from_raw(b"image/*").best_match(&offered), None);
+        assert_eq!(from_raw(b"text/html;q=0, */*").best_match(&offered), Some(&offered[0]));
+        assert_eq!(Accept::from_headers(&Headers::new()).best_match(&offered), Some(&offered[0]));
+        assert_eq!(from_raw(b"*/*").best_match(&[]), None);
+    }
+
+    #[test]
+    fn test_is_acceptable() {
+        assert!(from_raw(b"text/html, application/json;q=0.5").is_acceptable(&mt("text/html")));
+        assert!(from_raw(b"text/*").is_acceptable(&mt("text/plain; charset=utf-8")));
+        assert!(!from_raw(b"*/*;q=0").is_acceptable(&mt("text/html")));
+        assert!(!from_raw(b"image/*").is_acceptable(&mt("text/html")));
+        // A more specific range can exclude what a broader one would accept, and vice versa.
+        assert!(!from_raw(b"text/html;q=0, */*").is_acceptable(&mt("text/html")));
+        assert!(from_raw(b"text/html, */*;q=0").is_acceptable(&mt("text/html")));
+        // No Accept header means anything goes.
+        assert!(Accept::from_headers(&Headers::new()).is_acceptable(&mt("image/png")));
+    }
+}
@@ -0,0 +1,266 @@
+//! The `Accept-Encoding` header (RFC 7231, section 5.3.4).
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+
+use grammar::parameters::{self, split_token};
+use super::{ToHeader, Header, Headers};
+use super::accept::Quality;
+use super::content_encoding::ContentCoding;
+
+/// What an item of an `Accept-Encoding` header names: a content coding, or `*`.
+///
+/// RFC 7231 grammar:
+///
+/// ```abnf
+/// codings = content-coding / "identity" / "*"
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CodingMatch {
+    /// `*`: any content coding not otherwise named in the header, `identity` included.
+    Any,
+    /// A specific content coding, which may be `identity`.
+    Coding(ContentCoding),
+}
+
+impl CodingMatch {
+    /// The coding with the given name, matched case-insensitively, or `Any` for `*`.
+    pub fn from_name(name: &str) -> CodingMatch {
+        if name == "*" {
+            CodingMatch::Any
+        } else {
+            CodingMatch::Coding(ContentCoding::from_name(name))
+        }
+    }
+
+    /// The canonical name.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            CodingMatch::Any => "*",
+            CodingMatch::Coding(ref coding) => coding.as_str(),
+        }
+    }
+}
+
+/// A content coding with its weight, one item of an `Accept-Encoding` header.
+///
+/// RFC 7231 grammar:
+///
+/// ```abnf
+/// Accept-Encoding  = #( codings [ weight ] )
+/// ```
+///
+/// Content codings are case-insensitive; see `ContentCoding`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AcceptCoding {
+    /// The content coding, such as `gzip` or `identity`, or `*`.
+    pub coding: CodingMatch,
+    /// The weight.
+    pub quality: Quality,
+}
+
+impl AcceptCoding {
+    /// Construct a content coding, by name (`*` for any), with the default weight, 1.
+    pub fn new(coding: &str) -> AcceptCoding {
+        AcceptCoding { coding: CodingMatch::from_name(coding), quality: Quality::default() }
+    }
+}
+
+impl ToHeader for AcceptCoding {
+    fn parse(raw: &[u8]) -> Option<AcceptCoding> {
+        let (coding, rest) = split_token(raw);
+        if coding.is_empty() {
+            return None;
+        }
+        let quality = match parameters::parse(rest) {
+            Some(ref parameters) if parameters.is_empty() => Quality::default(),
+            Some(ref parameters) if parameters.len() == 1 &&
+                                    parameters[0].0.eq_ignore_ascii_case("q") => {
+                match Quality::parse(parameters[0].1.as_bytes()) {
+                    Some(quality) => quality,
+                    None => return None,
+                }
+            },
+            _ => return None,
+        };
+        // Tokens are ASCII, so this can’t fail.
+        Some(AcceptCoding {
+            coding: CodingMatch::from_name(unsafe { str::from_utf8_unchecked(coding) }),
+            quality: quality,
+        })
+    }
+}
+
+impl Header for AcceptCoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str(self.coding.as_str()));
+        if self.quality != Quality::default() {
+            try!(f.write_str(";q="));
+            try!(self.quality.fmt(f));
+        }
+        Ok(())
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_list_header_marker! {
+    /// The `Accept-Encoding` header: the content codings which the user agent will accept in
+    /// the response.
+    pub ACCEPT_ENCODING: AcceptCoding = "accept-encoding"
+}
+
+/// The content codings of an `Accept-Encoding` header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AcceptEncoding {
+    codings: Vec<AcceptCoding>,
+}
+
+impl AcceptEncoding {
+    /// Construct a set of content codings.
+    pub fn new<I: IntoIterator<Item = AcceptCoding>>(codings: I) -> AcceptEncoding {
+        AcceptEncoding { codings: codings.into_iter().collect() }
+    }
+
+    /// Read the content codings from a message’s `Accept-Encoding` header.
+    pub fn from_headers(headers: &Headers) -> AcceptEncoding {
+        AcceptEncoding::new(headers.get(ACCEPT_ENCODING).into_owned())
+    }
+
+    /// The content codings, in the order they were given.
+    pub fn codings(&self) -> &[AcceptCoding] {
+        &self.codings
+    }
+
+    /// The content codings, for setting as the `Accept-Encoding` header.
+    pub fn into_codings(self) -> Vec<AcceptCoding> {
+        self.codings
+    }
+
+    /// Add `identity` with the lowest non-zero weight, 0.001, unless it is already named or `*`
+    /// is present (which covers it).
+    ///
+    /// RFC 7231 has `identity` acceptable unless it is excluded, but some servers take an
+    /// `Accept-Encoding` which doesn’t mention it to mean that it isn’t, and fail the request if
+    /// they can’t compress the response. Naming it, as common clients do, avoids that, without
+    /// changing which coding a well-behaved server picks. An `identity;q=0` or `*;q=0` is left
+    /// alone, for that is deliberate.
+    pub fn ensure_identity(&mut self) {
+        let covered = self.codings.iter().any(|accept| match accept.coding {
+            CodingMatch::Any | CodingMatch::Coding(ContentCoding::Identity) => true,
+            CodingMatch::Coding(_) => false,
+        });
+        if !covered {
+            self.codings.push(AcceptCoding {
+                coding: CodingMatch::Coding(ContentCoding::Identity),
+                quality: Quality::from_thousandths(1).unwrap(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use headers::accept::Quality;
+    use headers::content_encoding::ContentCoding;
+    use super::{AcceptCoding, AcceptEncoding, CodingMatch, ACCEPT_ENCODING};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    fn weighted(coding: &str, thousandths: u16) -> AcceptCoding {
+        AcceptCoding { quality: Quality::from_thousandths(thousandths).unwrap(),
+                       ..AcceptCoding::new(coding) }
+    }
+
+    #[test]
+    fn test_accept_coding() {
+        eq(b"gzip", AcceptCoding::new("gzip"));
+        eq(b"*", AcceptCoding::new("*"));
+        eq(b"identity;q=0.5", weighted("identity", 500));
+        eq(b"br;q=0", weighted("br", 0));
+        assert_eq!(AcceptCoding::parse(b"GZIP ; Q=1.0"), Some(AcceptCoding::new("gzip")));
+        assert_eq!(AcceptCoding::parse(b"br;q=0.9").unwrap().coding,
+                   CodingMatch::Coding(ContentCoding::Br));
+        assert_eq!(AcceptCoding::parse(b"ZSTD").unwrap().coding,
+                   CodingMatch::Coding(ContentCoding::Zstd));
+        assert_eq!(AcceptCoding::parse(b"*").unwrap().coding, CodingMatch::Any);
+        assert_eq!(AcceptCoding::new("*").coding, CodingMatch::Any);
+        assert_eq!(AcceptCoding::parse(b"x-custom").unwrap().coding,
+                   CodingMatch::Coding(ContentCoding::Other("x-custom".to_owned())));
+        bad::<AcceptCoding>(b"");
+        bad::<AcceptCoding>(b";q=1");
+        bad::<AcceptCoding>(b"gzip;q=2");
+        bad::<AcceptCoding>(b"gzip;level=9");
+        bad::<AcceptCoding>(b"gzip deflate");
+    }
+
+    #[test]
+    fn test_combined_lines_reparse_identically() {
+        let headers = Headers::parse_block(b"Accept-Encoding: gzip;q=0.8, br\r\n\
+                                             Accept-Encoding: identity;q=0\r\n").unwrap();
+        let expected = vec![weighted("gzip", 800), AcceptCoding::new("br"),
+                            weighted("identity", 0)];
+        assert_eq!(&*headers.get(ACCEPT_ENCODING), &expected[..]);
+
+        // Written out, the two lines become one, as RFC 7230 permits of a list header.
+        let mut out = vec![];
+        headers.write_to(&mut out).unwrap();
+        assert_eq!(&out[..], &b"accept-encoding: gzip;q=0.8, br, identity;q=0\r\n"[..]);
+        let combined = Headers::parse_block(&out).unwrap();
+        assert_eq!(&*combined.get(ACCEPT_ENCODING), &expected[..]);
+
+        // And the lines as received, forwarded separately, mean just the same.
+        let lines = headers.get_raw_lines("accept-encoding").unwrap();
+        assert_eq!(lines.len(), 2);
+        let mut block = vec![];
+        for line in lines.iter() {
+            block.extend_from_slice(b"Accept-Encoding: ");
+            block.extend_from_slice(line);
+            block.extend_from_slice(b"\r\n");
+        }
+        assert_eq!(&*Headers::parse_block(&block).unwrap().get(ACCEPT_ENCODING), &expected[..]);
+    }
+
+    #[test]
+    fn test_ensure_identity() {
+        let mut headers = Headers::parse_block(b"Accept-Encoding: gzip, br\r\n").unwrap();
+        let mut accept = AcceptEncoding::from_headers(&headers);
+        accept.ensure_identity();
+        assert_eq!(accept.codings(), &[AcceptCoding::new("gzip"), AcceptCoding::new("br"),
+                                       weighted("identity", 1)][..]);
+        headers.set(ACCEPT_ENCODING, accept.clone().into_codings());
+        assert_eq!(headers.get_raw_combined("accept-encoding").unwrap(),
+                   b"gzip, br, identity;q=0.001".to_vec());
+
+        // Only once.
+        let mut again = accept.clone();
+        again.ensure_identity();
+        assert_eq!(again, accept);
+
+        // Not if identity is already named, with whatever weight, or `*` covers it.
+        for codings in &[vec![AcceptCoding::new("gzip"), weighted("identity", 0)],
+                         vec![weighted("identity", 500)],
+                         vec![AcceptCoding::new("br"), weighted("*", 0)]] {
+            let mut accept = AcceptEncoding::new(codings.clone());
+            accept.ensure_identity();
+            assert_eq!(accept.codings(), &codings[..]);
+        }
+
+        // An empty header gets it too.
+        let mut empty = AcceptEncoding::new(vec![]);
+        empty.ensure_identity();
+        assert_eq!(empty.codings(), &[weighted("identity", 1)][..]);
+    }
+}
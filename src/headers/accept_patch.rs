@@ -0,0 +1,42 @@
+//! The `Accept-Patch` header (RFC 5789, section 3.1).
+
+use super::content_type::MediaType;
+
+define_list_header_marker! {
+    /// The `Accept-Patch` header: the patch document formats which the server accepts.
+    ///
+    /// RFC 5789 grammar:
+    ///
+    /// ```abnf
+    /// Accept-Patch = "Accept-Patch" ":" 1#media-type
+    /// ```
+    pub ACCEPT_PATCH: MediaType = "accept-patch"
+}
+
+#[cfg(test)]
+mod tests {
+    use smallvec::SmallVec;
+    use tendril::ByteTendril;
+    use headers::Headers;
+    use headers::content_type::MediaType;
+    use super::ACCEPT_PATCH;
+
+    #[test]
+    fn test_accept_patch() {
+        let mut headers = Headers::new();
+        let mut value = SmallVec::new();
+        value.push(ByteTendril::from_slice(
+            b"application/json-patch+json, application/merge-patch+json"));
+        headers.set_raw(ACCEPT_PATCH, value);
+        assert_eq!(&*headers.get(ACCEPT_PATCH), &[
+            MediaType::new("application", "json-patch+json"),
+            MediaType::new("application", "merge-patch+json"),
+        ]);
+        assert!(headers.get(ACCEPT_PATCH).iter().all(|media_type| media_type.is_json()));
+
+        headers.set(ACCEPT_PATCH, vec![MediaType::new("text", "x-diff"),
+                                       MediaType::new("application", "merge-patch+json")]);
+        assert_eq!(&headers.get_raw(ACCEPT_PATCH).unwrap()[0][..],
+                   b"text/x-diff, application/merge-patch+json");
+    }
+}
@@ -0,0 +1,87 @@
+//! The `Allow` header (RFC 7231, section 7.4.1), and its use in answering `OPTIONS` requests and
+//! producing `405 Method Not Allowed` responses.
+
+use method::Method;
+use super::Headers;
+
+define_list_header_marker! {
+    /// The `Allow` header: the methods which the target resource supports.
+    ///
+    /// RFC 7231 grammar:
+    ///
+    /// ```abnf
+    /// Allow = #method
+    /// ```
+    ///
+    /// Note that an empty `Allow` header is legal, meaning that the resource allows no methods.
+    pub ALLOW: Method<'static> = "allow"
+}
+
+/// The set of methods which a resource supports.
+///
+/// Every server ends up writing this logic one way or another: construct one of these from the
+/// methods your resource handles, then use `is_method_allowed` to decide whether to respond with
+/// `405 Method Not Allowed`, and `set_allow` on the response to an `OPTIONS` request or a 405
+/// (for which RFC 7231 says the `Allow` header MUST be sent).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Allow {
+    methods: Vec<Method<'static>>,
+}
+
+impl Allow {
+    /// Construct a method set. Duplicates are discarded; otherwise, order is preserved.
+    pub fn new<I: IntoIterator<Item = Method<'static>>>(methods: I) -> Allow {
+        let mut allow = Allow { methods: vec![] };
+        for method in methods {
+            if !allow.is_method_allowed(&method) {
+                allow.methods.push(method);
+            }
+        }
+        allow
+    }
+
+    /// Read the method set from a message’s `Allow` header.
+    ///
+    /// If there is no `Allow` header, the set will be empty.
+    pub fn from_headers(headers: &Headers) -> Allow {
+        Allow::new(headers.get(ALLOW).into_owned())
+    }
+
+    /// Whether the method is in the set.
+    pub fn is_method_allowed(&self, method: &Method) -> bool {
+        self.methods.iter().any(|allowed| allowed == method)
+    }
+
+    /// The methods in the set.
+    pub fn methods(&self) -> &[Method<'static>] {
+        &self.methods
+    }
+
+    /// Set the `Allow` header of the given header collection to this method set.
+    pub fn set_allow(&self, headers: &mut Headers) {
+        headers.set(ALLOW, self.methods.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use method::{Get, Head, Post, Put, Delete, Options};
+    use headers::Headers;
+    use super::{Allow, ALLOW};
+
+    #[test]
+    fn test_allow() {
+        let allow = Allow::new(vec![Get, Head, Get, Post, Options]);
+        assert_eq!(allow.methods(), &[Get, Head, Post, Options]);
+        assert!(allow.is_method_allowed(&Get));
+        assert!(allow.is_method_allowed(&Post));
+        assert!(!allow.is_method_allowed(&Put));
+        assert!(!allow.is_method_allowed(&Delete));
+
+        let mut headers = Headers::new();
+        allow.set_allow(&mut headers);
+        assert_eq!(&headers.get_raw(ALLOW).unwrap()[0][..], b"GET, HEAD, POST, OPTIONS");
+        assert_eq!(Allow::from_headers(&headers), allow);
+        assert_eq!(Allow::from_headers(&Headers::new()), Allow::new(vec![]));
+    }
+}
@@ -0,0 +1,516 @@
+//! The `Authorization` and `Proxy-Authorization` headers (RFC 7235, sections 4.2 and 4.4).
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+
+use grammar::{base64, is_field_content, trim_ows_start};
+use grammar::parameters::split_token;
+use grammar::quoted_string;
+use grammar::token::is_tchar;
+use super::{ToHeader, Header};
+
+/// Whether a character may appear in a token68, before any trailing `=`.
+#[inline]
+fn is_token68_char(o: u8) -> bool {
+    (o >= b'A' && o <= b'Z') || (o >= b'a' && o <= b'z') || (o >= b'0' && o <= b'9') ||
+    o == b'-' || o == b'.' || o == b'_' || o == b'~' || o == b'+' || o == b'/'
+}
+
+/// Whether this is a token68.
+///
+/// RFC 7235 grammar:
+///
+/// ```abnf
+/// token68 = 1*( ALPHA / DIGIT / "-" / "." / "_" / "~" / "+" / "/" ) *"="
+/// ```
+fn is_token68(raw: &[u8]) -> bool {
+    let end = raw.iter().position(|&c| !is_token68_char(c)).unwrap_or(raw.len());
+    end > 0 && raw[end..].iter().all(|&c| c == b'=')
+}
+
+/// The credentials data, if it is a token68.
+fn token68(data: Option<&[u8]>) -> Option<&[u8]> {
+    data.and_then(|data| if is_token68(data) { Some(data) } else { None })
+}
+
+/// Compare two byte strings in time which depends only on their lengths, not their contents, so
+/// that a secret can’t be discovered a byte at a time by timing failed comparisons.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |difference, (&a, &b)| difference | (a ^ b)) == 0
+}
+
+/// Parse a comma-separated list of auth-params, lowercasing the names and unquoting the values.
+///
+/// RFC 7235 grammar:
+///
+/// ```abnf
+/// auth-param = token BWS "=" BWS ( token / quoted-string )
+/// ```
+///
+/// Empty list elements are skipped. Returns `None` if the list is malformed, or if any value is not
+/// valid UTF-8.
+fn parse_auth_params(mut raw: &[u8]) -> Option<Vec<(String, String)>> {
+    let mut params = vec![];
+    loop {
+        raw = trim_ows_start(raw);
+        if raw.is_empty() {
+            return Some(params);
+        }
+        if raw[0] == b',' {
+            raw = &raw[1..];
+            continue;
+        }
+        let (name, rest) = split_token(raw);
+        let rest = trim_ows_start(rest);
+        if name.is_empty() || rest.first() != Some(&b'=') {
+            return None;
+        }
+        let rest = trim_ows_start(&rest[1..]);
+        let (value, rest) = if rest.first() == Some(&b'"') {
+            match quoted_string::split(rest) {
+                Some((quoted, rest)) => (quoted_string::unquote(quoted).unwrap(), rest),
+                None => return None,
+            }
+        } else {
+            let (value, rest) = split_token(rest);
+            if value.is_empty() {
+                return None;
+            }
+            (value.to_vec(), rest)
+        };
+        let rest = trim_ows_start(rest);
+        if !rest.is_empty() && rest[0] != b',' {
+            return None;
+        }
+        let value = match String::from_utf8(value) {
+            Ok(value) => value,
+            Err(_) => return None,
+        };
+        // Tokens are ASCII, so this can’t fail.
+        let name = unsafe { str::from_utf8_unchecked(name) }.to_ascii_lowercase();
+        params.push((name, value));
+        raw = rest;
+    }
+}
+
+/// Write `, name=value`, or without the comma if `first`, quoting the value if `quoted` or if it
+/// isn’t a token.
+fn fmt_auth_param(f: &mut fmt::Formatter, first: bool, name: &str, value: &str, quoted: bool)
+                  -> fmt::Result {
+    try!(write!(f, "{}{}=", if first { "" } else { ", " }, name));
+    if !quoted && !value.is_empty() && value.bytes().all(is_tchar) {
+        return f.write_str(value);
+    }
+    match quoted_string::quote(value.as_bytes()) {
+        // quote only adds ASCII to what was already UTF-8, so this can’t fail.
+        Some(quoted) => f.write_str(unsafe { str::from_utf8_unchecked(&quoted) }),
+        None => Err(fmt::Error),
+    }
+}
+
+/// The credentials of the `Digest` authentication scheme (RFC 7616, section 3.4).
+///
+/// Values are held unquoted. On parsing, any parameter may be a token or a quoted-string; on
+/// output, those which RFC 7616 says are quoted-strings are quoted, and `algorithm`, `qop` and
+/// `nc` are written as tokens.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DigestCredentials {
+    /// The user’s name in the realm.
+    pub username: String,
+    /// The protection space, as given in the challenge.
+    pub realm: String,
+    /// The nonce, as given in the challenge.
+    pub nonce: String,
+    /// The effective request URI, in the form of the request-target.
+    pub uri: String,
+    /// The digest proving that the user knows the password, in hexadecimal.
+    pub response: String,
+    /// The algorithm, such as `SHA-256`; `MD5` is implied if absent.
+    pub algorithm: Option<String>,
+    /// The quality of protection: `auth` or `auth-int`.
+    pub qop: Option<String>,
+    /// The nonce count, eight hexadecimal digits, as `00000001`. Present if `qop` is.
+    pub nc: Option<String>,
+    /// The client nonce. Present if `qop` is.
+    pub cnonce: Option<String>,
+    /// The opaque data from the challenge, returned unchanged.
+    pub opaque: Option<String>,
+    /// Any other parameters, such as `userhash`, names lowercase, in the order given.
+    pub extensions: Vec<(String, String)>,
+}
+
+impl DigestCredentials {
+    /// Parse the auth-params of `Digest` credentials. `username`, `realm`, `nonce`, `uri` and
+    /// `response` are required, and no parameter may be given twice.
+    fn parse(data: &[u8]) -> Option<DigestCredentials> {
+        let params = match parse_auth_params(data) {
+            Some(params) => params,
+            None => return None,
+        };
+        let (mut username, mut realm, mut nonce, mut uri, mut response) =
+            (None, None, None, None, None);
+        let (mut algorithm, mut qop, mut nc, mut cnonce, mut opaque) =
+            (None, None, None, None, None);
+        let mut extensions: Vec<(String, String)> = vec![];
+        for (name, value) in params {
+            let field = match &name[..] {
+                "username" => &mut username,
+                "realm" => &mut realm,
+                "nonce" => &mut nonce,
+                "uri" => &mut uri,
+                "response" => &mut response,
+                "algorithm" => &mut algorithm,
+                "qop" => &mut qop,
+                "nc" => &mut nc,
+                "cnonce" => &mut cnonce,
+                "opaque" => &mut opaque,
+                _ => {
+                    if extensions.iter().any(|&(ref n, _)| *n == name) {
+                        return None;
+                    }
+                    extensions.push((name, value));
+                    continue;
+                },
+            };
+            if field.is_some() {
+                return None;
+            }
+            *field = Some(value);
+        }
+        match (username, realm, nonce, uri, response) {
+            (Some(username), Some(realm), Some(nonce), Some(uri), Some(response)) => {
+                Some(DigestCredentials {
+                    username: username,
+                    realm: realm,
+                    nonce: nonce,
+                    uri: uri,
+                    response: response,
+                    algorithm: algorithm,
+                    qop: qop,
+                    nc: nc,
+                    cnonce: cnonce,
+                    opaque: opaque,
+                    extensions: extensions,
+                })
+            },
+            _ => None,
+        }
+    }
+
+    /// Everything but the response, for comparing that separately.
+    fn public_parts(&self) -> (&str, &str, &str, &str, &Option<String>, &Option<String>,
+                               &Option<String>, &Option<String>, &Option<String>,
+                               &[(String, String)]) {
+        (&self.username, &self.realm, &self.nonce, &self.uri, &self.algorithm, &self.qop,
+         &self.nc, &self.cnonce, &self.opaque, &self.extensions)
+    }
+}
+
+impl fmt::Display for DigestCredentials {
+    /// The auth-params, in the order of the examples of RFC 7616.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(fmt_auth_param(f, true, "username", &self.username, true));
+        try!(fmt_auth_param(f, false, "realm", &self.realm, true));
+        try!(fmt_auth_param(f, false, "uri", &self.uri, true));
+        if let Some(ref algorithm) = self.algorithm {
+            try!(fmt_auth_param(f, false, "algorithm", algorithm, false));
+        }
+        try!(fmt_auth_param(f, false, "nonce", &self.nonce, true));
+        if let Some(ref nc) = self.nc {
+            try!(fmt_auth_param(f, false, "nc", nc, false));
+        }
+        if let Some(ref cnonce) = self.cnonce {
+            try!(fmt_auth_param(f, false, "cnonce", cnonce, true));
+        }
+        if let Some(ref qop) = self.qop {
+            try!(fmt_auth_param(f, false, "qop", qop, false));
+        }
+        try!(fmt_auth_param(f, false, "response", &self.response, true));
+        if let Some(ref opaque) = self.opaque {
+            try!(fmt_auth_param(f, false, "opaque", opaque, true));
+        }
+        for &(ref name, ref value) in &self.extensions {
+            try!(fmt_auth_param(f, false, name, value, false));
+        }
+        Ok(())
+    }
+}
+
+/// The credentials of an `Authorization` or `Proxy-Authorization` header.
+///
+/// RFC 7235 grammar:
+///
+/// ```abnf
+/// credentials = auth-scheme [ 1*SP ( token68 / #auth-param ) ]
+/// auth-scheme = token
+/// ```
+///
+/// Authentication schemes are case-insensitive. Those other than `Basic` (RFC 7617), `Bearer`
+/// (RFC 6750) and `Digest` (RFC 7616) are kept with their case preserved and their data as text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Credentials {
+    /// `Basic`: a user-id and password, sent base 64-encoded.
+    Basic {
+        /// The user-id, which can’t contain a colon.
+        username: String,
+        /// The password.
+        password: String,
+    },
+    /// `Bearer`: an access token, being a token68.
+    Bearer(String),
+    /// `Digest`: a digest of the password and parts of the request, among other things.
+    Digest(DigestCredentials),
+    /// Any other scheme, and what follows it, if anything: a token68 or auth-params.
+    Other(String, Option<String>),
+}
+
+impl Credentials {
+    /// Compare two sets of credentials without the time taken revealing how much of the secret
+    /// matched, as a server checking credentials against its own should.
+    ///
+    /// Schemes are compared as usual, for they are no secret; the rest (the user-id and password,
+    /// the token or the data) is compared in constant time, but for its length, which may be
+    /// learned.
+    pub fn ct_eq(&self, other: &Credentials) -> bool {
+        match (self, other) {
+            (&Credentials::Basic { username: ref u1, password: ref p1 },
+             &Credentials::Basic { username: ref u2, password: ref p2 }) => {
+                // Both are compared whatever the outcome of the first comparison.
+                constant_time_eq(u1.as_bytes(), u2.as_bytes()) &
+                    constant_time_eq(p1.as_bytes(), p2.as_bytes())
+            },
+            (&Credentials::Bearer(ref t1), &Credentials::Bearer(ref t2)) => {
+                constant_time_eq(t1.as_bytes(), t2.as_bytes())
+            },
+            (&Credentials::Digest(ref d1), &Credentials::Digest(ref d2)) => {
+                // Only the response is secret; the rest came from the challenge or the request.
+                constant_time_eq(d1.response.as_bytes(), d2.response.as_bytes()) &
+                    (d1.public_parts() == d2.public_parts())
+            },
+            (&Credentials::Other(ref s1, ref d1), &Credentials::Other(ref s2, ref d2)) => {
+                s1.eq_ignore_ascii_case(s2) && match (d1, d2) {
+                    (&Some(ref d1), &Some(ref d2)) => {
+                        constant_time_eq(d1.as_bytes(), d2.as_bytes())
+                    },
+                    (&None, &None) => true,
+                    _ => false,
+                }
+            },
+            _ => false,
+        }
+    }
+}
+
+impl ToHeader for Credentials {
+    fn parse(raw: &[u8]) -> Option<Credentials> {
+        let (scheme, rest) = split_token(raw);
+        if scheme.is_empty() {
+            return None;
+        }
+        let data = match rest.iter().position(|&c| c != b' ') {
+            None if rest.is_empty() => None,
+            Some(start) if start > 0 => Some(&rest[start..]),
+            _ => return None,
+        };
+        // Tokens are ASCII, so this can’t fail.
+        let scheme = unsafe { str::from_utf8_unchecked(scheme) };
+        if scheme.eq_ignore_ascii_case("basic") {
+            let decoded = match token68(data).and_then(base64::decode) {
+                Some(decoded) => decoded,
+                None => return None,
+            };
+            let decoded = match String::from_utf8(decoded) {
+                Ok(decoded) => decoded,
+                Err(_) => return None,
+            };
+            let colon = match decoded.find(':') {
+                Some(colon) => colon,
+                None => return None,
+            };
+            Some(Credentials::Basic {
+                username: decoded[..colon].to_owned(),
+                password: decoded[colon + 1..].to_owned(),
+            })
+        } else if scheme.eq_ignore_ascii_case("bearer") {
+            // A token68 is ASCII, so this can’t fail.
+            token68(data).map(|token| {
+                Credentials::Bearer(unsafe { str::from_utf8_unchecked(token) }.to_owned())
+            })
+        } else if scheme.eq_ignore_ascii_case("digest") {
+            data.and_then(DigestCredentials::parse).map(Credentials::Digest)
+        } else {
+            match data {
+                None => Some(Credentials::Other(scheme.to_owned(), None)),
+                Some(data) if is_field_content(data) => str::from_utf8(data).ok().map(|data| {
+                    Credentials::Other(scheme.to_owned(), Some(data.to_owned()))
+                }),
+                Some(_) => None,
+            }
+        }
+    }
+}
+
+impl Header for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Credentials::Basic { ref username, ref password } => {
+                write!(f, "Basic {}", base64::encode(format!("{}:{}", username, password)
+                                                         .as_bytes()))
+            },
+            Credentials::Bearer(ref token) => write!(f, "Bearer {}", token),
+            Credentials::Digest(ref digest) => write!(f, "Digest {}", digest),
+            Credentials::Other(ref scheme, None) => f.write_str(scheme),
+            Credentials::Other(ref scheme, Some(ref data)) => write!(f, "{} {}", scheme, data),
+        }
+    }
+}
+
+define_single_header_marker! {
+    /// The `Authorization` header: the user agent’s credentials for the origin server.
+    pub AUTHORIZATION: Credentials = "authorization"
+}
+
+define_single_header_marker! {
+    /// The `Proxy-Authorization` header: the user agent’s credentials for a proxy.
+    pub PROXY_AUTHORIZATION: Credentials = "proxy-authorization"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use super::{Credentials, DigestCredentials, AUTHORIZATION, PROXY_AUTHORIZATION};
+    use super::Credentials::{Basic, Bearer, Digest, Other};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    fn basic(username: &str, password: &str) -> Credentials {
+        Basic { username: username.to_owned(), password: password.to_owned() }
+    }
+
+    #[test]
+    fn test_credentials() {
+        eq(b"Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==", basic("Aladdin", "open sesame"));
+        eq(b"Bearer mF_9.B5f-4.1JqM", Bearer("mF_9.B5f-4.1JqM".to_owned()));
+        eq(b"Negotiate", Other("Negotiate".to_owned(), None));
+        eq(b"Custom realm=\"http-auth@example.org\", a=b",
+           Other("Custom".to_owned(), Some("realm=\"http-auth@example.org\", a=b".to_owned())));
+        assert_eq!(Credentials::parse(b"basic  QWxhZGRpbjpvcGVuIHNlc2FtZQ=="),
+                   Some(basic("Aladdin", "open sesame")));
+        assert_eq!(Credentials::parse(b"Basic OnNlY3JldA=="), Some(basic("", "secret")));
+        bad::<Credentials>(b"");
+        bad::<Credentials>(b"Basic");
+        bad::<Credentials>(b"Basic QWxhZGRpbg==");
+        bad::<Credentials>(b"Basic !!!");
+        bad::<Credentials>(b"Bearer a b");
+        bad::<Credentials>(b"Bearer");
+        bad::<Credentials>(b"Bearer\tabc");
+    }
+
+    fn digest() -> DigestCredentials {
+        DigestCredentials {
+            username: "Mufasa".to_owned(),
+            realm: "http-auth@example.org".to_owned(),
+            nonce: "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v".to_owned(),
+            uri: "/dir/index.html".to_owned(),
+            response: "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1"
+                          .to_owned(),
+            algorithm: Some("SHA-256".to_owned()),
+            qop: Some("auth".to_owned()),
+            nc: Some("00000001".to_owned()),
+            cnonce: Some("f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ".to_owned()),
+            opaque: Some("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS".to_owned()),
+            extensions: vec![],
+        }
+    }
+
+    #[test]
+    fn test_digest() {
+        // The example of RFC 7616, section 3.9.1.
+        let headers = Headers::parse_block(b"Authorization: Digest username=\"Mufasa\", \
+            realm=\"http-auth@example.org\", uri=\"/dir/index.html\", algorithm=SHA-256, \
+            nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", nc=00000001, \
+            cnonce=\"f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ\", qop=auth, \
+            response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\", \
+            opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"\r\n").unwrap();
+        assert_eq!(*headers.get(AUTHORIZATION).unwrap(), Digest(digest()));
+        let raw = headers.get_raw_lines("authorization").unwrap()[0].to_vec();
+        eq(&raw, Digest(digest()));
+
+        // Quoted and unquoted forms are both accepted, in any order and case, and are re-quoted.
+        let parsed = Credentials::parse(b"digest NONCE=abc , Username=Mufasa,realm=\"r\",\
+                                          uri=\"/\", response=\"0f\", qop=\"auth\", \
+                                          userhash=false").unwrap();
+        let expected = DigestCredentials {
+            username: "Mufasa".to_owned(),
+            realm: "r".to_owned(),
+            nonce: "abc".to_owned(),
+            uri: "/".to_owned(),
+            response: "0f".to_owned(),
+            algorithm: None,
+            qop: Some("auth".to_owned()),
+            nc: None,
+            cnonce: None,
+            opaque: None,
+            extensions: vec![("userhash".to_owned(), "false".to_owned())],
+        };
+        assert_eq!(parsed, Digest(expected.clone()));
+        eq(b"Digest username=\"Mufasa\", realm=\"r\", uri=\"/\", nonce=\"abc\", qop=auth, \
+             response=\"0f\", userhash=false", Digest(expected));
+
+        // Required parameters must be present, and none repeated.
+        bad::<Credentials>(b"Digest");
+        bad::<Credentials>(b"Digest username=\"Mufasa\", realm=\"r\", uri=\"/\", nonce=a");
+        bad::<Credentials>(b"Digest username=a, realm=r, uri=u, nonce=n, response=r, realm=s");
+        bad::<Credentials>(b"Digest username=a realm=r, uri=u, nonce=n, response=r");
+        bad::<Credentials>(b"Digest username=\"a, realm=r, uri=u, nonce=n, response=r");
+    }
+
+    #[test]
+    fn test_ct_eq() {
+        assert!(basic("Aladdin", "open sesame").ct_eq(&basic("Aladdin", "open sesame")));
+        assert!(!basic("Aladdin", "open sesame").ct_eq(&basic("Aladdin", "open sesamf")));
+        assert!(!basic("Aladdin", "open sesame").ct_eq(&basic("Aladdim", "open sesame")));
+        assert!(!basic("Aladdin", "open sesame").ct_eq(&basic("Aladdin", "open")));
+        let token = Bearer("mF_9.B5f-4.1JqM".to_owned());
+        assert!(token.ct_eq(&Bearer("mF_9.B5f-4.1JqM".to_owned())));
+        assert!(!token.ct_eq(&Bearer("mF_9.B5f-4.1JqN".to_owned())));
+        assert!(!token.ct_eq(&basic("mF_9.B5f-4.1JqM", "")));
+        assert!(Other("Custom".to_owned(), Some("abc".to_owned()))
+                    .ct_eq(&Other("custom".to_owned(), Some("abc".to_owned()))));
+        assert!(!Other("Custom".to_owned(), Some("abc".to_owned()))
+                     .ct_eq(&Other("Custom".to_owned(), None)));
+        assert!(Digest(digest()).ct_eq(&Digest(digest())));
+        let mut other = digest();
+        other.response.pop();
+        assert!(!Digest(digest()).ct_eq(&Digest(other)));
+        let mut other = digest();
+        other.nc = Some("00000002".to_owned());
+        assert!(!Digest(digest()).ct_eq(&Digest(other)));
+    }
+
+    #[test]
+    fn test_proxy_authorization() {
+        // The proxy’s credentials and the origin server’s are quite separate.
+        let headers = Headers::parse_block(b"Proxy-Authorization: Basic \
+                                             QWxhZGRpbjpvcGVuIHNlc2FtZQ==\r\n\
+                                             Authorization: Bearer mF_9.B5f-4.1JqM\r\n").unwrap();
+        assert_eq!(*headers.get(PROXY_AUTHORIZATION).unwrap(), basic("Aladdin", "open sesame"));
+        assert_eq!(*headers.get(AUTHORIZATION).unwrap(), Bearer("mF_9.B5f-4.1JqM".to_owned()));
+
+        let headers = Headers::parse_block(b"Proxy-Authorization: Basic Zm9vOmJhcg==\r\n").unwrap();
+        assert_eq!(*headers.get(PROXY_AUTHORIZATION).unwrap(), basic("foo", "bar"));
+        assert!(headers.get(AUTHORIZATION).is_none());
+    }
+}
@@ -0,0 +1,670 @@
+//! Parsing a whole HTTP/1.x header block into a `Headers`, with limits on how much is accepted,
+//! and writing one out again.
+
+use std::ascii::AsciiExt;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry::{Occupied, Vacant};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::str;
+
+use tendril::ByteTendril;
+use smallvec::SmallVec;
+
+use grammar::token::is_tchar;
+use grammar::{is_ows, trim_ows};
+use super::{Headers, is_field_name};
+use super::internals::Item;
+use super::trailer::{Trailer, is_forbidden_trailer};
+use super::unknown::{UnknownPolicy, check_unknown};
+
+/// The default maximum length of a single header field line, in bytes: 8 KiB.
+pub const DEFAULT_MAX_FIELD_LENGTH: usize = 8 * 1024;
+
+/// The default maximum length of a whole header block, in bytes: 64 KiB.
+pub const DEFAULT_MAX_TOTAL_LENGTH: usize = 64 * 1024;
+
+/// The kind of a `HeaderParseError`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HeaderParseErrorKind {
+    /// A header field line, or the header block as a whole, exceeded the configured limit.
+    TooLong,
+    /// The header block was not well-formed: a line without a colon, an invalid field name, or
+    /// obsolete line folding (which RFC 7230 permits recipients to reject).
+    Malformed,
+    /// A header which may only occur once occurred more than once, as with `Host`.
+    Duplicate,
+    /// A required header was missing, as with `Host` in an HTTP/1.1 request.
+    Missing,
+    /// Headers which must not occur together did, as with `Content-Length` and
+    /// `Transfer-Encoding`.
+    Conflicting,
+    /// A header’s value was invalid where that can’t be ignored, as with `Content-Length`.
+    Invalid,
+    /// A header occurred where it is not permitted, as with `Content-Length` in a trailer
+    /// section, or a trailer which the `Trailer` header did not announce.
+    Forbidden,
+}
+
+impl HeaderParseErrorKind {
+    fn description(&self) -> &'static str {
+        match *self {
+            HeaderParseErrorKind::TooLong => "header too long",
+            HeaderParseErrorKind::Malformed => "malformed header",
+            HeaderParseErrorKind::Duplicate => "duplicate header",
+            HeaderParseErrorKind::Missing => "missing header",
+            HeaderParseErrorKind::Conflicting => "conflicting headers",
+            HeaderParseErrorKind::Invalid => "invalid header value",
+            HeaderParseErrorKind::Forbidden => "forbidden header",
+        }
+    }
+}
+
+/// The maximum number of offending bytes kept in a `HeaderParseError`.
+const MAX_SNIPPET_LENGTH: usize = 64;
+
+/// An error encountered when parsing headers.
+///
+/// As well as the kind of error, this records the name of the header concerned, where known,
+/// and a snippet of the offending bytes (the first 64 of them), for error messages and logging.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HeaderParseError {
+    kind: HeaderParseErrorKind,
+    name: Option<String>,
+    snippet: Vec<u8>,
+    truncated: bool,
+}
+
+impl HeaderParseError {
+    /// Construct an error, taking a snippet of the offending bytes.
+    pub fn new(kind: HeaderParseErrorKind, name: Option<&str>, value: &[u8]) -> HeaderParseError {
+        let truncated = value.len() > MAX_SNIPPET_LENGTH;
+        HeaderParseError {
+            kind: kind,
+            name: name.map(|name| name.to_owned()),
+            snippet: value[..if truncated { MAX_SNIPPET_LENGTH } else { value.len() }].to_vec(),
+            truncated: truncated,
+        }
+    }
+
+    /// The kind of error.
+    pub fn kind(&self) -> HeaderParseErrorKind {
+        self.kind
+    }
+
+    /// The name of the header concerned (lowercase), if known.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|name| &name[..])
+    }
+
+    /// The start of the offending bytes.
+    pub fn snippet(&self) -> &[u8] {
+        &self.snippet
+    }
+}
+
+/// The message is of the form `malformed header: "X-Foo 1"` or, where the header name is known,
+/// `header too long (x-foo): "aaaa…"`. The offending bytes are rendered lossily as UTF-8, with
+/// control characters escaped, and are omitted if there are none, as for a missing header.
+impl fmt::Display for HeaderParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str(self.kind.description()));
+        if let Some(ref name) = self.name {
+            try!(write!(f, " ({})", name));
+        }
+        if self.snippet.is_empty() {
+            return Ok(());
+        }
+        try!(f.write_str(": \""));
+        for c in String::from_utf8_lossy(&self.snippet).chars() {
+            try!(write!(f, "{}", c.escape_default()));
+        }
+        if self.truncated {
+            try!(f.write_str("…"));
+        }
+        f.write_str("\"")
+    }
+}
+
+impl Error for HeaderParseError {
+    fn description(&self) -> &str {
+        self.kind.description()
+    }
+}
+
+/// Limits on how much will be accepted when parsing a header block, as a defence against memory
+/// exhaustion from absurdly long headers.
+///
+/// The lengths are measured on the raw field lines, excluding line terminators. The defaults are
+/// `DEFAULT_MAX_FIELD_LENGTH` and `DEFAULT_MAX_TOTAL_LENGTH`: generous, but finite.
+///
+/// They are enforced as the block is parsed, not again by the parsers of individual headers: no
+/// value taken from a block can be longer than its field line, and a parser, which returns an
+/// `Option` of the value, could only drop an over-long one rather than report it as `TooLong`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Limits {
+    /// The maximum length of a single header field line.
+    pub max_field_length: usize,
+    /// The maximum combined length of all the header field lines.
+    pub max_total_length: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_field_length: DEFAULT_MAX_FIELD_LENGTH,
+            max_total_length: DEFAULT_MAX_TOTAL_LENGTH,
+        }
+    }
+}
+
+/// Where a field line was in the input to `Headers::parse_block_with_spans`, as byte offsets.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FieldSpan {
+    /// The whole line, from the start of the name to the end of the value, excluding the line
+    /// terminator.
+    pub line: Range<usize>,
+    /// The field value, excluding surrounding whitespace; the bytes of the raw value stored.
+    pub value: Range<usize>,
+}
+
+/// The spans of the field lines of a parsed header block, by header name.
+///
+/// These are kept apart from the `Headers`, rather than in each item, so that the usual parse
+/// pays nothing for them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HeaderSpans {
+    spans: HashMap<String, Vec<FieldSpan>>,
+}
+
+impl HeaderSpans {
+    /// The spans of the field lines of the named header (case-insensitive), in the order that
+    /// they occurred, which is the order of its raw values; empty if there were none.
+    pub fn get(&self, name: &str) -> &[FieldSpan] {
+        self.spans.get(&name.to_ascii_lowercase()).map_or(&[], |spans| &spans[..])
+    }
+}
+
+impl Headers {
+    /// Parse a header block, with the default limits.
+    ///
+    /// The block is a sequence of `field-name ":" OWS field-value OWS` lines, each terminated by
+    /// CRLF (or a bare LF, which RFC 7230 permits recipients to accept). Parsing stops at the
+    /// first empty line or the end of the input, whichever comes first. Field values are stored
+    /// raw; typed parsing happens on access, as usual.
+    pub fn parse_block(raw: &[u8]) -> Result<Headers, HeaderParseError> {
+        Headers::parse_block_with_limits(raw, Limits::default())
+    }
+
+    /// Parse a header block, as `parse_block`, with the given limits.
+    pub fn parse_block_with_limits(raw: &[u8], limits: Limits)
+                                   -> Result<Headers, HeaderParseError> {
+        parse_block(raw, limits, UnknownPolicy::Accept, None)
+    }
+
+    /// Parse a header block, as `parse_block_with_limits`, subjecting unknown headers to the
+    /// given policy, as `append_raw` would; the headers returned keep the policy.
+    ///
+    /// A field line which the policy refuses is an error, as from `append_raw`, and nothing is
+    /// returned.
+    pub fn parse_block_with_policy(raw: &[u8], limits: Limits, policy: UnknownPolicy)
+                                   -> Result<Headers, HeaderParseError> {
+        parse_block(raw, limits, policy, None)
+    }
+
+    /// Parse a header block, as `parse_block_with_limits`, also recording where in `raw` each
+    /// field line was, so that a header which later fails to parse in typed form can be pointed
+    /// at precisely.
+    pub fn parse_block_with_spans(raw: &[u8], limits: Limits)
+                                  -> Result<(Headers, HeaderSpans), HeaderParseError> {
+        let mut spans = HeaderSpans { spans: HashMap::new() };
+        let headers = try!(parse_block(raw, limits, UnknownPolicy::Accept, Some(&mut spans)));
+        Ok((headers, spans))
+    }
+
+    /// Parse the trailer section which follows a chunked body (RFC 7230, section 4.1.2), with the
+    /// default limits.
+    ///
+    /// This is parsed as `parse_block` does, but fields which must not be sent as trailers (such
+    /// as `Content-Length` or `Host`) are rejected; so too, if the message’s `Trailer` header is
+    /// given as `announced`, are fields which it did not announce. Either is a `Forbidden` error.
+    /// When there are several such fields, the error is for the first by name.
+    pub fn parse_trailers(raw: &[u8], announced: Option<&Trailer>)
+                          -> Result<Headers, HeaderParseError> {
+        let trailers = try!(Headers::parse_block(raw));
+        for name in trailers.sorted_names() {
+            let name = &**name;
+            if is_forbidden_trailer(name) || announced.map_or(false, |t| !t.announces(name)) {
+                let raw = trailers.item_by_name(name).and_then(|item| item.raw()).unwrap();
+                return Err(HeaderParseError::new(HeaderParseErrorKind::Forbidden, Some(name),
+                                                 &raw[0]));
+            }
+        }
+        Ok(trailers)
+    }
+
+    /// Check that the headers are fit for HTTP/2 (RFC 7540, section 8.1.2).
+    ///
+    /// Field names must be lowercase in HTTP/2, so a name with an uppercase letter (possible only
+    /// through a marker with such a name) is a `Malformed` error. Connection-specific headers
+    /// (`Connection`, `Keep-Alive`, `Proxy-Connection`, `Transfer-Encoding` and `Upgrade`) are
+    /// not permitted, nor is `TE` with any value but `trailers`; these are `Forbidden` errors.
+    /// When there are several such headers, the error is for the first by name.
+    pub fn validate_h2(&self) -> Result<(), HeaderParseError> {
+        for name in self.sorted_names() {
+            let raw = match self.data[name].raw() {
+                Some(raw) => raw,
+                None => continue,
+            };
+            let name = &**name;
+            let value = raw.first().map_or(&[][..], |value| &value[..]);
+            if name.bytes().any(|c| c >= b'A' && c <= b'Z') {
+                return Err(HeaderParseError::new(HeaderParseErrorKind::Malformed, Some(name),
+                                                 value));
+            }
+            let forbidden = match name {
+                "connection" | "keep-alive" | "proxy-connection" | "transfer-encoding" |
+                "upgrade" => true,
+                "te" => raw.iter().any(|value| !trim_ows(value).eq_ignore_ascii_case(b"trailers")),
+                _ => false,
+            };
+            if forbidden {
+                return Err(HeaderParseError::new(HeaderParseErrorKind::Forbidden, Some(name),
+                                                 value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the headers out as an HTTP/1.x header block: a `name: value` line, terminated by
+    /// CRLF, for each field line, as `pairs` gives them. The empty line which ends the block is
+    /// left to the caller.
+    ///
+    /// HTTP/1 has no pseudo-header fields, so if there are any, nothing is written and the error
+    /// is of kind `InvalidInput`. A value containing CR, LF or NUL, as may be stored with
+    /// `set_raw` or `get_raw_mut`, would split the header block or inject lines into it, as would
+    /// a name which is not a token, from a marker defined with one (which only debug builds catch
+    /// when it is used); so if there is any, nothing is written and the error is of kind
+    /// `InvalidData`.
+    ///
+    /// A header whose marker says it is combinable (`Marker::combinable`), as for `Accept` or
+    /// `Vary`, is written as one field line, its lines joined with commas, as RFC 7230 section
+    /// 3.2.2 permits; this re-parses to the very same list. That goes for a header set or parsed
+    /// through any marker, including one defined outside this crate. Any other header, including
+    /// `Set-Cookie` and a header which has only ever been handled raw, is written as the lines it
+    /// holds, which re-parse to the same list too, and for which combining could change the
+    /// meaning.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        if self.has_pseudo() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "pseudo-header fields can’t be sent over HTTP/1"));
+        }
+        let mut headers = vec![];
+        for name in self.sorted_names() {
+            let item = &self.data[name];
+            if !item.is_valid() {
+                continue;
+            }
+            if !is_field_name(name) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          format!("{:?} is not a valid header name", &name[..])));
+            }
+            let raw = match item.raw() {
+                Some(raw) => raw,
+                None => continue,
+            };
+            if raw.iter().any(|value| value.iter().any(|&c| c == b'\r' || c == b'\n' || c == 0)) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          format!("the value of {} contains CR, LF or NUL",
+                                                  &name[..])));
+            }
+            headers.push((name, item.combinable(), raw));
+        }
+        for (name, combinable, raw) in headers {
+            if combinable {
+                try!(write!(w, "{}: ", &name[..]));
+                for (i, value) in raw.iter().enumerate() {
+                    if i > 0 {
+                        try!(w.write_all(b", "));
+                    }
+                    try!(w.write_all(value));
+                }
+                try!(w.write_all(b"\r\n"));
+            } else {
+                for value in raw.iter() {
+                    try!(write!(w, "{}: ", &name[..]));
+                    try!(w.write_all(value));
+                    try!(w.write_all(b"\r\n"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a header block, recording the spans of the field lines if `spans` is given.
+fn parse_block(raw: &[u8], limits: Limits, policy: UnknownPolicy,
+               mut spans: Option<&mut HeaderSpans>) -> Result<Headers, HeaderParseError> {
+    let mut headers = Headers::new();
+    headers.set_unknown_policy(policy);
+    let mut total_length = 0;
+    let mut next_start = 0;
+    for line in raw.split(|&c| c == b'\n') {
+        let start = next_start;
+        next_start += line.len() + 1;
+        let line = match line.last() {
+            Some(&b'\r') => &line[..line.len() - 1],
+            _ => line,
+        };
+        if line.is_empty() {
+            break;
+        }
+        total_length += line.len();
+        // Before anything else looks at the line, so that an over-long one is always reported
+        // as such, however else it is wrong; but with its name, if it has one.
+        if line.len() > limits.max_field_length || total_length > limits.max_total_length {
+            let name = field_name(line);
+            return Err(HeaderParseError::new(HeaderParseErrorKind::TooLong,
+                                             name.as_ref().map(|name| &name[..]), line));
+        }
+        // A line of obs-fold, starting with whitespace, has no name either.
+        let name = match field_name(line) {
+            Some(name) => name,
+            None => return Err(HeaderParseError::new(HeaderParseErrorKind::Malformed, None, line)),
+        };
+        let colon = name.len();
+        try!(check_unknown(policy, &name, trim_ows(&line[colon + 1..])));
+        if let Some(ref mut spans) = spans {
+            let after_colon = &line[colon + 1..];
+            let leading = after_colon.iter().take_while(|&&c| is_ows(c)).count();
+            let value_start = start + colon + 1 + leading;
+            let value_end = value_start + trim_ows(after_colon).len();
+            spans.spans.entry(name.clone()).or_insert_with(Vec::new).push(FieldSpan {
+                line: start..start + line.len(),
+                value: value_start..value_end,
+            });
+        }
+        let value = ByteTendril::from_slice(trim_ows(&line[colon + 1..]));
+        match headers.data_mut().entry((&name[..]).into()) {
+            Vacant(entry) => {
+                let mut values = SmallVec::new();
+                values.push(value);
+                let _ = entry.insert(Item::from_raw(values, false));
+            },
+            Occupied(entry) => entry.into_mut().raw_mut().push(value),
+        }
+    }
+    Ok(headers)
+}
+
+/// The name of a field line (lowercase), if it starts with a token and a colon.
+///
+/// The line need not be complete, but without its colon it has no name yet.
+pub fn field_name(line: &[u8]) -> Option<String> {
+    let colon = match line.iter().position(|&c| c == b':') {
+        Some(colon) => colon,
+        None => return None,
+    };
+    let name = &line[..colon];
+    if name.is_empty() || !name.iter().all(|&c| is_tchar(c)) {
+        return None;
+    }
+    // Tokens are ASCII, so this can’t fail.
+    Some(unsafe { str::from_utf8_unchecked(name) }.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ascii::AsciiExt;
+    use std::iter;
+    use tendril::ByteTendril;
+    use smallvec::SmallVec;
+    use std::io;
+    use headers::{Headers, PseudoHeader};
+    use headers::accept::ACCEPT;
+    use headers::content_type::{MediaType, CONTENT_TYPE};
+    use headers::cookie::SET_COOKIE;
+    use headers::trailer::Trailer;
+    use headers::accept_encoding::AcceptCoding;
+    use headers::vary::VARY;
+    use headers::internals::Item;
+    use headers::unknown::UnknownPolicy;
+    use super::{HeaderParseError, HeaderParseErrorKind, Limits, DEFAULT_MAX_FIELD_LENGTH,
+                FieldSpan};
+
+    define_list_header_marker! {
+        X_LIST: usize = "x-list"
+    }
+
+    fn kind(result: Result<Headers, HeaderParseError>) -> Option<HeaderParseErrorKind> {
+        result.err().map(|error| error.kind())
+    }
+
+    fn line(name: &str, length: usize) -> Vec<u8> {
+        let mut line = format!("{}: ", name).into_bytes();
+        let padding = length - line.len();
+        line.extend(iter::repeat(b'a').take(padding));
+        line
+    }
+
+    #[test]
+    fn test_parse_block() {
+        let headers = Headers::parse_block(b"Content-Type: text/html\r\n\
+                                             X-List: 1, 2\r\n\
+                                             x-list:3\n\
+                                             \r\n\
+                                             Ignored: after the end").unwrap();
+        assert_eq!(*headers.get(CONTENT_TYPE).unwrap(), MediaType::new("text", "html"));
+        assert_eq!(&*headers.get(X_LIST), &[1, 2, 3]);
+        assert_eq!(headers.get_raw(X_LIST).unwrap().len(), 2);
+        assert!(Headers::parse_block(b"").unwrap() == Headers::new());
+
+        let malformed = Some(HeaderParseErrorKind::Malformed);
+        assert_eq!(kind(Headers::parse_block(b"X-List 1\r\n")), malformed);
+        assert_eq!(kind(Headers::parse_block(b": 1\r\n")), malformed);
+        assert_eq!(kind(Headers::parse_block(b"X-List : 1\r\n")), malformed);
+        assert_eq!(kind(Headers::parse_block(b"X-List: 1,\r\n 2\r\n")), malformed);
+    }
+
+    #[test]
+    fn test_parse_block_with_spans() {
+        let raw = b"Host: example.com\r\nVary:accept  \nX-List: a\r\nvary: origin\r\n\r\nbody";
+        let (headers, spans) = Headers::parse_block_with_spans(raw, Limits::default()).unwrap();
+        assert_eq!(spans.get("host"), &[FieldSpan { line: 0..17, value: 6..17 }]);
+        assert_eq!(spans.get("Vary"), &[FieldSpan { line: 19..32, value: 24..30 },
+                                        FieldSpan { line: 44..56, value: 50..56 }]);
+        assert!(spans.get("x-absent").is_empty());
+        // The spans correspond to the lines and to the raw values stored.
+        for name in &["host", "vary", "x-list"] {
+            let lines = headers.get_raw_lines(name).unwrap();
+            let spans = spans.get(name);
+            assert_eq!(lines.len(), spans.len());
+            for (value, span) in lines.iter().zip(spans) {
+                assert_eq!(&raw[span.value.clone()], &value[..]);
+                assert!(raw[span.line.clone()].to_ascii_lowercase().starts_with(name.as_bytes()));
+            }
+        }
+        assert_eq!(&raw[spans.get("x-list")[0].line.clone()], b"X-List: a");
+    }
+
+    #[test]
+    fn test_parse_block_limits() {
+        let too_long = Some(HeaderParseErrorKind::TooLong);
+        let at_limit = line("X-Long", DEFAULT_MAX_FIELD_LENGTH);
+        assert!(Headers::parse_block(&at_limit).is_ok());
+        let over_limit = line("X-Long", DEFAULT_MAX_FIELD_LENGTH + 1);
+        assert_eq!(kind(Headers::parse_block(&over_limit)), too_long);
+
+        let limits = Limits { max_field_length: 20, max_total_length: 30 };
+        let mut block = line("X-A", 20);
+        block.extend(b"\r\n");
+        assert!(Headers::parse_block_with_limits(&block, limits).is_ok());
+        block.extend(&line("X-B", 10)[..]);
+        assert!(Headers::parse_block_with_limits(&block, limits).is_ok());
+        block.extend(b"b");
+        assert_eq!(kind(Headers::parse_block_with_limits(&block, limits)), too_long);
+        assert_eq!(kind(Headers::parse_block_with_limits(&line("X-C", 21), limits)), too_long);
+
+        // An over-long line is too long, whatever else is wrong with it.
+        let mut folded = b" ".to_vec();
+        folded.extend(&over_limit[..]);
+        assert_eq!(kind(Headers::parse_block(&folded)), too_long);
+        let mut nameless = over_limit.clone();
+        nameless.retain(|&c| c != b':');
+        assert_eq!(kind(Headers::parse_block(&nameless)), too_long);
+    }
+
+    #[test]
+    fn test_error_display() {
+        let error = Headers::parse_block(&line("X-Long", DEFAULT_MAX_FIELD_LENGTH + 1)).err()
+                                                                                     .unwrap();
+        assert_eq!(error.name(), Some("x-long"));
+        let snippet: String = iter::repeat('a').take(64 - "X-Long: ".len()).collect();
+        assert_eq!(error.to_string(),
+                   format!("header too long (x-long): \"X-Long: {}…\"", snippet));
+        // Without a name to be had, there is none in the message.
+        let mut folded = b" ".to_vec();
+        folded.extend(&line("X-Long", DEFAULT_MAX_FIELD_LENGTH)[..]);
+        let error = Headers::parse_block(&folded).err().unwrap();
+        assert_eq!(error.name(), None);
+        assert!(error.to_string().starts_with("header too long: \" X-Long: "));
+
+        let error = Headers::parse_block_with_policy(b"Vary: accept\r\nX-Whatever: 1\r\n",
+                                                     Limits::default(), UnknownPolicy::Reject)
+                        .err().unwrap();
+        assert_eq!(error.to_string(), "forbidden header (x-whatever): \"1\"");
+
+        let error = Headers::parse_block(b"X-Bad\r\nName: caf\xc3\xa9\t\xff").err().unwrap();
+        assert_eq!(error.to_string(), "malformed header: \"X-Bad\"");
+        let error = Headers::parse_block(b"X-Bad\xc3\xa9\xff: 1").err().unwrap();
+        assert_eq!(error.to_string(), "malformed header: \"X-Bad\\u{e9}\\u{fffd}: 1\"");
+    }
+
+    #[test]
+    fn test_parse_trailers() {
+        let announced = Trailer::new(&["X-Checksum", "Server-Timing"]).unwrap();
+        let trailers = Headers::parse_trailers(b"x-checksum: abc\r\n\r\n", Some(&announced))
+                           .unwrap();
+        assert_eq!(trailers.get_raw_lines("X-Checksum").unwrap().len(), 1);
+        assert!(Headers::parse_trailers(b"", Some(&announced)).unwrap().pairs().is_empty());
+
+        let error = Headers::parse_trailers(b"X-Checksum: abc\r\nX-Other: 1\r\n\r\n",
+                                            Some(&announced)).err().unwrap();
+        assert_eq!(error.kind(), HeaderParseErrorKind::Forbidden);
+        assert_eq!(error.name(), Some("x-other"));
+        assert_eq!(error.snippet(), b"1");
+        // Without a Trailer header, anything goes, bar the forbidden.
+        assert!(Headers::parse_trailers(b"X-Other: 1\r\n", None).is_ok());
+
+        let error = Headers::parse_trailers(b"Content-Length: 5\r\n", None).err().unwrap();
+        assert_eq!(error.kind(), HeaderParseErrorKind::Forbidden);
+        assert_eq!(error.name(), Some("content-length"));
+        let announced = Trailer::new(&["Content-Length"]).unwrap();
+        assert_eq!(kind(Headers::parse_trailers(b"Content-Length: 5\r\n", Some(&announced))),
+                   Some(HeaderParseErrorKind::Forbidden));
+        assert_eq!(kind(Headers::parse_trailers(b"X-Bad\r\n", None)),
+                   Some(HeaderParseErrorKind::Malformed));
+    }
+
+    #[test]
+    fn test_write_to() {
+        let raw = b"Content-Type: text/html\r\nVary: accept\r\nVary: origin\r\n";
+        let headers = Headers::parse_block(raw).unwrap();
+        assert!(headers.get(VARY).is_some());
+        let mut out = vec![];
+        headers.write_to(&mut out).unwrap();
+        assert_eq!(&out[..], &b"content-type: text/html\r\nvary: accept, origin\r\n"[..]);
+
+        let mut headers = headers;
+        headers.set_pseudo(PseudoHeader::Status, "200");
+        let mut out = vec![];
+        assert_eq!(headers.write_to(&mut out).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_write_to_combines_by_marker() {
+        // A list of a combinable type, under a name this crate doesn’t know.
+        define_list_header_marker!(X_CODINGS: AcceptCoding = "x-codings");
+        let raw = b"Accept: text/html\r\nAccept: */*;q=0.1\r\nSet-Cookie: a=1\r\n\
+                    Set-Cookie: b=2\r\nX-Codings: gzip\r\nX-Codings: br\r\n";
+        // Only ever seen raw, so nothing says that the lines may be combined.
+        let headers = Headers::parse_block(raw).unwrap();
+        let mut out = vec![];
+        headers.write_to(&mut out).unwrap();
+        assert_eq!(&out[..], &b"accept: text/html\r\naccept: */*;q=0.1\r\nset-cookie: a=1\r\n\
+                                set-cookie: b=2\r\nx-codings: gzip\r\nx-codings: br\r\n"[..]);
+
+        // Parsed through their markers, the lists are combined, but Set-Cookie is not.
+        assert_eq!(headers.get(ACCEPT).len(), 2);
+        assert_eq!(headers.get(SET_COOKIE).len(), 2);
+        assert_eq!(headers.get(X_CODINGS).len(), 2);
+        let mut out = vec![];
+        headers.write_to(&mut out).unwrap();
+        assert_eq!(&out[..], &b"accept: text/html, */*;q=0.1\r\nset-cookie: a=1\r\n\
+                                set-cookie: b=2\r\nx-codings: gzip, br\r\n"[..]);
+
+        // Set through a marker, likewise, even from raw values.
+        let mut lines = SmallVec::new();
+        lines.push(ByteTendril::from_slice(b"gzip"));
+        lines.push(ByteTendril::from_slice(b"br"));
+        let mut headers = Headers::new();
+        headers.set_raw(X_CODINGS, lines);
+        let mut out = vec![];
+        headers.write_to(&mut out).unwrap();
+        assert_eq!(&out[..], &b"x-codings: gzip, br\r\n"[..]);
+    }
+
+    #[test]
+    fn test_write_to_rejects_line_breaks() {
+        for value in &[&b"text/html\r\nX-Injected: 1"[..], b"text/html\nX: 1", b"text/\0html"] {
+            let mut headers = Headers::parse_block(b"Accept: */*\r\nContent-Type: text/html\r\n")
+                                      .unwrap();
+            headers.get_raw_mut(CONTENT_TYPE).unwrap()[0] = ByteTendril::from_slice(value);
+            let mut out = vec![];
+            assert_eq!(headers.write_to(&mut out).unwrap_err().kind(),
+                       io::ErrorKind::InvalidData);
+            // Not even the headers before it are written.
+            assert!(out.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_write_to_rejects_bad_names() {
+        // As a marker with such a name would store it, in a release build.
+        for name in &["x space", "x-colon:", "x-\r\nx-injected"] {
+            let mut headers = Headers::parse_block(b"Accept: */*\r\n").unwrap();
+            let mut lines = SmallVec::new();
+            lines.push(ByteTendril::from_slice(b"1"));
+            let _ = headers.data_mut().insert((*name).into(), Item::from_raw(lines, false));
+            let mut out = vec![];
+            assert_eq!(headers.write_to(&mut out).unwrap_err().kind(),
+                       io::ErrorKind::InvalidData);
+            assert!(out.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_validate_h2() {
+        let headers = Headers::parse_block(b"Content-Type: text/html\r\nTE: trailers\r\n").unwrap();
+        assert_eq!(headers.validate_h2(), Ok(()));
+
+        let headers = Headers::parse_block(b"Content-Type: text/html\r\nConnection: close\r\n\
+                                             Upgrade: websocket\r\n").unwrap();
+        let error = headers.validate_h2().unwrap_err();
+        assert_eq!(error.kind(), HeaderParseErrorKind::Forbidden);
+        assert_eq!(error.name(), Some("connection"));
+        assert_eq!(error.snippet(), b"close");
+        let headers = Headers::parse_block(b"TE: trailers, deflate\r\n").unwrap();
+        assert_eq!(headers.validate_h2().unwrap_err().kind(), HeaderParseErrorKind::Forbidden);
+
+        define_single_header_marker!(X_SHOUTING: usize = "X-Shouting");
+        let mut headers = Headers::new();
+        headers.set(X_SHOUTING, 1);
+        let error = headers.validate_h2().unwrap_err();
+        assert_eq!(error.kind(), HeaderParseErrorKind::Malformed);
+        assert_eq!(error.name(), Some("X-Shouting"));
+    }
+}
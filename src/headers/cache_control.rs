@@ -0,0 +1,552 @@
+//! The `Cache-Control` (RFC 7234, section 5.2) and `Age` (section 5.1) headers, and the freshness
+//! calculations of RFC 7234, section 4.2, which combine them with `Expires` and `Date`.
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+use std::time::Duration;
+
+use time::Tm;
+
+use grammar::parameters::split_token;
+use grammar::token::is_tchar;
+use grammar::quoted_string;
+use super::{ToHeader, Header, Headers};
+use super::implementations::fmt_decimal;
+use super::date_based::DATE;
+use super::expires::{Expires, EXPIRES};
+
+/// The value used for a delta-seconds which is too large to represent, as RFC 7234, section
+/// 1.2.1 recommends: 2³¹.
+pub const DELTA_SECONDS_MAX: u64 = 2147483648;
+
+/// Parse a delta-seconds value, saturating at `DELTA_SECONDS_MAX`.
+///
+/// RFC 7234 grammar:
+///
+/// ```abnf
+/// delta-seconds = 1*DIGIT
+/// ```
+pub fn parse_delta_seconds(raw: &[u8]) -> Option<u64> {
+    if raw.is_empty() || !raw.iter().all(|&c| c >= b'0' && c <= b'9') {
+        return None;
+    }
+    let mut value = 0u64;
+    for &c in raw {
+        value = value * 10 + (c - b'0') as u64;
+        if value > DELTA_SECONDS_MAX {
+            return Some(DELTA_SECONDS_MAX);
+        }
+    }
+    Some(value)
+}
+
+/// Convert a number of seconds from a delta header into a `Duration`, saturating at
+/// `DELTA_SECONDS_MAX`, as a parsed delta-seconds value would.
+pub fn delta_seconds_to_duration(seconds: u64) -> Duration {
+    Duration::from_secs(if seconds > DELTA_SECONDS_MAX { DELTA_SECONDS_MAX } else { seconds })
+}
+
+/// A cache directive, being one item of the `Cache-Control` header.
+///
+/// RFC 7234 grammar:
+///
+/// ```abnf
+/// Cache-Control   = 1#cache-directive
+/// cache-directive = token [ "=" ( token / quoted-string ) ]
+/// ```
+///
+/// Directive names are case-insensitive; extension names are lowercased. Arguments may be given
+/// as tokens or quoted-strings, whatever the directive.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CacheDirective {
+    /// `max-age=delta-seconds`: the response is fresh for this many seconds (in a response), or
+    /// the client won’t accept a response older than this (in a request).
+    MaxAge(u64),
+    /// `s-maxage=delta-seconds`: `max-age`, for shared caches only, overriding it.
+    SMaxAge(u64),
+    /// `max-stale[=delta-seconds]`: the client will accept a stale response, optionally only if
+    /// stale by no more than this.
+    MaxStale(Option<u64>),
+    /// `min-fresh=delta-seconds`: the client wants a response that will still be fresh for this
+    /// many seconds.
+    MinFresh(u64),
+    /// `no-cache[="field-name, …"]`: a stored response must not be used without revalidation.
+    /// In a response, the argument limits this to the named header fields.
+    NoCache(Option<String>),
+    /// `no-store`: the message must not be stored.
+    NoStore,
+    /// `no-transform`: intermediaries must not transform the payload.
+    NoTransform,
+    /// `only-if-cached`: the client only wants a stored response.
+    OnlyIfCached,
+    /// `must-revalidate`: once stale, the response must not be used without revalidation.
+    MustRevalidate,
+    /// `proxy-revalidate`: `must-revalidate`, for shared caches only.
+    ProxyRevalidate,
+    /// `public`: any cache may store the response.
+    Public,
+    /// `private[="field-name, …"]`: a shared cache must not store the response (or, with the
+    /// argument, the named header fields).
+    Private(Option<String>),
+    /// Any other directive, lowercase, and its argument, unquoted.
+    Extension(String, Option<String>),
+}
+
+impl ToHeader for CacheDirective {
+    fn parse(raw: &[u8]) -> Option<CacheDirective> {
+        let (name, rest) = split_token(raw);
+        if name.is_empty() {
+            return None;
+        }
+        let argument = if rest.is_empty() {
+            None
+        } else if rest[0] != b'=' {
+            return None;
+        } else if rest.get(1) == Some(&b'"') {
+            match quoted_string::unquote(&rest[1..]) {
+                Some(argument) => Some(argument),
+                None => return None,
+            }
+        } else {
+            let (argument, rest) = split_token(&rest[1..]);
+            if argument.is_empty() || !rest.is_empty() {
+                return None;
+            }
+            Some(argument.to_vec())
+        };
+        // Tokens are ASCII, so this can’t fail.
+        let name = unsafe { str::from_utf8_unchecked(name) }.to_ascii_lowercase();
+        let delta = |argument: &Option<Vec<u8>>| {
+            argument.as_ref().and_then(|argument| parse_delta_seconds(argument))
+        };
+        Some(match (&name[..], argument) {
+            ("max-age", ref argument) => match delta(argument) {
+                Some(seconds) => CacheDirective::MaxAge(seconds),
+                None => return None,
+            },
+            ("s-maxage", ref argument) => match delta(argument) {
+                Some(seconds) => CacheDirective::SMaxAge(seconds),
+                None => return None,
+            },
+            ("min-fresh", ref argument) => match delta(argument) {
+                Some(seconds) => CacheDirective::MinFresh(seconds),
+                None => return None,
+            },
+            ("max-stale", None) => CacheDirective::MaxStale(None),
+            ("max-stale", ref argument) => match delta(argument) {
+                Some(seconds) => CacheDirective::MaxStale(Some(seconds)),
+                None => return None,
+            },
+            ("no-store", None) => CacheDirective::NoStore,
+            ("no-transform", None) => CacheDirective::NoTransform,
+            ("only-if-cached", None) => CacheDirective::OnlyIfCached,
+            ("must-revalidate", None) => CacheDirective::MustRevalidate,
+            ("proxy-revalidate", None) => CacheDirective::ProxyRevalidate,
+            ("public", None) => CacheDirective::Public,
+            (_, argument) => {
+                let argument = match argument.map(String::from_utf8) {
+                    Some(Ok(argument)) => Some(argument),
+                    Some(Err(_)) => return None,
+                    None => None,
+                };
+                match &name[..] {
+                    "no-cache" => CacheDirective::NoCache(argument),
+                    "private" => CacheDirective::Private(argument),
+                    _ => CacheDirective::Extension(name, argument),
+                }
+            },
+        })
+    }
+}
+
+/// Write a directive argument, as a token if possible and otherwise as a quoted-string.
+fn fmt_argument(f: &mut fmt::Formatter, argument: &str, force_quotes: bool) -> fmt::Result {
+    if !force_quotes && !argument.is_empty() && argument.bytes().all(is_tchar) {
+        return write!(f, "={}", argument);
+    }
+    match quoted_string::quote(argument.as_bytes()) {
+        // quote only adds ASCII to what was already UTF-8, so this can’t fail.
+        Some(quoted) => write!(f, "={}", unsafe { str::from_utf8_unchecked(&quoted) }),
+        None => Err(fmt::Error),
+    }
+}
+
+impl Header for CacheDirective {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CacheDirective::MaxAge(seconds) => write!(f, "max-age={}", seconds),
+            CacheDirective::SMaxAge(seconds) => write!(f, "s-maxage={}", seconds),
+            CacheDirective::MaxStale(None) => f.write_str("max-stale"),
+            CacheDirective::MaxStale(Some(seconds)) => write!(f, "max-stale={}", seconds),
+            CacheDirective::MinFresh(seconds) => write!(f, "min-fresh={}", seconds),
+            CacheDirective::NoStore => f.write_str("no-store"),
+            CacheDirective::NoTransform => f.write_str("no-transform"),
+            CacheDirective::OnlyIfCached => f.write_str("only-if-cached"),
+            CacheDirective::MustRevalidate => f.write_str("must-revalidate"),
+            CacheDirective::ProxyRevalidate => f.write_str("proxy-revalidate"),
+            CacheDirective::Public => f.write_str("public"),
+            // RFC 7234 says that the field names should be quoted, even if there is only one.
+            CacheDirective::NoCache(ref fields) => {
+                try!(f.write_str("no-cache"));
+                match *fields {
+                    Some(ref fields) => fmt_argument(f, fields, true),
+                    None => Ok(()),
+                }
+            },
+            CacheDirective::Private(ref fields) => {
+                try!(f.write_str("private"));
+                match *fields {
+                    Some(ref fields) => fmt_argument(f, fields, true),
+                    None => Ok(()),
+                }
+            },
+            CacheDirective::Extension(ref name, ref argument) => {
+                try!(f.write_str(name));
+                match *argument {
+                    Some(ref argument) => fmt_argument(f, argument, false),
+                    None => Ok(()),
+                }
+            },
+        }
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+impl CacheDirective {
+    /// The directive’s delta-seconds argument as a `Duration`, for `max-age`, `s-maxage`,
+    /// `min-fresh` and `max-stale` with an argument; `None` for any other directive.
+    pub fn as_duration(&self) -> Option<Duration> {
+        match *self {
+            CacheDirective::MaxAge(seconds) |
+            CacheDirective::SMaxAge(seconds) |
+            CacheDirective::MinFresh(seconds) |
+            CacheDirective::MaxStale(Some(seconds)) => Some(delta_seconds_to_duration(seconds)),
+            _ => None,
+        }
+    }
+}
+
+define_list_header_marker! {
+    /// The `Cache-Control` header.
+    pub CACHE_CONTROL: CacheDirective = "cache-control"
+}
+
+/// The `Age` header: the sender’s estimate of the time since the response was generated or
+/// validated by the origin server, in seconds.
+///
+/// RFC 7234 grammar:
+///
+/// ```abnf
+/// Age = delta-seconds
+/// ```
+///
+/// Values too large to represent saturate at `DELTA_SECONDS_MAX`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Age(pub u64);
+
+impl Age {
+    /// The age as a `Duration`.
+    pub fn as_duration(&self) -> Duration {
+        delta_seconds_to_duration(self.0)
+    }
+}
+
+impl ToHeader for Age {
+    fn parse(raw: &[u8]) -> Option<Age> {
+        parse_delta_seconds(raw).map(Age)
+    }
+}
+
+impl Header for Age {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_decimal(self.0, f)
+    }
+}
+
+define_single_header_marker! {
+    /// The `Age` header.
+    pub AGE: Age = "age"
+}
+
+/// The number of whole seconds from `earlier` to `later`, or zero if `later` is earlier.
+fn seconds_between(earlier: &Tm, later: &Tm) -> u64 {
+    let seconds = (later.to_timespec() - earlier.to_timespec()).num_seconds();
+    if seconds < 0 { 0 } else { seconds as u64 }
+}
+
+/// Compute the freshness lifetime of a response, in seconds, as a private cache would (RFC 7234,
+/// section 4.2.1).
+///
+/// This is the `max-age` directive of `Cache-Control` if present, or else the difference between
+/// `Expires` and `Date` (zero if `Expires` is invalid or not after `Date`). Where the freshness
+/// information is invalid, as with multiple `max-age` directives or multiple `Expires` fields,
+/// it is treated as zero, which RFC 7234 encourages.
+///
+/// `None` means that there is no explicit freshness information, which includes `Expires`
+/// without `Date`. A cache may then use a heuristic freshness lifetime, if the response permits
+/// it; that is left to the caller.
+pub fn freshness_lifetime(headers: &Headers) -> Option<u64> {
+    let max_ages = headers.get(CACHE_CONTROL).iter().filter_map(|directive| match *directive {
+        CacheDirective::MaxAge(seconds) => Some(seconds),
+        _ => None,
+    }).collect::<Vec<u64>>();
+    match max_ages.len() {
+        0 => (),
+        1 => return Some(max_ages[0]),
+        _ => return Some(0),
+    }
+    expires_lifetime(headers).map(|seconds| if seconds < 0 { 0 } else { seconds as u64 })
+}
+
+/// The freshness lifetime given by `Expires`, in seconds: `Expires` less `Date` (RFC 7234,
+/// section 4.2.1), as a cache uses when there is no `max-age`.
+///
+/// This ignores `Cache-Control`; use `freshness_lifetime` for the lifetime a cache should
+/// actually apply. The difference may be negative, where `Expires` is before `Date`. `Expires`
+/// being `Past`, as it is for `0` or any other invalid date, gives zero, for RFC 7234 has such a
+/// response already stale, as do multiple `Expires` fields. `None` means that `Expires` or `Date`
+/// is missing.
+pub fn expires_lifetime(headers: &Headers) -> Option<i64> {
+    if !headers.contains(EXPIRES) {
+        return None;
+    }
+    let date = match headers.get(DATE) {
+        Some(date) => date,
+        None => return None,
+    };
+    // Expires can’t fail to parse, so if there is no value then there are multiple field lines.
+    match headers.get(EXPIRES) {
+        Some(expires) => match *expires {
+            Expires::At(ref expires) => {
+                Some((expires.to_timespec() - date.0.to_timespec()).num_seconds())
+            },
+            Expires::Past => Some(0),
+        },
+        None => Some(0),
+    }
+}
+
+/// Compute the current age of a response, in seconds, per RFC 7234, section 4.2.3.
+///
+/// `request_time` is when the request which produced the response was made, `response_time`
+/// when the response was received, and `now` the current time; they should all come from the
+/// cache’s own clock. The response’s `Date` header is used for the apparent age (taken to be
+/// zero if it is missing or invalid) and its `Age` header for any age already accrued.
+pub fn current_age(headers: &Headers, request_time: Tm, response_time: Tm, now: Tm) -> u64 {
+    let apparent_age = match headers.get(DATE) {
+        Some(date) => seconds_between(&date.0, &response_time),
+        None => 0,
+    };
+    let age_value = headers.get(AGE).map_or(0, |age| age.0);
+    let response_delay = seconds_between(&request_time, &response_time);
+    let corrected_age_value = age_value.saturating_add(response_delay);
+    let corrected_initial_age = if apparent_age > corrected_age_value {
+        apparent_age
+    } else {
+        corrected_age_value
+    };
+    let resident_time = seconds_between(&response_time, &now);
+    corrected_initial_age.saturating_add(resident_time)
+}
+
+/// Determine whether a stored response is stale at `now`, per RFC 7234, section 4.2.
+///
+/// A response is fresh while its freshness lifetime exceeds its current age. Without the request
+/// and response times, the current age is taken as of `now` alone: the greater of the apparent
+/// age from `Date` and the `Age` header. A response with no explicit freshness information is
+/// conservatively treated as stale; a cache wishing to apply a heuristic freshness lifetime
+/// should use `freshness_lifetime` and `current_age` directly.
+pub fn is_stale(headers: &Headers, now: Tm) -> bool {
+    match freshness_lifetime(headers) {
+        Some(lifetime) => lifetime <= current_age(headers, now, now, now),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::time::Duration;
+    use time::{self, Timespec, Tm};
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use super::{CacheDirective, Age, DELTA_SECONDS_MAX, freshness_lifetime, expires_lifetime,
+                current_age, is_stale};
+    use super::CacheDirective::{MaxAge, SMaxAge, MaxStale, MinFresh, NoCache, NoStore, Private,
+                                Public, Extension};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    fn at(seconds: i64) -> Tm {
+        time::at_utc(Timespec::new(seconds, 0))
+    }
+
+    fn headers(block: &[u8]) -> Headers {
+        Headers::parse_block(block).unwrap()
+    }
+
+    #[test]
+    fn test_cache_directive() {
+        eq(b"max-age=3600", MaxAge(3600));
+        eq(b"s-maxage=0", SMaxAge(0));
+        eq(b"max-stale", MaxStale(None));
+        eq(b"max-stale=60", MaxStale(Some(60)));
+        eq(b"no-store", NoStore);
+        eq(b"public", Public);
+        eq(b"no-cache", NoCache(None));
+        eq(b"private=\"set-cookie, x-foo\"", Private(Some("set-cookie, x-foo".to_owned())));
+        eq(b"no-cache=\"set-cookie\"", NoCache(Some("set-cookie".to_owned())));
+        eq(b"community=ucb", Extension("community".to_owned(), Some("ucb".to_owned())));
+        eq(b"foo=\"a b\"", Extension("foo".to_owned(), Some("a b".to_owned())));
+        eq(b"immutable", Extension("immutable".to_owned(), None));
+        assert_eq!(CacheDirective::parse(b"Max-Age=\"60\""), Some(MaxAge(60)));
+        assert_eq!(CacheDirective::parse(b"no-cache=set-cookie"),
+                   Some(NoCache(Some("set-cookie".to_owned()))));
+        assert_eq!(CacheDirective::parse(b"public=yes"),
+                   Some(Extension("public".to_owned(), Some("yes".to_owned()))));
+        assert_eq!(CacheDirective::parse(b"max-age=99999999999999999999999"),
+                   Some(MaxAge(DELTA_SECONDS_MAX)));
+        bad::<CacheDirective>(b"max-age");
+        bad::<CacheDirective>(b"max-age=-1");
+        bad::<CacheDirective>(b"max-age=1 2");
+        bad::<CacheDirective>(b"=1");
+        bad::<CacheDirective>(b"foo=");
+    }
+
+    #[test]
+    fn test_age() {
+        eq(b"0", Age(0));
+        eq(b"86400", Age(86400));
+        assert_eq!(Age::parse(b"4294967296"), Some(Age(DELTA_SECONDS_MAX)));
+        bad::<Age>(b"");
+        bad::<Age>(b"+1");
+        bad::<Age>(b"1.5");
+    }
+
+    #[test]
+    fn test_as_duration() {
+        assert_eq!(MaxAge(3600).as_duration(), Some(Duration::from_secs(3600)));
+        assert_eq!(SMaxAge(0).as_duration(), Some(Duration::from_secs(0)));
+        assert_eq!(MinFresh(60).as_duration(), Some(Duration::from_secs(60)));
+        assert_eq!(MaxStale(Some(30)).as_duration(), Some(Duration::from_secs(30)));
+        assert_eq!(MaxStale(None).as_duration(), None);
+        assert_eq!(NoStore.as_duration(), None);
+        // Too large a max-age saturates, whether parsed or constructed.
+        let max = Some(Duration::from_secs(DELTA_SECONDS_MAX));
+        let parsed = CacheDirective::parse(b"max-age=99999999999999999999999").unwrap();
+        assert_eq!(parsed.as_duration(), max);
+        assert_eq!(MaxAge(!0).as_duration(), max);
+        assert_eq!(Age(86400).as_duration(), Duration::from_secs(86400));
+        assert_eq!(Age(!0).as_duration(), Duration::from_secs(DELTA_SECONDS_MAX));
+    }
+
+    #[test]
+    fn test_freshness_lifetime() {
+        // max-age takes precedence over Expires.
+        assert_eq!(freshness_lifetime(&headers(b"Cache-Control: public, max-age=600\r\n\
+                                                 Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n\
+                                                 Expires: Thu, 01 Dec 1994 17:00:00 GMT\r\n")),
+                   Some(600));
+        assert_eq!(freshness_lifetime(&headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n\
+                                                 Expires: Thu, 01 Dec 1994 17:00:00 GMT\r\n")),
+                   Some(3600));
+        // Expires before Date, or invalid, means already stale.
+        assert_eq!(freshness_lifetime(&headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n\
+                                                 Expires: Thu, 01 Dec 1994 15:00:00 GMT\r\n")),
+                   Some(0));
+        assert_eq!(freshness_lifetime(&headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n\
+                                                 Expires: 0\r\n")),
+                   Some(0));
+        // As do conflicting values.
+        assert_eq!(freshness_lifetime(&headers(b"Cache-Control: max-age=60, max-age=600\r\n")),
+                   Some(0));
+        // And no information is no information.
+        assert_eq!(freshness_lifetime(&headers(b"Cache-Control: no-cache\r\n")), None);
+        assert_eq!(freshness_lifetime(&headers(b"Expires: Thu, 01 Dec 1994 17:00:00 GMT\r\n")),
+                   None);
+        assert_eq!(freshness_lifetime(&Headers::new()), None);
+    }
+
+    #[test]
+    fn test_expires_lifetime() {
+        assert_eq!(expires_lifetime(&headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n\
+                                               Expires: Thu, 01 Dec 1994 17:00:00 GMT\r\n")),
+                   Some(3600));
+        // Unlike freshness_lifetime, this may be negative, and ignores max-age.
+        assert_eq!(expires_lifetime(&headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n\
+                                               Cache-Control: max-age=600\r\n\
+                                               Expires: Thu, 01 Dec 1994 15:00:00 GMT\r\n")),
+                   Some(-3600));
+        // Past, however written, is zero.
+        for expires in &["0", "-1", "Thu, 01 Dec 1994"] {
+            let block = format!("Date: Thu, 01 Dec 1994 16:00:00 GMT\r\nExpires: {}\r\n", expires);
+            assert_eq!(expires_lifetime(&headers(block.as_bytes())), Some(0));
+        }
+        assert_eq!(expires_lifetime(&headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n\
+                                               Expires: Thu, 01 Dec 1994 17:00:00 GMT\r\n\
+                                               Expires: Thu, 01 Dec 1994 18:00:00 GMT\r\n")),
+                   Some(0));
+        assert_eq!(expires_lifetime(&headers(b"Expires: Thu, 01 Dec 1994 17:00:00 GMT\r\n")),
+                   None);
+        assert_eq!(expires_lifetime(&headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n")),
+                   None);
+    }
+
+    #[test]
+    fn test_current_age() {
+        // Date: Thu, 01 Dec 1994 16:00:00 GMT is 786297600.
+        let date = 786297600;
+        // A response straight from the origin server, received two seconds after the request
+        // and ten seconds after its Date (the origin server’s clock being slow): the apparent
+        // age wins.
+        let response = headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n");
+        assert_eq!(current_age(&response, at(date + 8), at(date + 10), at(date + 10)), 10);
+        // Thirty seconds later, that has accrued thirty seconds of resident time.
+        assert_eq!(current_age(&response, at(date + 8), at(date + 10), at(date + 40)), 40);
+        // From an intermediate cache, with an Age exceeding the apparent age: the corrected age
+        // value (Age plus the response delay) wins.
+        let response = headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\nAge: 100\r\n");
+        assert_eq!(current_age(&response, at(date + 8), at(date + 10), at(date + 10)), 102);
+        assert_eq!(current_age(&response, at(date + 8), at(date + 10), at(date + 70)), 162);
+        // A Date from the future (the origin server’s clock being fast) is no negative age.
+        let response = headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n");
+        assert_eq!(current_age(&response, at(date - 60), at(date - 59), at(date - 59)), 1);
+        // Without Date, only the Age and the response delay count.
+        let response = headers(b"Age: 5\r\n");
+        assert_eq!(current_age(&response, at(date), at(date + 1), at(date + 2)), 7);
+    }
+
+    #[test]
+    fn test_is_stale() {
+        // Date: Thu, 01 Dec 1994 16:00:00 GMT is 786297600.
+        let date = 786297600;
+        let response = headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n\
+                                 Cache-Control: max-age=60\r\n");
+        assert!(!is_stale(&response, at(date)));
+        assert!(!is_stale(&response, at(date + 59)));
+        assert!(is_stale(&response, at(date + 60)));
+        assert!(is_stale(&response, at(date + 3600)));
+        // Age counts towards the current age even when Date is recent.
+        let response = headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\nAge: 60\r\n\
+                                 Cache-Control: max-age=60\r\n");
+        assert!(is_stale(&response, at(date)));
+        // Expires works too.
+        let response = headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n\
+                                 Expires: Thu, 01 Dec 1994 17:00:00 GMT\r\n");
+        assert!(!is_stale(&response, at(date + 1800)));
+        assert!(is_stale(&response, at(date + 3600)));
+        // Without freshness information, a response is stale.
+        assert!(is_stale(&headers(b"Date: Thu, 01 Dec 1994 16:00:00 GMT\r\n"), at(date)));
+        assert!(is_stale(&Headers::new(), at(date)));
+    }
+}
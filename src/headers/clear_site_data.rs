@@ -0,0 +1,200 @@
+//! The `Clear-Site-Data` header (W3C Clear Site Data), with which a response asks the user agent
+//! to remove locally stored data for the response’s origin.
+
+use std::fmt;
+use std::str;
+
+use grammar::quoted_string;
+use super::{ToHeader, Header, Headers};
+
+/// The kind of data to clear, being one item of the `Clear-Site-Data` header.
+///
+/// Grammar:
+///
+/// ```abnf
+/// Clear-Site-Data = 1#( quoted-string )
+/// ```
+///
+/// Each type is a quoted-string, and types are matched case-sensitively once unquoted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SiteDataType {
+    /// `"cache"`: locally cached data, such as the HTTP cache.
+    Cache,
+    /// `"cookies"`: cookies, and authentication data such as cached credentials.
+    Cookies,
+    /// `"storage"`: DOM storage, such as `localStorage`, IndexedDB and service worker
+    /// registrations.
+    Storage,
+    /// `"executionContexts"`: reload all browsing contexts for the origin.
+    ExecutionContexts,
+    /// `"*"`: all of the above, and any types defined in the future.
+    All,
+    /// Any other type, unquoted. User agents ignore types they don’t recognise.
+    Other(String),
+}
+
+impl SiteDataType {
+    /// The unquoted name of the type.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            SiteDataType::Cache => "cache",
+            SiteDataType::Cookies => "cookies",
+            SiteDataType::Storage => "storage",
+            SiteDataType::ExecutionContexts => "executionContexts",
+            SiteDataType::All => "*",
+            SiteDataType::Other(ref name) => name,
+        }
+    }
+}
+
+impl ToHeader for SiteDataType {
+    fn parse(raw: &[u8]) -> Option<SiteDataType> {
+        let name = match quoted_string::unquote(raw) {
+            Some(name) => name,
+            None => return None,
+        };
+        Some(match &name[..] {
+            b"cache" => SiteDataType::Cache,
+            b"cookies" => SiteDataType::Cookies,
+            b"storage" => SiteDataType::Storage,
+            b"executionContexts" => SiteDataType::ExecutionContexts,
+            b"*" => SiteDataType::All,
+            _ => match String::from_utf8(name) {
+                Ok(name) => SiteDataType::Other(name),
+                Err(_) => return None,
+            },
+        })
+    }
+}
+
+impl Header for SiteDataType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match quoted_string::quote(self.as_str().as_bytes()) {
+            // quote only adds ASCII to what was already UTF-8, so this can’t fail.
+            Some(quoted) => f.write_str(unsafe { str::from_utf8_unchecked(&quoted) }),
+            None => Err(fmt::Error),
+        }
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_list_header_marker! {
+    /// The `Clear-Site-Data` header: the types of data which the user agent should clear.
+    pub CLEAR_SITE_DATA: SiteDataType = "clear-site-data"
+}
+
+/// The set of site data types which a response asks to be cleared.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClearSiteData {
+    types: Vec<SiteDataType>,
+}
+
+impl ClearSiteData {
+    /// Construct a set of types to clear. Duplicates are discarded; otherwise, order is preserved.
+    pub fn new<I: IntoIterator<Item = SiteDataType>>(types: I) -> ClearSiteData {
+        let mut clear_site_data = ClearSiteData { types: vec![] };
+        for what in types {
+            if !clear_site_data.types.contains(&what) {
+                clear_site_data.types.push(what);
+            }
+        }
+        clear_site_data
+    }
+
+    /// Read the set from a message’s `Clear-Site-Data` header.
+    ///
+    /// If there is no `Clear-Site-Data` header, the set will be empty.
+    pub fn from_headers(headers: &Headers) -> ClearSiteData {
+        ClearSiteData::new(headers.get(CLEAR_SITE_DATA).into_owned())
+    }
+
+    /// Whether the given type of data is to be cleared, either by name or by the `"*"` wildcard.
+    ///
+    /// `clears(&SiteDataType::All)` is only true if the wildcard itself was given.
+    pub fn clears(&self, what: &SiteDataType) -> bool {
+        self.types.iter().any(|t| t == what || (*t == SiteDataType::All && !is_other(what)))
+    }
+
+    /// The types in the set.
+    pub fn types(&self) -> &[SiteDataType] {
+        &self.types
+    }
+
+    /// Set the `Clear-Site-Data` header of the given header collection to this set.
+    pub fn set_clear_site_data(&self, headers: &mut Headers) {
+        headers.set(CLEAR_SITE_DATA, self.types.clone());
+    }
+}
+
+/// Unrecognised types aren’t covered by the wildcard, for they mean nothing to a user agent.
+fn is_other(what: &SiteDataType) -> bool {
+    match *what {
+        SiteDataType::Other(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use smallvec::SmallVec;
+    use tendril::ByteTendril;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use super::{ClearSiteData, SiteDataType, CLEAR_SITE_DATA};
+    use super::SiteDataType::{Cache, Cookies, Storage, ExecutionContexts, All, Other};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    fn from_raw(raw: &[u8]) -> ClearSiteData {
+        let mut headers = Headers::new();
+        let mut value = SmallVec::new();
+        value.push(ByteTendril::from_slice(raw));
+        headers.set_raw(CLEAR_SITE_DATA, value);
+        ClearSiteData::from_headers(&headers)
+    }
+
+    #[test]
+    fn test_site_data_type() {
+        eq(b"\"cache\"", Cache);
+        eq(b"\"executionContexts\"", ExecutionContexts);
+        eq(b"\"*\"", All);
+        eq(b"\"x-\\\"y\\\"\"", Other("x-\"y\"".to_owned()));
+        assert_eq!(SiteDataType::parse(b"\"Cache\""), Some(Other("Cache".to_owned())));
+        bad::<SiteDataType>(b"cache");
+        bad::<SiteDataType>(b"\"cache");
+    }
+
+    #[test]
+    fn test_clears() {
+        let clear_site_data = from_raw(b"\"cache\", \"cookies\"");
+        assert_eq!(clear_site_data.types(), &[Cache, Cookies]);
+        assert!(clear_site_data.clears(&Cache));
+        assert!(clear_site_data.clears(&Cookies));
+        assert!(!clear_site_data.clears(&Storage));
+        assert!(!clear_site_data.clears(&All));
+
+        let clear_site_data = from_raw(b"\"*\"");
+        assert!(clear_site_data.clears(&Cache));
+        assert!(clear_site_data.clears(&Storage));
+        assert!(clear_site_data.clears(&ExecutionContexts));
+        assert!(clear_site_data.clears(&All));
+        assert!(!clear_site_data.clears(&Other("foo".to_owned())));
+
+        assert_eq!(ClearSiteData::from_headers(&Headers::new()).types(), &[]);
+        assert_eq!(from_raw(b"cache, \"storage\"").types(), &[Storage]);
+
+        let mut headers = Headers::new();
+        ClearSiteData::new(vec![Cache, Storage, Cache]).set_clear_site_data(&mut headers);
+        assert_eq!(&headers.get_raw(CLEAR_SITE_DATA).unwrap()[0][..], b"\"cache\", \"storage\"");
+    }
+}
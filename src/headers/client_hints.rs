@@ -0,0 +1,125 @@
+//! The `Accept-CH` header (RFC 8942, section 3.1), with which a server asks for client hints.
+
+use std::ascii::AsciiExt;
+use std::fmt;
+
+use super::{ToHeader, Header};
+use super::sf::{self, BareItem, Member};
+
+/// The `Accept-CH` header: the client hints which the server would like in subsequent requests.
+///
+/// This is a structured field list of tokens, each naming a client hint header field, such as
+/// `Sec-CH-UA`, `DPR` or `Width`. Being field names, they are lowercased, and duplicates are
+/// discarded; parameters are ignored.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AcceptCh {
+    hints: Vec<String>,
+}
+
+impl AcceptCh {
+    /// Construct a set of requested hints. Returns `None` if any hint is not a valid token.
+    pub fn new<I: IntoIterator<Item = S>, S: AsRef<str>>(hints: I) -> Option<AcceptCh> {
+        let mut accept_ch = AcceptCh { hints: vec![] };
+        for hint in hints {
+            let hint = hint.as_ref();
+            match sf::parse_item(hint.as_bytes()) {
+                Some((BareItem::Token(_), ref parameters)) if parameters.is_empty() => {
+                    accept_ch.push(hint);
+                },
+                _ => return None,
+            }
+        }
+        Some(accept_ch)
+    }
+
+    fn push(&mut self, hint: &str) {
+        if !self.requests(hint) {
+            self.hints.push(hint.to_ascii_lowercase());
+        }
+    }
+
+    /// Whether the server requests the client hint with the header field name given, in any case.
+    pub fn requests(&self, hint: &str) -> bool {
+        self.hints.iter().any(|requested| requested.eq_ignore_ascii_case(hint))
+    }
+
+    /// The hints requested, lowercase.
+    pub fn hints(&self) -> &[String] {
+        &self.hints
+    }
+}
+
+impl ToHeader for AcceptCh {
+    fn parse(raw: &[u8]) -> Option<AcceptCh> {
+        let list = match sf::parse_list(raw) {
+            Some(list) => list,
+            None => return None,
+        };
+        let mut accept_ch = AcceptCh { hints: vec![] };
+        for member in list {
+            match member {
+                Member::Item(BareItem::Token(ref hint), _) => accept_ch.push(hint),
+                _ => return None,
+            }
+        }
+        Some(accept_ch)
+    }
+}
+
+impl Header for AcceptCh {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = self.hints.iter()
+                             .map(|hint| Member::Item(BareItem::Token(hint.clone()), vec![]))
+                             .collect();
+        sf::fmt_list(f, &list)
+    }
+}
+
+define_single_header_marker! {
+    /// The `Accept-CH` header. Being a structured field list, it may be split across field lines,
+    /// which are combined (RFC 8941, section 4.2).
+    pub ACCEPT_CH: AcceptCh = "accept-ch", combine
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use super::{AcceptCh, ACCEPT_CH};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_accept_ch() {
+        let accept_ch = AcceptCh::parse(b"Sec-CH-UA-Mobile, Viewport-Width").unwrap();
+        assert!(accept_ch.requests("Sec-CH-UA-Mobile"));
+        assert!(accept_ch.requests("sec-ch-ua-mobile"));
+        assert!(accept_ch.requests("VIEWPORT-WIDTH"));
+        assert!(!accept_ch.requests("Sec-CH-UA"));
+        assert!(!accept_ch.requests("Width"));
+        assert_eq!(accept_ch, AcceptCh::new(&["sec-ch-ua-mobile", "viewport-width"]).unwrap());
+
+        eq(b"sec-ch-ua, dpr, width", AcceptCh::new(&["Sec-CH-UA", "DPR", "Width"]).unwrap());
+        eq(b"", AcceptCh::new(&[] as &[&str]).unwrap());
+        assert_eq!(AcceptCh::parse(b"DPR, dpr;x=1"), AcceptCh::new(&["dpr"]));
+        assert_eq!(AcceptCh::new(&["viewport width"]), None);
+        bad::<AcceptCh>(b"\"DPR\"");
+        bad::<AcceptCh>(b"(DPR Width)");
+        bad::<AcceptCh>(b"DPR,");
+    }
+
+    #[test]
+    fn test_several_field_lines() {
+        let headers = Headers::parse_block(b"Accept-CH: Sec-CH-UA-Mobile\r\n\
+                                             Accept-CH: Viewport-Width, DPR\r\n").unwrap();
+        assert_eq!(**headers.get(ACCEPT_CH).unwrap(),
+                   AcceptCh::new(&["sec-ch-ua-mobile", "viewport-width", "dpr"]).unwrap());
+    }
+}
@@ -0,0 +1,233 @@
+//! Conditional requests (RFC 7232): the `If-Modified-Since` and `If-Unmodified-Since` headers,
+//! and evaluating the preconditions of a request against the state of the target resource.
+
+use std::cmp::Ordering;
+
+use time::Tm;
+
+use method::{Method, Get, Head};
+use super::Headers;
+use super::date_based::{HttpDate, cmp_http_dates};
+use super::etag::{EntityTag, EntityTagMatch, IF_MATCH, IF_NONE_MATCH};
+
+define_single_header_marker! {
+    /// The `If-Modified-Since` header (RFC 7232, section 3.3): perform a `GET` or `HEAD` only if
+    /// the selected representation has been modified since this date.
+    pub IF_MODIFIED_SINCE: HttpDate = "if-modified-since"
+}
+
+define_single_header_marker! {
+    /// The `If-Unmodified-Since` header (RFC 7232, section 3.4): perform the request only if the
+    /// selected representation has not been modified since this date.
+    pub IF_UNMODIFIED_SINCE: HttpDate = "if-unmodified-since"
+}
+
+/// The outcome of evaluating the preconditions of a request.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Precondition {
+    /// There are no preconditions, or they all hold: perform the method as usual.
+    Proceed,
+    /// A `GET` or `HEAD` whose cached representation is still current: respond with
+    /// `304 Not Modified`.
+    NotModified,
+    /// A precondition failed: respond with `412 Precondition Failed`, without performing the
+    /// method.
+    PreconditionFailed,
+}
+
+/// Whether the request’s `If-Match` or `If-None-Match` list matches the current entity-tag.
+///
+/// `*` matches if there is a current representation at all, which is taken to be so if it has
+/// either validator.
+fn tag_matches(list: &[EntityTagMatch], etag: Option<&EntityTag>, exists: bool,
+               cmp: fn(&EntityTag, &EntityTag) -> bool) -> bool {
+    list.iter().any(|item| match *item {
+        EntityTagMatch::Any => exists,
+        EntityTagMatch::Tag(ref tag) => etag.map_or(false, |etag| cmp(tag, etag)),
+    })
+}
+
+/// Whether the resource was modified after the given date, to the second.
+///
+/// HTTP-dates have a resolution of a second, so a modification time with a fraction of a second
+/// would otherwise always be after the date it was sent out as.
+fn modified_since(last_modified: &Tm, date: &HttpDate) -> bool {
+    cmp_http_dates(last_modified, &date.0) == Ordering::Greater
+}
+
+/// Evaluate the preconditions of a request (RFC 7232, section 6), given the current entity-tag and
+/// modification date of the selected representation, if it has them.
+///
+/// The headers are evaluated in the order RFC 7232 requires, each step only happening if the one
+/// before it didn’t decide the matter:
+///
+/// 1. `If-Match`, by strong comparison; if it fails, `PreconditionFailed`;
+/// 2. if there is no `If-Match`, `If-Unmodified-Since`; if modified since, `PreconditionFailed`;
+/// 3. `If-None-Match`, by weak comparison; if any matches, `NotModified` for `GET` and `HEAD`, or
+///    `PreconditionFailed` for any other method;
+/// 4. if there is no `If-None-Match` and the method is `GET` or `HEAD`, `If-Modified-Since`; if
+///    not modified since, `NotModified`.
+///
+/// A date header that doesn’t parse is ignored, as is either date header if there is no
+/// `last_modified`. An `If-Match` with no valid entity-tags matches nothing, so fails.
+///
+/// `If-Range` is not evaluated here: it only affects whether a `Range` is honoured, which the
+/// range handling should decide after this returns `Proceed`. Nor is the RFC’s allowance for
+/// answering a failed `If-Match` or `If-Unmodified-Since` with a success status, where the state
+/// change has already happened, which only the handler can know.
+pub fn evaluate(request: &Headers, etag: Option<&EntityTag>, last_modified: Option<&Tm>,
+                method: &Method) -> Precondition {
+    let exists = etag.is_some() || last_modified.is_some();
+
+    if request.contains(IF_MATCH) {
+        if !tag_matches(&request.get(IF_MATCH), etag, exists, EntityTag::strong_eq) {
+            return Precondition::PreconditionFailed;
+        }
+    } else if let (Some(date), Some(last_modified)) = (request.get(IF_UNMODIFIED_SINCE),
+                                                       last_modified) {
+        if modified_since(last_modified, &date) {
+            return Precondition::PreconditionFailed;
+        }
+    }
+
+    let get_or_head = *method == Get || *method == Head;
+    if request.contains(IF_NONE_MATCH) {
+        if tag_matches(&request.get(IF_NONE_MATCH), etag, exists, EntityTag::weak_eq) {
+            return if get_or_head {
+                Precondition::NotModified
+            } else {
+                Precondition::PreconditionFailed
+            };
+        }
+    } else if get_or_head {
+        if let (Some(date), Some(last_modified)) = (request.get(IF_MODIFIED_SINCE),
+                                                    last_modified) {
+            if !modified_since(last_modified, &date) {
+                return Precondition::NotModified;
+            }
+        }
+    }
+
+    Precondition::Proceed
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{self, Timespec, Tm};
+    use headers::Headers;
+    use headers::etag::EntityTag;
+    use method::{Method, Get, Head, Post, Put, Delete};
+    use super::{evaluate, Precondition};
+    use super::Precondition::{Proceed, NotModified, PreconditionFailed};
+
+    // Sun, 06 Nov 1994 08:49:37 GMT, and a little after.
+    const MODIFIED: &'static [u8] = b"Sun, 06 Nov 1994 08:49:37 GMT";
+    const BEFORE: &'static [u8] = b"Sun, 06 Nov 1994 08:49:36 GMT";
+    const AFTER: &'static [u8] = b"Sun, 06 Nov 1994 08:49:38 GMT";
+
+    fn last_modified() -> Tm {
+        time::at_utc(Timespec::new(784111777, 500_000_000))
+    }
+
+    fn eval(lines: &[(&str, &[u8])], method: Method) -> Precondition {
+        let mut headers = Headers::new();
+        for &(name, value) in lines {
+            headers.append_raw(name, value).unwrap();
+        }
+        let etag = EntityTag::strong(b"v1").unwrap();
+        evaluate(&headers, Some(&etag), Some(&last_modified()), &method)
+    }
+
+    #[test]
+    fn test_unconditional() {
+        assert_eq!(eval(&[], Get), Proceed);
+        assert_eq!(eval(&[], Put), Proceed);
+        assert_eq!(evaluate(&Headers::new(), None, None, &Delete), Proceed);
+    }
+
+    #[test]
+    fn test_if_match() {
+        assert_eq!(eval(&[("If-Match", b"\"v1\"")], Put), Proceed);
+        assert_eq!(eval(&[("If-Match", b"\"v0\", \"v1\"")], Put), Proceed);
+        assert_eq!(eval(&[("If-Match", b"*")], Put), Proceed);
+        assert_eq!(eval(&[("If-Match", b"\"v0\"")], Put), PreconditionFailed);
+        // If-Match uses strong comparison.
+        assert_eq!(eval(&[("If-Match", b"W/\"v1\"")], Put), PreconditionFailed);
+        assert_eq!(eval(&[("If-Match", b"bogus")], Put), PreconditionFailed);
+        // `*` fails if there is no current representation.
+        let mut headers = Headers::new();
+        headers.append_raw("If-Match", b"*").unwrap();
+        assert_eq!(evaluate(&headers, None, None, &Put), PreconditionFailed);
+    }
+
+    #[test]
+    fn test_if_unmodified_since() {
+        assert_eq!(eval(&[("If-Unmodified-Since", MODIFIED)], Put), Proceed);
+        assert_eq!(eval(&[("If-Unmodified-Since", AFTER)], Put), Proceed);
+        assert_eq!(eval(&[("If-Unmodified-Since", BEFORE)], Put), PreconditionFailed);
+        assert_eq!(eval(&[("If-Unmodified-Since", b"yesterday")], Put), Proceed);
+        // Without a modification date, it is ignored.
+        let mut headers = Headers::new();
+        headers.append_raw("If-Unmodified-Since", BEFORE).unwrap();
+        assert_eq!(evaluate(&headers, None, None, &Put), Proceed);
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        assert_eq!(eval(&[("If-None-Match", b"\"v0\"")], Get), Proceed);
+        assert_eq!(eval(&[("If-None-Match", b"\"v1\"")], Get), NotModified);
+        assert_eq!(eval(&[("If-None-Match", b"\"v1\"")], Head), NotModified);
+        // If-None-Match uses weak comparison.
+        assert_eq!(eval(&[("If-None-Match", b"W/\"v1\"")], Get), NotModified);
+        // For other methods, a match is a failure; `If-None-Match: *` is how a client asks that
+        // a PUT not overwrite an existing resource.
+        assert_eq!(eval(&[("If-None-Match", b"\"v1\"")], Post), PreconditionFailed);
+        assert_eq!(eval(&[("If-None-Match", b"*")], Put), PreconditionFailed);
+        let mut headers = Headers::new();
+        headers.append_raw("If-None-Match", b"*").unwrap();
+        assert_eq!(evaluate(&headers, None, None, &Put), Proceed);
+    }
+
+    #[test]
+    fn test_if_modified_since() {
+        assert_eq!(eval(&[("If-Modified-Since", MODIFIED)], Get), NotModified);
+        assert_eq!(eval(&[("If-Modified-Since", AFTER)], Head), NotModified);
+        assert_eq!(eval(&[("If-Modified-Since", BEFORE)], Get), Proceed);
+        // Only for GET and HEAD.
+        assert_eq!(eval(&[("If-Modified-Since", MODIFIED)], Post), Proceed);
+
+        // A modification time in another timezone is compared by the instant it stands for.
+        let mut local = last_modified();
+        local.tm_hour += 1;
+        local.tm_utcoff = 3600;
+        let mut headers = Headers::new();
+        headers.append_raw("If-Modified-Since", MODIFIED).unwrap();
+        assert_eq!(evaluate(&headers, None, Some(&local), &Get), NotModified);
+        let mut headers = Headers::new();
+        headers.append_raw("If-Modified-Since", BEFORE).unwrap();
+        assert_eq!(evaluate(&headers, None, Some(&local), &Get), Proceed);
+    }
+
+    #[test]
+    fn test_evaluation_order() {
+        // Step 1 before step 3: a failed If-Match is 412 even if If-None-Match would be 304.
+        assert_eq!(eval(&[("If-Match", b"\"v0\""), ("If-None-Match", b"\"v1\"")], Get),
+                   PreconditionFailed);
+        // Step 2 is skipped when If-Match is present, even if it would fail.
+        assert_eq!(eval(&[("If-Match", b"\"v1\""), ("If-Unmodified-Since", BEFORE)], Put),
+                   Proceed);
+        // Step 2 before step 3.
+        assert_eq!(eval(&[("If-Unmodified-Since", BEFORE), ("If-None-Match", b"\"v1\"")], Get),
+                   PreconditionFailed);
+        // Step 4 is skipped when If-None-Match is present: a changed tag wins over an old date.
+        assert_eq!(eval(&[("If-None-Match", b"\"v0\""), ("If-Modified-Since", MODIFIED)], Get),
+                   Proceed);
+        // And a matching tag wins over a newer date.
+        assert_eq!(eval(&[("If-None-Match", b"\"v1\""), ("If-Modified-Since", BEFORE)], Get),
+                   NotModified);
+        // All four, all passing through to 304.
+        assert_eq!(eval(&[("If-Match", b"*"), ("If-Unmodified-Since", BEFORE),
+                          ("If-None-Match", b"\"v1\""), ("If-Modified-Since", BEFORE)], Get),
+                   NotModified);
+    }
+}
@@ -0,0 +1,83 @@
+//! The `Connection` header (RFC 7230, section 6.1).
+
+use std::ascii::AsciiExt;
+use std::fmt;
+
+use grammar::token::Token;
+use super::{ToHeader, Header};
+
+/// A connection option, being one item of the `Connection` header.
+///
+/// RFC 7230 grammar:
+///
+/// ```abnf
+/// Connection        = 1#connection-option
+/// connection-option = token
+/// ```
+///
+/// Connection options are case-insensitive. Apart from `close`, they are normally the names of
+/// other header fields which are specific to the current connection and which must not be
+/// forwarded by proxies; see `Headers::remove_hop_by_hop`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Connection {
+    /// `close`: the sender will close the connection after the current request or response.
+    Close,
+    /// Any other connection option, with its case preserved.
+    Other(Token<'static>),
+}
+
+impl ToHeader for Connection {
+    fn parse(raw: &[u8]) -> Option<Connection> {
+        if raw.eq_ignore_ascii_case(b"close") {
+            Some(Connection::Close)
+        } else if raw.is_empty() {
+            None
+        } else {
+            Token::from_slice(raw).map(|token| Connection::Other(token.into_owned()))
+        }
+    }
+}
+
+impl Header for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Connection::Close => f.write_str("close"),
+            Connection::Other(ref token) => f.write_str(token),
+        }
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_list_header_marker! {
+    /// The `Connection` header, a list of `Connection` options.
+    pub CONNECTION: Connection = "connection"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use grammar::token::Token;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter};
+    use super::Connection;
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_connection() {
+        eq(b"close", Connection::Close);
+        assert_eq!(Connection::parse(b"Close"), Some(Connection::Close));
+        eq(b"X-Custom", Connection::Other(Token::from_slice(b"X-Custom").unwrap().into_owned()));
+        bad::<Connection>(b"");
+        bad::<Connection>(b"foo bar");
+    }
+}
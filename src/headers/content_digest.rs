@@ -0,0 +1,259 @@
+//! The `Content-Digest` and `Want-Content-Digest` headers (RFC 9530).
+
+use std::fmt;
+
+use super::{ToHeader, Header};
+use super::sf::{self, BareItem, Member};
+
+/// The length in bytes of a digest produced by the algorithm, if it is one that is known.
+///
+/// Only the algorithms which RFC 9530 registers as active, `sha-256` and `sha-512`, are known.
+pub fn digest_length(algorithm: &str) -> Option<usize> {
+    match algorithm {
+        "sha-256" => Some(32),
+        "sha-512" => Some(64),
+        _ => None,
+    }
+}
+
+/// The `Content-Digest` header: digests of the message content, keyed by algorithm.
+///
+/// This is a structured field dictionary of byte sequences, for example
+/// `sha-256=:47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=:`.
+///
+/// Digests for unknown algorithms are kept, as they are opaque anyway, and their lengths are not
+/// checked; a digest which is not a byte sequence, or whose length is wrong for a known algorithm,
+/// makes the header invalid.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContentDigest {
+    digests: Vec<(String, Vec<u8>)>,
+}
+
+impl ContentDigest {
+    /// Construct a `Content-Digest` from algorithm and digest pairs. Returns `None` if an
+    /// algorithm is repeated or is not a valid dictionary key, or a length is wrong.
+    pub fn new<I: IntoIterator<Item = (String, Vec<u8>)>>(digests: I) -> Option<ContentDigest> {
+        let mut content_digest = ContentDigest { digests: vec![] };
+        for (algorithm, digest) in digests {
+            if !sf::is_key(algorithm.as_bytes()) || content_digest.digest(&algorithm).is_some() ||
+                    digest_length(&algorithm).map_or(false, |length| length != digest.len()) {
+                return None;
+            }
+            content_digest.digests.push((algorithm, digest));
+        }
+        Some(content_digest)
+    }
+
+    /// The algorithm and digest pairs, in order.
+    pub fn digests(&self) -> &[(String, Vec<u8>)] {
+        &self.digests
+    }
+
+    /// The digest for the given algorithm, if there is one.
+    pub fn digest(&self, algorithm: &str) -> Option<&[u8]> {
+        self.digests.iter()
+            .find(|&&(ref a, _)| *a == algorithm)
+            .map(|&(_, ref digest)| &digest[..])
+    }
+}
+
+impl ToHeader for ContentDigest {
+    fn parse(raw: &[u8]) -> Option<ContentDigest> {
+        let dictionary = match sf::parse_dictionary(raw) {
+            Some(dictionary) => dictionary,
+            None => return None,
+        };
+        let mut digests = vec![];
+        for (algorithm, member) in dictionary {
+            match member {
+                Member::Item(BareItem::ByteSequence(digest), _) => {
+                    digests.push((algorithm, digest));
+                },
+                _ => return None,
+            }
+        }
+        // Keys from a dictionary are unique and valid, so this only checks the lengths.
+        ContentDigest::new(digests)
+    }
+}
+
+impl Header for ContentDigest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dictionary = self.digests.iter().map(|&(ref algorithm, ref digest)| {
+            (algorithm.clone(), Member::Item(BareItem::ByteSequence(digest.clone()), vec![]))
+        }).collect();
+        sf::fmt_dictionary(f, &dictionary)
+    }
+}
+
+define_single_header_marker! {
+    /// The `Content-Digest` header.
+    pub CONTENT_DIGEST: ContentDigest = "content-digest", combine
+}
+
+/// The `Want-Content-Digest` header: the algorithms the sender would like `Content-Digest` to
+/// use, with preferences.
+///
+/// This is a structured field dictionary of integers from 0 to 10, for example
+/// `sha-512=10, sha-256=3`: 10 is the most preferred, 1 the least, and 0 means not acceptable. A
+/// preference which is not such an integer makes the header invalid.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WantContentDigest {
+    preferences: Vec<(String, u8)>,
+}
+
+impl WantContentDigest {
+    /// Construct a `Want-Content-Digest` from algorithm and preference pairs. Returns `None` if
+    /// an algorithm is repeated or is not a valid dictionary key, or a preference is over 10.
+    pub fn new<I: IntoIterator<Item = (String, u8)>>(preferences: I)
+                                                     -> Option<WantContentDigest> {
+        let mut want = WantContentDigest { preferences: vec![] };
+        for (algorithm, preference) in preferences {
+            if !sf::is_key(algorithm.as_bytes()) || want.preference(&algorithm).is_some() ||
+                    preference > 10 {
+                return None;
+            }
+            want.preferences.push((algorithm, preference));
+        }
+        Some(want)
+    }
+
+    /// The algorithm and preference pairs, in order.
+    pub fn preferences(&self) -> &[(String, u8)] {
+        &self.preferences
+    }
+
+    /// The preference for the given algorithm, if it was mentioned.
+    pub fn preference(&self, algorithm: &str) -> Option<u8> {
+        self.preferences.iter()
+            .find(|&&(ref a, _)| *a == algorithm)
+            .map(|&(_, preference)| preference)
+    }
+
+    /// The most preferred of the algorithms given which is acceptable, the first on a tie.
+    pub fn preferred<'a>(&self, supported: &[&'a str]) -> Option<&'a str> {
+        let mut best = None;
+        for &algorithm in supported {
+            match self.preference(algorithm) {
+                Some(preference) if preference > 0 &&
+                                    best.map_or(true, |(_, best)| preference > best) => {
+                    best = Some((algorithm, preference));
+                },
+                _ => (),
+            }
+        }
+        best.map(|(algorithm, _)| algorithm)
+    }
+}
+
+impl ToHeader for WantContentDigest {
+    fn parse(raw: &[u8]) -> Option<WantContentDigest> {
+        let dictionary = match sf::parse_dictionary(raw) {
+            Some(dictionary) => dictionary,
+            None => return None,
+        };
+        let mut preferences = vec![];
+        for (algorithm, member) in dictionary {
+            match member {
+                Member::Item(BareItem::Integer(preference), _) if preference >= 0 &&
+                                                                  preference <= 10 => {
+                    preferences.push((algorithm, preference as u8));
+                },
+                _ => return None,
+            }
+        }
+        WantContentDigest::new(preferences)
+    }
+}
+
+impl Header for WantContentDigest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dictionary = self.preferences.iter().map(|&(ref algorithm, preference)| {
+            (algorithm.clone(), Member::Item(BareItem::Integer(preference as i64), vec![]))
+        }).collect();
+        sf::fmt_dictionary(f, &dictionary)
+    }
+}
+
+define_single_header_marker! {
+    /// The `Want-Content-Digest` header.
+    pub WANT_CONTENT_DIGEST: WantContentDigest = "want-content-digest", combine
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, Headers, ToHeader, HeaderDisplayAdapter};
+    use super::{ContentDigest, WantContentDigest, CONTENT_DIGEST, WANT_CONTENT_DIGEST};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    /// The SHA-256 digest of nothing at all.
+    const EMPTY_SHA_256: &'static [u8] =
+        b"\xe3\xb0\xc4\x42\x98\xfc\x1c\x14\x9a\xfb\xf4\xc8\x99\x6f\xb9\x24\
+          \x27\xae\x41\xe4\x64\x9b\x93\x4c\xa4\x95\x99\x1b\x78\x52\xb8\x55";
+
+    #[test]
+    fn test_content_digest() {
+        let digest = ContentDigest::parse(b"sha-256=:47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=:")
+                         .unwrap();
+        assert_eq!(digest.digest("sha-256"), Some(EMPTY_SHA_256));
+        assert_eq!(digest.digest("sha-512"), None);
+        eq(b"sha-256=:47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=:, x-custom=:AQI=:",
+           ContentDigest::new(vec![("sha-256".to_owned(), EMPTY_SHA_256.to_vec()),
+                                   ("x-custom".to_owned(), vec![1, 2])]).unwrap());
+        eq(b"", ContentDigest::new(vec![]).unwrap());
+    }
+
+    #[test]
+    fn test_content_digest_lengths() {
+        assert_eq!(ContentDigest::new(vec![("sha-256".to_owned(), vec![0; 31])]), None);
+        assert_eq!(ContentDigest::new(vec![("sha-512".to_owned(), vec![0; 32])]), None);
+        assert!(ContentDigest::new(vec![("sha-512".to_owned(), vec![0; 64])]).is_some());
+        bad::<ContentDigest>(b"sha-256=:AQI=:");
+        bad::<ContentDigest>(b"sha-512=:47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=:");
+        bad::<ContentDigest>(b"sha-256=\"47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=\"");
+        bad::<ContentDigest>(b"sha-256");
+    }
+
+    #[test]
+    fn test_want_content_digest() {
+        let want = WantContentDigest::parse(b"sha-512=3, sha-256=10, md5=0").unwrap();
+        assert_eq!(want.preference("sha-256"), Some(10));
+        assert_eq!(want.preference("md5"), Some(0));
+        assert_eq!(want.preference("sha"), None);
+        assert_eq!(want.preferred(&["sha-512", "sha-256"]), Some("sha-256"));
+        assert_eq!(want.preferred(&["sha-512"]), Some("sha-512"));
+        assert_eq!(want.preferred(&["md5", "crc32c"]), None);
+        eq(b"sha-512=3, sha-256=10, md5=0", want);
+        assert_eq!(WantContentDigest::new(vec![("sha-256".to_owned(), 11)]), None);
+        bad::<WantContentDigest>(b"sha-256=11");
+        bad::<WantContentDigest>(b"sha-256=-1");
+        bad::<WantContentDigest>(b"sha-256=5.0");
+        bad::<WantContentDigest>(b"sha-256");
+    }
+
+    #[test]
+    fn test_several_field_lines() {
+        let headers = Headers::parse_block(b"Content-Digest: x-custom=:AQI=:\r\n\
+            Content-Digest: sha-256=:47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=:\r\n\
+            Want-Content-Digest: sha-512=3\r\n\
+            Want-Content-Digest: sha-256=10\r\n").unwrap();
+        let digest = headers.get(CONTENT_DIGEST).unwrap();
+        assert_eq!(digest.digest("x-custom"), Some(&[1, 2][..]));
+        assert_eq!(digest.digest("sha-256"), Some(EMPTY_SHA_256));
+        let want = headers.get(WANT_CONTENT_DIGEST).unwrap();
+        assert_eq!(want.preferred(&["sha-512", "sha-256"]), Some("sha-256"));
+        // A bad digest in any line spoils the whole header.
+        let headers = Headers::parse_block(b"Content-Digest: x-custom=:AQI=:\r\n\
+                                             Content-Digest: sha-256=:AQI=:\r\n").unwrap();
+        assert!(headers.get(CONTENT_DIGEST).is_none());
+    }
+}
@@ -0,0 +1,135 @@
+//! The `Content-Encoding` header (RFC 7231, section 3.1.2.2) and content codings.
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+
+use grammar::token::is_tchar;
+use super::{ToHeader, Header};
+
+/// A content coding, being one item of the `Content-Encoding` header, and what `Accept-Encoding`
+/// is made of.
+///
+/// RFC 7231 grammar:
+///
+/// ```abnf
+/// Content-Encoding = 1#content-coding
+/// content-coding   = token
+/// ```
+///
+/// Content codings are case-insensitive. Those registered with IANA which are in common use have
+/// variants of their own; any other is lowercased.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ContentCoding {
+    /// `gzip` (or `x-gzip`).
+    Gzip,
+    /// `compress` (or `x-compress`).
+    Compress,
+    /// `deflate`.
+    Deflate,
+    /// `br`, Brotli (RFC 7932).
+    Br,
+    /// `zstd`, Zstandard (RFC 8878).
+    Zstd,
+    /// `identity`, meaning no coding at all. It may be named in `Accept-Encoding`, but should not
+    /// be used in `Content-Encoding`.
+    Identity,
+    /// Any other content coding, lowercase.
+    Other(String),
+}
+
+impl ContentCoding {
+    /// The content coding with the given name, matched case-insensitively.
+    ///
+    /// The name is not checked for being a token; `ToHeader::parse` does that.
+    pub fn from_name(name: &str) -> ContentCoding {
+        let name = name.to_ascii_lowercase();
+        match &name[..] {
+            "gzip" | "x-gzip" => ContentCoding::Gzip,
+            "compress" | "x-compress" => ContentCoding::Compress,
+            "deflate" => ContentCoding::Deflate,
+            "br" => ContentCoding::Br,
+            "zstd" => ContentCoding::Zstd,
+            "identity" => ContentCoding::Identity,
+            _ => ContentCoding::Other(name),
+        }
+    }
+
+    /// The canonical name.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Compress => "compress",
+            ContentCoding::Deflate => "deflate",
+            ContentCoding::Br => "br",
+            ContentCoding::Zstd => "zstd",
+            ContentCoding::Identity => "identity",
+            ContentCoding::Other(ref name) => name,
+        }
+    }
+}
+
+impl ToHeader for ContentCoding {
+    fn parse(raw: &[u8]) -> Option<ContentCoding> {
+        if raw.is_empty() || !raw.iter().all(|&c| is_tchar(c)) {
+            return None;
+        }
+        // Tokens are ASCII, so this can’t fail.
+        Some(ContentCoding::from_name(unsafe { str::from_utf8_unchecked(raw) }))
+    }
+}
+
+impl Header for ContentCoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_list_header_marker! {
+    /// The `Content-Encoding` header, a list of `ContentCoding`s in the order applied.
+    pub CONTENT_ENCODING: ContentCoding = "content-encoding"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use super::{ContentCoding, CONTENT_ENCODING};
+    use super::ContentCoding::{Gzip, Compress, Deflate, Br, Zstd, Identity, Other};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_content_coding() {
+        eq(b"gzip", Gzip);
+        eq(b"compress", Compress);
+        eq(b"deflate", Deflate);
+        eq(b"br", Br);
+        eq(b"zstd", Zstd);
+        eq(b"identity", Identity);
+        eq(b"exi", Other("exi".to_owned()));
+        assert_eq!(ContentCoding::parse(b"BR"), Some(Br));
+        assert_eq!(ContentCoding::parse(b"ZStd"), Some(Zstd));
+        assert_eq!(ContentCoding::parse(b"x-gzip"), Some(Gzip));
+        assert_eq!(ContentCoding::parse(b"Pack200-GZip"), Some(Other("pack200-gzip".to_owned())));
+        bad::<ContentCoding>(b"");
+        bad::<ContentCoding>(b"g zip");
+    }
+
+    #[test]
+    fn test_content_encoding() {
+        let headers = Headers::parse_block(b"Content-Encoding: zstd, Br\r\n").unwrap();
+        assert_eq!(&*headers.get(CONTENT_ENCODING), &[Zstd, Br][..]);
+    }
+}
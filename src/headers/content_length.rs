@@ -0,0 +1,141 @@
+//! The `Content-Length` header (RFC 7230, section 3.3.2), and checking a body against it.
+
+use std::error::Error;
+use std::fmt;
+use std::str;
+
+use super::{ToHeader, Header};
+use super::implementations::fmt_decimal;
+
+/// The `Content-Length` header: the length of the body, in bytes.
+///
+/// RFC 7230 grammar:
+///
+/// ```abnf
+/// Content-Length = 1*DIGIT
+/// ```
+///
+/// A value too large for a `u64` fails to parse. This is the lenient view of a single field
+/// value; a recipient deciding where a message ends should use `Headers::content_length`, which
+/// also deals with multiple and comma-separated values as RFC 7230 requires.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct ContentLength(pub u64);
+
+impl ToHeader for ContentLength {
+    fn parse(raw: &[u8]) -> Option<ContentLength> {
+        if raw.is_empty() || !raw.iter().all(|&c| c >= b'0' && c <= b'9') {
+            return None;
+        }
+        // All ASCII digits, so this can only fail by overflow.
+        unsafe { str::from_utf8_unchecked(raw) }.parse().ok().map(ContentLength)
+    }
+}
+
+impl Header for ContentLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_decimal(self.0, f)
+    }
+}
+
+define_single_header_marker! {
+    /// The `Content-Length` header.
+    pub CONTENT_LENGTH: ContentLength = "content-length"
+}
+
+/// How the length of a body differed from its `Content-Length`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LengthMismatch {
+    /// The body ended early: only `actual` of the `expected` bytes were read. The connection was
+    /// probably closed, and the message is incomplete (RFC 7230, section 3.4).
+    Short {
+        /// The declared length.
+        expected: u64,
+        /// The length read.
+        actual: u64,
+    },
+    /// More than the `expected` bytes were read, `actual` in all. The excess is not part of the
+    /// body, and the connection can’t be trusted to be in step.
+    Overrun {
+        /// The declared length.
+        expected: u64,
+        /// The length read.
+        actual: u64,
+    },
+}
+
+/// The message is of the form `body too short: 10 of 20 bytes` or
+/// `body too long: 30 bytes, 20 expected`.
+impl fmt::Display for LengthMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LengthMismatch::Short { expected, actual } => {
+                write!(f, "body too short: {} of {} bytes", actual, expected)
+            },
+            LengthMismatch::Overrun { expected, actual } => {
+                write!(f, "body too long: {} bytes, {} expected", actual, expected)
+            },
+        }
+    }
+}
+
+impl Error for LengthMismatch {
+    fn description(&self) -> &str {
+        match *self {
+            LengthMismatch::Short { .. } => "body shorter than its Content-Length",
+            LengthMismatch::Overrun { .. } => "body longer than its Content-Length",
+        }
+    }
+}
+
+/// Check that a body of `actual` bytes is exactly as long as its `Content-Length` says.
+pub fn verify(expected: &ContentLength, actual: u64) -> Result<(), LengthMismatch> {
+    let expected = expected.0;
+    if actual < expected {
+        Err(LengthMismatch::Short { expected: expected, actual: actual })
+    } else if actual > expected {
+        Err(LengthMismatch::Overrun { expected: expected, actual: actual })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use super::{ContentLength, CONTENT_LENGTH, LengthMismatch, verify};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_content_length() {
+        eq(b"0", ContentLength(0));
+        eq(b"3495", ContentLength(3495));
+        eq(b"18446744073709551615", ContentLength(!0));
+        bad::<ContentLength>(b"18446744073709551616");
+        bad::<ContentLength>(b"");
+        bad::<ContentLength>(b"+1");
+        bad::<ContentLength>(b"1, 1");
+    }
+
+    #[test]
+    fn test_verify() {
+        let headers = Headers::parse_block(b"Content-Length: 20\r\n").unwrap();
+        let expected = headers.get(CONTENT_LENGTH).unwrap();
+        assert_eq!(verify(&expected, 20), Ok(()));
+        let short = verify(&expected, 10).unwrap_err();
+        assert_eq!(short, LengthMismatch::Short { expected: 20, actual: 10 });
+        assert_eq!(short.to_string(), "body too short: 10 of 20 bytes");
+        let overrun = verify(&expected, 30).unwrap_err();
+        assert_eq!(overrun, LengthMismatch::Overrun { expected: 20, actual: 30 });
+        assert_eq!(overrun.to_string(), "body too long: 30 bytes, 20 expected");
+        assert_eq!(verify(&ContentLength(0), 0), Ok(()));
+    }
+}
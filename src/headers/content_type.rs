@@ -0,0 +1,530 @@
+//! The `Content-Type` header and media types (RFC 7231, sections 3.1.1.1 and 3.1.1.5).
+
+use std::ascii::AsciiExt;
+use std::borrow::Cow;
+use std::fmt;
+use std::str;
+
+use grammar::parameters::{self, split_token};
+use grammar::quoted_string;
+use grammar::token::is_tchar;
+use super::{ToHeader, Header, ParseMode};
+
+/// A media type, as used by `Content-Type` and, in the form of media ranges, `Accept`.
+///
+/// RFC 7231 grammar:
+///
+/// ```abnf
+/// media-type = type "/" subtype *( OWS ";" OWS parameter )
+/// type       = token
+/// subtype    = token
+/// ```
+///
+/// The type and subtype are case-insensitive and are lowercased on parsing. Parameters are stored
+/// as they were received, with quoted-string values still quoted.
+///
+/// Equality is semantic rather than structural: the order of parameters is insignificant, as are
+/// the case of their names and whether their values are quoted, and `charset` values are compared
+/// case-insensitively. So `text/html; a=1; b=2` equals `text/html; b=2; A="1"`. Use
+/// `essence_eq` to ignore the parameters altogether.
+#[derive(Clone, Debug, Eq)]
+pub struct MediaType {
+    /// The type, e.g. `text` in `text/html`. Always lowercase.
+    pub type_: String,
+    /// The subtype, e.g. `html` in `text/html`. Always lowercase.
+    pub subtype: String,
+    /// The parameters, e.g. `[("charset", "utf-8")]` in `text/html; charset=utf-8`.
+    pub parameters: Vec<(String, String)>,
+}
+
+impl MediaType {
+    /// Construct a media type with no parameters.
+    ///
+    /// The type and subtype are lowercased.
+    pub fn new(type_: &str, subtype: &str) -> MediaType {
+        MediaType {
+            type_: type_.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            parameters: vec![],
+        }
+    }
+
+    /// The structured syntax suffix of the subtype (RFC 6838, section 4.2.8), if there is one:
+    /// `json` for `application/vnd.api+json`, for example.
+    pub fn suffix(&self) -> Option<&str> {
+        self.subtype.rfind('+').map(|i| &self.subtype[i + 1..])
+    }
+
+    /// Whether this is a JSON media type: `application/json`, `text/json`, or anything with the
+    /// `+json` structured syntax suffix.
+    pub fn is_json(&self) -> bool {
+        ((self.type_ == "application" || self.type_ == "text") && self.subtype == "json") ||
+            self.suffix() == Some("json")
+    }
+
+    /// Whether this is an XML media type: `application/xml`, `text/xml`, or anything with the
+    /// `+xml` structured syntax suffix.
+    pub fn is_xml(&self) -> bool {
+        ((self.type_ == "application" || self.type_ == "text") && self.subtype == "xml") ||
+            self.suffix() == Some("xml")
+    }
+
+    /// The raw value of the first parameter with the given name, which is case-insensitive.
+    ///
+    /// quoted-string values are returned still quoted.
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters.iter()
+            .find(|&&(ref n, _)| n.eq_ignore_ascii_case(name))
+            .map(|&(_, ref value)| &value[..])
+    }
+
+    /// The value of the first parameter with the given name, which is case-insensitive,
+    /// unquoted if it was a quoted-string but otherwise verbatim.
+    ///
+    /// The value’s case is preserved, for whether it matters depends on the parameter: a
+    /// `charset` should be compared case-insensitively, but a `boundary` is case-sensitive.
+    /// `None` is also returned for a quoted value which isn’t UTF-8 once unquoted.
+    pub fn get_param(&self, name: &str) -> Option<Cow<str>> {
+        match self.parameter(name) {
+            Some(value) if value.starts_with('"') => {
+                quoted_string::unquote(value.as_bytes())
+                    .and_then(|value| String::from_utf8(value).ok())
+                    .map(Cow::Owned)
+            },
+            Some(value) => Some(Cow::Borrowed(value)),
+            None => None,
+        }
+    }
+
+    /// The `boundary` parameter of a `multipart/*` media type, unquoted, as needed to parse a
+    /// multipart body.
+    ///
+    /// RFC 2046 restricts the boundary to characters which never need escaping in a
+    /// quoted-string, so a quoted boundary containing a quoted-pair is treated as invalid, as is
+    /// an empty boundary.
+    pub fn boundary(&self) -> Option<&str> {
+        if self.type_ != "multipart" {
+            return None;
+        }
+        let boundary = match self.parameter("boundary") {
+            Some(value) if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') => {
+                &value[1..value.len() - 1]
+            },
+            Some(value) => value,
+            None => return None,
+        };
+        if boundary.is_empty() || boundary.contains('\\') {
+            None
+        } else {
+            Some(boundary)
+        }
+    }
+
+    /// Construct `multipart/byteranges` with the given boundary, the media type of a
+    /// `206 Partial Content` response carrying several ranges (RFC 7233, appendix A).
+    ///
+    /// Returns `None` if the boundary isn’t valid by RFC 2046: 1 to 70 characters, being ASCII
+    /// letters and digits, space (but not last) and `'()+_,-./:=?`. It is quoted if it isn’t a
+    /// token, so `boundary` will return it as given.
+    pub fn byteranges(boundary: &str) -> Option<MediaType> {
+        let is_bchar = |c: u8| match c {
+            b'0'...b'9' | b'A'...b'Z' | b'a'...b'z' => true,
+            _ => b" '()+_,-./:=?".contains(&c),
+        };
+        if boundary.is_empty() || boundary.len() > 70 || boundary.ends_with(' ') ||
+                !boundary.bytes().all(is_bchar) {
+            return None;
+        }
+        let value = if boundary.bytes().all(is_tchar) {
+            boundary.to_owned()
+        } else {
+            format!("\"{}\"", boundary)
+        };
+        let mut media_type = MediaType::new("multipart", "byteranges");
+        media_type.parameters.push(("boundary".to_owned(), value));
+        Some(media_type)
+    }
+
+    /// Whether this is `multipart/byteranges`, whose `boundary` delimits the parts of a
+    /// multiple-range response.
+    pub fn is_byteranges(&self) -> bool {
+        self.type_ == "multipart" && self.subtype == "byteranges"
+    }
+}
+
+impl MediaType {
+    /// Whether the type and subtype are the same, whatever the parameters.
+    pub fn essence_eq(&self, other: &MediaType) -> bool {
+        self.type_ == other.type_ && self.subtype == other.subtype
+    }
+
+    /// The parameters in a form fit for comparison: names lowercased, values unquoted (and
+    /// lowercased for `charset`), sorted.
+    fn canonical_parameters(&self) -> Vec<(String, Vec<u8>)> {
+        let mut parameters = self.parameters.iter().map(|&(ref name, ref value)| {
+            let name = name.to_ascii_lowercase();
+            let mut value = unquoted(value);
+            if name == "charset" {
+                value.make_ascii_lowercase();
+            }
+            (name, value)
+        }).collect::<Vec<_>>();
+        parameters.sort();
+        parameters
+    }
+}
+
+impl PartialEq for MediaType {
+    fn eq(&self, other: &MediaType) -> bool {
+        self.essence_eq(other) && self.parameters.len() == other.parameters.len() &&
+            self.canonical_parameters() == other.canonical_parameters()
+    }
+}
+
+/// A parameter value, unquoted if it is a quoted-string (or verbatim if it is malformed as one).
+pub fn unquoted(value: &str) -> Vec<u8> {
+    if value.starts_with('"') {
+        quoted_string::unquote(value.as_bytes()).unwrap_or_else(|| value.as_bytes().to_vec())
+    } else {
+        value.as_bytes().to_vec()
+    }
+}
+
+impl ToHeader for MediaType {
+    fn parse(raw: &[u8]) -> Option<MediaType> {
+        MediaType::parse_with_mode(raw, ParseMode::Strict)
+    }
+
+    /// In `ParseMode::Lenient`, a missing subtype (as in `text` or `text; charset=utf-8`, which
+    /// some servers send) is taken to be `*`, so that the type is still available; such a media
+    /// type is only good for examining `type_`.
+    fn parse_with_mode(raw: &[u8], mode: ParseMode) -> Option<MediaType> {
+        let (type_, rest) = split_token(raw);
+        if type_.is_empty() {
+            return None;
+        }
+        let (subtype, rest) = if rest.first() == Some(&b'/') {
+            split_token(&rest[1..])
+        } else if mode == ParseMode::Lenient {
+            (&b"*"[..], rest)
+        } else {
+            return None;
+        };
+        if subtype.is_empty() {
+            return None;
+        }
+        let parameters = match parameters::parse(rest) {
+            Some(parameters) => parameters,
+            None => return None,
+        };
+        // Tokens are ASCII, so these can’t fail.
+        Some(MediaType {
+            type_: unsafe { str::from_utf8_unchecked(type_) }.to_ascii_lowercase(),
+            subtype: unsafe { str::from_utf8_unchecked(subtype) }.to_ascii_lowercase(),
+            parameters: parameters,
+        })
+    }
+}
+
+impl Header for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{}/{}", self.type_, self.subtype));
+        for &(ref name, ref value) in &self.parameters {
+            try!(write!(f, "; {}={}", name, value));
+        }
+        Ok(())
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_single_header_marker! {
+    /// The `Content-Type` header, a `MediaType`.
+    pub CONTENT_TYPE: MediaType = "content-type"
+}
+
+/// What kind of codec a body of some media type wants, for dispatching deserialisation on.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum CodecHint {
+    /// JSON: `application/json`, `text/json` or a `+json` type.
+    Json,
+    /// XML: `application/xml`, `text/xml` or a `+xml` type.
+    Xml,
+    /// An HTML form: `application/x-www-form-urlencoded` or `multipart/form-data`.
+    Form,
+    /// Text to be taken as it is: `text/*`, other than those above.
+    Text,
+    /// Anything else: opaque bytes.
+    Binary,
+    /// A codec registered with a `CodecRegistry`, by name.
+    Custom(String),
+}
+
+impl CodecHint {
+    /// The symbolic name of the codec: `json`, `xml`, `form`, `text`, `binary`, or the custom
+    /// name.
+    pub fn name(&self) -> &str {
+        match *self {
+            CodecHint::Json => "json",
+            CodecHint::Xml => "xml",
+            CodecHint::Form => "form",
+            CodecHint::Text => "text",
+            CodecHint::Binary => "binary",
+            CodecHint::Custom(ref name) => name,
+        }
+    }
+}
+
+/// The codec given by the base table for the types which the suffix rules don’t cover.
+fn base_codec_hint(media_type: &MediaType) -> Option<CodecHint> {
+    match (&media_type.type_[..], &media_type.subtype[..]) {
+        ("application", "x-www-form-urlencoded") | ("multipart", "form-data") => {
+            Some(CodecHint::Form)
+        },
+        ("application", "javascript") | ("text", _) => Some(CodecHint::Text),
+        _ => None,
+    }
+}
+
+/// Whether a media type matches a registered type and subtype, where the subtype may be `*`.
+fn matches_type(media_type: &MediaType, type_: &str, subtype: &str) -> bool {
+    media_type.type_ == type_ && (subtype == "*" || media_type.subtype == subtype)
+}
+
+impl MediaType {
+    /// The codec which a body of this media type wants, by the base table alone; see
+    /// `CodecRegistry::hint`.
+    pub fn codec_hint(&self) -> CodecHint {
+        CodecRegistry::new().hint(self)
+    }
+}
+
+/// A mapping of media types to codecs: the base table, plus any types registered.
+///
+/// A type’s codec is found by looking, in order, at:
+///
+/// 1. the types registered, the most recently registered first;
+/// 2. the structured syntax suffix, or the types `application/json`, `text/json`,
+///    `application/xml` and `text/xml` (see `MediaType::is_json` and `MediaType::is_xml`);
+/// 3. the base table: forms, `application/javascript`, and `text/*`;
+///
+/// and failing all of those, it is `CodecHint::Binary`. Parameters play no part.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CodecRegistry {
+    registered: Vec<(String, String, CodecHint)>,
+}
+
+impl CodecRegistry {
+    /// A registry with nothing registered, only the base table.
+    pub fn new() -> CodecRegistry {
+        CodecRegistry { registered: vec![] }
+    }
+
+    /// Register a codec for the given type and subtype, which are case-insensitive; the subtype
+    /// may be `*`, for all subtypes of the type. This takes precedence over all earlier
+    /// registrations and the built-in rules.
+    pub fn register(&mut self, type_: &str, subtype: &str, hint: CodecHint) {
+        self.registered.push((type_.to_ascii_lowercase(), subtype.to_ascii_lowercase(), hint));
+    }
+
+    /// The codec which a body of the media type wants.
+    pub fn hint(&self, media_type: &MediaType) -> CodecHint {
+        let registered = self.registered.iter().rev().find(|&&(ref type_, ref subtype, _)| {
+            matches_type(media_type, type_, subtype)
+        });
+        if let Some(&(_, _, ref hint)) = registered {
+            return hint.clone();
+        }
+        if media_type.is_json() {
+            return CodecHint::Json;
+        }
+        if media_type.is_xml() {
+            return CodecHint::Xml;
+        }
+        base_codec_hint(media_type).unwrap_or(CodecHint::Binary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, Headers, ToHeader, HeaderDisplayAdapter, ParseMode};
+    use super::{MediaType, CodecHint, CodecRegistry, CONTENT_TYPE};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    fn mt(raw: &str) -> MediaType {
+        MediaType::parse(raw.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_media_type() {
+        eq(b"text/html", MediaType::new("text", "html"));
+        eq(b"text/html; charset=utf-8", MediaType {
+            type_: "text".to_owned(),
+            subtype: "html".to_owned(),
+            parameters: vec![("charset".to_owned(), "utf-8".to_owned())],
+        });
+        eq(b"multipart/form-data; boundary=\"a b\"", MediaType {
+            type_: "multipart".to_owned(),
+            subtype: "form-data".to_owned(),
+            parameters: vec![("boundary".to_owned(), "\"a b\"".to_owned())],
+        });
+        assert_eq!(mt("Text/HTML;charset=UTF-8"), mt("text/html; charset=UTF-8"));
+        bad::<MediaType>(b"text");
+        bad::<MediaType>(b"text/");
+        bad::<MediaType>(b"/html");
+        bad::<MediaType>(b"text/html charset=utf-8");
+        bad::<MediaType>(b"text/html; charset");
+    }
+
+    #[test]
+    fn test_equality() {
+        assert_eq!(mt("text/html; a=1; b=2"), mt("text/html; b=2; a=1"));
+        assert_eq!(mt("text/html; A=\"1\""), mt("text/html; a=1"));
+        assert_eq!(mt("text/html; charset=UTF-8"), mt("text/html; charset=utf-8"));
+        assert!(mt("text/html; a=1; b=2") != mt("text/html; a=1; b=3"));
+        assert!(mt("text/html; a=1") != mt("text/html; a=1; b=2"));
+        assert!(mt("text/html; a=1; a=1") != mt("text/html; a=1"));
+        assert!(mt("multipart/mixed; boundary=AB") != mt("multipart/mixed; boundary=ab"));
+        assert!(mt("text/html; a=1") != mt("text/plain; a=1"));
+
+        assert!(mt("text/html; charset=utf-8").essence_eq(&mt("text/html")));
+        assert!(!mt("text/html").essence_eq(&mt("text/plain")));
+    }
+
+    #[test]
+    fn test_missing_subtype() {
+        assert_eq!(MediaType::parse_with_mode(b"text", ParseMode::Strict), None);
+        assert_eq!(MediaType::parse_with_mode(b"text", ParseMode::Lenient),
+                   Some(MediaType::new("text", "*")));
+        assert_eq!(MediaType::parse_with_mode(b"Text;charset=utf-8", ParseMode::Lenient),
+                   Some(mt("text/*; charset=utf-8")));
+        assert_eq!(MediaType::parse_with_mode(b"text/html", ParseMode::Lenient),
+                   Some(MediaType::new("text", "html")));
+        assert_eq!(MediaType::parse_with_mode(b"text/", ParseMode::Lenient), None);
+        assert_eq!(MediaType::parse_with_mode(b"/html", ParseMode::Lenient), None);
+    }
+
+    #[test]
+    fn test_missing_subtype_through_headers() {
+        let mut headers = Headers::parse_block(b"Content-Type: text; charset=utf-8\r\n").unwrap();
+        assert!(headers.get(CONTENT_TYPE).is_none());
+        headers.set_parse_mode(ParseMode::Lenient);
+        assert_eq!(**headers.get(CONTENT_TYPE).unwrap(), mt("text/*; charset=utf-8"));
+        // Nothing is rewritten unless it is changed.
+        let mut out = vec![];
+        headers.write_to(&mut out).unwrap();
+        assert_eq!(out, b"content-type: text; charset=utf-8\r\n".to_vec());
+    }
+
+    #[test]
+    fn test_structured_syntax_suffixes() {
+        assert!(mt("application/json").is_json());
+        assert!(mt("text/json").is_json());
+        assert!(mt("application/vnd.api+json").is_json());
+        assert!(mt("Application/VND.API+JSON; charset=utf-8").is_json());
+        assert!(!mt("application/vnd.api+json").is_xml());
+        assert!(mt("application/xml").is_xml());
+        assert!(mt("text/xml").is_xml());
+        assert!(mt("application/atom+xml").is_xml());
+        assert!(!mt("text/plain").is_json());
+        assert!(!mt("text/plain").is_xml());
+        assert!(!mt("image/json").is_json());
+        assert_eq!(mt("application/vnd.api+json").suffix(), Some("json"));
+        assert_eq!(mt("text/plain").suffix(), None);
+    }
+
+    #[test]
+    fn test_get_param() {
+        let media_type = mt("text/plain; CHARSET=UTF-8; format=\"Flowed\"");
+        assert_eq!(media_type.get_param("charset").as_ref().map(|v| &v[..]), Some("UTF-8"));
+        assert_eq!(media_type.get_param("CHARSET"), media_type.get_param("charset"));
+        assert_eq!(media_type.get_param("Format").as_ref().map(|v| &v[..]), Some("Flowed"));
+        assert_eq!(media_type.get_param("boundary"), None);
+        let media_type = mt("multipart/mixed; boundary=\"AbC\\\"d\"");
+        assert_eq!(media_type.get_param("BOUNDARY").as_ref().map(|v| &v[..]), Some("AbC\"d"));
+    }
+
+    #[test]
+    fn test_boundary() {
+        assert_eq!(mt("multipart/form-data; boundary=----abc").boundary(), Some("----abc"));
+        assert_eq!(mt("multipart/mixed; Boundary=\"gc0p4Jq0M2Yt08j34c0p:=?()+_,-./ x\"")
+                       .boundary(),
+                   Some("gc0p4Jq0M2Yt08j34c0p:=?()+_,-./ x"));
+        assert_eq!(mt("multipart/form-data").boundary(), None);
+        assert_eq!(mt("multipart/form-data; boundary=\"\"").boundary(), None);
+        assert_eq!(mt("multipart/form-data; boundary=\"a\\\"b\"").boundary(), None);
+        assert_eq!(mt("text/plain; boundary=abc").boundary(), None);
+    }
+
+    #[test]
+    fn test_byteranges() {
+        let media_type = MediaType::byteranges("THIS_STRING_SEPARATES").unwrap();
+        eq(b"multipart/byteranges; boundary=THIS_STRING_SEPARATES", media_type.clone());
+        assert!(media_type.is_byteranges());
+        assert_eq!(media_type.boundary(), Some("THIS_STRING_SEPARATES"));
+
+        // Not a token, so quoted, but the same boundary comes back out.
+        let media_type = MediaType::byteranges("a b:c").unwrap();
+        eq(b"multipart/byteranges; boundary=\"a b:c\"", media_type.clone());
+        assert_eq!(media_type.boundary(), Some("a b:c"));
+
+        // Recognized however it is written.
+        let parsed = mt("Multipart/ByteRanges; BOUNDARY=\"3d6b6a416f9b5\"");
+        assert!(parsed.is_byteranges());
+        assert_eq!(parsed.boundary(), Some("3d6b6a416f9b5"));
+        assert!(!mt("multipart/mixed; boundary=x").is_byteranges());
+        assert!(!mt("text/plain").is_byteranges());
+
+        assert_eq!(MediaType::byteranges(""), None);
+        assert_eq!(MediaType::byteranges("trailing "), None);
+        assert_eq!(MediaType::byteranges("a\"b"), None);
+        assert_eq!(MediaType::byteranges(&"x".repeat(71)), None);
+        assert!(MediaType::byteranges(&"x".repeat(70)).is_some());
+    }
+
+    #[test]
+    fn test_codec_hint() {
+        assert_eq!(mt("application/json; charset=utf-8").codec_hint(), CodecHint::Json);
+        assert_eq!(mt("application/problem+json").codec_hint(), CodecHint::Json);
+        assert_eq!(mt("application/atom+xml").codec_hint(), CodecHint::Xml);
+        assert_eq!(mt("text/xml").codec_hint(), CodecHint::Xml);
+        assert_eq!(mt("application/x-www-form-urlencoded").codec_hint(), CodecHint::Form);
+        assert_eq!(mt("multipart/form-data; boundary=x").codec_hint(), CodecHint::Form);
+        assert_eq!(mt("text/plain").codec_hint(), CodecHint::Text);
+        assert_eq!(mt("text/html").codec_hint().name(), "text");
+        assert_eq!(mt("image/png").codec_hint(), CodecHint::Binary);
+        assert_eq!(mt("application/octet-stream").codec_hint().name(), "binary");
+    }
+
+    #[test]
+    fn test_codec_registry() {
+        let mut registry = CodecRegistry::new();
+        registry.register("Application", "MsgPack", CodecHint::Custom("msgpack".to_owned()));
+        registry.register("text", "csv", CodecHint::Custom("csv".to_owned()));
+        registry.register("application", "vnd.legacy+json", CodecHint::Binary);
+        assert_eq!(registry.hint(&mt("application/msgpack")).name(), "msgpack");
+        assert_eq!(registry.hint(&mt("text/csv; header=present")).name(), "csv");
+        assert_eq!(registry.hint(&mt("text/plain")), CodecHint::Text);
+        assert_eq!(registry.hint(&mt("application/vnd.legacy+json")), CodecHint::Binary);
+        assert_eq!(registry.hint(&mt("application/vnd.api+json")), CodecHint::Json);
+
+        registry.register("image", "*", CodecHint::Custom("image".to_owned()));
+        registry.register("image", "svg+xml", CodecHint::Xml);
+        assert_eq!(registry.hint(&mt("image/png")).name(), "image");
+        assert_eq!(registry.hint(&mt("image/svg+xml")), CodecHint::Xml);
+        // The latest registration wins.
+        registry.register("image", "*", CodecHint::Binary);
+        assert_eq!(registry.hint(&mt("image/svg+xml")), CodecHint::Binary);
+    }
+}
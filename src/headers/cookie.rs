@@ -0,0 +1,361 @@
+//! The `Cookie` and `Set-Cookie` headers (RFC 6265, sections 4.2 and 4.1).
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+
+use time::Tm;
+
+use grammar::{is_field_content, trim_ows};
+use grammar::token::is_tchar;
+use super::{ToHeader, Header};
+use super::date_based::parse_http_date;
+
+/// cookie-octet: any US-ASCII character except CTLs, whitespace, DQUOTE, comma, semicolon and
+/// backslash.
+///
+/// RFC 6265 grammar:
+///
+/// ```abnf
+/// cookie-octet = %x21 / %x23-2B / %x2D-3A / %x3C-5B / %x5D-7E
+/// ```
+#[inline]
+pub fn is_cookie_octet(o: u8) -> bool {
+    o == 0x21 || (o >= 0x23 && o <= 0x2b) || (o >= 0x2d && o <= 0x3a) ||
+    (o >= 0x3c && o <= 0x5b) || (o >= 0x5d && o <= 0x7e)
+}
+
+/// Whether this is a valid cookie-value.
+///
+/// RFC 6265 grammar:
+///
+/// ```abnf
+/// cookie-value = *cookie-octet / ( DQUOTE *cookie-octet DQUOTE )
+/// ```
+///
+/// Spaces are also permitted between the double quotes, as plenty of servers put them there and
+/// the quotes keep them from being mistaken for separators.
+pub fn is_cookie_value(raw: &[u8]) -> bool {
+    if raw.len() >= 2 && raw[0] == b'"' && raw[raw.len() - 1] == b'"' {
+        raw[1..raw.len() - 1].iter().all(|&c| c == b' ' || is_cookie_octet(c))
+    } else {
+        raw.iter().all(|&c| is_cookie_octet(c))
+    }
+}
+
+/// Split a cookie-pair into its cookie-name and cookie-value, if it is valid.
+///
+/// RFC 6265 grammar:
+///
+/// ```abnf
+/// cookie-pair = cookie-name "=" cookie-value
+/// cookie-name = token
+/// ```
+fn split_cookie_pair(raw: &[u8]) -> Option<(&[u8], &[u8])> {
+    let eq = match raw.iter().position(|&c| c == b'=') {
+        Some(eq) => eq,
+        None => return None,
+    };
+    let (name, value) = (&raw[..eq], &raw[eq + 1..]);
+    if name.is_empty() || !name.iter().all(|&c| is_tchar(c)) || !is_cookie_value(value) {
+        return None;
+    }
+    Some((name, value))
+}
+
+/// A cookie-pair, as appears in the `Cookie` header and at the start of a `Set-Cookie` value.
+///
+/// The value is kept as written, including any surrounding double quotes: RFC 6265 gives them no
+/// special meaning, so they are part of the value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CookiePair {
+    name: String,
+    value: String,
+}
+
+impl CookiePair {
+    /// Construct a cookie-pair, if the name is a token and the value a valid cookie-value.
+    pub fn new(name: &str, value: &str) -> Option<CookiePair> {
+        if name.is_empty() || !name.bytes().all(is_tchar) || !is_cookie_value(value.as_bytes()) {
+            return None;
+        }
+        Some(CookiePair { name: name.to_owned(), value: value.to_owned() })
+    }
+
+    /// The cookie-name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The cookie-value, as written.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl ToHeader for CookiePair {
+    fn parse(raw: &[u8]) -> Option<CookiePair> {
+        // Cookie-pairs are ASCII, so the conversions can’t fail.
+        split_cookie_pair(raw).map(|(name, value)| CookiePair {
+            name: unsafe { str::from_utf8_unchecked(name) }.to_owned(),
+            value: unsafe { str::from_utf8_unchecked(value) }.to_owned(),
+        })
+    }
+}
+
+impl Header for CookiePair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)
+    }
+}
+
+/// The `Cookie` header: the cookies which the user agent is returning to the server.
+///
+/// RFC 6265 grammar:
+///
+/// ```abnf
+/// cookie-header = "Cookie:" OWS cookie-string OWS
+/// cookie-string = cookie-pair *( ";" SP cookie-pair )
+/// ```
+///
+/// Any amount of whitespace (including none) is tolerated after the semicolons, but every pair
+/// must be valid or the whole header is rejected.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Cookie(pub Vec<CookiePair>);
+
+impl ToHeader for Cookie {
+    fn parse(raw: &[u8]) -> Option<Cookie> {
+        let mut pairs = vec![];
+        for pair in raw.split(|&c| c == b';') {
+            let pair = match pair.iter().position(|&c| c != b' ' && c != b'\t') {
+                Some(start) if !pairs.is_empty() => &pair[start..],
+                _ => pair,
+            };
+            match CookiePair::parse(pair) {
+                Some(pair) => pairs.push(pair),
+                None => return None,
+            }
+        }
+        Some(Cookie(pairs))
+    }
+}
+
+impl Header for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, pair) in self.0.iter().enumerate() {
+            if i > 0 {
+                try!(f.write_str("; "));
+            }
+            try!(pair.fmt(f));
+        }
+        Ok(())
+    }
+}
+
+define_single_header_marker! {
+    /// The `Cookie` header.
+    pub COOKIE: Cookie = "cookie"
+}
+
+/// A `Set-Cookie` value: a cookie, with its attributes.
+///
+/// RFC 6265 grammar:
+///
+/// ```abnf
+/// set-cookie-string = cookie-pair *( ";" SP cookie-av )
+/// ```
+///
+/// The leading cookie-pair is validated, so that a malformed name or value can’t be passed
+/// along; the attributes are kept as text, needing only to be valid field-content, and are split
+/// up on demand by `attributes`.
+///
+/// A value may well contain commas, as in `Expires=Wed, 21 Oct 2015 07:28:00 GMT`, so values
+/// are never combined into one field line (RFC 7230, section 3.2.2).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SetCookie(String);
+
+impl SetCookie {
+    /// Construct the header value, if it starts with a valid cookie-pair and is valid
+    /// field-content.
+    pub fn new(value: &str) -> Option<SetCookie> {
+        let pair = value.as_bytes().split(|&c| c == b';').next().unwrap_or(b"");
+        if is_field_content(value.as_bytes()) && split_cookie_pair(pair).is_some() {
+            Some(SetCookie(value.to_owned()))
+        } else {
+            None
+        }
+    }
+
+    /// The text of the header value.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The cookie-pair at the start of the value.
+    pub fn pair(&self) -> CookiePair {
+        // This was validated on construction.
+        CookiePair::parse(self.0.as_bytes().split(|&c| c == b';').next().unwrap()).unwrap()
+    }
+
+    /// The attributes which follow the cookie-pair, as names and values, in order.
+    ///
+    /// The value is split on semicolons alone, never on commas, so that a comma in an attribute
+    /// value, as in `Expires=Wed, 21 Oct 2015 07:28:00 GMT`, is kept. Whitespace around names
+    /// and values is trimmed, and empty attributes are skipped. An attribute without `=`, such
+    /// as `Secure`, has no value.
+    pub fn attributes(&self) -> Vec<(&str, Option<&str>)> {
+        fn trim(s: &str) -> &str {
+            // Trimming OWS only removes ASCII, so this stays on character boundaries.
+            unsafe { str::from_utf8_unchecked(trim_ows(s.as_bytes())) }
+        }
+        self.0.split(';').skip(1).filter_map(|attribute| {
+            let (name, value) = match attribute.find('=') {
+                Some(eq) => (&attribute[..eq], Some(&attribute[eq + 1..])),
+                None => (attribute, None),
+            };
+            match trim(name) {
+                "" => None,
+                name => Some((name, value.map(trim))),
+            }
+        }).collect()
+    }
+
+    /// The value of the last attribute with the given name (case-insensitive), as RFC 6265,
+    /// section 5.3 has user agents use. An attribute without `=` has the empty value.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes().into_iter().rev()
+            .find(|&(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.unwrap_or(""))
+    }
+
+    /// When the cookie expires, from its `Expires` attribute, if that is a valid HTTP-date.
+    ///
+    /// `Max-Age`, which takes precedence over `Expires`, is not considered.
+    pub fn expires(&self) -> Option<Tm> {
+        self.attribute("expires").and_then(|expires| parse_http_date(expires.as_bytes()))
+    }
+}
+
+impl ToHeader for SetCookie {
+    fn parse(raw: &[u8]) -> Option<SetCookie> {
+        match str::from_utf8(raw) {
+            Ok(value) => SetCookie::new(value),
+            Err(_) => None,
+        }
+    }
+}
+
+impl Header for SetCookie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+define_list_header_marker! {
+    /// The `Set-Cookie` header: each value is a field line of its own.
+    pub SET_COOKIE: SetCookie = "set-cookie", no_combine
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{self, Timespec};
+    use headers::{Headers, ToHeader, HeaderDisplayAdapter};
+    use super::{Cookie, CookiePair, SetCookie, SET_COOKIE};
+
+    fn cookie(value: &str) -> SetCookie {
+        SetCookie::new(value).unwrap()
+    }
+
+    #[test]
+    fn test_set_cookie_never_combined() {
+        let mut headers = Headers::new();
+        headers.set(SET_COOKIE, vec![cookie("a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT"),
+                                     cookie("b=2; Path=/")]);
+        assert_eq!(headers.pairs(), vec![
+            ("set-cookie", b"a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT".to_vec()),
+            ("set-cookie", b"b=2; Path=/".to_vec()),
+        ]);
+
+        headers.get_mut(SET_COOKIE).push(cookie("c=3"));
+        assert_eq!(headers.get_raw_lines("Set-Cookie").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_set_cookie_lines_not_split() {
+        let headers = Headers::parse_block(b"Set-Cookie: a=1; Expires=Wed, 21 Oct 2015 07:28:00 \
+                                             GMT\r\nSet-Cookie:  b=2 \r\n").unwrap();
+        assert_eq!(&*headers.get(SET_COOKIE),
+                   &[cookie("a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT"), cookie("b=2")][..]);
+    }
+
+    #[test]
+    fn test_cookie() {
+        let cookie = Cookie::parse(b"SID=31d4d96e407aad42; lang=en-US").unwrap();
+        assert_eq!(cookie, Cookie(vec![CookiePair::new("SID", "31d4d96e407aad42").unwrap(),
+                                       CookiePair::new("lang", "en-US").unwrap()]));
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&cookie)),
+                   "SID=31d4d96e407aad42; lang=en-US");
+        assert_eq!(Cookie::parse(b"a=1;b=2"), Cookie::parse(b"a=1; b=2"));
+        assert_eq!(Cookie::parse(b"empty="),
+                   Some(Cookie(vec![CookiePair::new("empty", "").unwrap()])));
+        assert_eq!(Cookie::parse(b"a=1; b"), None);
+        assert_eq!(Cookie::parse(b"a=1;"), None);
+        assert_eq!(Cookie::parse(b" a=1"), None);
+    }
+
+    #[test]
+    fn test_cookie_value_validation() {
+        assert_eq!(CookiePair::new("a", "1;b=2"), None);
+        assert_eq!(CookiePair::new("a", "x y"), None);
+        assert_eq!(CookiePair::new("a", "x,y"), None);
+        assert_eq!(CookiePair::new("a", "x\\y"), None);
+        assert_eq!(CookiePair::new("a", "x\"y"), None);
+        assert_eq!(CookiePair::new("a", "x\ny"), None);
+        assert_eq!(CookiePair::new("a b", "1"), None);
+        assert_eq!(CookiePair::new("", "1"), None);
+        let quoted = CookiePair::new("a", "\"x y\"").unwrap();
+        assert_eq!(quoted.value(), "\"x y\"");
+        assert_eq!(CookiePair::parse(b"a=\"x y\""), Some(quoted));
+        assert_eq!(CookiePair::parse(b"a=\"x;y\""), None);
+        assert_eq!(CookiePair::parse(b"a=\"x"), None);
+    }
+
+    #[test]
+    fn test_set_cookie_validation() {
+        assert_eq!(cookie("id=a3fWa; Max-Age=2592000").pair(),
+                   CookiePair::new("id", "a3fWa").unwrap());
+        assert_eq!(cookie("id=\"a b\"; Secure").pair().value(), "\"a b\"");
+        assert_eq!(SetCookie::parse(b"id=a b; Secure"), None);
+        assert_eq!(SetCookie::new("id; Secure"), None);
+        assert_eq!(SetCookie::new("i d=1"), None);
+        assert_eq!(SetCookie::new("id=1\r\nX-Injected: 1"), None);
+    }
+
+    #[test]
+    fn test_set_cookie_attributes() {
+        let set_cookie = cookie("id=a3fWa; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Secure;; \
+                                 Path = /docs ");
+        assert_eq!(set_cookie.attributes(), vec![("Expires", Some("Wed, 21 Oct 2015 07:28:00 GMT")),
+                                                 ("Secure", None), ("Path", Some("/docs"))]);
+        assert_eq!(set_cookie.attribute("path"), Some("/docs"));
+        assert_eq!(set_cookie.attribute("secure"), Some(""));
+        assert_eq!(set_cookie.attribute("domain"), None);
+        assert_eq!(cookie("a=1; Path=/a; path=/b").attribute("Path"), Some("/b"));
+        assert_eq!(cookie("a=1").attributes(), vec![]);
+        assert_eq!(cookie("a=1; Expires=yesterday").expires(), None);
+    }
+
+    #[test]
+    fn test_set_cookie_expires_comma() {
+        let headers = Headers::parse_block(b"Set-Cookie: id=a3fWa; Expires=Wed, 21 Oct 2015 \
+                                             07:28:00 GMT; HttpOnly\r\n\
+                                             Set-Cookie: lang=en\r\n").unwrap();
+        let cookies = headers.get(SET_COOKIE);
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies[0].pair(), CookiePair::new("id", "a3fWa").unwrap());
+        assert_eq!(cookies[0].expires(), Some(time::at_utc(Timespec::new(1445412480, 0))));
+        assert_eq!(cookies[0].attribute("httponly"), Some(""));
+        assert_eq!(cookies[1].pair(), CookiePair::new("lang", "en").unwrap());
+        assert_eq!(cookies[1].expires(), None);
+    }
+}
@@ -0,0 +1,260 @@
+//! The headers with which a resource opts in to cross-origin isolation (WHATWG Fetch and HTML):
+//! `Cross-Origin-Resource-Policy`, `Cross-Origin-Opener-Policy` and
+//! `Cross-Origin-Embedder-Policy`.
+//!
+//! Each of these is a single token from a fixed set, matched case-sensitively; anything else is
+//! rejected. The opener and embedder policies may also name a reporting endpoint group with a
+//! `report-to` parameter, e.g. `require-corp; report-to="coep"`.
+
+use std::fmt;
+use std::str;
+
+use grammar::{parameters, quoted_string};
+use grammar::parameters::split_token;
+use super::{ToHeader, Header};
+
+/// Split a policy token and its `report-to` parameter, if any, from a header value.
+///
+/// Other parameters are ignored, as they may be defined in future.
+fn parse_policy(raw: &[u8]) -> Option<(&[u8], Option<String>)> {
+    let (token, rest) = split_token(raw);
+    let parameters = match parameters::parse(rest) {
+        Some(parameters) => parameters,
+        None => return None,
+    };
+    let mut report_to = None;
+    for (name, value) in parameters {
+        if name == "report-to" {
+            // The endpoint group is a (structured field) string, so it must be quoted. Unquoting
+            // only removes ASCII from what was already UTF-8, so from_utf8 can’t fail.
+            match quoted_string::unquote(value.as_bytes()) {
+                Some(group) => report_to = Some(String::from_utf8(group).unwrap()),
+                None => return None,
+            }
+        }
+    }
+    Some((token, report_to))
+}
+
+fn fmt_policy(f: &mut fmt::Formatter, token: &str, report_to: &Option<String>) -> fmt::Result {
+    try!(f.write_str(token));
+    if let Some(ref group) = *report_to {
+        match quoted_string::quote(group.as_bytes()) {
+            // quote only adds ASCII to what was already UTF-8, so this can’t fail.
+            Some(quoted) => try!(write!(f, "; report-to={}",
+                                        unsafe { str::from_utf8_unchecked(&quoted) })),
+            None => return Err(fmt::Error),
+        }
+    }
+    Ok(())
+}
+
+/// The `Cross-Origin-Resource-Policy` header: which origins may load this resource with a
+/// no-cors request.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CrossOriginResourcePolicy {
+    /// `same-origin`: only the same origin.
+    SameOrigin,
+    /// `same-site`: only the same site (registrable domain and scheme).
+    SameSite,
+    /// `cross-origin`: any origin.
+    CrossOrigin,
+}
+
+impl ToHeader for CrossOriginResourcePolicy {
+    fn parse(raw: &[u8]) -> Option<CrossOriginResourcePolicy> {
+        match raw {
+            b"same-origin" => Some(CrossOriginResourcePolicy::SameOrigin),
+            b"same-site" => Some(CrossOriginResourcePolicy::SameSite),
+            b"cross-origin" => Some(CrossOriginResourcePolicy::CrossOrigin),
+            _ => None,
+        }
+    }
+}
+
+impl Header for CrossOriginResourcePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            CrossOriginResourcePolicy::SameOrigin => "same-origin",
+            CrossOriginResourcePolicy::SameSite => "same-site",
+            CrossOriginResourcePolicy::CrossOrigin => "cross-origin",
+        })
+    }
+}
+
+define_single_header_marker! {
+    /// The `Cross-Origin-Resource-Policy` header.
+    pub CROSS_ORIGIN_RESOURCE_POLICY: CrossOriginResourcePolicy =
+        "cross-origin-resource-policy"
+}
+
+/// The policy of a `Cross-Origin-Opener-Policy` header.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OpenerPolicy {
+    /// `unsafe-none`: the default; the document may share a browsing context group with any
+    /// other.
+    UnsafeNone,
+    /// `same-origin-allow-popups`: like `same-origin`, but popups opened by the document keep
+    /// their reference to it, unless they set their own opener policy.
+    SameOriginAllowPopups,
+    /// `same-origin`: only share a browsing context group with same-origin documents with the
+    /// same policy.
+    SameOrigin,
+    /// `noopener-allow-popups`: always get a new browsing context group, even from same-origin
+    /// openers.
+    NoopenerAllowPopups,
+}
+
+/// The `Cross-Origin-Opener-Policy` header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CrossOriginOpenerPolicy {
+    /// The policy itself.
+    pub policy: OpenerPolicy,
+    /// The reporting endpoint group to send violation reports to, if any.
+    pub report_to: Option<String>,
+}
+
+impl ToHeader for CrossOriginOpenerPolicy {
+    fn parse(raw: &[u8]) -> Option<CrossOriginOpenerPolicy> {
+        let (token, report_to) = match parse_policy(raw) {
+            Some(policy) => policy,
+            None => return None,
+        };
+        let policy = match token {
+            b"unsafe-none" => OpenerPolicy::UnsafeNone,
+            b"same-origin-allow-popups" => OpenerPolicy::SameOriginAllowPopups,
+            b"same-origin" => OpenerPolicy::SameOrigin,
+            b"noopener-allow-popups" => OpenerPolicy::NoopenerAllowPopups,
+            _ => return None,
+        };
+        Some(CrossOriginOpenerPolicy { policy: policy, report_to: report_to })
+    }
+}
+
+impl Header for CrossOriginOpenerPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_policy(f, match self.policy {
+            OpenerPolicy::UnsafeNone => "unsafe-none",
+            OpenerPolicy::SameOriginAllowPopups => "same-origin-allow-popups",
+            OpenerPolicy::SameOrigin => "same-origin",
+            OpenerPolicy::NoopenerAllowPopups => "noopener-allow-popups",
+        }, &self.report_to)
+    }
+}
+
+define_single_header_marker! {
+    /// The `Cross-Origin-Opener-Policy` header.
+    pub CROSS_ORIGIN_OPENER_POLICY: CrossOriginOpenerPolicy = "cross-origin-opener-policy"
+}
+
+/// The policy of a `Cross-Origin-Embedder-Policy` header.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EmbedderPolicy {
+    /// `unsafe-none`: the default; any cross-origin resource may be loaded.
+    UnsafeNone,
+    /// `require-corp`: cross-origin resources must opt in with CORS or
+    /// `Cross-Origin-Resource-Policy`.
+    RequireCorp,
+    /// `credentialless`: cross-origin no-cors requests are sent without credentials instead.
+    Credentialless,
+}
+
+/// The `Cross-Origin-Embedder-Policy` header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CrossOriginEmbedderPolicy {
+    /// The policy itself.
+    pub policy: EmbedderPolicy,
+    /// The reporting endpoint group to send violation reports to, if any.
+    pub report_to: Option<String>,
+}
+
+impl ToHeader for CrossOriginEmbedderPolicy {
+    fn parse(raw: &[u8]) -> Option<CrossOriginEmbedderPolicy> {
+        let (token, report_to) = match parse_policy(raw) {
+            Some(policy) => policy,
+            None => return None,
+        };
+        let policy = match token {
+            b"unsafe-none" => EmbedderPolicy::UnsafeNone,
+            b"require-corp" => EmbedderPolicy::RequireCorp,
+            b"credentialless" => EmbedderPolicy::Credentialless,
+            _ => return None,
+        };
+        Some(CrossOriginEmbedderPolicy { policy: policy, report_to: report_to })
+    }
+}
+
+impl Header for CrossOriginEmbedderPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_policy(f, match self.policy {
+            EmbedderPolicy::UnsafeNone => "unsafe-none",
+            EmbedderPolicy::RequireCorp => "require-corp",
+            EmbedderPolicy::Credentialless => "credentialless",
+        }, &self.report_to)
+    }
+}
+
+define_single_header_marker! {
+    /// The `Cross-Origin-Embedder-Policy` header.
+    pub CROSS_ORIGIN_EMBEDDER_POLICY: CrossOriginEmbedderPolicy = "cross-origin-embedder-policy"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter};
+    use super::{CrossOriginResourcePolicy, CrossOriginOpenerPolicy, OpenerPolicy,
+                CrossOriginEmbedderPolicy, EmbedderPolicy};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    fn coop(policy: OpenerPolicy, report_to: Option<&str>) -> CrossOriginOpenerPolicy {
+        CrossOriginOpenerPolicy { policy: policy, report_to: report_to.map(|s| s.to_owned()) }
+    }
+
+    fn coep(policy: EmbedderPolicy, report_to: Option<&str>) -> CrossOriginEmbedderPolicy {
+        CrossOriginEmbedderPolicy { policy: policy, report_to: report_to.map(|s| s.to_owned()) }
+    }
+
+    #[test]
+    fn test_resource_policy() {
+        eq(b"same-origin", CrossOriginResourcePolicy::SameOrigin);
+        eq(b"same-site", CrossOriginResourcePolicy::SameSite);
+        eq(b"cross-origin", CrossOriginResourcePolicy::CrossOrigin);
+        bad::<CrossOriginResourcePolicy>(b"same-domain");
+        bad::<CrossOriginResourcePolicy>(b"Same-Origin");
+        bad::<CrossOriginResourcePolicy>(b"same-origin; report-to=\"x\"");
+        bad::<CrossOriginResourcePolicy>(b"");
+    }
+
+    #[test]
+    fn test_opener_policy() {
+        eq(b"unsafe-none", coop(OpenerPolicy::UnsafeNone, None));
+        eq(b"same-origin-allow-popups", coop(OpenerPolicy::SameOriginAllowPopups, None));
+        eq(b"same-origin", coop(OpenerPolicy::SameOrigin, None));
+        eq(b"noopener-allow-popups", coop(OpenerPolicy::NoopenerAllowPopups, None));
+        eq(b"same-origin; report-to=\"coop\"", coop(OpenerPolicy::SameOrigin, Some("coop")));
+        assert_eq!(CrossOriginOpenerPolicy::parse(b"same-origin;foo=bar"),
+                   Some(coop(OpenerPolicy::SameOrigin, None)));
+        bad::<CrossOriginOpenerPolicy>(b"same-site");
+        bad::<CrossOriginOpenerPolicy>(b"same-origin; report-to=coop");
+        bad::<CrossOriginOpenerPolicy>(b"same-origin; report-to");
+    }
+
+    #[test]
+    fn test_embedder_policy() {
+        eq(b"unsafe-none", coep(EmbedderPolicy::UnsafeNone, None));
+        eq(b"require-corp", coep(EmbedderPolicy::RequireCorp, None));
+        eq(b"credentialless; report-to=\"a \\\"b\\\"\"",
+           coep(EmbedderPolicy::Credentialless, Some("a \"b\"")));
+        bad::<CrossOriginEmbedderPolicy>(b"require-cors");
+        bad::<CrossOriginEmbedderPolicy>(b"require-corp, credentialless");
+    }
+}
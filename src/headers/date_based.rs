@@ -0,0 +1,435 @@
+//! HTTP-date handling (RFC 7231, section 7.1.1.1), for `Date`, `Expires`, `Last-Modified` et al.
+//!
+//! RFC 7231 grammar:
+//!
+//! ```abnf
+//! HTTP-date    = IMF-fixdate / obs-date
+//! obs-date     = rfc850-date / asctime-date
+//! ```
+//!
+//! Which is to say, these three forms of the same date are all acceptable, though the latter two
+//! are obsolete and only the first is ever produced:
+//!
+//! ```text
+//! Sun, 06 Nov 1994 08:49:37 GMT    ; IMF-fixdate
+//! Sunday, 06-Nov-94 08:49:37 GMT   ; obsolete RFC 850 format
+//! Sun Nov  6 08:49:37 1994         ; ANSI C's asctime() format
+//! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str;
+
+use time::{self, Timespec, Tm};
+
+use super::{ToHeader, Header, Headers, Marker, ParseMode};
+
+/// The `strptime` format of an IMF-fixdate.
+const IMF_FIXDATE: &'static str = "%a, %d %b %Y %T GMT";
+/// The `strptime` format of an RFC 850 date.
+const RFC_850_DATE: &'static str = "%A, %d-%b-%y %T GMT";
+/// The `strptime` format of an asctime date.
+const ASCTIME_DATE: &'static str = "%a %b %e %T %Y";
+
+/// Which forms of HTTP-date to accept.
+///
+/// Recipients are required to accept all three, which is the default, but some deployments
+/// would rather reject the obsolete forms outright, as nobody has any business sending them. An
+/// IMF-fixdate is always accepted.
+///
+/// `HttpDate::parse`, and so `Headers::get`, always accepts all three; to apply a configuration
+/// to a received header, use `Headers::get_date_with`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DateParseConfig {
+    /// Whether to accept the obsolete RFC 850 format.
+    pub rfc_850: bool,
+    /// Whether to accept the obsolete asctime format.
+    pub asctime: bool,
+}
+
+impl DateParseConfig {
+    /// Accept only IMF-fixdates (the RFC 1123 format).
+    pub fn imf_fixdate_only() -> DateParseConfig {
+        DateParseConfig { rfc_850: false, asctime: false }
+    }
+}
+
+/// Accept all three forms.
+impl Default for DateParseConfig {
+    fn default() -> DateParseConfig {
+        DateParseConfig { rfc_850: true, asctime: true }
+    }
+}
+
+/// Parse an HTTP-date in any of its three forms, producing a UTC `Tm`.
+pub fn parse_http_date(raw: &[u8]) -> Option<Tm> {
+    parse_date_with(raw, DateParseConfig::default())
+}
+
+/// Parse an HTTP-date in the forms which the configuration permits, producing a UTC `Tm`.
+pub fn parse_date_with(raw: &[u8], config: DateParseConfig) -> Option<Tm> {
+    let s = match str::from_utf8(raw) {
+        Ok(s) => s,
+        Err(_) => return None,
+    };
+    let formats = [(IMF_FIXDATE, true), (RFC_850_DATE, config.rfc_850),
+                   (ASCTIME_DATE, config.asctime)];
+    for &(format, _) in formats.iter().filter(|&&(_, enabled)| enabled) {
+        let mut tm = match time::strptime(s, format) {
+            Ok(tm) => tm,
+            Err(_) => continue,
+        };
+        // strptime ignores anything left over after the format is exhausted, so we check that
+        // formatting it again produces the same thing. This also rejects unpadded values.
+        match tm.strftime(format) {
+            Ok(ref formatted) if formatted.to_string() == s => (),
+            _ => continue,
+        }
+        if format == RFC_850_DATE && tm.tm_year < 70 {
+            // RFC 7231: “Recipients of a timestamp value in rfc850-date format, which uses a
+            // two-digit year, MUST interpret a timestamp that appears to be more than 50 years in
+            // the future as representing the most recent year in the past that had the same last
+            // two digits.” This is a simplification of that.
+            tm.tm_year += 100;
+        }
+        // This normalises the fields that strptime doesn’t fill in, like tm_yday.
+        return Some(time::at_utc(tm.to_timespec()));
+    }
+    None
+}
+
+/// Remove a fraction of a second from the time in a date, if there is one: `08:49:37.123`
+/// becomes `08:49:37`.
+fn strip_fractional_seconds(raw: &[u8]) -> Option<Vec<u8>> {
+    let is_digit = |c: u8| c >= b'0' && c <= b'9';
+    for dot in 6..raw.len() {
+        if raw[dot] == b'.' && raw[dot - 3] == b':' && is_digit(raw[dot - 2]) &&
+                is_digit(raw[dot - 1]) {
+            let end = dot + 1 + raw[dot + 1..].iter().take_while(|&&c| is_digit(c)).count();
+            if end > dot + 1 {
+                return Some([&raw[..dot], &raw[end..]].concat());
+            }
+        }
+    }
+    None
+}
+
+/// The instant a `Tm` stands for, in whole seconds since the epoch, whatever its UTC offset.
+///
+/// `Tm::to_timespec` only honours a non-zero `tm_utcoff` by way of the system’s local timezone,
+/// so a time from a different zone (such as one parsed with `%z`) comes out wrong; this reads the
+/// fields as UTC and applies the offset itself.
+fn utc_seconds(tm: &Tm) -> i64 {
+    let mut utc = *tm;
+    utc.tm_utcoff = 0;
+    utc.tm_isdst = 0;
+    utc.to_timespec().sec - tm.tm_utcoff as i64
+}
+
+/// Compare two dates by the instant they stand for, to the second, whatever timezone each is in.
+///
+/// HTTP-dates are always GMT, but a date may have come from elsewhere, such as a file’s
+/// modification time in local time, and comparing `Tm`s field by field would be wrong then.
+/// Fractions of a second are ignored, for an HTTP-date can’t express them.
+pub fn cmp_http_dates(a: &Tm, b: &Tm) -> Ordering {
+    utc_seconds(a).cmp(&utc_seconds(b))
+}
+
+/// An HTTP-date, as used by `Date`, `Last-Modified`, `Sunset` and the like.
+///
+/// This is a newtype rather than an implementation of `ToHeader` and `Header` on `Tm` itself,
+/// so that only the values which are meant to be HTTP-dates get their semantics.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct HttpDate(pub Tm);
+
+impl HttpDate {
+    /// The current time, in UTC, truncated to whole seconds, as for the `Date` of a response.
+    ///
+    /// An HTTP-date can’t express a fraction of a second, so this drops it here rather than on
+    /// formatting; otherwise the value set would not equal the value parsed back from the
+    /// message, and comparisons with it, as for conditional requests, would be off.
+    pub fn now() -> HttpDate {
+        let now = time::get_time();
+        HttpDate(time::at_utc(Timespec::new(now.sec, 0)))
+    }
+}
+
+impl From<Tm> for HttpDate {
+    fn from(tm: Tm) -> HttpDate {
+        HttpDate(tm)
+    }
+}
+
+/// Parse an HTTP-date as `parse_date_with`, but in `ParseMode::Lenient` also tolerating and
+/// truncating a fraction of a second.
+fn parse_date_in_mode(raw: &[u8], config: DateParseConfig, mode: ParseMode) -> Option<Tm> {
+    match parse_date_with(raw, config) {
+        None if mode == ParseMode::Lenient => {
+            strip_fractional_seconds(raw).and_then(|raw| parse_date_with(&raw, config))
+        },
+        tm => tm,
+    }
+}
+
+impl ToHeader for HttpDate {
+    fn parse(raw: &[u8]) -> Option<HttpDate> {
+        parse_http_date(raw).map(HttpDate)
+    }
+
+    /// In `ParseMode::Lenient`, a fraction of a second after the time, as some servers send, is
+    /// tolerated and truncated.
+    fn parse_with_mode(raw: &[u8], mode: ParseMode) -> Option<HttpDate> {
+        parse_date_in_mode(raw, DateParseConfig::default(), mode).map(HttpDate)
+    }
+}
+
+/// HTTP-dates are always written as IMF-fixdates, in GMT.
+impl Header for HttpDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tm = self.0.to_utc();
+        let year = tm.tm_year + 1900;
+        if year < 0 || year > 9999 || tm.tm_wday < 0 || tm.tm_wday > 6 || tm.tm_mon < 0 ||
+                tm.tm_mon > 11 || tm.tm_mday < 1 || tm.tm_mday > 31 || tm.tm_hour < 0 ||
+                tm.tm_hour > 23 || tm.tm_min < 0 || tm.tm_min > 59 || tm.tm_sec < 0 ||
+                tm.tm_sec > 60 {
+            // Not representable in the fixed format (a `Tm` built by hand may hold anything); let
+            // the time crate do its best.
+            return write!(f, "{}", tm.rfc822());
+        }
+        // Dates go in every response, so this is written straight into a buffer of the fixed
+        // shape, `Sun, 06 Nov 1994 08:49:37 GMT`, rather than through `strftime`.
+        let mut buf = *b"Sun, 00 Jan 0000 00:00:00 GMT";
+        fn two_digits(buf: &mut [u8], n: i32) {
+            buf[0] = b'0' + (n / 10 % 10) as u8;
+            buf[1] = b'0' + (n % 10) as u8;
+        }
+        fn name(buf: &mut [u8], names: &[u8], i: i32) {
+            for (out, &c) in buf.iter_mut().zip(&names[i as usize * 3..]) {
+                *out = c;
+            }
+        }
+        name(&mut buf[..3], b"SunMonTueWedThuFriSat", tm.tm_wday);
+        two_digits(&mut buf[5..7], tm.tm_mday);
+        name(&mut buf[8..11], b"JanFebMarAprMayJunJulAugSepOctNovDec", tm.tm_mon);
+        two_digits(&mut buf[12..14], year / 100);
+        two_digits(&mut buf[14..16], year % 100);
+        two_digits(&mut buf[17..19], tm.tm_hour);
+        two_digits(&mut buf[20..22], tm.tm_min);
+        two_digits(&mut buf[23..25], tm.tm_sec);
+        // All ASCII, so this can’t fail.
+        f.write_str(unsafe { str::from_utf8_unchecked(&buf) })
+    }
+}
+
+define_single_header_marker! {
+    /// The `Date` header (RFC 7231, section 7.1.1.2): when the message was originated.
+    pub DATE: HttpDate = "date"
+}
+
+impl Headers {
+    /// Get an HTTP-date header, accepting only the forms which the configuration permits, where
+    /// `get` accepts all three.
+    ///
+    /// This parses the raw value afresh each time, in the collection’s `parse_mode`; a date which
+    /// was set typed is written as an IMF-fixdate, so is always accepted.
+    pub fn get_date_with<'a, M: Marker<'a, Base = HttpDate>>(&'a self, marker: M,
+                                                              config: DateParseConfig)
+                                                              -> Option<HttpDate> {
+        let raw = match self.get_raw(marker) {
+            Some(raw) => raw,
+            None => return None,
+        };
+        if raw.len() != 1 {
+            return None;
+        }
+        parse_date_in_mode(&raw[0], config, self.parse_mode()).map(HttpDate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::fmt;
+    use time::{self, Timespec};
+    use headers::{Header, Headers, ToHeader, HeaderDisplayAdapter, ParseMode};
+    use headers::conditional::IF_MODIFIED_SINCE;
+    use super::{DateParseConfig, HttpDate, DATE, parse_date_with, cmp_http_dates};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    fn sunday_06_nov_1994() -> HttpDate {
+        HttpDate(time::at_utc(Timespec::new(784111777, 0)))
+    }
+
+    #[test]
+    fn test_imf_fixdate() {
+        eq(b"Sun, 06 Nov 1994 08:49:37 GMT", sunday_06_nov_1994());
+        eq(b"Thu, 01 Jan 1970 00:00:00 GMT", HttpDate(time::at_utc(Timespec::new(0, 0))));
+    }
+
+    #[test]
+    fn test_formatting() {
+        for &seconds in &[0, 784111777, 951782400, 1234567890, 253402300799] {
+            let tm = time::at_utc(Timespec::new(seconds, 0));
+            assert_eq!(format!("{}", HeaderDisplayAdapter(&HttpDate(tm))),
+                       tm.rfc822().to_string());
+        }
+        let local = time::at(Timespec::new(784111777, 0));
+        eq(b"Sun, 06 Nov 1994 08:49:37 GMT", HttpDate(local.to_utc()));
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&HttpDate(local))),
+                   "Sun, 06 Nov 1994 08:49:37 GMT");
+
+        // Fields out of range, as a `Tm` built by hand may have, go to `strftime` too.
+        let gmt = sunday_06_nov_1994().0;
+        for &(mday, hour, min, sec) in &[(-1, 8, 49, 37), (32, 8, 49, 37), (6, -1, 49, 37),
+                                         (6, 300, 49, 37), (6, 8, -60, 37), (6, 8, 60, 37),
+                                         (6, 8, 49, -1), (6, 8, 49, 61)] {
+            let mut tm = gmt;
+            tm.tm_mday = mday;
+            tm.tm_hour = hour;
+            tm.tm_min = min;
+            tm.tm_sec = sec;
+            assert_eq!(format!("{}", HeaderDisplayAdapter(&HttpDate(tm))),
+                       tm.rfc822().to_string());
+        }
+    }
+
+    #[test]
+    fn test_obsolete_formats() {
+        assert_eq!(HttpDate::parse(b"Sunday, 06-Nov-94 08:49:37 GMT"), Some(sunday_06_nov_1994()));
+        assert_eq!(HttpDate::parse(b"Sun Nov  6 08:49:37 1994"), Some(sunday_06_nov_1994()));
+        assert_eq!(HttpDate::parse(b"Thursday, 01-Jan-15 00:00:00 GMT"),
+                   Some(HttpDate(time::at_utc(Timespec::new(1420070400, 0)))));
+    }
+
+    #[test]
+    fn test_date_parse_config() {
+        let strict = DateParseConfig::imf_fixdate_only();
+        assert_eq!(parse_date_with(b"Sun, 06 Nov 1994 08:49:37 GMT", strict),
+                   Some(sunday_06_nov_1994().0));
+        assert_eq!(parse_date_with(b"Sun Nov  6 08:49:37 1994", strict), None);
+        assert_eq!(parse_date_with(b"Sunday, 06-Nov-94 08:49:37 GMT", strict), None);
+
+        let no_asctime = DateParseConfig { asctime: false, .. DateParseConfig::default() };
+        assert_eq!(parse_date_with(b"Sun Nov  6 08:49:37 1994", no_asctime), None);
+        assert_eq!(parse_date_with(b"Sunday, 06-Nov-94 08:49:37 GMT", no_asctime),
+                   Some(sunday_06_nov_1994().0));
+        assert_eq!(parse_date_with(b"Sun Nov  6 08:49:37 1994", DateParseConfig::default()),
+                   Some(sunday_06_nov_1994().0));
+    }
+
+    #[test]
+    fn test_get_date_with() {
+        let strict = DateParseConfig::imf_fixdate_only();
+        let mut headers = Headers::parse_block(b"Date: Sun Nov  6 08:49:37 1994\r\n").unwrap();
+        headers.append_raw("If-Modified-Since", b"Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        assert_eq!(headers.get_date_with(DATE, strict), None);
+        assert_eq!(headers.get_date_with(DATE, DateParseConfig::default()),
+                   Some(sunday_06_nov_1994()));
+        assert_eq!(**headers.get(DATE).unwrap(), sunday_06_nov_1994());
+        assert_eq!(headers.get_date_with(IF_MODIFIED_SINCE, strict), Some(sunday_06_nov_1994()));
+
+        // A date set typed is written as an IMF-fixdate, which any configuration accepts.
+        headers.set(DATE, sunday_06_nov_1994());
+        assert_eq!(headers.get_date_with(DATE, strict), Some(sunday_06_nov_1994()));
+
+        // The collection’s parse mode applies too.
+        let mut headers = Headers::parse_block(b"Date: Sun, 06 Nov 1994 08:49:37.5 GMT\r\n")
+                                  .unwrap();
+        assert_eq!(headers.get_date_with(DATE, strict), None);
+        headers.set_parse_mode(ParseMode::Lenient);
+        assert_eq!(headers.get_date_with(DATE, strict), Some(sunday_06_nov_1994()));
+        assert_eq!(Headers::new().get_date_with(DATE, strict), None);
+    }
+
+    #[test]
+    fn test_fractional_seconds() {
+        let noon = HttpDate(time::at_utc(Timespec::new(784123200, 0)));
+        for raw in &[&b"Sun, 06 Nov 1994 12:00:00.123 GMT"[..], b"Sunday, 06-Nov-94 12:00:00.5 GMT",
+                     b"Sun Nov  6 12:00:00.123456 1994"] {
+            assert_eq!(HttpDate::parse_with_mode(raw, ParseMode::Strict), None);
+            assert_eq!(HttpDate::parse(raw), None);
+            assert_eq!(HttpDate::parse_with_mode(raw, ParseMode::Lenient), Some(noon));
+        }
+        assert_eq!(HttpDate::parse_with_mode(b"Sun, 06 Nov 1994 12:00:00 GMT", ParseMode::Lenient),
+                   Some(noon));
+        assert_eq!(HttpDate::parse_with_mode(b"Sun, 06 Nov 1994 12:00:00. GMT", ParseMode::Lenient),
+                   None);
+        assert_eq!(HttpDate::parse_with_mode(b"Sun, 06 Nov 1994 12:00.123 GMT", ParseMode::Lenient),
+                   None);
+    }
+
+    #[test]
+    fn test_fractional_seconds_through_headers() {
+        let noon = HttpDate(time::at_utc(Timespec::new(784123200, 0)));
+        let mut headers = Headers::parse_block(b"Date: Sun, 06 Nov 1994 12:00:00.123 GMT\r\n")
+                                  .unwrap();
+        assert!(headers.get(DATE).is_none());
+        headers.set_parse_mode(ParseMode::Lenient);
+        assert_eq!(**headers.get(DATE).unwrap(), noon);
+        // The raw value is still what was received.
+        assert_eq!(headers.get_raw_combined("date"),
+                   Some(b"Sun, 06 Nov 1994 12:00:00.123 GMT".to_vec()));
+    }
+
+    #[test]
+    fn test_cmp_http_dates() {
+        let gmt = sunday_06_nov_1994().0;
+        // The same instant, as 09:49:37 +0100.
+        let mut plus_one = gmt;
+        plus_one.tm_hour += 1;
+        plus_one.tm_utcoff = 3600;
+        assert_eq!(cmp_http_dates(&plus_one, &gmt), Ordering::Equal);
+        assert_eq!(cmp_http_dates(&gmt, &plus_one), Ordering::Equal);
+
+        // And across midnight, as 23:49:37 -0900 the day before.
+        let mut minus_nine = gmt;
+        minus_nine.tm_mday -= 1;
+        minus_nine.tm_wday = 6;
+        minus_nine.tm_hour = 23;
+        minus_nine.tm_utcoff = -9 * 3600;
+        assert_eq!(cmp_http_dates(&minus_nine, &gmt), Ordering::Equal);
+
+        let later = time::at_utc(Timespec::new(784111778, 0));
+        assert_eq!(cmp_http_dates(&plus_one, &later), Ordering::Less);
+        assert_eq!(cmp_http_dates(&later, &plus_one), Ordering::Greater);
+        // A fraction of a second makes no difference.
+        let fraction = time::at_utc(Timespec::new(784111777, 999_999_999));
+        assert_eq!(cmp_http_dates(&fraction, &gmt), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_now() {
+        let before = time::get_time().sec;
+        let now = HttpDate::now();
+        let after = time::get_time().sec;
+        assert_eq!(now.0.tm_nsec, 0);
+        let sec = now.0.to_timespec().sec;
+        assert!(before <= sec && sec <= after);
+
+        // It formats as a plain IMF-fixdate, and survives the round trip exactly.
+        let formatted = format!("{}", HeaderDisplayAdapter(&now));
+        assert_eq!(formatted.len(), "Sun, 06 Nov 1994 08:49:37 GMT".len());
+        assert!(formatted.ends_with(" GMT") && !formatted.contains('.'));
+        assert_eq!(HttpDate::parse(formatted.as_bytes()), Some(now));
+    }
+
+    #[test]
+    fn test_bad_dates() {
+        bad::<HttpDate>(b"");
+        bad::<HttpDate>(b"0");
+        bad::<HttpDate>(b"3600");
+        bad::<HttpDate>(b"Sun, 06 Nov 1994 08:49:37 GMT ");
+        bad::<HttpDate>(b"Sun, 06 Nov 1994 08:49:37 PST");
+        bad::<HttpDate>(b"Sun, 6 Nov 1994 08:49:37 GMT");
+        bad::<HttpDate>(b"Sun Nov 6 08:49:37 1994");
+        bad::<HttpDate>(b"Sun, 06 Nov 1994 08:49:37");
+    }
+}
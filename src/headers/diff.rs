@@ -0,0 +1,193 @@
+//! Comparing two header collections, for logging what a piece of middleware changed.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::{Headers, push_escaped};
+
+/// A difference in one header between two collections. Values are raw field lines, in order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HeaderChange {
+    /// The header is only in the new collection.
+    Added {
+        /// The header name, lowercase.
+        name: String,
+        /// Its field lines.
+        new: Vec<Vec<u8>>,
+    },
+    /// The header is only in the old collection.
+    Removed {
+        /// The header name, lowercase.
+        name: String,
+        /// Its field lines.
+        old: Vec<Vec<u8>>,
+    },
+    /// The header is in both collections, with different field lines.
+    Changed {
+        /// The header name, lowercase.
+        name: String,
+        /// Its field lines in the old collection.
+        old: Vec<Vec<u8>>,
+        /// Its field lines in the new collection.
+        new: Vec<Vec<u8>>,
+    },
+}
+
+impl HeaderChange {
+    /// The name of the header which differs, lowercase.
+    pub fn name(&self) -> &str {
+        match *self {
+            HeaderChange::Added { ref name, .. } |
+            HeaderChange::Removed { ref name, .. } |
+            HeaderChange::Changed { ref name, .. } => name,
+        }
+    }
+}
+
+/// The differences between two header collections, as produced by `Headers::diff`.
+///
+/// The changes are sorted by header name. The `Display` implementation writes one line for each
+/// field line, `- name: value` for old and `+ name: value` for new, escaped as for
+/// `Headers::debug_dump`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HeaderDiff {
+    /// The headers which differ.
+    pub changes: Vec<HeaderChange>,
+}
+
+impl HeaderDiff {
+    /// Whether the collections had the same field lines.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The headers which are only in the new collection.
+    pub fn added(&self) -> Vec<&HeaderChange> {
+        self.changes.iter().filter(|c| match **c {
+            HeaderChange::Added { .. } => true,
+            _ => false,
+        }).collect()
+    }
+
+    /// The headers which are only in the old collection.
+    pub fn removed(&self) -> Vec<&HeaderChange> {
+        self.changes.iter().filter(|c| match **c {
+            HeaderChange::Removed { .. } => true,
+            _ => false,
+        }).collect()
+    }
+
+    /// The headers which are in both collections, with different field lines.
+    pub fn changed(&self) -> Vec<&HeaderChange> {
+        self.changes.iter().filter(|c| match **c {
+            HeaderChange::Changed { .. } => true,
+            _ => false,
+        }).collect()
+    }
+}
+
+impl fmt::Display for HeaderDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn lines(out: &mut String, sign: &str, name: &str, values: &[Vec<u8>]) {
+            for value in values {
+                out.push_str(sign);
+                out.push_str(name);
+                out.push_str(": ");
+                push_escaped(out, value);
+                out.push('\n');
+            }
+        }
+        let mut out = String::new();
+        for change in &self.changes {
+            match *change {
+                HeaderChange::Added { ref name, ref new } => lines(&mut out, "+ ", name, new),
+                HeaderChange::Removed { ref name, ref old } => lines(&mut out, "- ", name, old),
+                HeaderChange::Changed { ref name, ref old, ref new } => {
+                    lines(&mut out, "- ", name, old);
+                    lines(&mut out, "+ ", name, new);
+                },
+            }
+        }
+        f.write_str(&out)
+    }
+}
+
+/// Group the field lines of a collection by name.
+fn by_name(headers: &Headers) -> BTreeMap<&str, Vec<Vec<u8>>> {
+    let mut map = BTreeMap::new();
+    for (name, value) in headers.pairs() {
+        map.entry(name).or_insert_with(Vec::new).push(value);
+    }
+    map
+}
+
+impl Headers {
+    /// Find which headers were added, removed or changed between this collection and `other`.
+    ///
+    /// Headers are compared in raw form, as for `semantically_eq`, so whether a header is held
+    /// raw or typed makes no difference, but the order of its field lines does. Pseudo-header
+    /// fields are not compared. This is meant for middleware logging what it did to a message:
+    /// take a clone before, which is cheap, and diff it against the result after.
+    pub fn diff(&self, other: &Headers) -> HeaderDiff {
+        let mut old = by_name(self);
+        let mut changes = vec![];
+        for (name, new) in by_name(other) {
+            match old.remove(name) {
+                None => changes.push(HeaderChange::Added { name: name.to_owned(), new: new }),
+                Some(old) => if old != new {
+                    changes.push(HeaderChange::Changed {
+                        name: name.to_owned(),
+                        old: old,
+                        new: new,
+                    });
+                },
+            }
+        }
+        changes.extend(old.into_iter().map(|(name, old)| {
+            HeaderChange::Removed { name: name.to_owned(), old: old }
+        }));
+        changes.sort_by(|a, b| a.name().cmp(b.name()));
+        HeaderDiff { changes: changes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::Headers;
+    use super::HeaderChange;
+
+    #[test]
+    fn test_diff() {
+        let before = Headers::parse_block(b"Host: example.com\r\nX-Debug: 1\r\n\
+                                            Cache-Control: no-cache\r\n").unwrap();
+        let mut after = before.clone();
+        assert!(before.diff(&after).is_empty());
+
+        after.append_raw("Via", b"1.1 proxy").unwrap();
+        after.retain(|name, _| name != "x-debug");
+        after.append_raw("Cache-Control", b"max-age=60").unwrap();
+        let diff = before.diff(&after);
+        assert_eq!(diff.changes, vec![
+            HeaderChange::Changed {
+                name: "cache-control".to_owned(),
+                old: vec![b"no-cache".to_vec()],
+                new: vec![b"no-cache".to_vec(), b"max-age=60".to_vec()],
+            },
+            HeaderChange::Added { name: "via".to_owned(), new: vec![b"1.1 proxy".to_vec()] },
+            HeaderChange::Removed { name: "x-debug".to_owned(), old: vec![b"1".to_vec()] },
+        ]);
+        assert_eq!(diff.added().len(), 1);
+        assert_eq!(diff.removed()[0].name(), "x-debug");
+        assert_eq!(diff.changed()[0].name(), "cache-control");
+        assert_eq!(diff.to_string(), "- cache-control: no-cache\n\
+                                      + cache-control: no-cache\n\
+                                      + cache-control: max-age=60\n\
+                                      + via: 1.1 proxy\n\
+                                      - x-debug: 1\n");
+
+        // The other way round, additions and removals swap.
+        let reverse = after.diff(&before);
+        assert_eq!(reverse.added()[0].name(), "x-debug");
+        assert_eq!(reverse.removed()[0].name(), "via");
+    }
+}
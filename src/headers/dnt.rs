@@ -0,0 +1,107 @@
+//! The `DNT` header (Tracking Preference Expression, W3C).
+
+use std::fmt;
+
+use super::{ToHeader, Header, Headers};
+
+/// The user’s tracking preference, as expressed by the `DNT` header.
+///
+/// Grammar:
+///
+/// ```abnf
+/// DNT-field-value = ( "0" / "1" ) *DNT-extension
+/// ```
+///
+/// Extensions are not supported: only the single-character values are accepted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Dnt {
+    /// No preference was expressed: the header is absent (or invalid).
+    ///
+    /// This is never produced by parsing; it is what `Dnt::from_headers` returns when there is no
+    /// valid header. It has no representation, so it fails to format, and a `DNT` header set to
+    /// it produces no field line; to express no preference, remove the header.
+    NotSet,
+    /// `1`: the user prefers not to be tracked.
+    OptOut,
+    /// `0`: the user consents to being tracked.
+    OptIn,
+}
+
+impl Dnt {
+    /// Read the tracking preference from a message’s `DNT` header, producing `NotSet` if there is
+    /// no valid `DNT` header.
+    pub fn from_headers(headers: &Headers) -> Dnt {
+        headers.get(DNT).map_or(Dnt::NotSet, |dnt| *dnt)
+    }
+}
+
+impl ToHeader for Dnt {
+    fn parse(raw: &[u8]) -> Option<Dnt> {
+        match raw {
+            b"1" => Some(Dnt::OptOut),
+            b"0" => Some(Dnt::OptIn),
+            _ => None,
+        }
+    }
+}
+
+impl Header for Dnt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Dnt::NotSet => Err(fmt::Error),
+            Dnt::OptOut => f.write_str("1"),
+            Dnt::OptIn => f.write_str("0"),
+        }
+    }
+}
+
+define_single_header_marker! {
+    /// The `DNT` header, a tracking preference.
+    pub DNT: Dnt = "dnt"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use super::{Dnt, DNT};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_dnt() {
+        eq(b"1", Dnt::OptOut);
+        eq(b"0", Dnt::OptIn);
+        bad::<Dnt>(b"2");
+        bad::<Dnt>(b"");
+        bad::<Dnt>(b"10");
+    }
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = Headers::new();
+        assert_eq!(Dnt::from_headers(&headers), Dnt::NotSet);
+        headers.set(DNT, Dnt::OptOut);
+        assert_eq!(Dnt::from_headers(&headers), Dnt::OptOut);
+    }
+
+    #[test]
+    fn test_not_set_is_not_written() {
+        let mut headers = Headers::new();
+        headers.set(DNT, Dnt::NotSet);
+        assert!(headers.pairs().is_empty());
+        let mut out = vec![];
+        headers.write_to(&mut out).unwrap();
+        assert_eq!(out, b"");
+        let mut formatted = String::new();
+        let not_set = HeaderDisplayAdapter(&Dnt::NotSet);
+        assert!(fmt::write(&mut formatted, format_args!("{}", not_set)).is_err());
+    }
+}
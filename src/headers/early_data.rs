@@ -0,0 +1,74 @@
+//! The `Early-Data` header (RFC 8470, section 5.1).
+
+use std::fmt;
+
+use super::{ToHeader, Header};
+
+/// The `Early-Data` header, which an intermediary adds to a request that it received in TLS early
+/// data (0-RTT), so that the origin server can decide whether to risk processing it or to respond
+/// with `425 Too Early`.
+///
+/// RFC 8470 grammar:
+///
+/// ```abnf
+/// Early-Data = "1"
+/// ```
+///
+/// As `1` is the only valid value, the header’s presence is all that matters.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EarlyData;
+
+impl EarlyData {
+    /// Whether the request was conveyed in early data. Always true, given that the header has
+    /// parsed; it exists to make code testing for the header read well.
+    pub fn is_early_data(&self) -> bool {
+        true
+    }
+}
+
+impl ToHeader for EarlyData {
+    fn parse(raw: &[u8]) -> Option<EarlyData> {
+        if raw == b"1" {
+            Some(EarlyData)
+        } else {
+            None
+        }
+    }
+}
+
+impl Header for EarlyData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("1")
+    }
+}
+
+define_single_header_marker! {
+    /// The `Early-Data` header.
+    pub EARLY_DATA: EarlyData = "early-data"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter};
+    use super::EarlyData;
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_early_data() {
+        eq(b"1", EarlyData);
+        assert!(EarlyData.is_early_data());
+        bad::<EarlyData>(b"0");
+        bad::<EarlyData>(b"true");
+        bad::<EarlyData>(b"");
+        bad::<EarlyData>(b"11");
+    }
+}
@@ -0,0 +1,210 @@
+//! Entity-tags (RFC 7232, section 2.3), for `ETag`, `If-Match` and `If-None-Match`.
+
+use std::fmt;
+use std::str;
+
+use grammar::quoted_string;
+use super::{ToHeader, Header};
+
+/// An entity-tag: an opaque validator for a representation, strong or weak.
+///
+/// RFC 7232 grammar:
+///
+/// ```abnf
+/// entity-tag = [ weak ] opaque-tag
+/// weak       = %x57.2F ; "W/", case-sensitive
+/// opaque-tag = DQUOTE *etagc DQUOTE
+/// etagc      = %x21 / %x23-7E / obs-text
+/// ```
+///
+/// RFC 7232 has no escaping inside an opaque-tag, but RFC 2616 defined it as a quoted-string, and
+/// servers which base 64 or percent-encode things into their tags sometimes escape with
+/// quoted-pairs too. So an opaque-tag is parsed as a quoted-string, and its quoted-pairs resolved:
+/// what is kept is the true opaque value, and two tags differing only in escaping are equal. The
+/// cost is that a lone backslash in a tag, which RFC 7232 permits, is taken as an escape.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct EntityTag {
+    weak: bool,
+    opaque: Vec<u8>,
+}
+
+impl EntityTag {
+    /// Construct a strong entity-tag. Returns `None` if the opaque value contains control
+    /// characters or non-ASCII bytes.
+    pub fn strong(opaque: &[u8]) -> Option<EntityTag> {
+        EntityTag::new(false, opaque)
+    }
+
+    /// Construct a weak entity-tag. Returns `None` if the opaque value contains control
+    /// characters or non-ASCII bytes.
+    pub fn weak(opaque: &[u8]) -> Option<EntityTag> {
+        EntityTag::new(true, opaque)
+    }
+
+    fn new(weak: bool, opaque: &[u8]) -> Option<EntityTag> {
+        if opaque.iter().all(|&c| c >= 0x20 && c <= 0x7e) {
+            Some(EntityTag { weak: weak, opaque: opaque.to_vec() })
+        } else {
+            None
+        }
+    }
+
+    /// Whether this is a weak entity-tag.
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// The opaque value, unquoted, with any quoted-pairs resolved.
+    pub fn opaque_bytes(&self) -> &[u8] {
+        &self.opaque
+    }
+
+    /// Strong comparison (RFC 7232, section 2.3.2): both tags are strong, with the same opaque
+    /// value.
+    pub fn strong_eq(&self, other: &EntityTag) -> bool {
+        !self.weak && !other.weak && self.opaque == other.opaque
+    }
+
+    /// Weak comparison (RFC 7232, section 2.3.2): the tags have the same opaque value, whether
+    /// strong or weak.
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.opaque == other.opaque
+    }
+}
+
+impl ToHeader for EntityTag {
+    fn parse(raw: &[u8]) -> Option<EntityTag> {
+        let (weak, raw) = if raw.starts_with(b"W/") {
+            (true, &raw[2..])
+        } else {
+            (false, raw)
+        };
+        quoted_string::unquote(raw).map(|opaque| EntityTag { weak: weak, opaque: opaque })
+    }
+}
+
+/// Tags which were parsed with obs-text in them can’t be written, as they aren’t UTF-8.
+impl Header for EntityTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.weak {
+            try!(f.write_str("W/"));
+        }
+        match quoted_string::quote(&self.opaque) {
+            Some(ref quoted) => match str::from_utf8(quoted) {
+                Ok(quoted) => f.write_str(quoted),
+                Err(_) => Err(fmt::Error),
+            },
+            None => Err(fmt::Error),
+        }
+    }
+}
+
+/// An item of an `If-Match` or `If-None-Match` header.
+///
+/// RFC 7232 grammar:
+///
+/// ```abnf
+/// If-Match      = "*" / 1#entity-tag
+/// If-None-Match = "*" / 1#entity-tag
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EntityTagMatch {
+    /// `*`: any current representation.
+    Any,
+    /// A specific entity-tag.
+    Tag(EntityTag),
+}
+
+impl ToHeader for EntityTagMatch {
+    fn parse(raw: &[u8]) -> Option<EntityTagMatch> {
+        if raw == b"*" {
+            Some(EntityTagMatch::Any)
+        } else {
+            EntityTag::parse(raw).map(EntityTagMatch::Tag)
+        }
+    }
+}
+
+impl Header for EntityTagMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EntityTagMatch::Any => f.write_str("*"),
+            EntityTagMatch::Tag(ref tag) => tag.fmt(f),
+        }
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_single_header_marker! {
+    /// The `ETag` header: the entity-tag of the selected representation.
+    pub ETAG: EntityTag = "etag"
+}
+
+define_list_header_marker! {
+    /// The `If-Match` header: perform the request only if the current representation matches.
+    pub IF_MATCH: EntityTagMatch = "if-match"
+}
+
+define_list_header_marker! {
+    /// The `If-None-Match` header: perform the request only if no current representation
+    /// matches.
+    pub IF_NONE_MATCH: EntityTagMatch = "if-none-match"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter};
+    use super::{EntityTag, EntityTagMatch};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_entity_tag() {
+        eq(b"\"xyzzy\"", EntityTag::strong(b"xyzzy").unwrap());
+        eq(b"W/\"xyzzy\"", EntityTag::weak(b"xyzzy").unwrap());
+        eq(b"\"\"", EntityTag::strong(b"").unwrap());
+        eq(b"*", EntityTagMatch::Any);
+        eq(b"W/\"1\"", EntityTagMatch::Tag(EntityTag::weak(b"1").unwrap()));
+        assert_eq!(EntityTag::strong(b"\x7f"), None);
+        bad::<EntityTag>(b"xyzzy");
+        bad::<EntityTag>(b"w/\"xyzzy\"");
+        bad::<EntityTag>(b"\"xyzzy");
+        bad::<EntityTag>(b"\"xy\"zzy");
+        bad::<EntityTagMatch>(b"W/*");
+    }
+
+    #[test]
+    fn test_escaping() {
+        let tag = EntityTag::parse(b"\"a\\\"b\"").unwrap();
+        assert_eq!(tag.opaque_bytes(), b"a\"b");
+        eq(b"\"a\\\"b\"", tag.clone());
+        let unnecessarily_escaped = EntityTag::parse(b"\"\\a\\\"\\b\"").unwrap();
+        assert_eq!(unnecessarily_escaped.opaque_bytes(), b"a\"b");
+        assert!(tag.strong_eq(&unnecessarily_escaped));
+        assert_eq!(tag, unnecessarily_escaped);
+    }
+
+    #[test]
+    fn test_comparison() {
+        let strong = EntityTag::strong(b"1").unwrap();
+        let weak = EntityTag::weak(b"1").unwrap();
+        let other = EntityTag::strong(b"2").unwrap();
+        assert!(strong.strong_eq(&strong));
+        assert!(!strong.strong_eq(&weak));
+        assert!(!weak.strong_eq(&weak));
+        assert!(strong.weak_eq(&weak));
+        assert!(weak.weak_eq(&weak));
+        assert!(!strong.weak_eq(&other));
+    }
+}
@@ -0,0 +1,190 @@
+//! The `Expect-CT` header (RFC 9163), now obsolete but still sent by some servers.
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+
+use grammar::parameters::split_token;
+use grammar::{quoted_string, trim_ows_start};
+use super::{ToHeader, Header};
+use super::cache_control::parse_delta_seconds;
+
+/// The `Expect-CT` header: a request that the user agent require Certificate Transparency for
+/// this host.
+///
+/// RFC 9163 grammar:
+///
+/// ```abnf
+/// Expect-CT           = 1#expect-ct-directive
+/// expect-ct-directive = directive-name [ "=" directive-value ]
+/// directive-name      = token
+/// directive-value     = token / quoted-string
+/// ```
+///
+/// Directive names are case-insensitive. A known directive given twice, or with the wrong kind of
+/// value, makes the header invalid, as does the lack of any directive at all; unknown directives
+/// are ignored.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExpectCt {
+    /// `max-age`: how many seconds to remember the policy for. RFC 9163 requires it, but it’s
+    /// optional here for the sake of lax servers.
+    pub max_age: Option<u64>,
+    /// `enforce`: whether to refuse connections which don’t comply, rather than just reporting.
+    pub enforce: bool,
+    /// `report-uri`: where to report failures, unquoted.
+    pub report_uri: Option<String>,
+}
+
+impl ToHeader for ExpectCt {
+    fn parse(mut raw: &[u8]) -> Option<ExpectCt> {
+        let mut expect_ct = ExpectCt { max_age: None, enforce: false, report_uri: None };
+        let mut seen = vec![];
+        loop {
+            raw = trim_ows_start(raw);
+            if raw.first() == Some(&b',') {
+                raw = &raw[1..];
+                continue;
+            }
+            if raw.is_empty() {
+                break;
+            }
+            let (name, rest) = split_token(raw);
+            if name.is_empty() {
+                return None;
+            }
+            let (value, rest) = if rest.first() == Some(&b'=') {
+                let rest = &rest[1..];
+                let (value, rest) = if rest.first() == Some(&b'"') {
+                    match quoted_string::split(rest) {
+                        Some((quoted, rest)) => (quoted_string::unquote(quoted), rest),
+                        None => return None,
+                    }
+                } else {
+                    let (value, rest) = split_token(rest);
+                    if value.is_empty() {
+                        return None;
+                    }
+                    (Some(value.to_vec()), rest)
+                };
+                (value, rest)
+            } else {
+                (None, rest)
+            };
+            raw = trim_ows_start(rest);
+            if !raw.is_empty() && raw[0] != b',' {
+                return None;
+            }
+            // Tokens are ASCII, so this can’t fail.
+            let name = unsafe { str::from_utf8_unchecked(name) }.to_ascii_lowercase();
+            match &name[..] {
+                "max-age" | "enforce" | "report-uri" if seen.contains(&name) => return None,
+                "max-age" => match value.and_then(|value| parse_delta_seconds(&value)) {
+                    Some(max_age) => expect_ct.max_age = Some(max_age),
+                    None => return None,
+                },
+                "enforce" if value.is_none() => expect_ct.enforce = true,
+                "report-uri" => match value.map(String::from_utf8) {
+                    Some(Ok(report_uri)) => expect_ct.report_uri = Some(report_uri),
+                    _ => return None,
+                },
+                "enforce" => return None,
+                _ => (),
+            }
+            seen.push(name);
+        }
+        if seen.is_empty() {
+            return None;
+        }
+        Some(expect_ct)
+    }
+}
+
+/// Directives are written in the order `max-age`, `enforce`, `report-uri`, the last quoted.
+impl Header for ExpectCt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        let mut separate = |f: &mut fmt::Formatter| {
+            if first {
+                first = false;
+                Ok(())
+            } else {
+                f.write_str(", ")
+            }
+        };
+        if let Some(max_age) = self.max_age {
+            try!(separate(f));
+            try!(write!(f, "max-age={}", max_age));
+        }
+        if self.enforce {
+            try!(separate(f));
+            try!(f.write_str("enforce"));
+        }
+        if let Some(ref report_uri) = self.report_uri {
+            try!(separate(f));
+            try!(f.write_str("report-uri="));
+            match quoted_string::quote(report_uri.as_bytes()) {
+                // quote only adds ASCII to what was already UTF-8, so this can’t fail.
+                Some(quoted) => try!(f.write_str(unsafe { str::from_utf8_unchecked(&quoted) })),
+                None => return Err(fmt::Error),
+            }
+        }
+        Ok(())
+    }
+}
+
+define_single_header_marker! {
+    /// The `Expect-CT` header.
+    pub EXPECT_CT: ExpectCt = "expect-ct", combine
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, Headers, ToHeader, HeaderDisplayAdapter};
+    use super::{ExpectCt, EXPECT_CT};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_expect_ct() {
+        eq(b"max-age=86400, enforce, report-uri=\"https://ex.com/r\"", ExpectCt {
+            max_age: Some(86400),
+            enforce: true,
+            report_uri: Some("https://ex.com/r".to_owned()),
+        });
+        eq(b"max-age=0", ExpectCt { max_age: Some(0), enforce: false, report_uri: None });
+        assert_eq!(ExpectCt::parse(b"Report-URI=\"https://ex.com/a,b\",ENFORCE ,x=\"y\", z"),
+                   Some(ExpectCt {
+                       max_age: None,
+                       enforce: true,
+                       report_uri: Some("https://ex.com/a,b".to_owned()),
+                   }));
+        bad::<ExpectCt>(b"max-age=1, max-age=2");
+        bad::<ExpectCt>(b"max-age");
+        bad::<ExpectCt>(b"max-age=-1");
+        bad::<ExpectCt>(b"enforce=yes");
+        bad::<ExpectCt>(b"report-uri=\"https://ex.com/r");
+        bad::<ExpectCt>(b"max-age=1 enforce");
+        bad::<ExpectCt>(b"");
+        bad::<ExpectCt>(b" , ,");
+    }
+
+    #[test]
+    fn test_several_lines() {
+        let mut headers = Headers::new();
+        headers.append_raw("Expect-CT", b"max-age=60").unwrap();
+        headers.append_raw("Expect-CT", b"enforce").unwrap();
+        assert_eq!(*headers.get(EXPECT_CT).unwrap(),
+                   ExpectCt { max_age: Some(60), enforce: true, report_uri: None });
+        // A directive on two of the lines is as much a duplicate as on one.
+        headers.append_raw("Expect-CT", b"max-age=0").unwrap();
+        assert!(headers.get(EXPECT_CT).is_none());
+    }
+}
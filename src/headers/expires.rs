@@ -0,0 +1,72 @@
+//! The `Expires` header (RFC 7234, section 5.3).
+
+use std::fmt;
+
+use time::Tm;
+
+use super::date_based::{HttpDate, parse_http_date};
+use super::{ToHeader, Header};
+
+/// The `Expires` header: the date after which the response is considered stale.
+///
+/// RFC 7234 grammar:
+///
+/// ```abnf
+/// Expires = HTTP-date
+/// ```
+///
+/// RFC 7234 requires that caches treat an invalid date, “especially including the value "0"”, as
+/// a time in the past, so parsing never fails: anything which isn’t an HTTP-date is `Past`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Expires {
+    /// The response becomes stale at this time.
+    At(Tm),
+    /// The response has already expired. This is written as `0`.
+    Past,
+}
+
+impl ToHeader for Expires {
+    fn parse(raw: &[u8]) -> Option<Expires> {
+        Some(match parse_http_date(raw) {
+            Some(date) => Expires::At(date),
+            None => Expires::Past,
+        })
+    }
+}
+
+impl Header for Expires {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Expires::At(ref date) => HttpDate(*date).fmt(f),
+            Expires::Past => f.write_str("0"),
+        }
+    }
+}
+
+define_single_header_marker! {
+    /// The `Expires` header.
+    pub EXPIRES: Expires = "expires"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use time::{self, Timespec};
+    use headers::{Header, ToHeader, HeaderDisplayAdapter};
+    use super::Expires;
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    #[test]
+    fn test_expires() {
+        eq(b"Thu, 01 Dec 1994 16:00:00 GMT",
+           Expires::At(time::at_utc(Timespec::new(786297600, 0))));
+        eq(b"0", Expires::Past);
+        assert_eq!(Expires::parse(b"-1"), Some(Expires::Past));
+        assert_eq!(Expires::parse(b""), Some(Expires::Past));
+        assert_eq!(Expires::parse(b"Thu, 01 Dec 1994"), Some(Expires::Past));
+    }
+}
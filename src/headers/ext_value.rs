@@ -0,0 +1,214 @@
+//! The RFC 5987 `ext-value` syntax for header field parameters.
+//!
+//! This is the encoding used by parameters like `filename*` in `Content-Disposition`, where the
+//! value is permitted to contain characters beyond US-ASCII. RFC 5987 grammar:
+//!
+//! ```abnf
+//! ext-value     = charset  "'" [ language ] "'" value-chars
+//!               ; like RFC 2231's <extended-initial-value>
+//!               ; (see [RFC2231], Section 7)
+//!
+//! charset       = "UTF-8" / "ISO-8859-1" / mime-charset
+//!
+//! value-chars   = *( pct-encoded / attr-char )
+//!
+//! pct-encoded   = "%" HEXDIG HEXDIG
+//!               ; see [RFC3986], Section 2.1
+//!
+//! attr-char     = ALPHA / DIGIT
+//!               / "!" / "#" / "$" / "&" / "+" / "-" / "."
+//!               / "^" / "_" / "`" / "|" / "~"
+//!               ; token except ( "*" / "'" / "%" )
+//! ```
+//!
+//! Only the two charsets which RFC 5987 requires recipients to support, UTF-8 and ISO-8859-1, are
+//! supported; anything else is rejected.
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+
+/// The character set of an `ext-value`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Charset {
+    /// `UTF-8`.
+    Utf8,
+    /// `ISO-8859-1`, also known as Latin-1.
+    Iso88591,
+}
+
+impl Charset {
+    /// The canonical name of the charset, as it is written in an `ext-value`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Charset::Utf8 => "UTF-8",
+            Charset::Iso88591 => "ISO-8859-1",
+        }
+    }
+}
+
+/// A decoded `ext-value`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExtValue {
+    /// The character set which `value` is encoded in.
+    pub charset: Charset,
+    /// The language tag, if one was specified.
+    pub lang: Option<String>,
+    /// The value, percent-decoded but still in the encoding specified by `charset`.
+    pub value: Vec<u8>,
+}
+
+impl ExtValue {
+    /// Decode the value according to its charset, producing a string.
+    ///
+    /// This returns `None` if the charset is UTF-8 and the value is not valid UTF-8; ISO-8859-1
+    /// values can always be decoded.
+    pub fn decoded(&self) -> Option<String> {
+        match self.charset {
+            Charset::Utf8 => str::from_utf8(&self.value).ok().map(|s| s.to_owned()),
+            Charset::Iso88591 => Some(self.value.iter().map(|&b| b as char).collect()),
+        }
+    }
+}
+
+/// attr-char: the characters which may appear in an `ext-value` without being percent-encoded.
+#[inline]
+pub fn is_attr_char(o: u8) -> bool {
+    o == b'!' || o == b'#' || o == b'$' || o == b'&' || o == b'+' || o == b'-' ||
+    o == b'.' || o == b'^' || o == b'_' || o == b'`' || o == b'|' || o == b'~' ||
+    (o >= b'0' && o <= b'9') || (o >= b'A' && o <= b'Z') || (o >= b'a' && o <= b'z')
+}
+
+#[inline]
+fn from_hex(o: u8) -> Option<u8> {
+    match o {
+        b'0'...b'9' => Some(o - b'0'),
+        b'a'...b'f' => Some(o - b'a' + 10),
+        b'A'...b'F' => Some(o - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Decode an `ext-value`, returning `None` if it is malformed or uses an unsupported charset.
+pub fn decode(raw: &[u8]) -> Option<ExtValue> {
+    let mut parts = raw.splitn(3, |&c| c == b'\'');
+    let (charset, lang, value_chars) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(charset), Some(lang), Some(value_chars)) => (charset, lang, value_chars),
+        _ => return None,
+    };
+
+    let charset = if charset.eq_ignore_ascii_case(b"UTF-8") {
+        Charset::Utf8
+    } else if charset.eq_ignore_ascii_case(b"ISO-8859-1") {
+        Charset::Iso88591
+    } else {
+        return None;
+    };
+
+    // language = <Language-Tag, defined in [RFC5646], Section 2.1>; we don’t go validating it
+    // thoroughly, but it can certainly only contain alphanumerics and hyphens.
+    let lang = if lang.is_empty() {
+        None
+    } else if lang.iter().all(|&c| c == b'-' || (c >= b'0' && c <= b'9') ||
+                                   (c >= b'A' && c <= b'Z') || (c >= b'a' && c <= b'z')) {
+        Some(unsafe { str::from_utf8_unchecked(lang) }.to_owned())
+    } else {
+        return None;
+    };
+
+    let mut value = Vec::with_capacity(value_chars.len());
+    let mut iter = value_chars.iter();
+    while let Some(&c) = iter.next() {
+        if c == b'%' {
+            let high = match iter.next().and_then(|&c| from_hex(c)) {
+                Some(high) => high,
+                None => return None,
+            };
+            let low = match iter.next().and_then(|&c| from_hex(c)) {
+                Some(low) => low,
+                None => return None,
+            };
+            value.push(high << 4 | low);
+        } else if is_attr_char(c) {
+            value.push(c);
+        } else {
+            return None;
+        }
+    }
+
+    Some(ExtValue {
+        charset: charset,
+        lang: lang,
+        value: value,
+    })
+}
+
+/// Encode an `ext-value`; this is the inverse of `decode`.
+///
+/// All bytes which are not `attr-char` are percent-encoded, with uppercase hexadecimal digits.
+impl fmt::Display for ExtValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str(self.charset.name()));
+        try!(f.write_str("'"));
+        if let Some(ref lang) = self.lang {
+            try!(f.write_str(lang));
+        }
+        try!(f.write_str("'"));
+        for &byte in &self.value {
+            if is_attr_char(byte) {
+                try!(write!(f, "{}", byte as char));
+            } else {
+                try!(write!(f, "%{:02X}", byte));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, Charset, ExtValue};
+
+    #[test]
+    fn test_decode_utf8() {
+        let value = decode(b"UTF-8''%e2%82%ac%20rates").unwrap();
+        assert_eq!(value, ExtValue {
+            charset: Charset::Utf8,
+            lang: None,
+            value: b"\xe2\x82\xac rates".to_vec(),
+        });
+        assert_eq!(value.decoded(), Some("€ rates".to_owned()));
+    }
+
+    #[test]
+    fn test_decode_iso_8859_1() {
+        let value = decode(b"iso-8859-1'en'%A3%20rates").unwrap();
+        assert_eq!(value.charset, Charset::Iso88591);
+        assert_eq!(value.lang, Some("en".to_owned()));
+        assert_eq!(value.decoded(), Some("£ rates".to_owned()));
+    }
+
+    #[test]
+    fn test_decode_bad() {
+        assert_eq!(decode(b"UTF-8"), None);
+        assert_eq!(decode(b"UTF-8'%e2%82%ac"), None);
+        assert_eq!(decode(b"KOI8-R''%e2"), None);
+        assert_eq!(decode(b"UTF-8''%e"), None);
+        assert_eq!(decode(b"UTF-8''%zz"), None);
+        assert_eq!(decode(b"UTF-8''with space"), None);
+        assert_eq!(decode(b"UTF-8''%ff").unwrap().decoded(), None);
+    }
+
+    #[test]
+    fn test_encode() {
+        let value = ExtValue {
+            charset: Charset::Utf8,
+            lang: Some("en".to_owned()),
+            value: "€ rates".as_bytes().to_vec(),
+        };
+        assert_eq!(value.decoded(), Some("€ rates".to_owned()));
+        let encoded = format!("{}", value);
+        assert_eq!(encoded, "UTF-8'en'%E2%82%AC%20rates");
+        assert_eq!(decode(encoded.as_bytes()), Some(value));
+    }
+}
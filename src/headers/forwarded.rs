@@ -0,0 +1,455 @@
+//! The `Forwarded` header (RFC 7239), its nodes in a chain of proxies (section 6), and the
+//! legacy `X-Forwarded-For` header.
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::net::{IpAddr, Ipv6Addr};
+use std::str;
+
+use grammar::{parameters, quoted_string};
+use grammar::token::is_tchar;
+use super::{ToHeader, Header, Headers, HeaderDisplayAdapter};
+
+/// Whether a character may appear in an obfuscated identifier, after the leading `_`.
+#[inline]
+fn is_obfchar(o: u8) -> bool {
+    (o >= b'A' && o <= b'Z') || (o >= b'a' && o <= b'z') || (o >= b'0' && o <= b'9') ||
+    o == b'.' || o == b'_' || o == b'-'
+}
+
+/// Whether a string is an obfuscated identifier, as used for node names and ports.
+fn is_obfuscated_identifier(s: &str) -> bool {
+    s.len() > 1 && s.starts_with('_') && s.bytes().all(is_obfchar)
+}
+
+/// The name of a node.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum NodeName {
+    /// An IP address.
+    Ip(IpAddr),
+    /// `unknown`: the proxy doesn’t know, or won’t say, who came before it.
+    Unknown,
+    /// An obfuscated identifier, such as `_hidden` or `_SEVKISEK`, which includes the leading
+    /// underscore. It identifies the node to those in the know, but isn’t an address.
+    Obfuscated(String),
+}
+
+/// The port of a node.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum NodePort {
+    /// A port number.
+    Port(u16),
+    /// An obfuscated identifier, including the leading underscore.
+    Obfuscated(String),
+}
+
+/// A node in a chain of proxies: one item of `X-Forwarded-For`, or the value of a `for` or `by`
+/// parameter of `Forwarded`.
+///
+/// RFC 7239 grammar:
+///
+/// ```abnf
+/// node      = nodename [ ":" node-port ]
+/// nodename  = IPv4address / "[" IPv6address "]" / "unknown" / obfnode
+/// obfnode   = "_" 1*( ALPHA / DIGIT / "." / "_" / "-")
+/// node-port = port / obfport
+/// port      = 1*5DIGIT
+/// obfport   = "_" 1*(ALPHA / DIGIT / "." / "_" / "-")
+/// ```
+///
+/// As `X-Forwarded-For` has no specification and IPv6 addresses are normally sent in it without
+/// brackets, an IPv6 address without brackets (and so without a port) is also accepted, and one
+/// without a port is written that way.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ForwardedNode {
+    /// The node’s name.
+    pub name: NodeName,
+    /// The node’s port, if given.
+    pub port: Option<NodePort>,
+}
+
+impl ForwardedNode {
+    /// Construct a node for an IP address, without a port.
+    pub fn from_ip(ip: IpAddr) -> ForwardedNode {
+        ForwardedNode { name: NodeName::Ip(ip), port: None }
+    }
+
+    /// The node’s IP address, if it has one.
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self.name {
+            NodeName::Ip(ip) => Some(ip),
+            _ => None,
+        }
+    }
+
+    /// Whether the node’s name is obfuscated or `unknown`, rather than an IP address.
+    pub fn is_obfuscated(&self) -> bool {
+        self.ip().is_none()
+    }
+}
+
+impl ToHeader for ForwardedNode {
+    fn parse(raw: &[u8]) -> Option<ForwardedNode> {
+        let raw = match str::from_utf8(raw) {
+            Ok(raw) => raw,
+            Err(_) => return None,
+        };
+        let (name, port) = if raw.starts_with('[') {
+            let end = match raw.find(']') {
+                Some(end) => end,
+                None => return None,
+            };
+            let ip = match raw[1..end].parse::<Ipv6Addr>() {
+                Ok(ip) => ip,
+                Err(_) => return None,
+            };
+            let port = match &raw[end + 1..] {
+                "" => None,
+                rest if rest.starts_with(':') => Some(&rest[1..]),
+                _ => return None,
+            };
+            (NodeName::Ip(IpAddr::V6(ip)), port)
+        } else if let Ok(ip) = raw.parse::<Ipv6Addr>() {
+            (NodeName::Ip(IpAddr::V6(ip)), None)
+        } else {
+            let (name, port) = match raw.find(':') {
+                Some(colon) => (&raw[..colon], Some(&raw[colon + 1..])),
+                None => (raw, None),
+            };
+            let name = if name.eq_ignore_ascii_case("unknown") {
+                NodeName::Unknown
+            } else if is_obfuscated_identifier(name) {
+                NodeName::Obfuscated(name.to_owned())
+            } else {
+                match name.parse() {
+                    Ok(ip) => NodeName::Ip(IpAddr::V4(ip)),
+                    Err(_) => return None,
+                }
+            };
+            (name, port)
+        };
+        let port = match port {
+            None => None,
+            Some(port) if is_obfuscated_identifier(port) => {
+                Some(NodePort::Obfuscated(port.to_owned()))
+            },
+            Some(port) if !port.is_empty() && port.len() <= 5 &&
+                          port.bytes().all(|c| c >= b'0' && c <= b'9') => {
+                match port.parse() {
+                    Ok(port) => Some(NodePort::Port(port)),
+                    Err(_) => return None,
+                }
+            },
+            Some(_) => return None,
+        };
+        Some(ForwardedNode { name: name, port: port })
+    }
+}
+
+impl Header for ForwardedNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name {
+            NodeName::Ip(IpAddr::V6(ip)) if self.port.is_some() => try!(write!(f, "[{}]", ip)),
+            NodeName::Ip(ip) => try!(write!(f, "{}", ip)),
+            NodeName::Unknown => try!(f.write_str("unknown")),
+            NodeName::Obfuscated(ref name) => try!(f.write_str(name)),
+        }
+        match self.port {
+            Some(NodePort::Port(port)) => write!(f, ":{}", port),
+            Some(NodePort::Obfuscated(ref port)) => write!(f, ":{}", port),
+            None => Ok(()),
+        }
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_list_header_marker! {
+    /// The `X-Forwarded-For` header: the client, followed by each proxy but the last, through
+    /// which the request has passed.
+    pub X_FORWARDED_FOR: ForwardedNode = "x-forwarded-for"
+}
+
+/// The chain of nodes in an `X-Forwarded-For` header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct XForwardedFor {
+    nodes: Vec<ForwardedNode>,
+}
+
+impl XForwardedFor {
+    /// Construct a chain of nodes, client first.
+    pub fn new<I: IntoIterator<Item = ForwardedNode>>(nodes: I) -> XForwardedFor {
+        XForwardedFor { nodes: nodes.into_iter().collect() }
+    }
+
+    /// Read the chain from a request’s `X-Forwarded-For` header.
+    ///
+    /// Items which aren’t valid nodes are skipped. If there is no `X-Forwarded-For` header, the
+    /// chain will be empty.
+    pub fn from_headers(headers: &Headers) -> XForwardedFor {
+        XForwardedFor::new(headers.get(X_FORWARDED_FOR).into_owned())
+    }
+
+    /// The nodes, client first.
+    pub fn nodes(&self) -> &[ForwardedNode] {
+        &self.nodes
+    }
+
+    /// The IP address of the client: the first node with one, skipping any which are obfuscated
+    /// or `unknown`.
+    ///
+    /// Remember that anyone can send any `X-Forwarded-For` header they like; this is only
+    /// trustworthy as far as the proxies which added to it are.
+    pub fn client_ip(&self) -> Option<IpAddr> {
+        self.nodes.iter().filter_map(|node| node.ip()).next()
+    }
+}
+
+/// One element of the `Forwarded` header: what one proxy knew of the request it forwarded.
+///
+/// RFC 7239 grammar:
+///
+/// ```abnf
+/// Forwarded         = 1#forwarded-element
+/// forwarded-element = [ forwarded-pair ] *( ";" [ forwarded-pair ] )
+/// forwarded-pair    = token "=" value
+/// value             = token / quoted-string
+/// ```
+///
+/// Parameter names are case-insensitive and each may appear at most once. Parameters other than
+/// the four defined are kept, lowercase and unquoted, in `extensions`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ForwardedElement {
+    /// `by`: the interface where the request came in to the proxy.
+    pub by: Option<ForwardedNode>,
+    /// `for`: the node which made the request to the proxy.
+    pub for_: Option<ForwardedNode>,
+    /// `host`: the `Host` header of the request as the proxy received it.
+    pub host: Option<String>,
+    /// `proto`: the protocol used to make the request, such as `http` or `https`.
+    pub proto: Option<String>,
+    /// Any other parameters.
+    pub extensions: Vec<(String, String)>,
+}
+
+impl ToHeader for ForwardedElement {
+    fn parse(raw: &[u8]) -> Option<ForwardedElement> {
+        let pairs = match parameters::parse(&[&b";"[..], raw].concat()) {
+            Some(pairs) => pairs,
+            None => return None,
+        };
+        let mut element = ForwardedElement::default();
+        let mut seen = vec![];
+        for (name, value) in pairs {
+            let name = name.to_ascii_lowercase();
+            if seen.contains(&name) {
+                return None;
+            }
+            let value = if value.starts_with('"') {
+                match quoted_string::unquote(value.as_bytes()).map(String::from_utf8) {
+                    Some(Ok(value)) => value,
+                    _ => return None,
+                }
+            } else {
+                value
+            };
+            let node = || ForwardedNode::parse(value.as_bytes());
+            match &name[..] {
+                "by" => element.by = match node() {
+                    Some(node) => Some(node),
+                    None => return None,
+                },
+                "for" => element.for_ = match node() {
+                    Some(node) => Some(node),
+                    None => return None,
+                },
+                "host" => element.host = Some(value.clone()),
+                "proto" => element.proto = Some(value.clone()),
+                _ => element.extensions.push((name.clone(), value.clone())),
+            }
+            seen.push(name);
+        }
+        Some(element)
+    }
+}
+
+impl Header for ForwardedElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn pair(f: &mut fmt::Formatter, first: &mut bool, name: &str, value: &str)
+                -> fmt::Result {
+            if !*first {
+                try!(f.write_str(";"));
+            }
+            *first = false;
+            if !value.is_empty() && value.bytes().all(is_tchar) {
+                return write!(f, "{}={}", name, value);
+            }
+            match quoted_string::quote(value.as_bytes()) {
+                // quote only adds ASCII to what was already UTF-8, so this can’t fail.
+                Some(quoted) => write!(f, "{}={}", name, unsafe {
+                    str::from_utf8_unchecked(&quoted)
+                }),
+                None => Err(fmt::Error),
+            }
+        }
+        let mut first = true;
+        if let Some(ref by) = self.by {
+            try!(pair(f, &mut first, "by", &format!("{}", HeaderDisplayAdapter(by))));
+        }
+        if let Some(ref for_) = self.for_ {
+            try!(pair(f, &mut first, "for", &format!("{}", HeaderDisplayAdapter(for_))));
+        }
+        if let Some(ref host) = self.host {
+            try!(pair(f, &mut first, "host", host));
+        }
+        if let Some(ref proto) = self.proto {
+            try!(pair(f, &mut first, "proto", proto));
+        }
+        for &(ref name, ref value) in &self.extensions {
+            try!(pair(f, &mut first, name, value));
+        }
+        Ok(())
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_list_header_marker! {
+    /// The `Forwarded` header: an element for each proxy through which the request has passed,
+    /// the first being nearest the client.
+    pub FORWARDED: ForwardedElement = "forwarded"
+}
+
+/// The chain of nodes through which a request has passed, client first, from whichever of
+/// `Forwarded` and `X-Forwarded-For` the request has.
+///
+/// `Forwarded` is preferred when present: its `for` parameters make up the chain, elements
+/// without one being skipped, and any `X-Forwarded-For` header is ignored. Otherwise the chain
+/// comes from `X-Forwarded-For`, as with `XForwardedFor::from_headers`. With neither, the chain
+/// is empty.
+///
+/// As ever, this is only as trustworthy as the proxies which added to the headers.
+pub fn reconcile(headers: &Headers) -> Vec<ForwardedNode> {
+    let forwarded = headers.get(FORWARDED);
+    if !forwarded.is_empty() {
+        forwarded.iter().filter_map(|element| element.for_.clone()).collect()
+    } else {
+        XForwardedFor::from_headers(headers).nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::net::IpAddr;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use super::{ForwardedElement, ForwardedNode, NodeName, NodePort, XForwardedFor, reconcile};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    fn ip(raw: &str) -> IpAddr {
+        raw.parse().unwrap()
+    }
+
+    #[test]
+    fn test_forwarded_node() {
+        eq(b"192.0.2.43", ForwardedNode::from_ip(ip("192.0.2.43")));
+        eq(b"2001:db8:cafe::17", ForwardedNode::from_ip(ip("2001:db8:cafe::17")));
+        eq(b"[2001:db8:cafe::17]:4711", ForwardedNode {
+            name: NodeName::Ip(ip("2001:db8:cafe::17")),
+            port: Some(NodePort::Port(4711)),
+        });
+        eq(b"unknown", ForwardedNode { name: NodeName::Unknown, port: None });
+        eq(b"_hidden:_SEVKISEK", ForwardedNode {
+            name: NodeName::Obfuscated("_hidden".to_owned()),
+            port: Some(NodePort::Obfuscated("_SEVKISEK".to_owned())),
+        });
+        assert_eq!(ForwardedNode::parse(b"[2001:db8:cafe::17]"),
+                   Some(ForwardedNode::from_ip(ip("2001:db8:cafe::17"))));
+        assert_eq!(ForwardedNode::parse(b"UNKNOWN").map(|node| node.name), Some(NodeName::Unknown));
+        bad::<ForwardedNode>(b"_");
+        bad::<ForwardedNode>(b"hidden");
+        bad::<ForwardedNode>(b"192.0.2.43:");
+        bad::<ForwardedNode>(b"192.0.2.43:65536");
+        bad::<ForwardedNode>(b"192.0.2.43:+80");
+        bad::<ForwardedNode>(b"[2001:db8:cafe::17");
+        bad::<ForwardedNode>(b"[192.0.2.43]");
+    }
+
+    #[test]
+    fn test_is_obfuscated() {
+        assert!(!ForwardedNode::parse(b"192.0.2.43:80").unwrap().is_obfuscated());
+        assert!(ForwardedNode::parse(b"unknown").unwrap().is_obfuscated());
+        assert!(ForwardedNode::parse(b"_hidden").unwrap().is_obfuscated());
+        assert!(!ForwardedNode::parse(b"[::1]:_port").unwrap().is_obfuscated());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let headers = Headers::parse_block(b"X-Forwarded-For: unknown, _hidden, 192.0.2.43\r\n\
+                                             X-Forwarded-For: 198.51.100.17, 2001:db8::1\r\n")
+                          .unwrap();
+        let chain = XForwardedFor::from_headers(&headers);
+        assert_eq!(chain.nodes().len(), 5);
+        assert_eq!(chain.client_ip(), Some(ip("192.0.2.43")));
+
+        let headers = Headers::parse_block(b"X-Forwarded-For: unknown, garbage, ::1\r\n").unwrap();
+        assert_eq!(XForwardedFor::from_headers(&headers).client_ip(), Some(ip("::1")));
+        let headers = Headers::parse_block(b"X-Forwarded-For: unknown, _hidden\r\n").unwrap();
+        assert_eq!(XForwardedFor::from_headers(&headers).client_ip(), None);
+        assert_eq!(XForwardedFor::from_headers(&Headers::new()).client_ip(), None);
+    }
+
+    #[test]
+    fn test_forwarded_element() {
+        let element = ForwardedElement {
+            by: Some(ForwardedNode::from_ip(ip("203.0.113.43"))),
+            for_: Some(ForwardedNode::from_ip(ip("192.0.2.60"))),
+            proto: Some("http".to_owned()),
+            .. ForwardedElement::default()
+        };
+        eq(b"by=203.0.113.43;for=192.0.2.60;proto=http", element.clone());
+        assert_eq!(ForwardedElement::parse(b"for=192.0.2.60;proto=http;by=203.0.113.43"),
+                   Some(element));
+        eq(b"for=\"[2001:db8:cafe::17]:4711\"", ForwardedElement {
+            for_: Some(ForwardedNode::parse(b"[2001:db8:cafe::17]:4711").unwrap()),
+            .. ForwardedElement::default()
+        });
+        assert_eq!(ForwardedElement::parse(b"For=\"_gazonk\"; secret=egg"), Some(ForwardedElement {
+            for_: Some(ForwardedNode::parse(b"_gazonk").unwrap()),
+            extensions: vec![("secret".to_owned(), "egg".to_owned())],
+            .. ForwardedElement::default()
+        }));
+        bad::<ForwardedElement>(b"for=192.0.2.60;for=192.0.2.61");
+        bad::<ForwardedElement>(b"for=garbage");
+        bad::<ForwardedElement>(b"for=[2001:db8:cafe::17]:4711");
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let chain = vec![ForwardedNode::from_ip(ip("192.0.2.43")),
+                         ForwardedNode::from_ip(ip("198.51.100.17"))];
+        let headers = Headers::parse_block(b"Forwarded: for=192.0.2.43, proto=https, \
+                                             for=198.51.100.17\r\n").unwrap();
+        assert_eq!(reconcile(&headers), chain);
+        let headers = Headers::parse_block(b"X-Forwarded-For: 192.0.2.43, 198.51.100.17\r\n")
+                          .unwrap();
+        assert_eq!(reconcile(&headers), chain);
+        let headers = Headers::parse_block(b"X-Forwarded-For: 203.0.113.1\r\n\
+                                             Forwarded: for=192.0.2.43;by=_proxy\r\n\
+                                             Forwarded: for=198.51.100.17\r\n").unwrap();
+        assert_eq!(reconcile(&headers), chain);
+        assert_eq!(reconcile(&Headers::new()), vec![]);
+    }
+}
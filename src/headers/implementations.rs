@@ -3,6 +3,8 @@
 use std::str;
 use std::fmt;
 
+use grammar::token::Token;
+use method::Method;
 use super::{Header, ToHeader};
 
 impl ToHeader for usize {
@@ -11,15 +13,61 @@ impl ToHeader for usize {
     }
 }
 
+/// Write a non-negative integer in decimal.
+///
+/// This is faster than `write!`, which matters for the likes of `Content-Length` and `Age`, which
+/// are written in nearly every response.
+pub fn fmt_decimal(mut n: u64, f: &mut fmt::Formatter) -> fmt::Result {
+    // The largest u64 has twenty digits.
+    let mut buf = [0u8; 20];
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    // It’s all ASCII digits, so this can’t fail.
+    f.write_str(unsafe { str::from_utf8_unchecked(&buf[start..]) })
+}
+
 impl Header for usize {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", *self)
+        fmt_decimal(*self as u64, f)
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+impl ToHeader for Method<'static> {
+    fn parse(raw: &[u8]) -> Option<Method<'static>> {
+        if raw.is_empty() {
+            return None;
+        }
+        Token::from_slice(raw).map(|token| Method::from_token(token).into_owned())
+    }
+}
+
+impl Header for Method<'static> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&*self.name())
+    }
+
+    fn is_list_combinable() -> bool {
+        true
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::fmt;
+    use std::fmt::{self, Write};
+    use test::{self, Bencher};
+    use grammar::token::Token;
+    use method::{Method, Get, Options, UnregisteredMethod};
     use headers::{Header, ToHeader, HeaderDisplayAdapter};
 
     fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
@@ -42,4 +90,52 @@ mod tests {
         bad::<usize>(b"1234567890123467901245790");
         bad::<usize>(b"1,000");
     }
+
+    #[test]
+    fn test_fmt_decimal() {
+        struct Decimal(u64);
+        impl fmt::Display for Decimal {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                super::fmt_decimal(self.0, f)
+            }
+        }
+        for &n in &[0, 9, 10, 99, 100, 1234567890, !0] {
+            assert_eq!(Decimal(n).to_string(), n.to_string());
+        }
+    }
+
+    #[bench]
+    fn bench_fmt_content_length(b: &mut Bencher) {
+        let mut out = String::with_capacity(20);
+        b.iter(|| {
+            out.clear();
+            let _ = write!(out, "{}", HeaderDisplayAdapter(&test::black_box(1234567usize)));
+            test::black_box(&out);
+        });
+    }
+
+    #[bench]
+    fn bench_fmt_content_length_with_write_macro(b: &mut Bencher) {
+        let mut out = String::with_capacity(20);
+        b.iter(|| {
+            out.clear();
+            let _ = write!(out, "{}", test::black_box(1234567usize));
+            test::black_box(&out);
+        });
+    }
+
+    #[test]
+    fn test_method() {
+        eq(b"GET", Get);
+        eq(b"OPTIONS", Options);
+        eq(b"PANIC", UnregisteredMethod {
+            name: Token::from_slice(b"PANIC").unwrap().into_owned(),
+            safe: false,
+            idempotent: false,
+        });
+        // Method names are case-sensitive.
+        assert!(Method::parse(b"get") != Some(Get));
+        bad::<Method>(b"");
+        bad::<Method>(b"GET POST");
+    }
 }
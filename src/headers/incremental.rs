@@ -0,0 +1,218 @@
+//! Parsing an HTTP/1.x header block incrementally, as its bytes arrive, for use with
+//! non-blocking I/O.
+
+use super::Headers;
+use super::block::{HeaderParseError, HeaderParseErrorKind, Limits, field_name};
+
+/// The result of feeding bytes to a `HeaderBlockParser`.
+pub enum ParseProgress {
+    /// The end of the header block has not been reached yet; feed more bytes.
+    NeedMore,
+    /// The header block is complete. Any bytes after it are available from `remainder`.
+    Complete(Headers),
+    /// The header block is invalid, or exceeded the total length limit before it ended.
+    Error(HeaderParseError),
+}
+
+/// A parser for a header block which is fed its input in chunks, as it is received.
+///
+/// This finds the end of the block, buffering it, and then parses it exactly as
+/// `Headers::parse_block_with_limits` does. So that a client can’t make the buffer grow without
+/// bound by never ending the block, the total length limit is enforced as bytes arrive, rather
+/// than once the block is complete.
+///
+/// Once `feed` has returned `Complete` or `Error`, the parser is finished, and must not be fed
+/// any more.
+pub struct HeaderBlockParser {
+    limits: Limits,
+    buffer: Vec<u8>,
+    /// The position in `buffer` of the start of the current, incomplete, line.
+    line_start: usize,
+    /// The combined length of the complete lines so far, measured as `Limits` describes.
+    total_length: usize,
+    /// The position in `buffer` just after the end of the block, once it has been found.
+    block_end: Option<usize>,
+    finished: bool,
+}
+
+impl HeaderBlockParser {
+    /// Construct a parser with the default limits.
+    pub fn new() -> HeaderBlockParser {
+        HeaderBlockParser::with_limits(Limits::default())
+    }
+
+    /// Construct a parser with the given limits.
+    pub fn with_limits(limits: Limits) -> HeaderBlockParser {
+        HeaderBlockParser {
+            limits: limits,
+            buffer: vec![],
+            line_start: 0,
+            total_length: 0,
+            block_end: None,
+            finished: false,
+        }
+    }
+
+    /// Feed the next chunk of input to the parser.
+    ///
+    /// A line terminator may be split across chunks; chunks may be of any size, including empty.
+    ///
+    /// # Panics
+    ///
+    /// If the parser has already returned `Complete` or `Error`.
+    pub fn feed(&mut self, chunk: &[u8]) -> ParseProgress {
+        assert!(!self.finished, "HeaderBlockParser fed after it had finished");
+        let mut scan_from = self.buffer.len();
+        self.buffer.extend(chunk);
+        while let Some(offset) = self.buffer[scan_from..].iter().position(|&c| c == b'\n') {
+            let newline = scan_from + offset;
+            let length = line_length(&self.buffer[self.line_start..newline]);
+            if length == 0 {
+                self.finished = true;
+                self.block_end = Some(newline + 1);
+                return match Headers::parse_block_with_limits(&self.buffer[..newline + 1],
+                                                              self.limits) {
+                    Ok(headers) => ParseProgress::Complete(headers),
+                    Err(error) => ParseProgress::Error(error),
+                };
+            }
+            self.total_length += length;
+            if self.total_length > self.limits.max_total_length {
+                return self.too_long(newline);
+            }
+            self.line_start = newline + 1;
+            scan_from = newline + 1;
+        }
+        let length = line_length(&self.buffer[self.line_start..]);
+        if self.total_length + length > self.limits.max_total_length {
+            let end = self.buffer.len();
+            return self.too_long(end);
+        }
+        ParseProgress::NeedMore
+    }
+
+    /// The bytes fed after the end of the header block, such as the start of the message body.
+    ///
+    /// This is empty until `feed` has returned `Complete`.
+    pub fn remainder(&self) -> &[u8] {
+        match self.block_end {
+            Some(block_end) => &self.buffer[block_end..],
+            None => &[],
+        }
+    }
+
+    fn too_long(&mut self, line_end: usize) -> ParseProgress {
+        self.finished = true;
+        let line = &self.buffer[self.line_start..line_end];
+        let name = field_name(line);
+        ParseProgress::Error(HeaderParseError::new(HeaderParseErrorKind::TooLong,
+                                                   name.as_ref().map(|name| &name[..]), line))
+    }
+}
+
+/// The length of a line as `Limits` measures it, being without any CR before the LF.
+fn line_length(line: &[u8]) -> usize {
+    match line.last() {
+        Some(&b'\r') => line.len() - 1,
+        _ => line.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::{Headers, HeaderParseErrorKind, Limits};
+    use headers::content_type::{MediaType, CONTENT_TYPE};
+    use super::{HeaderBlockParser, ParseProgress};
+
+    const BLOCK: &'static [u8] = b"Content-Type: text/html\r\nX-Foo: bar\nX-Foo: baz\r\n\r\nbody";
+
+    /// Feed the input one byte at a time, returning the headers and how many bytes it took.
+    fn byte_at_a_time(input: &[u8]) -> Result<(Headers, usize), HeaderParseErrorKind> {
+        let mut parser = HeaderBlockParser::with_limits(Limits {
+            max_field_length: 50,
+            max_total_length: 100,
+        });
+        for (i, byte) in input.iter().enumerate() {
+            match parser.feed(&[*byte]) {
+                ParseProgress::NeedMore => (),
+                ParseProgress::Complete(headers) => {
+                    assert_eq!(parser.remainder(), b"");
+                    return Ok((headers, i + 1));
+                },
+                ParseProgress::Error(error) => return Err(error.kind()),
+            }
+        }
+        panic!("the block never ended");
+    }
+
+    #[test]
+    fn test_byte_at_a_time() {
+        let (headers, length) = byte_at_a_time(BLOCK).ok().unwrap();
+        assert_eq!(length, BLOCK.len() - 4);
+        assert!(headers == Headers::parse_block(BLOCK).unwrap());
+        assert_eq!(*headers.get(CONTENT_TYPE).unwrap(), MediaType::new("text", "html"));
+
+        let (headers, length) = byte_at_a_time(b"\r\n").ok().unwrap();
+        assert!(headers == Headers::new());
+        assert_eq!(length, 2);
+        let (headers, length) = byte_at_a_time(b"X-Foo: bar\n\nbody").ok().unwrap();
+        assert!(headers == Headers::parse_block(b"X-Foo: bar\n").unwrap());
+        assert_eq!(length, 12);
+
+        assert_eq!(byte_at_a_time(b"X-Foo bar\r\n\r\n").err(),
+                   Some(HeaderParseErrorKind::Malformed));
+    }
+
+    #[test]
+    fn test_chunks() {
+        let mut parser = HeaderBlockParser::new();
+        assert!(match parser.feed(b"X-Foo: bar\r") { ParseProgress::NeedMore => true, _ => false });
+        assert!(match parser.feed(b"") { ParseProgress::NeedMore => true, _ => false });
+        assert!(match parser.feed(b"\n\r") { ParseProgress::NeedMore => true, _ => false });
+        assert_eq!(parser.remainder(), b"");
+        match parser.feed(b"\nbody, and more") {
+            ParseProgress::Complete(headers) => {
+                assert!(headers == Headers::parse_block(b"X-Foo: bar\r\n").unwrap());
+            },
+            _ => panic!(),
+        }
+        assert_eq!(parser.remainder(), b"body, and more");
+    }
+
+    #[test]
+    fn test_total_limit() {
+        // Twenty bytes on each of five lines is fine, and the line terminators don’t count.
+        let mut block = vec![];
+        for _ in 0..5 {
+            block.extend(b"X-Foo: aaaaaaaaaaaaa\r\n");
+        }
+        block.extend(b"\r\n");
+        assert_eq!(byte_at_a_time(&block).ok().map(|(_, length)| length), Some(block.len()));
+
+        // The limit is enforced as soon as it is exceeded, even without the line ending.
+        let mut block = vec![];
+        for _ in 0..200 {
+            block.extend(b"X-Foo: a");
+        }
+        assert_eq!(byte_at_a_time(&block).err(), Some(HeaderParseErrorKind::TooLong));
+        block.truncate(101);
+        assert_eq!(byte_at_a_time(&block).err(), Some(HeaderParseErrorKind::TooLong));
+        // Too long for a single field, but within the total, so only detected at the end.
+        block.truncate(100);
+        block.extend(b"\r\n\r\n");
+        assert_eq!(byte_at_a_time(&block).err(), Some(HeaderParseErrorKind::TooLong));
+
+        // The error names the header, as parse_block’s does, once its name is all there.
+        let limits = Limits { max_field_length: 50, max_total_length: 10 };
+        let mut parser = HeaderBlockParser::with_limits(limits);
+        match parser.feed(b"X-Foo: aaaaaaaaaa") {
+            ParseProgress::Error(error) => assert_eq!(error.name(), Some("x-foo")),
+            _ => panic!(),
+        }
+        let mut parser = HeaderBlockParser::with_limits(limits);
+        match parser.feed(b"X-Foooooooo") {
+            ParseProgress::Error(error) => assert_eq!(error.name(), None),
+            _ => panic!(),
+        }
+    }
+}
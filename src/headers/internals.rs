@@ -4,6 +4,7 @@ use std::borrow::Cow;
 use std::collections::hash_map;
 use std::ops::Deref;
 use std::fmt;
+use std::io;
 use std::mem;
 use std::slice;
 
@@ -12,7 +13,7 @@ use smallvec::SmallVec;
 
 use mucell::{MuCell, Ref};
 
-use super::{ToHeader, Header, HeaderDisplayAdapter};
+use super::{ToHeader, Header, HeaderDisplayAdapter, ParseMode};
 
 // Nothing even remotely fancy here like counting how many items,
 // because I don’t need it in my simple cases.
@@ -58,6 +59,7 @@ macro_rules! smallvec {
 ///   there is a legal unknown-type header.
 ///
 /// No other states may exist.
+#[derive(Clone)]
 struct Inner {
     /// A raw, unparsed header. Each item in the outer vector is a header field value, the names of
     /// which were equivalent. Each inner vector is opaque data with no restrictions except that CR
@@ -67,9 +69,20 @@ struct Inner {
 
     /// A strongly typed header which has been parsed from the raw value.
     typed: Typed,
+
+    /// Whether the field lines may be combined into one comma-separated line, in parsing and on
+    /// output, as from `Marker::combinable`: true for most list headers, false for headers like
+    /// `Set-Cookie`. This is recorded whenever the header is set or parsed through a marker; a
+    /// header only ever handled raw has no marker to go by, so it is false.
+    combine: bool,
+
+    /// The mode in which the typed representation was parsed from the raw one. While the raw
+    /// representation is valid, a typed one parsed in another mode is parsed again.
+    mode: ParseMode,
 }
 
 /// The representation of a strongly typed header.
+#[derive(Clone)]
 enum Typed {
     /// There is no header stored.
     // Yeah, we could have done `Option<InnerTyped>` and omitted this variant, but this optimises
@@ -101,6 +114,17 @@ impl fmt::Debug for Typed {
     }
 }
 
+/// Format a typed value into its raw form, or `None` if its `write_raw` fails, as a value which
+/// can’t be represented (such as `Dnt::NotSet`) does. Such a value produces no field line at all,
+/// rather than whatever was written before the failure.
+fn formatted<H: Header + ?Sized>(h: &H) -> Option<ByteTendril> {
+    let mut out = ByteTendril::new();
+    match h.write_raw(&mut out) {
+        Ok(()) => Some(out),
+        Err(_) => None,
+    }
+}
+
 #[doc(hidden)]
 trait ListHeader: Header + ListHeaderClone {
     fn into_header_iter(self: Box<Self>) -> Box<Iterator<Item = Box<Header + 'static>> + 'static>;
@@ -150,9 +174,13 @@ impl Header for Box<ListHeader> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         (**self).fmt(f)
     }
+
+    fn write_raw(&self, out: &mut io::Write) -> io::Result<()> {
+        (**self).write_raw(out)
+    }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct Item {
     inner: MuCell<Inner>,
 }
@@ -170,27 +198,31 @@ impl PartialEq for Inner {
     }
 }
 
-#[doc(hidden)]
-trait MyIteratorExt: Iterator {
-    fn into_single(self) -> Option<Self::Item>;
-}
-
-impl<T: Iterator> MyIteratorExt for T {
-    fn into_single(mut self) -> Option<T::Item> {
-        let output = match self.next() {
-            Some(x) => x,
-            None => return None,
-        };
-        match self.next() {
-            Some(_) => return None,
-            None => Some(output),
-        }
+/// The value of a single header: its one field line, or, if `combine`, all its lines joined with
+/// commas, as RFC 7230 section 3.2.2 permits of a header defined as a comma-separated list.
+fn single_value(lines: &[ByteTendril], combine: bool) -> Option<Cow<[u8]>> {
+    match lines.len() {
+        0 => None,
+        1 => Some(Cow::Borrowed(&lines[0][..])),
+        _ if combine => {
+            let mut value = vec![];
+            for (i, line) in lines.iter().enumerate() {
+                if i > 0 {
+                    value.extend_from_slice(b", ");
+                }
+                value.extend_from_slice(line);
+            }
+            Some(Cow::Owned(value))
+        },
+        _ => None,
     }
 }
 
 struct ValueListIter<'a> {
     current_line: Option<&'a [u8]>,
     lines: slice::Iter<'a, ByteTendril>,
+    /// Whether a line may hold several comma-separated values; if not, each line is one value.
+    split_commas: bool,
 }
 
 macro_rules! DEBUG { ($($x:tt)*) => (println!($($x)*)) }
@@ -222,6 +254,13 @@ impl<'a> Iterator for ValueListIter<'a> {
             }
             DEBUG!("Line stripped, now {:?}", line);
 
+            if !self.split_commas {
+                self.current_line = None;
+                // There is a non-whitespace byte, so this can’t fail.
+                let end = line.iter().rposition(|&c| c != b' ' && c != b'\t').unwrap();
+                return Some(&line[..end + 1]);
+            }
+
             enum State {
                 Normal,
                 QuotedString,
@@ -329,13 +368,21 @@ impl<'a> Iterator for ValueListIter<'a> {
 #[doc(hidden)]
 trait RawHeaderExt {
     fn to_value_list_iter(&self) -> ValueListIter;
+
+    /// The values of a list header: as `to_value_list_iter` if `combine`, else one per line.
+    fn to_list_values(&self, combine: bool) -> ValueListIter;
 }
 
 impl RawHeaderExt for [ByteTendril] {
     fn to_value_list_iter(&self) -> ValueListIter {
+        self.to_list_values(true)
+    }
+
+    fn to_list_values(&self, combine: bool) -> ValueListIter {
         ValueListIter {
             current_line: None,
             lines: self.iter(),
+            split_commas: combine,
         }
     }
 }
@@ -386,14 +433,22 @@ impl Inner {
             self.raw = Some(if invalidate_others {
                 match mem::replace(&mut self.typed, Typed::None) {
                     Typed::None => smallvec![],
-                    Typed::Single(single) => smallvec![single.into_raw()],
-                    Typed::List(list) => smallvec![list.to_raw()],
+                    Typed::Single(single) => formatted(&*single).into_iter().collect(),
+                    Typed::List(list) => if self.combine {
+                        formatted(&*list).into_iter().collect()
+                    } else {
+                        list.into_header_iter().filter_map(|h| formatted(&*h)).collect()
+                    },
                 }
             } else {
                 match self.typed {
                     Typed::None => smallvec![],
-                    Typed::Single(ref single) => smallvec![single.to_raw()],
-                    Typed::List(ref list) => smallvec![list.to_raw()],
+                    Typed::Single(ref single) => formatted(&**single).into_iter().collect(),
+                    Typed::List(ref list) => if self.combine {
+                        formatted(&**list).into_iter().collect()
+                    } else {
+                        list.as_header_iter().filter_map(|h| formatted(h)).collect()
+                    },
                 }
             });
         }
@@ -409,34 +464,49 @@ impl Inner {
             Some(ref vec) => Some(Cow::Borrowed(&vec[..])),
             None => match self.typed {
                 Typed::None => None,
-                Typed::Single(ref single) => Some(Cow::Owned(vec![single.to_raw()])),
-                Typed::List(ref list) => Some(Cow::Owned(vec![list.to_raw()])),
+                Typed::Single(ref single) => {
+                    Some(Cow::Owned(formatted(&**single).into_iter().collect()))
+                },
+                Typed::List(ref list) if self.combine => {
+                    Some(Cow::Owned(formatted(&**list).into_iter().collect()))
+                },
+                Typed::List(ref list) => {
+                    Some(Cow::Owned(list.as_header_iter().filter_map(|h| formatted(h)).collect()))
+                },
             }
         }
     }
 
+    /// Whether a typed representation may be used as it is by access in the given mode: it was
+    /// parsed in that mode, or there is nothing left to parse it from again.
+    fn typed_current(&self, mode: ParseMode) -> bool {
+        self.raw.is_none() || self.mode == mode
+    }
+
     fn single_typed_mut<H: ToHeader + Header>
-                       (&mut self, invalidate_others: bool)
+                       (&mut self, invalidate_others: bool, combine: bool, mode: ParseMode)
                        -> Option<&mut H> {
         let already_happy = match self.typed {
             Typed::Single(ref mut h) => h.is::<H>(),
             _ => false,
-        };
+        } && self.typed_current(mode);
         if !already_happy {
             // It doesn’t matter whether typed is None, Single or List, we’ll need to have it
             // in raw form first. Fortunately raw_mut can do this for us!
             let h: Option<H> = {
-                let raw = match self.raw_mut(invalidate_others).iter().into_single() {
+                let raw = match single_value(self.raw_mut(invalidate_others), combine) {
                     Some(raw) => raw,
                     None => return None,
                 };
-                ToHeader::parse(&raw[..])
+                ToHeader::parse_with_mode(&raw[..], mode)
             };
+            self.mode = mode;
             self.typed = match h {
                 Some(h) => Typed::Single(Box::new(h)),
                 None => Typed::None,
             };
         }
+        self.combine = combine;
         if invalidate_others {
             self.raw = None;
         }
@@ -447,10 +517,11 @@ impl Inner {
     }
 
     fn list_typed_mut<H: ToHeader + Header + Clone>
-                     (&mut self, invalidate_others: bool)
+                     (&mut self, invalidate_others: bool, combine: bool, mode: ParseMode)
                      -> &mut Vec<H> {
+        let current = self.typed_current(mode);
         match self.typed {
-            Typed::List(ref mut h) if h.is::<Vec<H>>() => {
+            Typed::List(ref mut h) if h.is::<Vec<H>>() && self.combine == combine && current => {
                 if invalidate_others {
                     self.raw = None;
                 }
@@ -460,12 +531,14 @@ impl Inner {
                 // It doesn’t matter whether typed is None, Single or List, we’ll need to have it
                 // in raw form first. Fortunately raw_mut can do this for us!
                 let h = self.raw_mut(invalidate_others)
-                            .to_value_list_iter()
-                            .filter_map(|value| ToHeader::parse(value))
+                            .to_list_values(combine)
+                            .filter_map(|value| ToHeader::parse_with_mode(value, mode))
                             .collect::<Vec<H>>();
                 // The vector may be empty, but we do NOT change it to Typed::None.
                 // It MUST end up a Typed::List.
                 self.typed = Typed::List(Box::new(h));
+                self.combine = combine;
+                self.mode = mode;
                 if invalidate_others {
                     self.raw = None;
                 }
@@ -480,16 +553,16 @@ impl Inner {
     // Pass `false` to convert_if_necessary if `typed_mut` was called with the same `H`
     // immediately before; otherwise pass `true`.
     fn single_typed_cow<H: ToHeader + Header + Clone>
-                       (&self, convert_if_necessary: bool)
+                       (&self, convert_if_necessary: bool, combine: bool, mode: ParseMode)
                        -> Option<Cow<H>> {
         match self.typed {
-            Typed::Single(ref h) if h.is::<H>() => {
+            Typed::Single(ref h) if h.is::<H>() && self.typed_current(mode) => {
                 Some(unsafe { Cow::Borrowed(h.downcast_ref_unchecked()) })
             },
             _ if convert_if_necessary => {
                 self.raw_cow().and_then(
-                    |raw| raw.iter().into_single().and_then(
-                        |raw| ToHeader::parse(&**raw).map(|x| Cow::Owned(x))))
+                    |raw| single_value(&raw, combine).and_then(
+                        |raw| ToHeader::parse_with_mode(&raw[..], mode).map(|x| Cow::Owned(x))))
             },
             _ => None,
         }
@@ -498,16 +571,17 @@ impl Inner {
     // Pass `false` to convert_if_necessary if `typed_mut` was called with the same `H`
     // immediately before; otherwise pass `true`.
     fn list_typed_cow<H: ToHeader + Header + Clone>
-                     (&self, convert_if_necessary: bool)
+                     (&self, convert_if_necessary: bool, combine: bool, mode: ParseMode)
                      -> Cow<[H]> {
         match self.typed {
-            Typed::List(ref h) if h.is::<Vec<H>>() => {
+            Typed::List(ref h) if h.is::<Vec<H>>() && self.combine == combine &&
+                                  self.typed_current(mode) => {
                 unsafe { Cow::Borrowed(&**h.downcast_ref_unchecked::<Vec<H>>()) }
             },
             _ if convert_if_necessary => {
                 Cow::Owned(self.raw_cow().unwrap_or(Cow::Borrowed(&[]))
-                                         .to_value_list_iter()
-                                         .filter_map(|value| ToHeader::parse(value))
+                                         .to_list_values(combine)
+                                         .filter_map(|value| ToHeader::parse_with_mode(value, mode))
                                          .collect())
             },
             _ => Cow::Owned(vec![]),
@@ -547,32 +621,44 @@ impl<'a, H: ToHeader + Header + Clone> TypedListRef<'a, H> {
 
 impl Item {
     /// Construct a new Item from a raw representation.
-    pub fn from_raw(raw: SmallVec<[ByteTendril; 1]>) -> Item {
+    ///
+    /// `combine` is as from `Marker::combinable`, or false if there is no marker.
+    pub fn from_raw(raw: SmallVec<[ByteTendril; 1]>, combine: bool) -> Item {
         assert!(raw.len() > 0);
         Item {
             inner: MuCell::new(Inner {
                 raw: Some(raw),
                 typed: Typed::None,
+                combine: combine,
+                mode: ParseMode::Strict,
             }),
         }
     }
 
     /// Construct a new Item from a single-typed representation.
-    pub fn from_single_typed<H: ToHeader + Header + Clone>(typed: H) -> Item {
+    ///
+    /// `combine` is as from `Marker::combinable`.
+    pub fn from_single_typed<H: ToHeader + Header + Clone>(typed: H, combine: bool) -> Item {
         Item {
             inner: MuCell::new(Inner {
                 raw: None,
                 typed: Typed::Single(Box::new(typed)),
+                combine: combine,
+                mode: ParseMode::Strict,
             }),
         }
     }
 
     /// Construct a new Item from a list-typed representation.
-    pub fn from_list_typed<H: ToHeader + Header + Clone>(typed: Vec<H>) -> Item {
+    ///
+    /// `combine` is as from `Marker::combinable`.
+    pub fn from_list_typed<H: ToHeader + Header + Clone>(typed: Vec<H>, combine: bool) -> Item {
         Item {
             inner: MuCell::new(Inner {
                 raw: None,
                 typed: Typed::List(Box::new(typed)),
+                combine: combine,
+                mode: ParseMode::Strict,
             }),
         }
     }
@@ -592,6 +678,13 @@ impl Item {
         }
     }
 
+    /// Whether the field lines may be combined into one comma-separated line on output, as the
+    /// `Marker::combinable` of the marker the header was last set or parsed through said. A
+    /// header only ever handled raw is not combined, for nothing says that it may be.
+    pub fn combinable(&self) -> bool {
+        self.inner.borrow().combine
+    }
+
     /// Get a mutable reference to the raw representation of the header values.
     ///
     /// Because you may modify the raw representation through this mutable reference, calling this
@@ -620,11 +713,12 @@ impl Item {
 
     /// Set the raw form of the header.
     ///
-    /// This invalidates the typed representation.
-    pub fn set_raw(&mut self, raw: SmallVec<[ByteTendril; 1]>) {
+    /// This invalidates the typed representation. `combine` is as from `Marker::combinable`.
+    pub fn set_raw(&mut self, raw: SmallVec<[ByteTendril; 1]>, combine: bool) {
         let inner = self.inner.borrow_mut();
         inner.raw = Some(raw);
         inner.typed = Typed::None;
+        inner.combine = combine;
     }
 
     /// Get a mutable reference to the single-typed representation of the header values.
@@ -636,8 +730,12 @@ impl Item {
     /// fashion, it will be produced from this typed form.
     ///
     /// Only use this if you need to mutate the typed form; if you don't, use `single_typed`.
-    pub fn single_typed_mut<H: ToHeader + Header>(&mut self) -> Option<&mut H> {
-        self.inner.borrow_mut().single_typed_mut(true)
+    ///
+    /// `combine` is as from `Marker::combinable`: whether several field lines are joined into
+    /// one value, rather than being no value at all. `mode` is as from `Headers::parse_mode`.
+    pub fn single_typed_mut<H: ToHeader + Header>(&mut self, combine: bool, mode: ParseMode)
+                                                 -> Option<&mut H> {
+        self.inner.borrow_mut().single_typed_mut(true, combine, mode)
     }
 
     /// Get a mutable reference to the list-typed representation of the header values.
@@ -648,8 +746,11 @@ impl Item {
     /// produced from this typed form.
     ///
     /// Only use this if you need to mutate the typed form; if you don't, use `typed`.
-    pub fn list_typed_mut<H: ToHeader + Header + Clone>(&mut self) -> &mut Vec<H> {
-        self.inner.borrow_mut().list_typed_mut(true)
+    ///
+    /// `combine` is as from `Marker::combinable`, and `mode` as from `Headers::parse_mode`.
+    pub fn list_typed_mut<H: ToHeader + Header + Clone>(&mut self, combine: bool, mode: ParseMode)
+                                                       -> &mut Vec<H> {
+        self.inner.borrow_mut().list_typed_mut(true, combine, mode)
     }
 
     /// Get a reference to the single-typed representation of the header values.
@@ -662,11 +763,15 @@ impl Item {
     /// can dereference to get your typed reference.
     ///
     /// See also `single_typed_mut`, if you wish to mutate the single-typed representation.
-    pub fn single_typed<H: ToHeader + Header + Clone>(&self) -> Option<Ref<Cow<H>>> {
+    ///
+    /// `combine` and `mode` are as for `single_typed_mut`.
+    pub fn single_typed<H: ToHeader + Header + Clone>(&self, combine: bool, mode: ParseMode)
+                                                     -> Option<Ref<Cow<H>>> {
         let convert_if_necessary = self.inner.try_mutate(|inner| {
-            let _ = inner.single_typed_mut::<H>(false);
+            let _ = inner.single_typed_mut::<H>(false, combine, mode);
         });
-        Ref::filter_map(self.inner.borrow(), move |inner| inner.single_typed_cow(convert_if_necessary))
+        Ref::filter_map(self.inner.borrow(),
+                        move |inner| inner.single_typed_cow(convert_if_necessary, combine, mode))
     }
 
     /// Get a reference to the list-typed representation of the header values.
@@ -679,69 +784,92 @@ impl Item {
     /// can dereference to get your typed reference.
     ///
     /// See also `list_typed_mut`, if you wish to mutate the list-typed representation.
-    pub fn list_typed<H: ToHeader + Header + Clone>(&self) -> Ref<Cow<[H]>> {
+    ///
+    /// `combine` and `mode` are as for `list_typed_mut`.
+    pub fn list_typed<H: ToHeader + Header + Clone>(&self, combine: bool, mode: ParseMode)
+                                                   -> Ref<Cow<[H]>> {
         let convert_if_necessary = self.inner.try_mutate(|inner| {
-            let _ = inner.list_typed_mut::<H>(false);
+            let _ = inner.list_typed_mut::<H>(false, combine, mode);
         });
-        Ref::map(self.inner.borrow(), move |inner| inner.list_typed_cow(convert_if_necessary))
+        Ref::map(self.inner.borrow(),
+                 move |inner| inner.list_typed_cow(convert_if_necessary, combine, mode))
     }
 
     /// Set the typed form of the header as a single-type.
     ///
-    /// This invalidates the raw representation.
-    pub fn set_single_typed<H: ToHeader + Header + Clone>(&mut self, value: H) {
+    /// This invalidates the raw representation. `combine` is as from `Marker::combinable`.
+    pub fn set_single_typed<H: ToHeader + Header + Clone>(&mut self, value: H, combine: bool) {
         let inner = self.inner.borrow_mut();
         inner.raw = None;
         inner.typed = Typed::Single(Box::new(value));
+        inner.combine = combine;
     }
 
     /// Set the typed form of the header as a list-type.
     ///
-    /// This invalidates the raw representation.
-    pub fn set_list_typed<H: ToHeader + Header + Clone>(&mut self, value: Vec<H>) {
+    /// This invalidates the raw representation. `combine` is as from `Marker::combinable`.
+    pub fn set_list_typed<H: ToHeader + Header + Clone>(&mut self, value: Vec<H>, combine: bool) {
         let inner = self.inner.borrow_mut();
         inner.raw = None;
         inner.typed = Typed::List(Box::new(value));
+        inner.combine = combine;
     }
 }
 
 #[doc(hidden)]
 pub trait Get<'a> {
-    fn get(item: Option<&'a Item>) -> Self;
+    /// `combine` is as from `Marker::combinable`, and `mode` as from `Headers::parse_mode`.
+    fn get(item: Option<&'a Item>, combine: bool, mode: ParseMode) -> Self;
+
+    /// Whether a usable value was obtained.
+    fn has_value(&self) -> bool;
 }
 
 impl<'a, T: ToHeader + Header + Clone> Get<'a> for Option<Ref<'a, Cow<'a, T>>> {
-    fn get(item: Option<&'a Item>) -> Self {
+    fn get(item: Option<&'a Item>, combine: bool, mode: ParseMode) -> Self {
         // TODO: consider shifting that method into here, if appropriate; ditto for all the rest
-        item.and_then(|item| item.single_typed())
+        item.and_then(|item| item.single_typed(combine, mode))
+    }
+
+    fn has_value(&self) -> bool {
+        self.is_some()
     }
 }
 
 impl<'a, T: ToHeader + Header + Clone> Get<'a> for TypedListRef<'a, T> {
-    fn get(item: Option<&'a Item>) -> Self {
+    fn get(item: Option<&'a Item>, combine: bool, mode: ParseMode) -> Self {
         TypedListRef {
-            value: item.map(|item| item.list_typed()),
+            value: item.map(|item| item.list_typed(combine, mode)),
         }
     }
+
+    fn has_value(&self) -> bool {
+        !self.is_empty()
+    }
 }
 
 #[doc(hidden)]
 pub trait GetMut<'a> {
-    fn get_mut(entry: hash_map::Entry<'a, StrTendril, Item>) -> Self;
+    /// `combine` is as from `Marker::combinable`, and `mode` as from `Headers::parse_mode`.
+    fn get_mut(entry: hash_map::Entry<'a, StrTendril, Item>, combine: bool, mode: ParseMode)
+              -> Self;
 }
 
 impl<'a, T: ToHeader + Header + Clone> GetMut<'a> for Option<&'a mut T> {
-    fn get_mut(entry: hash_map::Entry<'a, StrTendril, Item>) -> Self {
+    fn get_mut(entry: hash_map::Entry<'a, StrTendril, Item>, combine: bool, mode: ParseMode)
+              -> Self {
         match entry {
-            hash_map::Entry::Occupied(entry) => entry.into_mut().single_typed_mut(),
+            hash_map::Entry::Occupied(entry) => entry.into_mut().single_typed_mut(combine, mode),
             hash_map::Entry::Vacant(_) => None,
         }
     }
 }
 
 impl<'a, T: ToHeader + Header + Clone> GetMut<'a> for &'a mut Vec<T> {
-    fn get_mut(entry: hash_map::Entry<'a, StrTendril, Item>) -> Self {
-        entry.or_insert_with(|| Item::from_list_typed::<T>(vec![])).list_typed_mut()
+    fn get_mut(entry: hash_map::Entry<'a, StrTendril, Item>, combine: bool, mode: ParseMode)
+              -> Self {
+        entry.or_insert_with(|| Item::from_list_typed::<T>(vec![], combine))
+             .list_typed_mut(combine, mode)
     }
 }
 
@@ -1,10 +1,14 @@
 //! HTTP headers.
 
 use std::any::TypeId;
+use std::ascii::AsciiExt;
 use mopa::Any;
 use std::fmt;
+use std::io;
 use std::borrow::Cow;
 use std::mem;
+use std::rc::Rc;
+use std::str;
 
 use std::collections::hash_map::HashMap;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
@@ -12,6 +16,8 @@ use std::collections::hash_map::Entry::{Occupied, Vacant};
 use tendril::{ByteTendril, StrTendril};
 use smallvec::SmallVec;
 
+use grammar::token::is_tchar;
+
 use self::internals::Item;
 pub use mucell::Ref;
 pub use self::internals::TypedListRef;
@@ -33,6 +39,29 @@ pub trait ToHeader: Sized {
     /// syntax error (e.g. an unclosed `quoted-string`) where an entire line will be dropped—and
     /// even then, any other lines will still be handled if possible.
     fn parse(raw_field_value: &[u8]) -> Option<Self>;
+
+    /// Parse a header from a header field value, as `parse` does, in the given mode.
+    ///
+    /// The default implementation ignores the mode and calls `parse`. Types which accept more in
+    /// lenient mode override this, and document what they accept.
+    fn parse_with_mode(raw_field_value: &[u8], _mode: ParseMode) -> Option<Self> {
+        Self::parse(raw_field_value)
+    }
+}
+
+/// How forgiving to be when parsing header values.
+///
+/// Plenty of software in the wild sends header values which are not quite valid, but which have
+/// an obvious meaning; a client talking to such servers may prefer to accept them rather than
+/// treating the header as absent. `ToHeader::parse` is always strict; use
+/// `ToHeader::parse_with_mode` to opt in to leniency, or `Headers::set_parse_mode` for all typed
+/// access to a header collection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParseMode {
+    /// Accept only what the specifications permit.
+    Strict,
+    /// Also accept common deviations from the specifications, as documented by each header type.
+    Lenient,
 }
 
 /// The data type of an HTTP header for encoding and decoding.
@@ -52,19 +81,27 @@ pub trait Header: Any + HeaderClone {
     // advertising it in the public docs. Hence double slashes, not triple.)
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result;
 
+    /// Write the header’s raw value, as it is to go on the wire, failing if `fmt` does.
+    ///
+    /// The default implementation writes what `fmt` does, which is right for any value that is
+    /// ASCII or UTF-8. It need only be overridden by a type which writes other bytes, which `fmt`
+    /// can’t express, such as text that was decoded from ISO-8859-1 and must be encoded back to
+    /// it. This is what the header collection uses to produce field lines from typed values.
+    fn write_raw(&self, out: &mut io::Write) -> io::Result<()> {
+        write!(out, "{}", HeaderDisplayAdapter(&*self))
+    }
+
     /// Convert the header to its raw value, producing a new byte vector.
     ///
-    /// The default implementation will almost always be sufficient, being based on the `fmt`
-    /// method of this trait. This method only exists as it is because I consider it conceivable at
-    /// present that there may be cases where there is a better choice. It might be shifted out of
-    /// the trait later.
+    /// The default implementation will almost always be sufficient, being based on the
+    /// `write_raw` method of this trait. This method only exists as it is because I consider it
+    /// conceivable at present that there may be cases where there is a better choice. It might be
+    /// shifted out of the trait later.
     // unstable: might be removed from the trait
     fn to_raw(&self) -> ByteTendril {
-        use std::io::Write;
-
         let mut out = ByteTendril::new();
         // Meh, nothing is allowed to go wrong here.
-        let _ = write!(out, "{}", HeaderDisplayAdapter(&*self));
+        let _ = self.write_raw(&mut out);
         out
     }
 
@@ -81,6 +118,18 @@ pub trait Header: Any + HeaderClone {
     fn into_raw(self: Box<Self>) -> ByteTendril {
         self.to_raw()
     }
+
+    /// Whether, as the item type of a list header, values may be combined into one
+    /// comma-separated field line, and a field line split on commas into values.
+    ///
+    /// This is what `Marker::combinable` goes by, so a type used with
+    /// `define_list_header_marker!` should override it to return true, unless its values may
+    /// themselves contain commas outside quoted-strings, as with `Set-Cookie` (RFC 7230, section
+    /// 3.2.2); then each value is kept to a field line of its own. The default is false, which is
+    /// always safe, if unidiomatic for a list header.
+    fn is_list_combinable() -> bool where Self: Sized {
+        false
+    }
 }
 
 mopafy!(Header);
@@ -112,6 +161,16 @@ impl<T: ToHeader + Header + Clone + 'static> Header for Vec<T> {
         }
         Ok(())
     }
+
+    fn write_raw(&self, out: &mut io::Write) -> io::Result<()> {
+        for (i, h) in self.iter().enumerate() {
+            if i > 0 {
+                try!(out.write_all(b", "));
+            }
+            try!(h.write_raw(out));
+        }
+        Ok(())
+    }
 }
 
 // This implementation is needed by Headers.set; when Rust gets specialisation or negative impl
@@ -188,6 +247,20 @@ pub trait Marker<'a> {
 
     /// The name of the header that shall be used for retreiving and setting.
     fn header_name() -> &'static str;
+
+    /// Whether the values of a list header may be combined into one comma-separated field line.
+    ///
+    /// This is false for headers such as `Set-Cookie` whose values may themselves contain commas
+    /// (RFC 7230, section 3.2.2): each value is then kept to a line of its own, both in parsing
+    /// and on output. It is the base type which decides, with `Header::is_list_combinable`.
+    ///
+    /// For a single header, it is whether several field lines are joined with commas into one
+    /// value to parse, as for a header whose grammar is a list but whose type holds the whole of
+    /// it, like `Vary`; otherwise, several lines are no value at all. Single markers say so with
+    /// `, combine` (see `define_single_header_marker!`), and are otherwise false.
+    fn combinable() -> bool {
+        <Self::Base as Header>::is_list_combinable()
+    }
 }
 
 /// Define a single-type header marker.
@@ -227,11 +300,21 @@ pub trait Marker<'a> {
 /// - `headers.get(CONTENT_LENGTH) -> Option<impl Deref<Target = usize>>`;
 /// - `headers.get_mut(CONTENT_LENGTH) -> Option<&mut usize>>`;
 /// - `headers.set(CONTENT_LENGTH, usize)`.
+///
+/// The marker may be preceded by attributes (doc comments, most likely) and by `pub`, for markers
+/// that are to be exported: `define_single_header_marker!(#[doc = "…"] pub FOO: Foo = "foo");`.
+///
+/// The name must be a token (RFC 7230, section 3.2), or the header could not be written; in debug
+/// builds, using a marker whose name is not (`"x foo"`, say, or `"x-foo:"`) panics.
+///
+/// A header with several field lines normally has no single value. If the header’s grammar is a
+/// comma-separated list, though, which the type parses as a whole (as with `Vary` or a structured
+/// field), its lines may be joined with commas into one value, as RFC 7230 section 3.2.2 permits;
+/// follow the name with `, combine` for that: `define_single_header_marker!(VARY: Vary = "vary",
+/// combine);`.
 #[macro_export]
 macro_rules! define_single_header_marker {
-    ($marker:ident: $ty:ty = $name:expr) => {
-        struct $marker;
-
+    (@impl $marker:ident: $ty:ty = $name:expr, $combinable:expr) => {
         impl<'a> $crate::headers::Marker<'a> for $marker {
             type Base = $ty;
             type Get = Option<$crate::headers::Ref<'a, ::std::borrow::Cow<'a, $ty>>>;
@@ -239,10 +322,40 @@ macro_rules! define_single_header_marker {
             type Set = $ty;
 
             fn header_name() -> &'static str {
+                debug_assert!($crate::headers::is_field_name($name),
+                              "header name {:?} is not a token", $name);
                 $name
             }
+
+            fn combinable() -> bool {
+                $combinable
+            }
         }
-    }
+    };
+    ($(#[$attr:meta])* pub $marker:ident: $ty:ty = $name:expr, combine) => {
+        $(#[$attr])*
+        pub struct $marker;
+
+        define_single_header_marker!(@impl $marker: $ty = $name, true);
+    };
+    ($(#[$attr:meta])* $marker:ident: $ty:ty = $name:expr, combine) => {
+        $(#[$attr])*
+        struct $marker;
+
+        define_single_header_marker!(@impl $marker: $ty = $name, true);
+    };
+    ($(#[$attr:meta])* pub $marker:ident: $ty:ty = $name:expr) => {
+        $(#[$attr])*
+        pub struct $marker;
+
+        define_single_header_marker!(@impl $marker: $ty = $name, false);
+    };
+    ($(#[$attr:meta])* $marker:ident: $ty:ty = $name:expr) => {
+        $(#[$attr])*
+        struct $marker;
+
+        define_single_header_marker!(@impl $marker: $ty = $name, false);
+    };
 }
 
 /// Define a list-type header marker.
@@ -257,6 +370,9 @@ macro_rules! define_single_header_marker {
 /// #     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 /// #         unimplemented!();
 /// #     }
+/// #     fn is_list_combinable() -> bool {
+/// #         true
+/// #     }
 /// # }
 /// # impl teepee::headers::ToHeader for Method {
 /// #     fn parse(_: &[u8]) -> Option<Self> {
@@ -284,11 +400,34 @@ macro_rules! define_single_header_marker {
 /// - `headers.get(ALLOW) -> impl Deref<Target = &[Method]>` (an empty slice means no values);
 /// - `headers.get_mut(ALLOW) -> &mut Vec<Method>`;
 /// - `headers.set(ALLOW, Vec<Method>)`.
+///
+/// As with `define_single_header_marker!`, attributes and `pub` may precede the marker, and the
+/// name must be a token.
+///
+/// Whether the values may be combined into one field line with commas is up to the base type,
+/// which should override `Header::is_list_combinable` to say that they may; otherwise, each value
+/// has a field line of its own, as for `Set-Cookie`. A marker can also insist on the latter,
+/// whatever the type, by following the name with `, no_combine`:
+///
+/// ```rust
+/// # #[macro_use] extern crate teepee;
+/// # fn main() { }
+/// # #[derive(Clone)] struct Cookie;
+/// # impl teepee::headers::Header for Cookie {
+/// #     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+/// #         unimplemented!();
+/// #     }
+/// # }
+/// # impl teepee::headers::ToHeader for Cookie {
+/// #     fn parse(_: &[u8]) -> Option<Self> {
+/// #         unimplemented!();
+/// #     }
+/// # }
+/// define_list_header_marker!(SET_COOKIE: Cookie = "set-cookie", no_combine);
+/// ```
 #[macro_export]
 macro_rules! define_list_header_marker {
-    ($marker:ident: $ty:ty = $name:expr) => {
-        struct $marker;
-
+    (@impl $marker:ident: $ty:ty = $name:expr $(, $combinable:expr)*) => {
         impl<'a> $crate::headers::Marker<'a> for $marker {
             type Base = $ty;
             type Get = $crate::headers::TypedListRef<'a, $ty>;
@@ -296,15 +435,341 @@ macro_rules! define_list_header_marker {
             type Set = Vec<$ty>;
 
             fn header_name() -> &'static str {
+                debug_assert!($crate::headers::is_field_name($name),
+                              "header name {:?} is not a token", $name);
                 $name
             }
+
+            $(
+                fn combinable() -> bool {
+                    $combinable
+                }
+            )*
+        }
+    };
+    ($(#[$attr:meta])* pub $marker:ident: $ty:ty = $name:expr, no_combine) => {
+        $(#[$attr])*
+        pub struct $marker;
+
+        define_list_header_marker!(@impl $marker: $ty = $name, false);
+    };
+    ($(#[$attr:meta])* $marker:ident: $ty:ty = $name:expr, no_combine) => {
+        $(#[$attr])*
+        struct $marker;
+
+        define_list_header_marker!(@impl $marker: $ty = $name, false);
+    };
+    ($(#[$attr:meta])* pub $marker:ident: $ty:ty = $name:expr) => {
+        $(#[$attr])*
+        pub struct $marker;
+
+        define_list_header_marker!(@impl $marker: $ty = $name);
+    };
+    ($(#[$attr:meta])* $marker:ident: $ty:ty = $name:expr) => {
+        $(#[$attr])*
+        struct $marker;
+
+        define_list_header_marker!(@impl $marker: $ty = $name);
+    };
+}
+
+/// Define a header type which is simply a string of text, such as `Server` or a custom `X-`
+/// header.
+///
+/// This produces a wrapper around a `String`, implementing `ToHeader` and `Header`. Parsing accepts
+/// any valid `field-content` (visible characters with interior whitespace, as checked by
+/// `teepee::grammar::is_field_content`) which is UTF-8, rejecting control characters and in
+/// particular embedded CR and LF; `new` applies the same check to values being set.
+///
+/// In `ParseMode::Lenient`, a value which is not UTF-8 is instead decoded as ISO-8859-1, as
+/// legacy software sending obs-text usually means, so that the characters are preserved rather
+/// than the whole value being rejected. (A value which is valid UTF-8 is always decoded as such.)
+/// Such a value is encoded back to ISO-8859-1 when written, so that it goes out as it came in.
+///
+/// ```rust
+/// # #[macro_use] extern crate teepee;
+/// # fn main() { }
+/// text_header! {
+///     /// The `X-Request-Id` header.
+///     pub XRequestId
+/// }
+/// define_single_header_marker!(pub X_REQUEST_ID: XRequestId = "x-request-id");
+/// ```
+///
+/// As with the marker macros, attributes and `pub` may precede the type name.
+#[macro_export]
+macro_rules! text_header {
+    (@impl $ty:ident) => {
+        impl $ty {
+            /// Construct the header value, if it is valid field-content.
+            pub fn new(value: &str) -> Option<$ty> {
+                if $crate::grammar::is_field_content(value.as_bytes()) {
+                    Some($ty(value.to_owned(), false))
+                } else {
+                    None
+                }
+            }
+
+            /// The text of the header value.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl $crate::headers::ToHeader for $ty {
+            fn parse(raw: &[u8]) -> Option<$ty> {
+                $crate::headers::ToHeader::parse_with_mode(raw,
+                                                           $crate::headers::ParseMode::Strict)
+            }
+
+            fn parse_with_mode(raw: &[u8], mode: $crate::headers::ParseMode) -> Option<$ty> {
+                match ::std::str::from_utf8(raw) {
+                    Ok(value) => $ty::new(value),
+                    Err(_) if mode == $crate::headers::ParseMode::Lenient => {
+                        $ty::new(&$crate::grammar::decode_latin1(raw))
+                            .map(|value| $ty(value.0, true))
+                    },
+                    Err(_) => None,
+                }
+            }
+        }
+
+        impl $crate::headers::Header for $ty {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                f.write_str(&self.0)
+            }
+
+            fn write_raw(&self, out: &mut ::std::io::Write) -> ::std::io::Result<()> {
+                if self.1 {
+                    // Every character came from a byte, so fits back into one.
+                    let raw = self.0.chars().map(|c| c as u32 as u8).collect::<Vec<u8>>();
+                    out.write_all(&raw)
+                } else {
+                    out.write_all(self.0.as_bytes())
+                }
+            }
+        }
+
+        // Equal text is an equal value, whichever encoding it arrived in.
+        impl PartialEq for $ty {
+            fn eq(&self, other: &$ty) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl Eq for $ty { }
+    };
+    ($(#[$attr:meta])* pub $ty:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug)]
+        pub struct $ty(String, bool);
+
+        text_header!(@impl $ty);
+    };
+    ($(#[$attr:meta])* $ty:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug)]
+        struct $ty(String, bool);
+
+        text_header!(@impl $ty);
+    };
+}
+
+/// Define a header whose value is a single token from a fixed set, such as
+/// `X-Content-Type-Options`, along with its single-type marker.
+///
+/// This produces an enum with a variant for each token, an `as_str` method giving the canonical
+/// token, and implementations of `ToHeader` (matching the tokens case-insensitively) and `Header`
+/// (writing the canonical token).
+///
+/// ```rust
+/// # #[macro_use] extern crate teepee;
+/// # fn main() { }
+/// enum_header! {
+///     /// The `X-Robots-Mood` header.
+///     pub XRobotsMood {
+///         /// `happy`.
+///         Happy = "happy",
+///         /// `grumpy`.
+///         Grumpy = "grumpy",
+///     }
+///     /// The `X-Robots-Mood` header.
+///     pub X_ROBOTS_MOOD = "x-robots-mood"
+/// }
+/// ```
+///
+/// Unknown tokens are rejected, unless a fallback variant is given with `else`, thus:
+///
+/// ```rust
+/// # #[macro_use] extern crate teepee;
+/// # fn main() { }
+/// enum_header! {
+///     /// The `X-Robots-Mood` header.
+///     pub XRobotsMood {
+///         /// `happy`.
+///         Happy = "happy",
+///     } else {
+///         /// Any other token, with its case preserved.
+///         Other
+///     }
+///     /// The `X-Robots-Mood` header.
+///     pub X_ROBOTS_MOOD = "x-robots-mood"
+/// }
+/// ```
+///
+/// The fallback variant holds any other token as a `String`; the enum is then not `Copy`.
+#[macro_export]
+macro_rules! enum_header {
+    (@impl $ty:ident, $(#[$mattr:meta])* pub $marker:ident = $name:expr) => {
+        impl $crate::headers::Header for $ty {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        define_single_header_marker! {
+            $(#[$mattr])*
+            pub $marker: $ty = $name
+        }
+    };
+    ($(#[$attr:meta])* pub $ty:ident {
+        $($(#[$vattr:meta])* $variant:ident = $token:expr,)*
+    } else {
+        $(#[$fattr:meta])* $fallback:ident
+    }
+    $(#[$mattr:meta])* pub $marker:ident = $name:expr) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug, Eq, PartialEq)]
+        pub enum $ty {
+            $($(#[$vattr])* $variant,)*
+            $(#[$fattr])* $fallback(String),
+        }
+
+        impl $ty {
+            /// The token, canonical for all but the fallback variant.
+            pub fn as_str(&self) -> &str {
+                match *self {
+                    $($ty::$variant => $token,)*
+                    $ty::$fallback(ref token) => token,
+                }
+            }
+        }
+
+        impl $crate::headers::ToHeader for $ty {
+            fn parse(raw: &[u8]) -> Option<$ty> {
+                #[allow(unused_imports)]
+                use ::std::ascii::AsciiExt;
+                $(if raw.eq_ignore_ascii_case($token.as_bytes()) {
+                    return Some($ty::$variant);
+                })*
+                if raw.is_empty() || !raw.iter().all(|&c| $crate::grammar::token::is_tchar(c)) {
+                    return None;
+                }
+                // Tokens are ASCII, so this can’t fail.
+                Some($ty::$fallback(unsafe { ::std::str::from_utf8_unchecked(raw) }.to_owned()))
+            }
         }
+
+        enum_header!(@impl $ty, $(#[$mattr])* pub $marker = $name);
+    };
+    ($(#[$attr:meta])* pub $ty:ident {
+        $($(#[$vattr:meta])* $variant:ident = $token:expr,)*
     }
+    $(#[$mattr:meta])* pub $marker:ident = $name:expr) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+        pub enum $ty {
+            $($(#[$vattr])* $variant,)*
+        }
+
+        impl $ty {
+            /// The canonical token.
+            pub fn as_str(&self) -> &'static str {
+                match *self {
+                    $($ty::$variant => $token,)*
+                }
+            }
+        }
+
+        impl $crate::headers::ToHeader for $ty {
+            fn parse(raw: &[u8]) -> Option<$ty> {
+                #[allow(unused_imports)]
+                use ::std::ascii::AsciiExt;
+                $(if raw.eq_ignore_ascii_case($token.as_bytes()) {
+                    return Some($ty::$variant);
+                })*
+                None
+            }
+        }
+
+        enum_header!(@impl $ty, $(#[$mattr])* pub $marker = $name);
+    };
 }
 
+pub mod connection;
+pub mod ext_value;
+pub mod content_type;
+pub mod date_based;
+pub mod sunset;
+pub mod accept_patch;
+pub mod allow;
+pub mod nel;
+pub mod report_to;
+pub mod block;
+pub mod incremental;
+pub mod early_data;
+pub mod dnt;
+pub mod save_data;
+pub mod origin;
+pub mod timing_allow_origin;
+pub mod server;
+pub mod range;
+pub mod validation;
+pub mod transfer_encoding;
+pub mod clear_site_data;
+pub mod cross_origin;
+pub mod vary;
+pub mod expires;
+pub mod cache_control;
+pub mod sf;
+pub mod priority;
+pub mod client_hints;
+pub mod etag;
+pub mod accept;
+pub mod forwarded;
+pub mod sse;
+pub mod security;
+pub mod expect_ct;
+pub mod cookie;
+pub mod content_digest;
+pub mod trailer;
+pub mod retry_after;
+pub mod upgrade;
+pub mod parsed;
+pub mod authorization;
+pub mod pseudo;
+pub mod accept_encoding;
+pub mod unknown;
+pub mod via;
+pub mod warning;
+pub mod content_length;
+pub mod content_encoding;
+pub mod conditional;
+pub mod diff;
+
+use self::connection::{Connection, CONNECTION};
+pub use self::block::{HeaderParseError, HeaderParseErrorKind, Limits, FieldSpan, HeaderSpans};
+pub use self::incremental::{HeaderBlockParser, ParseProgress};
+pub use self::parsed::{ParsedHeader, TypedIter};
+pub use self::pseudo::PseudoHeader;
+pub use self::unknown::UnknownPolicy;
+pub use self::diff::{HeaderDiff, HeaderChange};
+
 impl Clone for Box<Header> {
     fn clone(&self) -> Box<Header> {
-        self.clone_boxed()
+        // Not `self.clone_boxed()`, for `Box<Header>` is itself `Header + Clone`, and that would
+        // recurse rather than cloning the value inside.
+        (**self).clone_boxed()
     }
 }
 
@@ -312,12 +777,69 @@ impl Header for Box<Header> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         (**self).fmt(f)
     }
+
+    fn write_raw(&self, out: &mut io::Write) -> io::Result<()> {
+        (**self).write_raw(out)
+    }
 }
 
 impl Header for &'static Header {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         (**self).fmt(f)
     }
+
+    fn write_raw(&self, out: &mut io::Write) -> io::Result<()> {
+        (**self).write_raw(out)
+    }
+}
+
+/// The headers which are always hop-by-hop, even if not named in the `Connection` header.
+const HOP_BY_HOP_HEADERS: &'static [&'static str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// The conditional request headers of RFC 7232 (and RFC 7233 for `If-Range`).
+const CONDITIONAL_HEADERS: &'static [&'static str] = &[
+    "if-match",
+    "if-none-match",
+    "if-modified-since",
+    "if-unmodified-since",
+    "if-range",
+];
+
+/// Whether a string is a valid field name: a token (RFC 7230, section 3.2), as it must be to be
+/// written. The marker macros check their names with this, in debug builds.
+#[doc(hidden)]
+pub fn is_field_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(is_tchar)
+}
+
+/// Check that a field name is a token, as `is_field_name`.
+fn check_field_name(name: &str) -> Result<(), HeaderParseError> {
+    if is_field_name(name) {
+        Ok(())
+    } else {
+        Err(HeaderParseError::new(HeaderParseErrorKind::Malformed, None, name.as_bytes()))
+    }
+}
+
+/// Append a field value to a string for display, as `debug_dump` writes it: a byte which is not
+/// printable ASCII or a space as `\xNN`, and a backslash as `\\`.
+fn push_escaped(out: &mut String, value: &[u8]) {
+    for &c in value {
+        match c {
+            b'\\' => out.push_str("\\\\"),
+            b' '...b'~' => out.push(c as char),
+            _ => out.push_str(&format!("\\x{:02x}", c)),
+        }
+    }
 }
 
 /// A collection of HTTP headers.
@@ -395,6 +917,15 @@ impl Header for &'static Header {
 /// - `get_raw_mut_ref`: mutable reference to the value, if it exists.
 /// - `set_raw`: assign the value.
 ///
+/// Cloning
+/// -------
+///
+/// Cloning a header collection is cheap, as is needed when retrying a request: the clone shares
+/// its storage with the original, and nothing is copied until one of the two is mutated, at which
+/// point the one being mutated takes its own copy of the storage. (Even then the raw header values
+/// are not copied, as tendrils are themselves shared.) Mutating a clone never affects the
+/// original, nor vice versa.
+///
 /// Aside: what is a header?
 /// ------------------------
 ///
@@ -456,31 +987,101 @@ impl Header for &'static Header {
 /// rather than `Vec<u8>` each header field can
 /// Each header name is thus associated with an
 /// item.
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct Headers {
-    data: HashMap<StrTendril, Item>,
+    data: Rc<HashMap<StrTendril, Item>>,
+    /// HTTP/2 pseudo-header fields, in `PseudoHeader` order; see the `pseudo` module.
+    pseudo: Vec<(PseudoHeader, String)>,
+    /// What `append_raw` does with unknown headers; see the `unknown` module.
+    unknown_policy: UnknownPolicy,
+    /// How typed access parses raw values; see `set_parse_mode`.
+    parse_mode: ParseMode,
 }
 
 impl Headers {
     /// Construct a new header collection.
     pub fn new() -> Headers {
         Headers {
-            data: HashMap::new(),
+            data: Rc::new(HashMap::new()),
+            pseudo: vec![],
+            unknown_policy: UnknownPolicy::default(),
+            parse_mode: ParseMode::Strict,
+        }
+    }
+
+    /// Construct a new header collection with room for at least `capacity` distinct headers
+    /// before it need reallocate.
+    pub fn with_capacity(capacity: usize) -> Headers {
+        Headers {
+            data: Rc::new(HashMap::with_capacity(capacity)),
+            pseudo: vec![],
+            unknown_policy: UnknownPolicy::default(),
+            parse_mode: ParseMode::Strict,
+        }
+    }
+
+    /// The number of distinct headers the collection can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
+    /// Reserve room for at least `additional` more distinct headers, as when a burst of them is
+    /// known to be coming.
+    ///
+    /// If the storage is shared with a clone, this copies it, as any mutation would.
+    pub fn reserve(&mut self, additional: usize) {
+        self.data_mut().reserve(additional)
+    }
+
+    /// Start building a header collection with chainable setters.
+    ///
+    /// ```rust,ignore
+    /// let headers = Headers::builder()
+    ///     .set(CONTENT_TYPE, MediaType::new("text", "html"))
+    ///     .set(CONNECTION, vec![Connection::Close])
+    ///     .build();
+    /// ```
+    pub fn builder() -> HeadersBuilder {
+        HeadersBuilder {
+            headers: Headers::new(),
         }
     }
 
+    /// Get mutable access to the storage, copying it first if it is shared with a clone.
+    #[inline]
+    fn data_mut(&mut self) -> &mut HashMap<StrTendril, Item> {
+        Rc::make_mut(&mut self.data)
+    }
+
+    /// The mode in which typed access parses raw values: `Strict` unless set otherwise.
+    pub fn parse_mode(&self) -> ParseMode {
+        self.parse_mode
+    }
+
+    /// Set the mode in which typed access (`get` and `get_mut`) parses raw values, as with
+    /// `ToHeader::parse_with_mode`.
+    ///
+    /// This takes effect on the next access to each header, even one which was already parsed in
+    /// the other mode, so long as its raw values are still held; values set typed are unaffected.
+    pub fn set_parse_mode(&mut self, mode: ParseMode) {
+        self.parse_mode = mode;
+    }
+
     /// Get a reference to a header value.
     ///
     /// The interface is strongly typed; see TODO for a more detailed explanation of how it works.
     pub fn get<'a, M: Marker<'a>>(&'a self, _marker: M) -> M::Get {
-        internals::Get::get(self.data.get(M::header_name().as_bytes()))
+        internals::Get::get(self.data.get(M::header_name().as_bytes()), M::combinable(),
+                            self.parse_mode)
     }
 
     /// Get a mutable reference to a header value.
     ///
     /// The interface is strongly typed; see TODO for a more detailed explanation of how it works.
     pub fn get_mut<'a, M: Marker<'a>>(&'a mut self, _marker: M) -> M::GetMut {
-        internals::GetMut::get_mut(self.data.entry(M::header_name().into()))
+        let mode = self.parse_mode;
+        internals::GetMut::get_mut(self.data_mut().entry(M::header_name().into()),
+                                   M::combinable(), mode)
     }
 
     /// Set the named header to the given value.
@@ -490,7 +1091,7 @@ impl Headers {
         // We’d like to do `internals::Set::set(self.data.entry(M::header_name().into()), value)`,
         // but this wouldn’t work because of the conflicting Set implementations.
         // So what do we do? We cheat! Yay for cheating!
-        let entry = self.data.entry(M::header_name().into());
+        let entry = self.data_mut().entry(M::header_name().into());
         if TypeId::of::<Vec<M::Base>>() == TypeId::of::<M::Set>() {
             // It’s a list header.
             // And now we want to transmute it, but we can’t do that so simply because of generics
@@ -500,17 +1101,17 @@ impl Headers {
             let value_vec: Vec<M::Base> = unsafe { mem::transmute_copy(&value) };
             mem::forget(value);
             match entry {
-                Occupied(entry) => entry.into_mut().set_list_typed(value_vec),
+                Occupied(entry) => entry.into_mut().set_list_typed(value_vec, M::combinable()),
                 Vacant(entry) => {
-                    let _ = entry.insert(Item::from_list_typed(value_vec));
+                    let _ = entry.insert(Item::from_list_typed(value_vec, M::combinable()));
                 },
             }
         } else {
             // It’s a single header.
             match entry {
-                Occupied(entry) => entry.into_mut().set_single_typed(value),
+                Occupied(entry) => entry.into_mut().set_single_typed(value, M::combinable()),
                 Vacant(entry) => {
-                    let _ = entry.insert(Item::from_single_typed(value));
+                    let _ = entry.insert(Item::from_single_typed(value, M::combinable()));
                 },
             }
         }
@@ -531,7 +1132,7 @@ impl Headers {
     pub fn get_raw_mut<'a, M: Marker<'a>>
                       (&'a mut self, _marker: M)
                       -> Option<&mut SmallVec<[ByteTendril; 1]>> {
-        self.data.get_mut(M::header_name().as_bytes()).map(|item| item.raw_mut())
+        self.data_mut().get_mut(M::header_name().as_bytes()).map(|item| item.raw_mut())
     }
 
     /// Set the raw value of a header, by name.
@@ -539,19 +1140,194 @@ impl Headers {
     /// This invalidates the typed representation.
     #[inline]
     pub fn set_raw<'a, M: Marker<'a>>(&'a mut self, _marker: M, value: SmallVec<[ByteTendril; 1]>) {
-        match self.data.entry(M::header_name().into()) {
-            Vacant(entry) => { let _ = entry.insert(Item::from_raw(value)); },
-            Occupied(entry) => entry.into_mut().set_raw(value),
+        match self.data_mut().entry(M::header_name().into()) {
+            Vacant(entry) => { let _ = entry.insert(Item::from_raw(value, M::combinable())); },
+            Occupied(entry) => entry.into_mut().set_raw(value, M::combinable()),
+        }
+    }
+
+    /// Get the item for the named header, where the name is not necessarily lowercase.
+    fn item_by_name(&self, name: &str) -> Option<&Item> {
+        self.data.get(name.to_ascii_lowercase().as_bytes())
+    }
+
+    /// Get the raw field lines of a header by its name (case-insensitive).
+    ///
+    /// Field lines are stored as they were received, never combined, so a list header received
+    /// as two lines comes back as two lines, even after it has been read in typed form; a proxy
+    /// can thus forward it split as it was. Only once the typed form has been modified (with
+    /// `set` or `get_mut`) is the header produced afresh, as a single line.
+    pub fn get_raw_lines(&self, name: &str) -> Option<Ref<Cow<[ByteTendril]>>> {
+        self.item_by_name(name).and_then(|item| item.raw())
+    }
+
+    /// Get the raw field lines of a header by its name (case-insensitive), joined with `, ` into
+    /// one value.
+    ///
+    /// For a list header, this is its whole value, as RFC 7230 section 3.2.2 defines it. It is
+    /// wrong for `Set-Cookie`, whose lines can’t be combined, for its values contain commas; use
+    /// `get_raw_lines` for that.
+    pub fn get_raw_combined(&self, name: &str) -> Option<Vec<u8>> {
+        let raw = match self.item_by_name(name).and_then(|item| item.raw()) {
+            Some(raw) => raw,
+            None => return None,
+        };
+        let mut combined = vec![];
+        for (i, value) in raw.iter().enumerate() {
+            if i > 0 {
+                combined.extend_from_slice(b", ");
+            }
+            combined.extend_from_slice(value);
+        }
+        Some(combined)
+    }
+
+    /// Get the raw values of a header by its name (case-insensitive), decoded as UTF-8.
+    ///
+    /// This is convenient when debugging or dealing with headers which you have no marker for.
+    /// Field values which are not valid UTF-8 are skipped if `skip_invalid` is true; otherwise,
+    /// any invalid field value causes `None` to be returned, as does the header being absent.
+    ///
+    /// The values are copied, because the raw form may be produced on demand from the typed form.
+    pub fn get_raw_str(&self, name: &str, skip_invalid: bool) -> Option<Vec<String>> {
+        let raw = match self.item_by_name(name).and_then(|item| item.raw()) {
+            Some(raw) => raw,
+            None => return None,
+        };
+        let mut values = Vec::with_capacity(raw.len());
+        for value in raw.iter() {
+            match str::from_utf8(value) {
+                Ok(value) => values.push(value.to_owned()),
+                Err(_) if skip_invalid => (),
+                Err(_) => return None,
+            }
+        }
+        Some(values)
+    }
+
+    /// The names of the headers in the collection, sorted, so that iteration is deterministic.
+    fn sorted_names(&self) -> Vec<&StrTendril> {
+        let mut names = self.data.keys().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Render the whole collection in a stable, human-readable form, for logging and the like.
+    ///
+    /// There is one line, `name: value`, for each field line, with the headers sorted by name. A
+    /// byte which is not printable ASCII or a space is written as `\xNN`, and a backslash as
+    /// `\\`, so that no value can span lines or mislead. A header whose value has been
+    /// invalidated by a failed `get_mut` is written as `name: <invalid>`.
+    ///
+    /// This is also what the `Debug` implementation writes.
+    pub fn debug_dump(&self) -> String {
+        let mut out = String::new();
+        for name in self.sorted_names() {
+            let item = &self.data[name];
+            // Check this first, for taking the raw form turns an invalid item into an empty one.
+            if !item.is_valid() {
+                out.push_str(name);
+                out.push_str(": <invalid>\n");
+                continue;
+            }
+            let raw = match item.raw() {
+                Some(raw) => raw,
+                None => continue,
+            };
+            for value in raw.iter() {
+                out.push_str(name);
+                out.push_str(": ");
+                push_escaped(&mut out, value);
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Flatten the collection into field lines, as pairs of name and value.
+    ///
+    /// There is one pair for each field line, so a header with several lines appears several
+    /// times, in order; the headers are sorted by name, as for `debug_dump`. Names are lowercase.
+    /// A header whose value has been invalidated by a failed `get_mut` has no field lines.
+    ///
+    /// This is for handing the headers over to something that doesn’t speak `Headers`, such as
+    /// another HTTP library or a logger. The values are copied, because the raw form may be
+    /// produced on demand from the typed form.
+    pub fn pairs(&self) -> Vec<(&str, Vec<u8>)> {
+        let mut pairs = vec![];
+        for name in self.sorted_names() {
+            let item = &self.data[name];
+            if !item.is_valid() {
+                continue;
+            }
+            if let Some(raw) = item.raw() {
+                pairs.extend(raw.iter().map(|value| (&name[..], value.to_vec())));
+            }
+        }
+        pairs
+    }
+
+    /// Flatten the collection into field lines, as `pairs` does, but with owned names.
+    pub fn into_pairs(self) -> Vec<(Cow<'static, str>, Vec<u8>)> {
+        self.pairs().into_iter().map(|(name, value)| (Cow::Owned(name.to_owned()), value))
+                    .collect()
+    }
+
+    /// Whether two header collections have the same field lines, compared in raw form.
+    ///
+    /// The order of distinct headers and the case of names make no difference (for names are
+    /// stored lowercase), but the order of field lines with the same name does, for it is
+    /// significant. Unlike `==`, this doesn’t care whether a header is held in raw or typed form,
+    /// so it is what you want for comparing produced headers with expected ones. Any pseudo-header
+    /// fields must also be the same.
+    pub fn semantically_eq(&self, other: &Headers) -> bool {
+        self.pseudo == other.pseudo && self.pairs() == other.pairs()
+    }
+
+    /// Parse a header, by its name (case-insensitive), as a single decimal integer.
+    ///
+    /// There must be exactly one field line, consisting of digits and, if `signed`, an optional
+    /// leading `-`; anything else, including a value which overflows the type, gives `None`.
+    fn get_integer<T: str::FromStr>(&self, name: &str, signed: bool) -> Option<T> {
+        let raw = match self.item_by_name(name).and_then(|item| item.raw()) {
+            Some(ref raw) if raw.len() == 1 => raw[0].clone(),
+            _ => return None,
+        };
+        let digits = if signed && raw.starts_with(b"-") { &raw[1..] } else { &raw[..] };
+        if digits.is_empty() || !digits.iter().all(|&c| c >= b'0' && c <= b'9') {
+            return None;
         }
+        // All ASCII, so this can’t fail.
+        unsafe { str::from_utf8_unchecked(&raw) }.parse().ok()
+    }
+
+    /// Get a header, by its name (case-insensitive), as a `u64`.
+    ///
+    /// This and its siblings are for numeric headers without a marker, and for when the width
+    /// matters; the `usize` implementation of `ToHeader` depends on the platform.
+    pub fn get_u64(&self, name: &str) -> Option<u64> {
+        self.get_integer(name, false)
+    }
+
+    /// Get a header, by its name (case-insensitive), as an `i64`.
+    pub fn get_i64(&self, name: &str) -> Option<i64> {
+        self.get_integer(name, true)
+    }
+
+    /// Get a header, by its name (case-insensitive), as a `u32`.
+    pub fn get_u32(&self, name: &str) -> Option<u32> {
+        self.get_integer(name, false)
     }
 
     /// Remove a header from the collection.
     /// Returns true if the named header was present.
     pub fn remove<'a, M: Marker<'a>>(&'a mut self, _marker: M) -> bool {
-        self.data.remove(M::header_name().as_bytes()).is_some()
+        self.data_mut().remove(M::header_name().as_bytes()).is_some()
     }
 
     /// Returns true if the named header exists in the collection.
+    ///
+    /// This doesn’t parse the header, so it may be present but unusable; see `contains_valid`.
     pub fn contains<'a, M: Marker<'a>>(&'a self, _marker: M) -> bool {
         match self.data.get(M::header_name().as_bytes()) {
             Some(item) => item.is_valid(),
@@ -559,10 +1335,99 @@ impl Headers {
         }
     }
 
+    /// Returns true if the named header exists in the collection and parses successfully.
+    ///
+    /// For a list header, this means that at least one item parses, for `get` skips any which
+    /// don’t. The parsed value is kept, so a following `get` will not need to parse it again.
+    pub fn contains_valid<'a, M: Marker<'a>>(&'a self, marker: M) -> bool {
+        internals::Get::has_value(&self.get(marker))
+    }
+
+    /// Remove every header for which the predicate returns false, given its name (lowercase) and
+    /// raw field values.
+    ///
+    /// This is for stripping headers by pattern, such as all `X-Debug-*` headers before
+    /// forwarding or logging a message; use `remove` if you know what you’re removing.
+    pub fn retain<F: FnMut(&str, &[ByteTendril]) -> bool>(&mut self, mut f: F) {
+        let doomed = self.data.iter().filter(|&(name, item)| {
+            match item.raw() {
+                Some(raw) => !f(name, &raw),
+                None => !f(name, &[]),
+            }
+        }).map(|(name, _)| name.clone()).collect::<Vec<StrTendril>>();
+        if !doomed.is_empty() {
+            let data = self.data_mut();
+            for name in doomed {
+                let _ = data.remove(&name);
+            }
+        }
+    }
+
+    /// Remove all hop-by-hop headers, as a proxy must before forwarding a message.
+    ///
+    /// This consists of the headers which RFC 7230 and its predecessors define as hop-by-hop
+    /// (`Connection`, `Keep-Alive`, `Proxy-Authenticate`, `Proxy-Authorization`, `TE`,
+    /// `Trailer`, `Transfer-Encoding` and `Upgrade`), plus any headers named as connection
+    /// options in the `Connection` header (RFC 7230, section 6.1).
+    pub fn remove_hop_by_hop(&mut self) {
+        let connection_options = self.get(CONNECTION).iter().filter_map(|option| match *option {
+            Connection::Other(ref name) => Some(name.to_ascii_lowercase()),
+            Connection::Close => None,
+        }).collect::<Vec<String>>();
+        for name in HOP_BY_HOP_HEADERS.iter().map(|name| *name)
+                                      .chain(connection_options.iter().map(|name| &name[..])) {
+            let _ = self.data_mut().remove(name.as_bytes());
+        }
+    }
+
+    /// Returns true if any conditional request header (`If-Match`, `If-None-Match`,
+    /// `If-Modified-Since`, `If-Unmodified-Since` or `If-Range`) is present.
+    ///
+    /// If this is false, a resource handler can skip precondition evaluation altogether. The
+    /// headers are not parsed, so this may be true even if none of them turns out to be usable.
+    pub fn is_conditional(&self) -> bool {
+        CONDITIONAL_HEADERS.iter().any(|name| {
+            self.data.get(name.as_bytes()).map_or(false, |item| item.is_valid())
+        })
+    }
+
     // TODO: make this more like a normal collection. Compare with what I did for AnyMap.
     // Methods to consider adding as appropriate/possible: entry, capacity, reserve, shrink_to_fit,
     // iter, iter_mut, len, is_empty, drain, clear.
-    // Also impl Debug.
+}
+
+impl fmt::Debug for Headers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.debug_dump())
+    }
+}
+
+/// A builder for a header collection, produced by `Headers::builder()`.
+///
+/// Each method corresponds to the `Headers` method of the same name, but consumes and returns the
+/// builder so that calls may be chained.
+pub struct HeadersBuilder {
+    headers: Headers,
+}
+
+impl HeadersBuilder {
+    /// Set the named header to the given value.
+    pub fn set<M: Marker<'static>>(mut self, marker: M, value: M::Set) -> HeadersBuilder {
+        self.headers.set(marker, value);
+        self
+    }
+
+    /// Set the raw value of a header, by name.
+    pub fn set_raw<M: for<'a> Marker<'a>>(mut self, marker: M, value: SmallVec<[ByteTendril; 1]>)
+                                          -> HeadersBuilder {
+        self.headers.set_raw(marker, value);
+        self
+    }
+
+    /// Finish building, producing the header collection.
+    pub fn build(self) -> Headers {
+        self.headers
+    }
 }
 
 /// An adapter which provides `std::fmt::Display` as equivalent to `Header.fmt`, so that you can
@@ -576,6 +1441,344 @@ impl<'a, H: Header + ?Sized> fmt::Display for HeaderDisplayAdapter<'a, H> {
     }
 }
 
+#[cfg(test)]
+mod collection_tests {
+    use std::borrow::Cow;
+    use smallvec::SmallVec;
+    use tendril::{ByteTendril, StrTendril};
+    use test::{self, Bencher};
+    use super::{Headers, Header, Marker};
+    use super::internals::Item;
+    use super::cache_control::{CacheDirective, CACHE_CONTROL};
+    use super::priority::PRIORITY;
+    use super::connection::{Connection, CONNECTION};
+    use super::content_type::{MediaType, CONTENT_TYPE};
+    use super::cookie::{SetCookie, SET_COOKIE};
+
+    define_single_header_marker!(X_CUSTOM: usize = "x-custom");
+    define_single_header_marker!(X_OTHER: usize = "x-other");
+    define_single_header_marker!(TRANSFER_ENCODING: usize = "transfer-encoding");
+    define_single_header_marker!(X_SPACE: usize = "x space");
+    define_single_header_marker!(X_COLON: usize = "x-colon:");
+
+    fn raw(lines: &[&[u8]]) -> SmallVec<[ByteTendril; 1]> {
+        let mut raw = SmallVec::new();
+        for line in lines {
+            raw.push(ByteTendril::from_slice(line));
+        }
+        raw
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut headers = Headers::new();
+        assert!(!headers.contains(X_CUSTOM));
+        assert!(!headers.contains_valid(X_CUSTOM));
+        headers.set_raw(X_CUSTOM, raw(&[b"seventeen"]));
+        assert!(headers.contains(X_CUSTOM));
+        assert!(!headers.contains_valid(X_CUSTOM));
+        headers.set_raw(X_CUSTOM, raw(&[b"17"]));
+        assert!(headers.contains_valid(X_CUSTOM));
+        headers.set(X_OTHER, 2);
+        assert!(headers.contains(X_OTHER));
+        assert!(headers.contains_valid(X_OTHER));
+
+        headers.set_raw(CONNECTION, raw(&[b"clo se"]));
+        assert!(headers.contains(CONNECTION));
+        assert!(!headers.contains_valid(CONNECTION));
+        headers.set_raw(CONNECTION, raw(&[b"clo se, close"]));
+        assert!(headers.contains_valid(CONNECTION));
+    }
+
+    #[test]
+    fn test_remove_hop_by_hop() {
+        let mut headers = Headers::new();
+        headers.set_raw(CONNECTION, raw(&[b"close, X-Custom"]));
+        headers.set_raw(X_CUSTOM, raw(&[b"1"]));
+        headers.set_raw(X_OTHER, raw(&[b"2"]));
+        headers.set_raw(TRANSFER_ENCODING, raw(&[b"chunked"]));
+        headers.remove_hop_by_hop();
+        assert!(!headers.contains(CONNECTION));
+        assert!(!headers.contains(X_CUSTOM));
+        assert!(!headers.contains(TRANSFER_ENCODING));
+        assert!(headers.contains(X_OTHER));
+    }
+
+    // The check on marker names is a debug assertion; `write_to` catches them otherwise.
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not a token")]
+    fn test_marker_name_with_space() {
+        Headers::new().set_raw(X_SPACE, raw(&[b"1"]));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not a token")]
+    fn test_marker_name_with_colon() {
+        Headers::new().set(X_COLON, 1);
+    }
+
+    #[test]
+    fn test_builder() {
+        let headers = Headers::builder()
+            .set(X_CUSTOM, 1)
+            .set(CONNECTION, vec![Connection::Close])
+            .set_raw(X_OTHER, raw(&[b"2"]))
+            .set(CONTENT_TYPE, MediaType::new("text", "plain"))
+            .build();
+        assert_eq!(*headers.get(X_CUSTOM).unwrap(), 1);
+        assert_eq!(&*headers.get(CONNECTION), &[Connection::Close]);
+        assert_eq!(*headers.get(X_OTHER).unwrap(), 2);
+        assert_eq!(*headers.get(CONTENT_TYPE).unwrap(), MediaType::new("text", "plain"));
+    }
+
+    #[test]
+    fn test_clone_on_write() {
+        let mut original = Headers::new();
+        original.set(X_CUSTOM, 1);
+        original.set_raw(X_OTHER, raw(&[b"2"]));
+
+        let mut clone = original.clone();
+        assert!(&*original.data as *const _ == &*clone.data as *const _);
+        assert_eq!(*clone.get(X_CUSTOM).unwrap(), 1);
+        assert!(&*original.data as *const _ == &*clone.data as *const _);
+
+        clone.set(X_CUSTOM, 3);
+        assert!(clone.remove(X_OTHER));
+        assert!(&*original.data as *const _ != &*clone.data as *const _);
+        assert_eq!(*original.get(X_CUSTOM).unwrap(), 1);
+        assert_eq!(*original.get(X_OTHER).unwrap(), 2);
+        assert_eq!(*clone.get(X_CUSTOM).unwrap(), 3);
+        assert!(!clone.contains(X_OTHER));
+    }
+
+    #[test]
+    fn test_get_raw_str() {
+        let mut headers = Headers::new();
+        headers.set_raw(X_OTHER, raw(&[b"caf\xc3\xa9", b"caf\xe9", b"tea"]));
+        headers.set(X_CUSTOM, 1);
+        assert_eq!(headers.get_raw_str("X-Other", true),
+                   Some(vec!["café".to_owned(), "tea".to_owned()]));
+        assert_eq!(headers.get_raw_str("x-other", false), None);
+        assert_eq!(headers.get_raw_str("X-CUSTOM", false), Some(vec!["1".to_owned()]));
+        assert_eq!(headers.get_raw_str("x-absent", true), None);
+    }
+
+    #[test]
+    fn test_get_raw_lines() {
+        let mut headers = Headers::parse_block(b"Connection: close, X-A\r\nConnection: X-B\r\n")
+                                  .unwrap();
+        assert_eq!(headers.get(CONNECTION).len(), 3);
+        {
+            let lines = headers.get_raw_lines("Connection").unwrap();
+            assert_eq!(lines.len(), 2);
+            assert_eq!(&lines[0][..], b"close, X-A");
+            assert_eq!(&lines[1][..], b"X-B");
+        }
+        assert!(headers.get_raw_lines("x-absent").is_none());
+
+        headers.get_mut(CONNECTION).pop();
+        let lines = headers.get_raw_lines("connection").unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(&lines[0][..], b"close, X-A");
+    }
+
+    #[test]
+    fn test_list_combinable() {
+        assert!(CacheDirective::is_list_combinable());
+        assert!(CACHE_CONTROL::combinable());
+        assert!(Connection::is_list_combinable());
+        assert!(!SetCookie::is_list_combinable());
+        assert!(!SET_COOKIE::combinable());
+
+        let headers = Headers::parse_block(b"Cache-Control: no-store, max-age=0\r\n\
+                                             Set-Cookie: a=1; Expires=Wed, 21 Oct 2015 07:28:00 \
+                                             GMT\r\n").unwrap();
+        assert_eq!(headers.get(CACHE_CONTROL).len(), 2);
+        assert_eq!(headers.get(SET_COOKIE).len(), 1);
+
+        // Single markers don’t combine unless they say so; two lines are then no value.
+        assert!(!X_CUSTOM::combinable());
+        assert!(PRIORITY::combinable());
+        let mut headers = Headers::new();
+        headers.set_raw(X_CUSTOM, raw(&[b"1", b"1"]));
+        assert!(headers.get(X_CUSTOM).is_none());
+    }
+
+    #[test]
+    fn test_get_raw_combined() {
+        let headers = Headers::parse_block(b"Vary: accept\r\nX-Other: 1\r\n\
+                                             vary: origin, te\r\n").unwrap();
+        assert_eq!(headers.get_raw_combined("Vary"), Some(b"accept, origin, te".to_vec()));
+        assert_eq!(headers.get_raw_combined("x-other"), Some(b"1".to_vec()));
+        assert_eq!(headers.get_raw_combined("x-absent"), None);
+    }
+
+    #[test]
+    fn test_get_integer() {
+        let mut headers = Headers::new();
+        headers.set_raw(X_CUSTOM, raw(&[b"4294967296"]));
+        headers.set_raw(X_OTHER, raw(&[b"-12"]));
+        headers.set_raw(TRANSFER_ENCODING, raw(&[b"1", b"2"]));
+        assert_eq!(headers.get_u64("X-Custom"), Some(4294967296));
+        assert_eq!(headers.get_i64("x-custom"), Some(4294967296));
+        assert_eq!(headers.get_u32("x-custom"), None);
+        assert_eq!(headers.get_i64("x-other"), Some(-12));
+        assert_eq!(headers.get_u64("x-other"), None);
+        assert_eq!(headers.get_u32("transfer-encoding"), None);
+        assert_eq!(headers.get_u32("x-absent"), None);
+
+        for bad in &[&b"+1"[..], b"", b"-", b"1.0", b"0x10", b"18446744073709551616"] {
+            headers.set_raw(X_CUSTOM, raw(&[bad]));
+            assert_eq!(headers.get_u64("x-custom"), None);
+        }
+        headers.set_raw(X_CUSTOM, raw(&[b"18446744073709551615"]));
+        assert_eq!(headers.get_u64("x-custom"), Some(18446744073709551615));
+        assert_eq!(headers.get_i64("x-custom"), None);
+    }
+
+    #[test]
+    fn test_debug_dump() {
+        let mut headers = Headers::new();
+        headers.set_raw(X_OTHER, raw(&[b"caf\xc3\xa9\\", b"2"]));
+        headers.set(X_CUSTOM, 1);
+        headers.set(CONNECTION, vec![Connection::Close]);
+        let dump = headers.debug_dump();
+        assert!(dump.contains("connection: close\n"));
+        assert!(dump.contains("x-custom: 1\n"));
+        assert!(dump.contains("x-other: caf\\xc3\\xa9\\\\\n"));
+        assert_eq!(dump, "connection: close\nx-custom: 1\nx-other: caf\\xc3\\xa9\\\\\n\
+                          x-other: 2\n");
+        assert_eq!(format!("{:?}", headers), dump);
+        assert_eq!(Headers::new().debug_dump(), "");
+
+        headers.set_raw(X_CUSTOM, raw(&[b"x"]));
+        assert!(headers.get_mut(X_CUSTOM).is_none());
+        assert!(headers.debug_dump().contains("x-custom: <invalid>\n"));
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut headers = Headers::new();
+        headers.set_raw(X_CUSTOM, raw(&[b"1"]));
+        headers.set_raw(X_OTHER, raw(&[b"2", b"3"]));
+        headers.set(CONNECTION, vec![Connection::Close]);
+        headers.set(CONTENT_TYPE, MediaType::new("text", "plain"));
+        let original = headers.clone();
+
+        let safe = ["content-type", "x-other"];
+        headers.retain(|name, _| safe.contains(&name));
+        assert!(headers.contains(CONTENT_TYPE));
+        assert!(headers.contains(X_OTHER));
+        assert!(!headers.contains(X_CUSTOM));
+        assert!(!headers.contains(CONNECTION));
+        assert!(original.contains(X_CUSTOM));
+
+        headers.retain(|_, values| values.len() == 1 && &values[0][..] == b"text/plain");
+        assert!(headers.contains(CONTENT_TYPE));
+        assert!(!headers.contains(X_OTHER));
+    }
+
+    #[test]
+    fn test_semantically_eq() {
+        let a = Headers::parse_block(b"X-Custom: 1\r\nConnection: a\r\nconnection: b\r\n").unwrap();
+        let b = Headers::parse_block(b"CONNECTION: a\r\nConnection: b\r\nx-custom: 1\r\n").unwrap();
+        assert!(a.semantically_eq(&b));
+        let mut typed = Headers::new();
+        typed.set(X_CUSTOM, 1);
+        typed.set_raw(CONNECTION, raw(&[b"a", b"b"]));
+        assert!(a.semantically_eq(&typed));
+        assert!(typed.semantically_eq(&a));
+
+        let reordered = Headers::parse_block(b"Connection: b\r\nConnection: a\r\nX-Custom: 1\r\n")
+                                  .unwrap();
+        assert!(!a.semantically_eq(&reordered));
+        let fewer = Headers::parse_block(b"X-Custom: 1\r\nConnection: a\r\n").unwrap();
+        assert!(!a.semantically_eq(&fewer));
+        assert!(!fewer.semantically_eq(&a));
+        assert!(Headers::new().semantically_eq(&Headers::new()));
+    }
+
+    #[test]
+    fn test_is_conditional() {
+        let mut headers = Headers::new();
+        headers.set(X_CUSTOM, 1);
+        headers.set(CONNECTION, vec![Connection::Close]);
+        assert!(!headers.is_conditional());
+        for name in &["If-Match", "If-None-Match", "If-Modified-Since", "If-Unmodified-Since",
+                      "If-Range"] {
+            let mut conditional = headers.clone();
+            conditional.append_raw(name, b"\"x\"").unwrap();
+            assert!(conditional.is_conditional(), "{}", name);
+        }
+        assert!(!headers.is_conditional());
+    }
+
+    #[test]
+    fn test_pairs() {
+        let mut headers = Headers::new();
+        headers.set_raw(X_OTHER, raw(&[b"2", b"3"]));
+        headers.set(X_CUSTOM, 1);
+        headers.set(CONNECTION, vec![Connection::Close]);
+        assert_eq!(headers.pairs(), vec![("connection", b"close".to_vec()),
+                                         ("x-custom", b"1".to_vec()),
+                                         ("x-other", b"2".to_vec()),
+                                         ("x-other", b"3".to_vec())]);
+        let owned = headers.into_pairs();
+        assert_eq!(owned.len(), 4);
+        assert_eq!(owned[3], (Cow::Borrowed("x-other"), b"3".to_vec()));
+        assert!(Headers::new().pairs().is_empty());
+    }
+
+    #[test]
+    fn test_capacity() {
+        assert!(Headers::with_capacity(20).capacity() >= 20);
+        let mut headers = Headers::new();
+        headers.set(X_CUSTOM, 1);
+        headers.reserve(30);
+        assert!(headers.capacity() >= 31);
+        assert_eq!(*headers.get(X_CUSTOM).unwrap(), 1);
+
+        // Reserving in a clone leaves the original alone.
+        let mut clone = headers.clone();
+        clone.reserve(100);
+        assert!(clone.capacity() >= 101);
+        assert!(headers.capacity() < 101);
+        assert!(clone.semantically_eq(&headers));
+    }
+
+    fn names() -> Vec<StrTendril> {
+        (0..32).map(|i| StrTendril::from_slice(&format!("x-header-{}", i))).collect()
+    }
+
+    fn insert_all(headers: &mut Headers, names: &[StrTendril]) {
+        for name in names {
+            let _ = headers.data_mut().insert(name.clone(), Item::from_raw(raw(&[b"1"]), false));
+        }
+    }
+
+    #[bench]
+    fn bench_insert_without_reserve(b: &mut Bencher) {
+        let names = names();
+        b.iter(|| {
+            let mut headers = Headers::new();
+            insert_all(&mut headers, &names);
+            test::black_box(&headers);
+        });
+    }
+
+    #[bench]
+    fn bench_insert_with_reserve(b: &mut Bencher) {
+        let names = names();
+        b.iter(|| {
+            let mut headers = Headers::with_capacity(names.len());
+            insert_all(&mut headers, &names);
+            test::black_box(&headers);
+        });
+    }
+}
+
 #[cfg(test_broken)]
 mod tests {
     use super::*;
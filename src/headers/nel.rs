@@ -0,0 +1,159 @@
+//! The `NEL` header (Network Error Logging, W3C Working Draft), whose value is a JSON object.
+
+use std::fmt;
+
+use grammar::json::{self, Value};
+use super::{ToHeader, Header};
+
+/// A Network Error Logging policy.
+///
+/// The header value is a JSON object, for example:
+///
+/// ```text
+/// NEL: {"report_to": "network-errors", "max_age": 2592000}
+/// ```
+///
+/// `report_to` and `max_age` are required. The other members are optional, defaulting as
+/// specified; the fractions must be between 0 and 1 inclusive. Unknown members are ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Nel {
+    /// The name of the endpoint group (from `Report-To`) to which reports should be sent.
+    pub report_to: String,
+    /// How long the policy should be remembered for, in seconds. Zero removes the policy.
+    pub max_age: u64,
+    /// Whether the policy applies to subdomains too. Defaults to `false`.
+    pub include_subdomains: bool,
+    /// The sampling rate for successful requests. Defaults to 0.
+    pub success_fraction: f64,
+    /// The sampling rate for failed requests. Defaults to 1.
+    pub failure_fraction: f64,
+}
+
+impl Nel {
+    /// Construct a policy with the default values for the optional members.
+    pub fn new(report_to: &str, max_age: u64) -> Nel {
+        Nel {
+            report_to: report_to.to_owned(),
+            max_age: max_age,
+            include_subdomains: false,
+            success_fraction: 0.0,
+            failure_fraction: 1.0,
+        }
+    }
+
+    /// Read a policy from a parsed JSON value.
+    pub fn from_json(value: &Value) -> Option<Nel> {
+        let report_to = match value.get("report_to").and_then(Value::as_str) {
+            Some(report_to) => report_to,
+            None => return None,
+        };
+        let max_age = match value.get("max_age").and_then(Value::as_u64) {
+            Some(max_age) => max_age,
+            None => return None,
+        };
+        let include_subdomains = match value.get("include_subdomains") {
+            Some(b) => match b.as_bool() {
+                Some(b) => b,
+                None => return None,
+            },
+            None => false,
+        };
+        let success_fraction = match fraction(value, "success_fraction", 0.0) {
+            Some(n) => n,
+            None => return None,
+        };
+        let failure_fraction = match fraction(value, "failure_fraction", 1.0) {
+            Some(n) => n,
+            None => return None,
+        };
+        Some(Nel {
+            report_to: report_to.to_owned(),
+            max_age: max_age,
+            include_subdomains: include_subdomains,
+            success_fraction: success_fraction,
+            failure_fraction: failure_fraction,
+        })
+    }
+}
+
+/// Read an optional sampling fraction, which must be between 0 and 1 inclusive.
+fn fraction(value: &Value, name: &str, default: f64) -> Option<f64> {
+    match value.get(name) {
+        Some(n) => match n.as_f64() {
+            Some(n) if n >= 0.0 && n <= 1.0 => Some(n),
+            _ => None,
+        },
+        None => Some(default),
+    }
+}
+
+impl ToHeader for Nel {
+    fn parse(raw: &[u8]) -> Option<Nel> {
+        json::parse(raw).and_then(|value| Nel::from_json(&value))
+    }
+}
+
+impl Header for Nel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str("{\"report_to\": "));
+        try!(json::write_string(f, &self.report_to));
+        try!(write!(f, ", \"max_age\": {}", self.max_age));
+        if self.include_subdomains {
+            try!(f.write_str(", \"include_subdomains\": true"));
+        }
+        if self.success_fraction != 0.0 {
+            try!(write!(f, ", \"success_fraction\": {}", self.success_fraction));
+        }
+        if self.failure_fraction != 1.0 {
+            try!(write!(f, ", \"failure_fraction\": {}", self.failure_fraction));
+        }
+        f.write_str("}")
+    }
+}
+
+define_single_header_marker! {
+    /// The `NEL` header, a Network Error Logging policy.
+    pub NEL: Nel = "nel"
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::{ToHeader, HeaderDisplayAdapter};
+    use super::Nel;
+
+    #[test]
+    fn test_nel() {
+        let raw = b"{\"report_to\": \"network-errors\", \"max_age\": 2592000, \
+                    \"include_subdomains\": true, \"success_fraction\": 0.25, \
+                    \"failure_fraction\": 0.5}";
+        let nel = Nel {
+            report_to: "network-errors".to_owned(),
+            max_age: 2592000,
+            include_subdomains: true,
+            success_fraction: 0.25,
+            failure_fraction: 0.5,
+        };
+        assert_eq!(Nel::parse(raw), Some(nel.clone()));
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&nel)).as_bytes(), &raw[..]);
+
+        assert_eq!(Nel::parse(b"{ \"max_age\":0,\"report_to\":\"a\",\"x\":[null] }"),
+                   Some(Nel::new("a", 0)));
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&Nel::new("a", 0))),
+                   "{\"report_to\": \"a\", \"max_age\": 0}");
+    }
+
+    #[test]
+    fn test_bad_nel() {
+        assert_eq!(Nel::parse(b""), None);
+        assert_eq!(Nel::parse(b"{\"max_age\": 60}"), None);
+        assert_eq!(Nel::parse(b"{\"report_to\": \"a\"}"), None);
+        assert_eq!(Nel::parse(b"{\"report_to\": 1, \"max_age\": 60}"), None);
+        assert_eq!(Nel::parse(b"{\"report_to\": \"a\", \"max_age\": -1}"), None);
+        assert_eq!(Nel::parse(b"{\"report_to\": \"a\", \"max_age\": 1.5}"), None);
+        assert_eq!(Nel::parse(b"{\"report_to\": \"a\", \"max_age\": 60, \
+                                 \"failure_fraction\": 2}"), None);
+        assert_eq!(Nel::parse(b"{\"report_to\": \"a\", \"max_age\": 60, \
+                                 \"include_subdomains\": 1}"), None);
+        assert_eq!(Nel::parse(b"[{\"report_to\": \"a\", \"max_age\": 60}]"), None);
+    }
+}
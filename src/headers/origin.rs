@@ -0,0 +1,185 @@
+//! Web origins (RFC 6454), and the `Origin` header (RFC 6454, section 7).
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+
+use super::{ToHeader, Header};
+
+/// A serialized web origin, as used by `Origin`, `Timing-Allow-Origin` and the CORS headers.
+///
+/// RFC 6454 grammar:
+///
+/// ```abnf
+/// serialized-origin = scheme "://" host [ ":" port ]
+///                   ; <scheme>, <host>, <port> from RFC 3986
+/// ```
+///
+/// Origins may also be opaque, serialized as `null`, as for a sandboxed document.
+///
+/// The scheme and host are lowercased, and a port which is the default port of the scheme (80
+/// for `http`, 443 for `https`) is dropped, so that equal origins compare equal.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Origin {
+    /// An opaque origin, `null`.
+    Null,
+    /// A scheme/host/port tuple.
+    Tuple {
+        /// The scheme, e.g. `https`. Always lowercase.
+        scheme: String,
+        /// The host, e.g. `example.com` or `[::1]`. Always lowercase.
+        host: String,
+        /// The port, if it is not the default port for the scheme.
+        port: Option<u16>,
+    },
+}
+
+/// The default port of a scheme, for the schemes where we know it.
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        _ => None,
+    }
+}
+
+impl Origin {
+    /// Construct a tuple origin, normalising it as described above.
+    pub fn new(scheme: &str, host: &str, port: Option<u16>) -> Origin {
+        let scheme = scheme.to_ascii_lowercase();
+        let port = if port.is_some() && port == default_port(&scheme) {
+            None
+        } else {
+            port
+        };
+        Origin::Tuple {
+            scheme: scheme,
+            host: host.to_ascii_lowercase(),
+            port: port,
+        }
+    }
+}
+
+fn is_scheme_char(c: u8) -> bool {
+    match c {
+        b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' | b'+' | b'-' | b'.' => true,
+        _ => false,
+    }
+}
+
+fn is_reg_name_char(c: u8) -> bool {
+    match c {
+        // unreserved / pct-encoded / sub-delims, less the characters that can’t be in a header
+        // value or would be confusing in a list.
+        b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' | b'-' | b'.' | b'_' | b'~' | b'%' | b'!' |
+        b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b';' | b'=' => true,
+        _ => false,
+    }
+}
+
+impl ToHeader for Origin {
+    fn parse(raw: &[u8]) -> Option<Origin> {
+        if raw == b"null" {
+            return Some(Origin::Null);
+        }
+        let scheme_end = match raw.windows(3).position(|w| w == b"://") {
+            Some(i) => i,
+            None => return None,
+        };
+        let scheme = &raw[..scheme_end];
+        match scheme.first() {
+            Some(&b'a'...b'z') | Some(&b'A'...b'Z') => (),
+            _ => return None,
+        }
+        if !scheme.iter().all(|&c| is_scheme_char(c)) {
+            return None;
+        }
+        let rest = &raw[scheme_end + 3..];
+        let host_end = if rest.first() == Some(&b'[') {
+            // IP-literal
+            match rest.iter().position(|&c| c == b']') {
+                Some(i) if i > 1 && rest[1..i].iter().all(|&c| (c as char).is_digit(16) ||
+                                                                 c == b':' || c == b'.') => i + 1,
+                _ => return None,
+            }
+        } else {
+            let end = rest.iter().position(|&c| !is_reg_name_char(c)).unwrap_or(rest.len());
+            if end == 0 {
+                return None;
+            }
+            end
+        };
+        let port = match &rest[host_end..] {
+            b"" => None,
+            port if port[0] == b':' && port.len() > 1 &&
+                    port[1..].iter().all(|&c| c >= b'0' && c <= b'9') => {
+                // All ASCII digits, so this can’t fail.
+                match unsafe { str::from_utf8_unchecked(&port[1..]) }.parse() {
+                    Ok(port) => Some(port),
+                    Err(_) => return None,
+                }
+            },
+            _ => return None,
+        };
+        // All ASCII, so these can’t fail.
+        Some(Origin::new(unsafe { str::from_utf8_unchecked(scheme) },
+                         unsafe { str::from_utf8_unchecked(&rest[..host_end]) },
+                         port))
+    }
+}
+
+impl Header for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Origin::Null => f.write_str("null"),
+            Origin::Tuple { ref scheme, ref host, port } => {
+                try!(write!(f, "{}://{}", scheme, host));
+                match port {
+                    Some(port) => write!(f, ":{}", port),
+                    None => Ok(()),
+                }
+            },
+        }
+    }
+}
+
+define_single_header_marker! {
+    /// The `Origin` header: the origin which caused the request.
+    pub ORIGIN: Origin = "origin"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter};
+    use super::Origin;
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_origin() {
+        eq(b"null", Origin::Null);
+        eq(b"https://example.com", Origin::new("https", "example.com", None));
+        eq(b"http://example.com:8080", Origin::new("http", "example.com", Some(8080)));
+        eq(b"http://[::1]:8080", Origin::new("http", "[::1]", Some(8080)));
+        assert_eq!(Origin::parse(b"HTTPS://Example.COM:443"),
+                   Some(Origin::new("https", "example.com", None)));
+        assert_eq!(Origin::new("https", "example.com", Some(443)),
+                   Origin::new("https", "example.com", None));
+        bad::<Origin>(b"");
+        bad::<Origin>(b"example.com");
+        bad::<Origin>(b"https://");
+        bad::<Origin>(b"https://example.com/");
+        bad::<Origin>(b"https://example.com:");
+        bad::<Origin>(b"https://example.com:65536");
+        bad::<Origin>(b"1http://example.com");
+        bad::<Origin>(b"http://[example.com]");
+    }
+}
@@ -0,0 +1,180 @@
+//! Iterating over the headers of a collection in typed form, for those which are known.
+
+use std::borrow::Cow;
+use std::vec;
+
+use tendril::{ByteTendril, StrTendril};
+
+use super::{ToHeader, Header, Headers, Ref, TypedListRef};
+use super::accept::{MediaRange, ACCEPT};
+use super::cache_control::{CacheDirective, Age, CACHE_CONTROL, AGE};
+use super::connection::{Connection, CONNECTION};
+use super::content_type::{MediaType, CONTENT_TYPE};
+use super::cookie::{Cookie, SetCookie, COOKIE, SET_COOKIE};
+use super::date_based::{HttpDate, DATE};
+use super::etag::{EntityTag, ETAG};
+use super::expires::{Expires, EXPIRES};
+use super::transfer_encoding::{TransferCoding, TRANSFER_ENCODING};
+use super::upgrade::{ProtocolName, UPGRADE};
+use super::vary::{Vary, VARY};
+
+/// A header from a collection, in typed form if it is one of those known here and parses, and
+/// in raw form otherwise, as produced by `Headers::typed_iter`.
+///
+/// A list header is only given in typed form if at least one of its items parses, as with
+/// `Headers::contains_valid`.
+pub enum ParsedHeader<'a> {
+    /// `Accept`.
+    Accept(TypedListRef<'a, MediaRange>),
+    /// `Age`.
+    Age(Ref<'a, Cow<'a, Age>>),
+    /// `Cache-Control`.
+    CacheControl(TypedListRef<'a, CacheDirective>),
+    /// `Connection`.
+    Connection(TypedListRef<'a, Connection>),
+    /// `Content-Type`.
+    ContentType(Ref<'a, Cow<'a, MediaType>>),
+    /// `Cookie`.
+    Cookie(Ref<'a, Cow<'a, Cookie>>),
+    /// `Date`.
+    Date(Ref<'a, Cow<'a, HttpDate>>),
+    /// `ETag`.
+    ETag(Ref<'a, Cow<'a, EntityTag>>),
+    /// `Expires`.
+    Expires(Ref<'a, Cow<'a, Expires>>),
+    /// `Set-Cookie`.
+    SetCookie(TypedListRef<'a, SetCookie>),
+    /// `Transfer-Encoding`.
+    TransferEncoding(TypedListRef<'a, TransferCoding>),
+    /// `Upgrade`.
+    Upgrade(TypedListRef<'a, ProtocolName>),
+    /// `Vary`.
+    Vary(Ref<'a, Cow<'a, Vary>>),
+    /// Any other header, or a known one which doesn’t parse: its name (lowercase) and raw field
+    /// lines.
+    Raw(&'a str, Ref<'a, Cow<'a, [ByteTendril]>>),
+}
+
+/// Keep a typed list only if it has something in it.
+fn non_empty<'a, T: ToHeader + Header + Clone>(list: TypedListRef<'a, T>)
+                                               -> Option<TypedListRef<'a, T>> {
+    if list.is_empty() { None } else { Some(list) }
+}
+
+impl<'a> ParsedHeader<'a> {
+    /// Get the named header (lowercase) from the collection, in typed form if possible.
+    ///
+    /// `None` means that the header has no usable value at all, having been invalidated.
+    fn from_headers(headers: &'a Headers, name: &'a str) -> Option<ParsedHeader<'a>> {
+        let typed = match name {
+            "accept" => non_empty(headers.get(ACCEPT)).map(ParsedHeader::Accept),
+            "age" => headers.get(AGE).map(ParsedHeader::Age),
+            "cache-control" => {
+                non_empty(headers.get(CACHE_CONTROL)).map(ParsedHeader::CacheControl)
+            },
+            "connection" => non_empty(headers.get(CONNECTION)).map(ParsedHeader::Connection),
+            "content-type" => headers.get(CONTENT_TYPE).map(ParsedHeader::ContentType),
+            "cookie" => headers.get(COOKIE).map(ParsedHeader::Cookie),
+            "date" => headers.get(DATE).map(ParsedHeader::Date),
+            "etag" => headers.get(ETAG).map(ParsedHeader::ETag),
+            "expires" => headers.get(EXPIRES).map(ParsedHeader::Expires),
+            "set-cookie" => non_empty(headers.get(SET_COOKIE)).map(ParsedHeader::SetCookie),
+            "transfer-encoding" => {
+                non_empty(headers.get(TRANSFER_ENCODING)).map(ParsedHeader::TransferEncoding)
+            },
+            "upgrade" => non_empty(headers.get(UPGRADE)).map(ParsedHeader::Upgrade),
+            "vary" => headers.get(VARY).map(ParsedHeader::Vary),
+            _ => None,
+        };
+        match typed {
+            Some(typed) => Some(typed),
+            None => headers.get_raw_lines(name).map(|raw| ParsedHeader::Raw(name, raw)),
+        }
+    }
+}
+
+/// An iterator over the headers of a collection, produced by `Headers::typed_iter`.
+pub struct TypedIter<'a> {
+    headers: &'a Headers,
+    names: vec::IntoIter<&'a StrTendril>,
+}
+
+impl<'a> Iterator for TypedIter<'a> {
+    type Item = ParsedHeader<'a>;
+
+    fn next(&mut self) -> Option<ParsedHeader<'a>> {
+        loop {
+            let name = match self.names.next() {
+                Some(name) => name,
+                None => return None,
+            };
+            if let Some(header) = ParsedHeader::from_headers(self.headers, name) {
+                return Some(header);
+            }
+        }
+    }
+}
+
+impl Headers {
+    /// Iterate over the headers in the collection, sorted by name, each in typed form if it is
+    /// known to `ParsedHeader` and in raw form otherwise.
+    ///
+    /// Headers are parsed only as the iterator reaches them, and the typed form is kept just as
+    /// it is by `get`, so iterating a second time (or a later `get`) doesn’t parse them again.
+    pub fn typed_iter(&self) -> TypedIter {
+        TypedIter {
+            headers: self,
+            names: self.sorted_names().into_iter(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use headers::Headers;
+    use headers::connection::Connection;
+    use headers::vary::Vary;
+    use super::ParsedHeader;
+
+    #[test]
+    fn test_typed_iter() {
+        let headers = Headers::parse_block(b"Content-Type: text/html; charset=utf-8\r\n\
+                                             Connection: close\r\n\
+                                             X-Custom: 1\r\n\
+                                             Vary: Accept-Encoding\r\n\
+                                             Date: garbage\r\n").unwrap();
+        for _ in 0..2 {
+            let mut names = vec![];
+            for header in headers.typed_iter() {
+                match header {
+                    ParsedHeader::Connection(ref options) => {
+                        assert_eq!(&options[..], &[Connection::Close][..]);
+                        names.push("connection");
+                    },
+                    ParsedHeader::ContentType(ref media_type) => {
+                        assert_eq!(media_type.type_, "text");
+                        names.push("content-type");
+                    },
+                    ParsedHeader::Vary(ref vary) => {
+                        assert_eq!(***vary, Vary::Fields(vec!["accept-encoding".to_owned()]));
+                        // The typed form is kept in the collection, so it is borrowed from there,
+                        // not parsed afresh, on every iteration.
+                        match **vary {
+                            Cow::Borrowed(_) => (),
+                            Cow::Owned(_) => panic!("Vary not kept in typed form"),
+                        }
+                        names.push("vary");
+                    },
+                    ParsedHeader::Raw(name, ref raw) => {
+                        assert_eq!(raw.len(), 1);
+                        names.push(name);
+                    },
+                    _ => panic!("unexpected header"),
+                }
+            }
+            assert_eq!(names, ["connection", "content-type", "date", "vary", "x-custom"]);
+        }
+        assert_eq!(Headers::new().typed_iter().count(), 0);
+    }
+}
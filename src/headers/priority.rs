@@ -0,0 +1,155 @@
+//! The `Priority` header (RFC 9218, section 5).
+
+use std::fmt;
+
+use super::{ToHeader, Header};
+use super::sf::{self, BareItem, Member};
+
+/// The default urgency: 3.
+pub const DEFAULT_URGENCY: u8 = 3;
+
+/// The `Priority` header: the client’s preference for the order in which responses are sent.
+///
+/// This is a structured field dictionary:
+///
+/// - `u`, the urgency, an integer from 0 (most urgent) to 7 (least urgent), defaulting to 3;
+/// - `i`, whether the response is handled incrementally, a boolean defaulting to false.
+///
+/// RFC 9218 requires that unknown members, members of the wrong type, and an urgency out of range
+/// all be ignored, meaning that the default applies; this does so, so parsing only fails if the
+/// value is not a valid dictionary at all.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Priority {
+    urgency: Option<u8>,
+    incremental: Option<bool>,
+}
+
+impl Priority {
+    /// Construct a priority. Returns `None` if the urgency is out of range.
+    pub fn new(urgency: u8, incremental: bool) -> Option<Priority> {
+        if urgency > 7 {
+            None
+        } else {
+            Some(Priority { urgency: Some(urgency), incremental: Some(incremental) })
+        }
+    }
+
+    /// The urgency, from 0 (most urgent) to 7 (least urgent). 3 if not specified.
+    pub fn urgency(&self) -> u8 {
+        self.urgency.unwrap_or(DEFAULT_URGENCY)
+    }
+
+    /// Whether the client will process the response incrementally. False if not specified.
+    pub fn incremental(&self) -> bool {
+        self.incremental.unwrap_or(false)
+    }
+}
+
+/// The defaults: no members at all.
+impl Default for Priority {
+    fn default() -> Priority {
+        Priority { urgency: None, incremental: None }
+    }
+}
+
+impl ToHeader for Priority {
+    fn parse(raw: &[u8]) -> Option<Priority> {
+        let dictionary = match sf::parse_dictionary(raw) {
+            Some(dictionary) => dictionary,
+            None => return None,
+        };
+        let mut priority = Priority::default();
+        for (key, member) in dictionary {
+            match (&key[..], member) {
+                ("u", Member::Item(BareItem::Integer(u), _)) if u >= 0 && u <= 7 => {
+                    priority.urgency = Some(u as u8);
+                },
+                ("i", Member::Item(BareItem::Boolean(i), _)) => priority.incremental = Some(i),
+                _ => (),
+            }
+        }
+        Some(priority)
+    }
+}
+
+/// Only the members which were specified are written. If neither was, that is `u=3`, which means
+/// the same, rather than an empty field value.
+impl Header for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut dictionary = vec![];
+        if let Some(u) = self.urgency {
+            dictionary.push(("u".to_owned(), Member::Item(BareItem::Integer(u as i64), vec![])));
+        }
+        if let Some(i) = self.incremental {
+            dictionary.push(("i".to_owned(), Member::Item(BareItem::Boolean(i), vec![])));
+        }
+        if dictionary.is_empty() {
+            let u = BareItem::Integer(DEFAULT_URGENCY as i64);
+            dictionary.push(("u".to_owned(), Member::Item(u, vec![])));
+        }
+        sf::fmt_dictionary(f, &dictionary)
+    }
+}
+
+define_single_header_marker! {
+    /// The `Priority` header. Being a dictionary, it may be split across field lines, which are
+    /// combined (RFC 8941, section 4.2).
+    pub PRIORITY: Priority = "priority", combine
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use super::{Priority, PRIORITY};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_priority() {
+        let priority = Priority::new(1, true).unwrap();
+        eq(b"u=1, i", priority);
+        assert_eq!(priority.urgency(), 1);
+        assert!(priority.incremental());
+        eq(b"u=7, i=?0", Priority::new(7, false).unwrap());
+        assert_eq!(Priority::parse(b"i, u=0"), Some(Priority::new(0, true).unwrap()));
+        assert_eq!(Priority::new(8, false), None);
+        bad::<Priority>(b"u=1,");
+        bad::<Priority>(b"U=1");
+    }
+
+    #[test]
+    fn test_defaults() {
+        let priority = Priority::parse(b"").unwrap();
+        assert_eq!(priority, Priority::default());
+        // Written out, the default urgency is explicit, rather than an empty value.
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&priority)), "u=3");
+        assert_eq!(Priority::parse(b"u=3").unwrap().urgency(), 3);
+        assert_eq!(priority.urgency(), 3);
+        assert!(!priority.incremental());
+        assert_eq!(Priority::parse(b"u=5").unwrap().incremental(), false);
+        assert_eq!(Priority::parse(b"i").unwrap().urgency(), 3);
+        // Out of range, of the wrong type, or unknown: ignored.
+        assert_eq!(Priority::parse(b"u=8, i=1, x=2"), Some(Priority::default()));
+        assert_eq!(Priority::parse(b"u=-1").unwrap().urgency(), 3);
+        assert_eq!(Priority::parse(b"u=\"1\"").unwrap().urgency(), 3);
+    }
+
+    #[test]
+    fn test_several_field_lines() {
+        let headers = Headers::parse_block(b"Priority: u=1\r\nPriority: i\r\n").unwrap();
+        assert_eq!(**headers.get(PRIORITY).unwrap(), Priority::new(1, true).unwrap());
+        // A later member overrides an earlier one, across lines as within them.
+        let headers = Headers::parse_block(b"Priority: u=1\r\nPriority: u=5\r\n").unwrap();
+        assert_eq!(headers.get(PRIORITY).unwrap().urgency(), 5);
+        let headers = Headers::parse_block(b"Priority: u=1\r\nPriority: u=\r\n").unwrap();
+        assert!(headers.get(PRIORITY).is_none());
+    }
+}
@@ -0,0 +1,124 @@
+//! HTTP/2 pseudo-header fields (RFC 7540, section 8.1.2.1), such as `:method` and `:status`.
+//!
+//! These carry what HTTP/1 puts in the request or status line. They are kept apart from the
+//! regular headers of a `Headers`, always come before them, and can’t be sent over HTTP/1.
+
+use std::ascii::AsciiExt;
+
+use super::Headers;
+
+/// A pseudo-header field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum PseudoHeader {
+    /// `:method`: the request method.
+    Method,
+    /// `:scheme`: the scheme of the target URI.
+    Scheme,
+    /// `:authority`: the authority of the target URI, in place of `Host`.
+    Authority,
+    /// `:path`: the path and query of the target URI.
+    Path,
+    /// `:status`: the response status code.
+    Status,
+}
+
+impl PseudoHeader {
+    /// The field name, including the leading colon.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            PseudoHeader::Method => ":method",
+            PseudoHeader::Scheme => ":scheme",
+            PseudoHeader::Authority => ":authority",
+            PseudoHeader::Path => ":path",
+            PseudoHeader::Status => ":status",
+        }
+    }
+
+    /// The pseudo-header with this field name (case-insensitive), if it is one.
+    pub fn from_name(name: &str) -> Option<PseudoHeader> {
+        [PseudoHeader::Method, PseudoHeader::Scheme, PseudoHeader::Authority, PseudoHeader::Path,
+         PseudoHeader::Status].iter().map(|which| *which)
+                              .find(|which| name.eq_ignore_ascii_case(which.as_str()))
+    }
+
+    /// Whether this belongs in a request, rather than a response.
+    pub fn is_request(&self) -> bool {
+        *self != PseudoHeader::Status
+    }
+}
+
+impl Headers {
+    /// Get the value of a pseudo-header field.
+    pub fn pseudo(&self, which: PseudoHeader) -> Option<&str> {
+        self.pseudo.iter().find(|&&(w, _)| w == which).map(|&(_, ref value)| &value[..])
+    }
+
+    /// Set the value of a pseudo-header field.
+    ///
+    /// A request and a response have different pseudo-headers, and mixing the two is malformed;
+    /// that is for the HTTP/2 layer to check.
+    pub fn set_pseudo(&mut self, which: PseudoHeader, value: &str) {
+        match self.pseudo.iter().position(|&(w, _)| w == which) {
+            Some(i) => self.pseudo[i].1 = value.to_owned(),
+            None => {
+                self.pseudo.push((which, value.to_owned()));
+                self.pseudo.sort_by(|a, b| a.0.cmp(&b.0));
+            },
+        }
+    }
+
+    /// Remove a pseudo-header field, returning true if it was present.
+    pub fn remove_pseudo(&mut self, which: PseudoHeader) -> bool {
+        let len = self.pseudo.len();
+        self.pseudo.retain(|&(w, _)| w != which);
+        self.pseudo.len() != len
+    }
+
+    /// Whether there are any pseudo-header fields, which would make the collection unfit for
+    /// HTTP/1.
+    pub fn has_pseudo(&self) -> bool {
+        !self.pseudo.is_empty()
+    }
+
+    /// The pseudo-header fields, as pairs of name and value, in the order in which they are to
+    /// be sent: before any regular header fields.
+    pub fn pseudo_pairs(&self) -> Vec<(&'static str, &str)> {
+        self.pseudo.iter().map(|&(which, ref value)| (which.as_str(), &value[..])).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::Headers;
+    use super::PseudoHeader;
+
+    #[test]
+    fn test_pseudo() {
+        let mut headers = Headers::new();
+        assert!(!headers.has_pseudo());
+        assert_eq!(headers.pseudo(PseudoHeader::Path), None);
+        headers.set_pseudo(PseudoHeader::Path, "/index.html");
+        headers.set_pseudo(PseudoHeader::Method, "GET");
+        headers.set_pseudo(PseudoHeader::Path, "/");
+        assert!(headers.has_pseudo());
+        assert_eq!(headers.pseudo(PseudoHeader::Method), Some("GET"));
+        assert_eq!(headers.pseudo(PseudoHeader::Path), Some("/"));
+        assert_eq!(headers.pseudo_pairs(), vec![(":method", "GET"), (":path", "/")]);
+        // They are no regular headers.
+        assert_eq!(headers.get_raw_lines(":method"), None);
+        assert!(headers.pairs().is_empty());
+        assert!(headers.remove_pseudo(PseudoHeader::Method));
+        assert!(!headers.remove_pseudo(PseudoHeader::Method));
+        assert_eq!(headers.pseudo(PseudoHeader::Method), None);
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(PseudoHeader::from_name(":status"), Some(PseudoHeader::Status));
+        assert_eq!(PseudoHeader::from_name(":Authority"), Some(PseudoHeader::Authority));
+        assert_eq!(PseudoHeader::from_name("status"), None);
+        assert_eq!(PseudoHeader::from_name(":protocol"), None);
+        assert!(PseudoHeader::Path.is_request());
+        assert!(!PseudoHeader::Status.is_request());
+    }
+}
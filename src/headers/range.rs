@@ -0,0 +1,401 @@
+//! The `Range` header and range units (RFC 7233).
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+
+use grammar::{is_field_vchar, trim_ows};
+use grammar::token::is_tchar;
+use super::{ToHeader, Header};
+
+/// The default maximum number of ranges in a byte range set: 100.
+///
+/// A legitimate client has no need of many ranges, while thousands of tiny or overlapping ones are
+/// a cheap way of making a server do a lot of work (RFC 7233, section 6.1).
+pub const DEFAULT_MAX_RANGES: usize = 100;
+
+/// A range unit (RFC 7233, section 2).
+///
+/// RFC 7233 grammar:
+///
+/// ```abnf
+/// range-unit       = bytes-unit / other-range-unit
+/// bytes-unit       = "bytes"
+/// other-range-unit = token
+/// ```
+///
+/// Range units are case-insensitive; `bytes` is recognised in any case, while other units are
+/// kept as they were received.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RangeUnit {
+    /// `bytes`, the only range unit defined by RFC 7233.
+    Bytes,
+    /// Any other range unit, case preserved.
+    Other(String),
+}
+
+impl RangeUnit {
+    /// Parse a range unit, which must be a non-empty token.
+    pub fn from_slice(raw: &[u8]) -> Option<RangeUnit> {
+        if raw.is_empty() || !raw.iter().all(|&c| is_tchar(c)) {
+            None
+        } else if raw.eq_ignore_ascii_case(b"bytes") {
+            Some(RangeUnit::Bytes)
+        } else {
+            // Tokens are ASCII, so this can’t fail.
+            Some(RangeUnit::Other(unsafe { str::from_utf8_unchecked(raw) }.to_owned()))
+        }
+    }
+}
+
+impl fmt::Display for RangeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RangeUnit::Bytes => f.write_str("bytes"),
+            RangeUnit::Other(ref unit) => f.write_str(unit),
+        }
+    }
+}
+
+/// One range of a byte range set.
+///
+/// RFC 7233 grammar:
+///
+/// ```abnf
+/// byte-range-spec        = first-byte-pos "-" [ last-byte-pos ]
+/// suffix-byte-range-spec = "-" suffix-length
+/// ```
+///
+/// Positions are inclusive and zero-based, so `0-499` is the first 500 bytes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ByteRangeSpec {
+    /// `first-last`: from the first position to the last position. `last` is never less than
+    /// `first`.
+    FromTo(u64, u64),
+    /// `first-`: from the first position to the end of the representation.
+    AllFrom(u64),
+    /// `-length`: the final `length` bytes of the representation.
+    Last(u64),
+}
+
+fn parse_u64(raw: &[u8]) -> Option<u64> {
+    if raw.is_empty() || !raw.iter().all(|&c| c >= b'0' && c <= b'9') {
+        return None;
+    }
+    // All ASCII digits, so this can’t fail.
+    unsafe { str::from_utf8_unchecked(raw) }.parse().ok()
+}
+
+impl ByteRangeSpec {
+    /// Parse a byte-range-spec or suffix-byte-range-spec.
+    pub fn from_slice(raw: &[u8]) -> Option<ByteRangeSpec> {
+        let dash = match raw.iter().position(|&c| c == b'-') {
+            Some(dash) => dash,
+            None => return None,
+        };
+        let (first, last) = (&raw[..dash], &raw[dash + 1..]);
+        if first.is_empty() {
+            parse_u64(last).map(ByteRangeSpec::Last)
+        } else {
+            let first = match parse_u64(first) {
+                Some(first) => first,
+                None => return None,
+            };
+            if last.is_empty() {
+                return Some(ByteRangeSpec::AllFrom(first));
+            }
+            match parse_u64(last) {
+                Some(last) if last >= first => Some(ByteRangeSpec::FromTo(first, last)),
+                _ => None,
+            }
+        }
+    }
+
+    /// The inclusive first and last positions which this range selects from a representation of
+    /// the given length, clamping the last position to the end, or `None` if it selects nothing
+    /// and so is unsatisfiable (RFC 7233, section 2.1).
+    pub fn to_satisfiable_range(&self, total_len: u64) -> Option<(u64, u64)> {
+        match *self {
+            ByteRangeSpec::FromTo(first, _) | ByteRangeSpec::AllFrom(first)
+                    if first >= total_len => None,
+            ByteRangeSpec::FromTo(first, last) => Some((first, last.min(total_len - 1))),
+            ByteRangeSpec::AllFrom(first) => Some((first, total_len - 1)),
+            ByteRangeSpec::Last(0) => None,
+            ByteRangeSpec::Last(_) if total_len == 0 => None,
+            ByteRangeSpec::Last(length) => Some((total_len.saturating_sub(length), total_len - 1)),
+        }
+    }
+}
+
+impl fmt::Display for ByteRangeSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ByteRangeSpec::FromTo(first, last) => write!(f, "{}-{}", first, last),
+            ByteRangeSpec::AllFrom(first) => write!(f, "{}-", first),
+            ByteRangeSpec::Last(length) => write!(f, "-{}", length),
+        }
+    }
+}
+
+/// The `Range` header: a request for only part of the representation.
+///
+/// RFC 7233 grammar:
+///
+/// ```abnf
+/// Range                  = byte-ranges-specifier / other-ranges-specifier
+/// byte-ranges-specifier  = bytes-unit "=" byte-range-set
+/// byte-range-set         = 1#( byte-range-spec / suffix-byte-range-spec )
+/// other-ranges-specifier = other-range-unit "=" other-range-set
+/// other-range-set        = 1*VCHAR
+/// ```
+///
+/// Byte ranges are parsed structurally; for any other range unit, the range set is kept verbatim
+/// as opaque text, as there is no way of knowing its structure.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Range {
+    /// A byte range set. Never empty.
+    Bytes(Vec<ByteRangeSpec>),
+    /// A range set in some other unit.
+    Other {
+        /// The range unit, case preserved. Never `bytes`, in any case.
+        unit: String,
+        /// The range set, verbatim.
+        spec: String,
+    },
+}
+
+impl Range {
+    /// The range unit.
+    pub fn unit(&self) -> RangeUnit {
+        match *self {
+            Range::Bytes(_) => RangeUnit::Bytes,
+            Range::Other { ref unit, .. } => RangeUnit::Other(unit.clone()),
+        }
+    }
+
+    /// Resolve a byte range set against a representation of the given length, producing the
+    /// inclusive first and last positions of each range, in the order requested.
+    ///
+    /// Suffix and open-ended ranges are made concrete, the last position is clamped to
+    /// `total_len - 1`, and ranges which select nothing are dropped. Ranges are neither sorted
+    /// nor coalesced. For a range unit other than `bytes`, there is nothing to resolve, and the
+    /// result is empty.
+    pub fn normalize(&self, total_len: u64) -> Vec<(u64, u64)> {
+        match *self {
+            Range::Bytes(ref ranges) => {
+                ranges.iter().filter_map(|range| range.to_satisfiable_range(total_len)).collect()
+            },
+            Range::Other { .. } => vec![],
+        }
+    }
+
+    /// Resolve a byte range set as `normalize` does, then sort the ranges and merge any which
+    /// overlap or are adjacent, giving the minimal set of ranges selecting the same bytes.
+    ///
+    /// A server may do this rather than send redundant parts (RFC 7233, section 4.1), though
+    /// it loses the order in which the client asked for them.
+    pub fn coalesce(&self, total_len: u64) -> Vec<(u64, u64)> {
+        let mut ranges = self.normalize(total_len);
+        ranges.sort();
+        let mut coalesced: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+        for (first, last) in ranges {
+            match coalesced.last_mut() {
+                // `last` is at most `total_len - 1`, so this can’t overflow.
+                Some(previous) if first <= previous.1 + 1 => {
+                    previous.1 = previous.1.max(last);
+                    continue;
+                },
+                _ => (),
+            }
+            coalesced.push((first, last));
+        }
+        coalesced
+    }
+
+    /// Whether any range of a byte range set selects part of a representation of the given
+    /// length. If not, the response should be `416 Range Not Satisfiable`; if so, `206 Partial
+    /// Content`. A range unit other than `bytes` is never satisfiable here.
+    pub fn is_satisfiable(&self, total_len: u64) -> bool {
+        match *self {
+            Range::Bytes(ref ranges) => {
+                ranges.iter().any(|range| range.to_satisfiable_range(total_len).is_some())
+            },
+            Range::Other { .. } => false,
+        }
+    }
+}
+
+impl Range {
+    /// Parse the header, as `parse`, but accepting at most `max_ranges` ranges in a byte range
+    /// set rather than `DEFAULT_MAX_RANGES`; more is a parse failure.
+    pub fn parse_with_limit(raw: &[u8], max_ranges: usize) -> Option<Range> {
+        let equals = match raw.iter().position(|&c| c == b'=') {
+            Some(equals) => equals,
+            None => return None,
+        };
+        let spec = &raw[equals + 1..];
+        match RangeUnit::from_slice(&raw[..equals]) {
+            Some(RangeUnit::Bytes) => {
+                let mut ranges = vec![];
+                for range in spec.split(|&c| c == b',').map(trim_ows) {
+                    if range.is_empty() {
+                        continue;
+                    }
+                    if ranges.len() == max_ranges {
+                        return None;
+                    }
+                    match ByteRangeSpec::from_slice(range) {
+                        Some(range) => ranges.push(range),
+                        None => return None,
+                    }
+                }
+                if ranges.is_empty() {
+                    None
+                } else {
+                    Some(Range::Bytes(ranges))
+                }
+            },
+            Some(RangeUnit::Other(unit)) => {
+                if spec.is_empty() || !spec.iter().all(|&c| c < 0x80 && is_field_vchar(c)) {
+                    return None;
+                }
+                Some(Range::Other {
+                    unit: unit,
+                    // All VCHAR, so this can’t fail.
+                    spec: unsafe { str::from_utf8_unchecked(spec) }.to_owned(),
+                })
+            },
+            None => None,
+        }
+    }
+}
+
+impl ToHeader for Range {
+    /// Byte range sets of more than `DEFAULT_MAX_RANGES` ranges are rejected; see
+    /// `Range::parse_with_limit`.
+    fn parse(raw: &[u8]) -> Option<Range> {
+        Range::parse_with_limit(raw, DEFAULT_MAX_RANGES)
+    }
+}
+
+impl Header for Range {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Range::Bytes(ref ranges) => {
+                try!(f.write_str("bytes="));
+                for (i, range) in ranges.iter().enumerate() {
+                    if i > 0 {
+                        try!(f.write_str(", "));
+                    }
+                    try!(write!(f, "{}", range));
+                }
+                Ok(())
+            },
+            Range::Other { ref unit, ref spec } => write!(f, "{}={}", unit, spec),
+        }
+    }
+}
+
+define_single_header_marker! {
+    /// The `Range` header.
+    pub RANGE: Range = "range"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter};
+    use super::{Range, RangeUnit, ByteRangeSpec, DEFAULT_MAX_RANGES};
+    use super::ByteRangeSpec::{FromTo, AllFrom, Last};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_byte_ranges() {
+        eq(b"bytes=0-499", Range::Bytes(vec![FromTo(0, 499)]));
+        eq(b"bytes=500-999, -500, 9500-", Range::Bytes(vec![FromTo(500, 999), Last(500),
+                                                              AllFrom(9500)]));
+        assert_eq!(Range::parse(b"Bytes=0-0,-1"), Some(Range::Bytes(vec![FromTo(0, 0), Last(1)])));
+        assert_eq!(Range::parse(b"bytes=0-0").unwrap().unit(), RangeUnit::Bytes);
+        bad::<Range>(b"bytes=");
+        bad::<Range>(b"bytes=,");
+        bad::<Range>(b"bytes=1-0");
+        bad::<Range>(b"bytes=-");
+        bad::<Range>(b"bytes=a-b");
+        bad::<Range>(b"bytes=0-1;");
+        bad::<Range>(b"0-499");
+        assert_eq!(ByteRangeSpec::from_slice(b"18446744073709551616-"), None);
+    }
+
+    #[test]
+    fn test_range_limit() {
+        let ranges = |n: usize| {
+            let specs = (0..n).map(|i| format!("{}-{}", i * 2, i * 2)).collect::<Vec<_>>();
+            format!("bytes={}", specs.join(","))
+        };
+        assert!(Range::parse(ranges(DEFAULT_MAX_RANGES).as_bytes()).is_some());
+        bad::<Range>(ranges(DEFAULT_MAX_RANGES + 1).as_bytes());
+        bad::<Range>(ranges(10000).as_bytes());
+        // Empty elements don’t count.
+        assert!(Range::parse_with_limit(b"bytes=0-0, , 2-2,", 2).is_some());
+        assert_eq!(Range::parse_with_limit(b"bytes=0-0,2-2,4-4", 2), None);
+        assert_eq!(Range::parse_with_limit(b"bytes=0-0", 0), None);
+        // Other units are opaque, so there is nothing to count.
+        assert!(Range::parse_with_limit(b"items=0-0,1-1", 1).is_some());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let range = |raw: &[u8]| Range::parse(raw).unwrap();
+        // A suffix range, including one longer than the representation.
+        assert_eq!(range(b"bytes=-500").normalize(10000), vec![(9500, 9999)]);
+        assert_eq!(range(b"bytes=-500").normalize(100), vec![(0, 99)]);
+        // Ranges running past the end are clamped.
+        assert_eq!(range(b"bytes=9500-").normalize(10000), vec![(9500, 9999)]);
+        assert_eq!(range(b"bytes=0-99999").normalize(10000), vec![(0, 9999)]);
+        // Unsatisfiable ranges are dropped, and the rest kept in order.
+        assert_eq!(range(b"bytes=20000-, 500-999, -0, 0-0").normalize(10000),
+                   vec![(500, 999), (0, 0)]);
+        assert!(range(b"bytes=20000-, 0-0").is_satisfiable(10000));
+        // And if none are left, the range set is unsatisfiable.
+        let unsatisfiable = range(b"bytes=10000-, 10000-20000, -0");
+        assert_eq!(unsatisfiable.normalize(10000), vec![]);
+        assert!(!unsatisfiable.is_satisfiable(10000));
+        assert!(!range(b"bytes=-500").is_satisfiable(0));
+        assert!(!range(b"items=0-9").is_satisfiable(10000));
+        assert_eq!(range(b"items=0-9").normalize(10000), vec![]);
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let range = |raw: &[u8]| Range::parse(raw).unwrap();
+        assert_eq!(range(b"bytes=0-10, 5-20").coalesce(100), vec![(0, 20)]);
+        assert_eq!(range(b"bytes=0-10, 20-30").coalesce(100), vec![(0, 10), (20, 30)]);
+        // Adjacent ranges merge, and order is lost.
+        assert_eq!(range(b"bytes=11-20, 0-10").coalesce(100), vec![(0, 20)]);
+        assert_eq!(range(b"bytes=50-60, 0-10, 5-8").coalesce(100), vec![(0, 10), (50, 60)]);
+        // After normalization.
+        assert_eq!(range(b"bytes=-10, 80-").coalesce(100), vec![(80, 99)]);
+        assert_eq!(range(b"bytes=0-0, 200-300").coalesce(100), vec![(0, 0)]);
+        assert_eq!(range(b"bytes=200-300").coalesce(100), vec![]);
+        assert_eq!(range(b"items=0-1").coalesce(100), vec![]);
+    }
+
+    #[test]
+    fn test_other_units() {
+        let range = Range::Other { unit: "Items".to_owned(), spec: "0-9,20-29;x".to_owned() };
+        eq(b"Items=0-9,20-29;x", range.clone());
+        assert_eq!(range.unit(), RangeUnit::Other("Items".to_owned()));
+        bad::<Range>(b"items=");
+        bad::<Range>(b"items=0 9");
+        bad::<Range>(b"my items=0-9");
+        bad::<Range>(b"=0-9");
+    }
+}
@@ -0,0 +1,283 @@
+//! The `Report-To` header (Reporting API, W3C Working Draft), whose value is a comma-separated
+//! list of JSON objects.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use grammar::json::{self, Value};
+use grammar::trim_ows_start;
+use super::{ToHeader, Header};
+
+/// A reporting endpoint, as found in an endpoint group.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Endpoint {
+    /// The URL to which reports are delivered.
+    pub url: String,
+    /// Endpoints with lower priorities are tried first. Defaults to 1.
+    pub priority: u64,
+    /// The weight for load balancing between endpoints of the same priority. Defaults to 1.
+    pub weight: u64,
+}
+
+impl Endpoint {
+    /// Construct an endpoint with the default priority and weight.
+    pub fn new(url: &str) -> Endpoint {
+        Endpoint {
+            url: url.to_owned(),
+            priority: 1,
+            weight: 1,
+        }
+    }
+
+    /// Read an endpoint from a parsed JSON value.
+    pub fn from_json(value: &Value) -> Option<Endpoint> {
+        let url = match value.get("url").and_then(Value::as_str) {
+            Some(url) => url,
+            None => return None,
+        };
+        let priority = match optional_u64(value, "priority", 1) {
+            Some(n) => n,
+            None => return None,
+        };
+        let weight = match optional_u64(value, "weight", 1) {
+            Some(n) => n,
+            None => return None,
+        };
+        Some(Endpoint {
+            url: url.to_owned(),
+            priority: priority,
+            weight: weight,
+        })
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str("{\"url\": "));
+        try!(json::write_string(f, &self.url));
+        if self.priority != 1 {
+            try!(write!(f, ", \"priority\": {}", self.priority));
+        }
+        if self.weight != 1 {
+            try!(write!(f, ", \"weight\": {}", self.weight));
+        }
+        f.write_str("}")
+    }
+}
+
+/// A reporting endpoint group.
+///
+/// `max_age` and a non-empty `endpoints` array are required; `group` defaults to `"default"` and
+/// `include_subdomains` to `false`. Unknown members are ignored.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EndpointGroup {
+    /// The name of the group, as referred to by, for example, `NEL`’s `report_to`.
+    pub group: Cow<'static, str>,
+    /// How long the group should be remembered for, in seconds. Zero removes the group.
+    pub max_age: u64,
+    /// Whether the group applies to subdomains too.
+    pub include_subdomains: bool,
+    /// The endpoints in the group. Never empty.
+    pub endpoints: Vec<Endpoint>,
+}
+
+impl EndpointGroup {
+    /// Read an endpoint group from a parsed JSON value.
+    pub fn from_json(value: &Value) -> Option<EndpointGroup> {
+        let group = match value.get("group") {
+            Some(group) => match group.as_str() {
+                Some(group) => Cow::Owned(group.to_owned()),
+                None => return None,
+            },
+            None => Cow::Borrowed("default"),
+        };
+        let max_age = match value.get("max_age").and_then(Value::as_u64) {
+            Some(max_age) => max_age,
+            None => return None,
+        };
+        let include_subdomains = match value.get("include_subdomains") {
+            Some(b) => match b.as_bool() {
+                Some(b) => b,
+                None => return None,
+            },
+            None => false,
+        };
+        let endpoints = match value.get("endpoints").and_then(Value::as_array) {
+            Some(endpoints) if !endpoints.is_empty() => endpoints,
+            _ => return None,
+        };
+        let mut parsed_endpoints = Vec::with_capacity(endpoints.len());
+        for endpoint in endpoints {
+            match Endpoint::from_json(endpoint) {
+                Some(endpoint) => parsed_endpoints.push(endpoint),
+                None => return None,
+            }
+        }
+        Some(EndpointGroup {
+            group: group,
+            max_age: max_age,
+            include_subdomains: include_subdomains,
+            endpoints: parsed_endpoints,
+        })
+    }
+}
+
+impl fmt::Display for EndpointGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str("{\"group\": "));
+        try!(json::write_string(f, &self.group));
+        try!(write!(f, ", \"max_age\": {}", self.max_age));
+        if self.include_subdomains {
+            try!(f.write_str(", \"include_subdomains\": true"));
+        }
+        try!(f.write_str(", \"endpoints\": ["));
+        for (i, endpoint) in self.endpoints.iter().enumerate() {
+            if i > 0 {
+                try!(f.write_str(", "));
+            }
+            try!(write!(f, "{}", endpoint));
+        }
+        f.write_str("]}")
+    }
+}
+
+fn optional_u64(value: &Value, name: &str, default: u64) -> Option<u64> {
+    match value.get(name) {
+        Some(n) => n.as_u64(),
+        None => Some(default),
+    }
+}
+
+/// The value of a `Report-To` header: one or more endpoint groups.
+///
+/// ```text
+/// Report-To: {"group": "csp", "max_age": 10886400,
+///             "endpoints": [{"url": "https://example.com/csp-reports"}]},
+///            {"max_age": 86400, "endpoints": [{"url": "https://example.com/reports"}]}
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReportTo {
+    /// The endpoint groups. Never empty.
+    pub groups: Vec<EndpointGroup>,
+}
+
+impl ToHeader for ReportTo {
+    fn parse(mut raw: &[u8]) -> Option<ReportTo> {
+        let mut groups = vec![];
+        loop {
+            let (value, rest) = match json::parse_prefix(raw) {
+                Some(x) => x,
+                None => return None,
+            };
+            match EndpointGroup::from_json(&value) {
+                Some(group) => groups.push(group),
+                None => return None,
+            }
+            raw = trim_ows_start(rest);
+            match raw.first() {
+                None => return Some(ReportTo { groups: groups }),
+                Some(&b',') => raw = &raw[1..],
+                Some(_) => return None,
+            }
+        }
+    }
+}
+
+impl Header for ReportTo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, group) in self.groups.iter().enumerate() {
+            if i > 0 {
+                try!(f.write_str(", "));
+            }
+            try!(write!(f, "{}", group));
+        }
+        Ok(())
+    }
+}
+
+define_single_header_marker! {
+    /// The `Report-To` header, a list of reporting endpoint groups.
+    pub REPORT_TO: ReportTo = "report-to", combine
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, Headers, ToHeader, HeaderDisplayAdapter};
+    use super::{ReportTo, EndpointGroup, Endpoint, REPORT_TO};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_report_to() {
+        let report_to = ReportTo {
+            groups: vec![
+                EndpointGroup {
+                    group: "csp".into(),
+                    max_age: 10886400,
+                    include_subdomains: true,
+                    endpoints: vec![Endpoint::new("https://example.com/csp-reports")],
+                },
+                EndpointGroup {
+                    group: "default".into(),
+                    max_age: 86400,
+                    include_subdomains: false,
+                    endpoints: vec![
+                        Endpoint::new("https://a.example.com/reports"),
+                        Endpoint {
+                            url: "https://b.example.com/reports".to_owned(),
+                            priority: 2,
+                            weight: 3,
+                        },
+                    ],
+                },
+            ],
+        };
+        eq(b"{\"group\": \"csp\", \"max_age\": 10886400, \"include_subdomains\": true, \
+             \"endpoints\": [{\"url\": \"https://example.com/csp-reports\"}]}, \
+             {\"group\": \"default\", \"max_age\": 86400, \
+             \"endpoints\": [{\"url\": \"https://a.example.com/reports\"}, \
+             {\"url\": \"https://b.example.com/reports\", \"priority\": 2, \"weight\": 3}]}",
+           report_to.clone());
+        assert_eq!(ReportTo::parse(b"{\"group\":\"csp\",\"max_age\":10886400,\
+                                      \"include_subdomains\":true,\
+                                      \"endpoints\":[{\"url\":\"https://example.com/csp-reports\"}]}\
+                                      ,{\"max_age\":86400,\"endpoints\":[\
+                                      {\"url\":\"https://a.example.com/reports\",\"priority\":1},\
+                                      {\"url\":\"https://b.example.com/reports\",\
+                                      \"priority\":2,\"weight\":3}]}"),
+                   Some(report_to));
+    }
+
+    #[test]
+    fn test_several_lines() {
+        let mut headers = Headers::new();
+        headers.append_raw("Report-To", b"{\"group\": \"csp\", \"max_age\": 60, \
+                                          \"endpoints\": [{\"url\": \"https://a.example/\"}]}")
+               .unwrap();
+        headers.append_raw("Report-To", b"{\"max_age\": 60, \
+                                          \"endpoints\": [{\"url\": \"https://b.example/\"}]}")
+               .unwrap();
+        let report_to = headers.get(REPORT_TO).unwrap();
+        let groups: Vec<_> = report_to.groups.iter().map(|group| &group.group[..]).collect();
+        assert_eq!(groups, ["csp", "default"]);
+    }
+
+    #[test]
+    fn test_bad_report_to() {
+        bad::<ReportTo>(b"");
+        bad::<ReportTo>(b"{\"max_age\": 60, \"endpoints\": []}");
+        bad::<ReportTo>(b"{\"max_age\": 60}");
+        bad::<ReportTo>(b"{\"endpoints\": [{\"url\": \"https://example.com/\"}]}");
+        bad::<ReportTo>(b"{\"max_age\": 60, \"endpoints\": [{\"priority\": 1}]}");
+        bad::<ReportTo>(b"{\"max_age\": 60, \"endpoints\": [{\"url\": \"https://example.com/\"}]},");
+        bad::<ReportTo>(b"{\"max_age\": 60, \"endpoints\": [{\"url\": \"https://example.com/\"}]} x");
+    }
+}
@@ -0,0 +1,122 @@
+//! The `Retry-After` header (RFC 7231, section 7.1.3).
+
+use std::fmt;
+use std::time::Duration;
+
+use time::Tm;
+
+use super::cache_control::delta_seconds_to_duration;
+use super::date_based::{HttpDate, parse_http_date};
+use super::{ToHeader, Header};
+use super::implementations::fmt_decimal;
+
+/// The `Retry-After` header: how long the user agent ought to wait before making a follow-up
+/// request.
+///
+/// RFC 7231 grammar:
+///
+/// ```abnf
+/// Retry-After = HTTP-date / delay-seconds
+/// delay-seconds  = 1*DIGIT
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RetryAfter {
+    /// Retry after this many seconds. Zero means that the request may be retried immediately.
+    ///
+    /// There is no upper limit in the grammar, so a value too large for a `u64` saturates at
+    /// `u64::MAX` rather than failing to parse; it is a very long time either way.
+    DeltaRA(u64),
+    /// Retry at or after this time.
+    DateRA(Tm),
+}
+
+impl RetryAfter {
+    /// The delay as a `Duration`, or `None` if a date was given.
+    ///
+    /// Like other delta headers, this saturates at `DELTA_SECONDS_MAX` (some sixty-eight years).
+    pub fn as_duration(&self) -> Option<Duration> {
+        match *self {
+            RetryAfter::DeltaRA(seconds) => Some(delta_seconds_to_duration(seconds)),
+            RetryAfter::DateRA(_) => None,
+        }
+    }
+}
+
+/// Parse delay-seconds, saturating at `u64::MAX`.
+fn parse_delay_seconds(raw: &[u8]) -> Option<u64> {
+    if raw.is_empty() || !raw.iter().all(|&c| c >= b'0' && c <= b'9') {
+        return None;
+    }
+    let mut value = 0u64;
+    for &c in raw {
+        value = match value.checked_mul(10).and_then(|v| v.checked_add((c - b'0') as u64)) {
+            Some(value) => value,
+            None => return Some(!0),
+        };
+    }
+    Some(value)
+}
+
+impl ToHeader for RetryAfter {
+    fn parse(raw: &[u8]) -> Option<RetryAfter> {
+        match parse_delay_seconds(raw) {
+            Some(seconds) => Some(RetryAfter::DeltaRA(seconds)),
+            None => parse_http_date(raw).map(RetryAfter::DateRA),
+        }
+    }
+}
+
+impl Header for RetryAfter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RetryAfter::DeltaRA(seconds) => fmt_decimal(seconds, f),
+            RetryAfter::DateRA(ref date) => HttpDate(*date).fmt(f),
+        }
+    }
+}
+
+define_single_header_marker! {
+    /// The `Retry-After` header.
+    pub RETRY_AFTER: RetryAfter = "retry-after"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::time::Duration;
+    use time::{self, Timespec};
+    use headers::{Header, ToHeader, HeaderDisplayAdapter};
+    use headers::cache_control::DELTA_SECONDS_MAX;
+    use super::RetryAfter;
+    use super::RetryAfter::{DeltaRA, DateRA};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        eq(b"120", DeltaRA(120));
+        eq(b"0", DeltaRA(0));
+        eq(b"Fri, 31 Dec 1999 23:59:59 GMT", DateRA(time::at_utc(Timespec::new(946684799, 0))));
+        eq(b"18446744073709551615", DeltaRA(!0));
+        assert_eq!(RetryAfter::parse(b"18446744073709551616"), Some(DeltaRA(!0)));
+        assert_eq!(RetryAfter::parse(b"99999999999999999999"), Some(DeltaRA(!0)));
+        bad::<RetryAfter>(b"-42");
+        bad::<RetryAfter>(b"");
+        bad::<RetryAfter>(b"1.5");
+        bad::<RetryAfter>(b"soon");
+    }
+
+    #[test]
+    fn test_as_duration() {
+        assert_eq!(DeltaRA(120).as_duration(), Some(Duration::from_secs(120)));
+        assert_eq!(DeltaRA(!0).as_duration(), Some(Duration::from_secs(DELTA_SECONDS_MAX)));
+        assert_eq!(DateRA(time::at_utc(Timespec::new(946684799, 0))).as_duration(), None);
+    }
+}
@@ -0,0 +1,88 @@
+//! The `Save-Data` header (Client Hints / Network Information API).
+
+use std::ascii::AsciiExt;
+use std::fmt;
+
+use grammar::trim_ows;
+use super::{ToHeader, Header};
+
+/// The `Save-Data` header: the user’s preference for reduced data usage.
+///
+/// Grammar:
+///
+/// ```abnf
+/// Save-Data = sd-token *( OWS ";" OWS [sd-token] )
+/// sd-token  = token
+/// ```
+///
+/// Only the `on` token (case-insensitive) is defined; following the convention of the Network
+/// Information API, any other value means that the preference is off. Parsing thus never fails.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SaveData {
+    /// `on`: the user prefers reduced data usage.
+    On,
+    /// Anything else.
+    Off,
+}
+
+impl SaveData {
+    /// Whether the user prefers reduced data usage.
+    pub fn is_on(&self) -> bool {
+        *self == SaveData::On
+    }
+}
+
+impl ToHeader for SaveData {
+    fn parse(raw: &[u8]) -> Option<SaveData> {
+        let first = raw.split(|&c| c == b';').next().unwrap_or(b"");
+        if trim_ows(first).eq_ignore_ascii_case(b"on") {
+            Some(SaveData::On)
+        } else {
+            Some(SaveData::Off)
+        }
+    }
+}
+
+/// `Off` is written as `off`, which is not a defined token but reads as off all the same.
+impl Header for SaveData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            SaveData::On => "on",
+            SaveData::Off => "off",
+        })
+    }
+}
+
+define_single_header_marker! {
+    /// The `Save-Data` header.
+    pub SAVE_DATA: SaveData = "save-data"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter};
+    use super::SaveData;
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    #[test]
+    fn test_save_data() {
+        eq(b"on", SaveData::On);
+        eq(b"off", SaveData::Off);
+        assert_eq!(SaveData::parse(b"ON"), Some(SaveData::On));
+        assert_eq!(SaveData::parse(b"on; foo"), Some(SaveData::On));
+        assert!(SaveData::parse(b"on").unwrap().is_on());
+    }
+
+    #[test]
+    fn test_unknown_is_off() {
+        assert_eq!(SaveData::parse(b"yes"), Some(SaveData::Off));
+        assert_eq!(SaveData::parse(b"foo; on"), Some(SaveData::Off));
+        assert_eq!(SaveData::parse(b""), Some(SaveData::Off));
+        assert!(!SaveData::parse(b"yes").unwrap().is_on());
+    }
+}
@@ -0,0 +1,519 @@
+//! Response headers which tell the user agent to enforce some security measure, and the request
+//! header with which it asks for a secure response.
+
+use std::ascii::AsciiExt;
+use std::fmt;
+
+use grammar::trim_ows;
+use grammar::token::is_tchar;
+use super::{ToHeader, Header};
+use super::HeaderDisplayAdapter;
+use super::origin::Origin;
+use super::sf::{self, BareItem, Member};
+
+enum_header! {
+    /// The `X-Content-Type-Options` header (Fetch Standard, section 3.5).
+    ///
+    /// Its only value is `nosniff`, compared case-insensitively, which forbids the user agent from
+    /// guessing at a response’s type instead of believing `Content-Type`.
+    pub XContentTypeOptions {
+        /// `nosniff`.
+        NoSniff = "nosniff",
+    }
+    /// The `X-Content-Type-Options` header.
+    pub X_CONTENT_TYPE_OPTIONS = "x-content-type-options"
+}
+
+impl XContentTypeOptions {
+    /// Whether this is `nosniff`, as it always is.
+    pub fn is_nosniff(&self) -> bool {
+        *self == XContentTypeOptions::NoSniff
+    }
+}
+
+/// The `X-Frame-Options` header (RFC 7034): whether the response may be displayed in a frame.
+///
+/// RFC 7034 grammar:
+///
+/// ```abnf
+/// X-Frame-Options = "DENY"
+///                 / "SAMEORIGIN"
+///                 / ( "ALLOW-FROM" RWS SERIALIZED-ORIGIN )
+/// ```
+///
+/// The keywords are case-insensitive. This header is superseded by the `frame-ancestors`
+/// directive of Content Security Policy, and `ALLOW-FROM` was never widely supported.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum XFrameOptions {
+    /// `DENY`: the response may not be framed at all.
+    Deny,
+    /// `SAMEORIGIN`: the response may only be framed by a page of the same origin.
+    SameOrigin,
+    /// `ALLOW-FROM`: the response may only be framed by a page of the origin given. Deprecated.
+    AllowFrom(Origin),
+}
+
+impl ToHeader for XFrameOptions {
+    fn parse(raw: &[u8]) -> Option<XFrameOptions> {
+        if raw.eq_ignore_ascii_case(b"deny") {
+            Some(XFrameOptions::Deny)
+        } else if raw.eq_ignore_ascii_case(b"sameorigin") {
+            Some(XFrameOptions::SameOrigin)
+        } else if raw.len() > 11 && raw[..10].eq_ignore_ascii_case(b"allow-from") &&
+                  (raw[10] == b' ' || raw[10] == b'\t') {
+            Origin::parse(trim_ows(&raw[11..])).map(XFrameOptions::AllowFrom)
+        } else {
+            None
+        }
+    }
+}
+
+impl Header for XFrameOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            XFrameOptions::Deny => f.write_str("DENY"),
+            XFrameOptions::SameOrigin => f.write_str("SAMEORIGIN"),
+            XFrameOptions::AllowFrom(ref origin) => {
+                try!(f.write_str("ALLOW-FROM "));
+                origin.fmt(f)
+            },
+        }
+    }
+}
+
+define_single_header_marker! {
+    /// The `X-Frame-Options` header.
+    pub X_FRAME_OPTIONS: XFrameOptions = "x-frame-options"
+}
+
+/// A referrer policy (Referrer Policy, W3C, section 3): how much of the referring URL to send in
+/// `Referer`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Policy {
+    /// `no-referrer`: never send it.
+    NoReferrer,
+    /// `no-referrer-when-downgrade`: send the full URL, except from HTTPS to HTTP.
+    NoReferrerWhenDowngrade,
+    /// `same-origin`: send the full URL to the same origin only.
+    SameOrigin,
+    /// `origin`: send only the origin.
+    Origin,
+    /// `strict-origin`: send only the origin, except from HTTPS to HTTP.
+    StrictOrigin,
+    /// `origin-when-cross-origin`: send the full URL to the same origin, and only the origin
+    /// elsewhere.
+    OriginWhenCrossOrigin,
+    /// `strict-origin-when-cross-origin`: as `origin-when-cross-origin`, but nothing from HTTPS to
+    /// HTTP. The default.
+    StrictOriginWhenCrossOrigin,
+    /// `unsafe-url`: always send the full URL.
+    UnsafeUrl,
+}
+
+impl Policy {
+    /// The policy token.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Policy::NoReferrer => "no-referrer",
+            Policy::NoReferrerWhenDowngrade => "no-referrer-when-downgrade",
+            Policy::SameOrigin => "same-origin",
+            Policy::Origin => "origin",
+            Policy::StrictOrigin => "strict-origin",
+            Policy::OriginWhenCrossOrigin => "origin-when-cross-origin",
+            Policy::StrictOriginWhenCrossOrigin => "strict-origin-when-cross-origin",
+            Policy::UnsafeUrl => "unsafe-url",
+        }
+    }
+
+    /// The policy for a token, compared case-insensitively, if it is one we know.
+    fn from_token(token: &[u8]) -> Option<Policy> {
+        [Policy::NoReferrer, Policy::NoReferrerWhenDowngrade, Policy::SameOrigin, Policy::Origin,
+         Policy::StrictOrigin, Policy::OriginWhenCrossOrigin, Policy::StrictOriginWhenCrossOrigin,
+         Policy::UnsafeUrl].iter().map(|policy| *policy)
+                           .find(|policy| token.eq_ignore_ascii_case(policy.as_str().as_bytes()))
+    }
+}
+
+/// The `Referrer-Policy` header (Referrer Policy, W3C, section 4.1).
+///
+/// Grammar:
+///
+/// ```abnf
+/// Referrer-Policy = 1#policy-token
+/// policy-token    = "no-referrer" / "no-referrer-when-downgrade" / "strict-origin" /
+///                   "strict-origin-when-cross-origin" / "same-origin" / "origin" /
+///                   "origin-when-cross-origin" / "unsafe-url"
+/// ```
+///
+/// Several policies may be given so that new ones can be introduced with a fallback; the user
+/// agent uses the last one it recognises. Accordingly, any token which isn’t a policy we know is
+/// dropped on parsing, but anything which isn’t a token makes the whole header invalid, as does
+/// an empty list.
+///
+/// With no recognised policies there is nothing to write, so formatting fails and the header
+/// produces no field line, rather than an empty one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReferrerPolicy {
+    policies: Vec<Policy>,
+}
+
+impl ReferrerPolicy {
+    /// Construct the header from policies, the preferred one last.
+    pub fn new(policies: Vec<Policy>) -> ReferrerPolicy {
+        ReferrerPolicy { policies: policies }
+    }
+
+    /// The recognised policies, in the order given.
+    pub fn policies(&self) -> &[Policy] {
+        &self.policies
+    }
+
+    /// The policy which applies: the last one recognised, if any.
+    pub fn effective(&self) -> Option<Policy> {
+        self.policies.last().cloned()
+    }
+}
+
+impl ToHeader for ReferrerPolicy {
+    fn parse(raw: &[u8]) -> Option<ReferrerPolicy> {
+        let mut policies = vec![];
+        let mut any = false;
+        for token in raw.split(|&c| c == b',').map(trim_ows).filter(|token| !token.is_empty()) {
+            if !token.iter().all(|&c| is_tchar(c)) {
+                return None;
+            }
+            any = true;
+            policies.extend(Policy::from_token(token));
+        }
+        if !any {
+            return None;
+        }
+        Some(ReferrerPolicy { policies: policies })
+    }
+}
+
+impl Header for ReferrerPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.policies.is_empty() {
+            return Err(fmt::Error);
+        }
+        for (i, policy) in self.policies.iter().enumerate() {
+            if i > 0 {
+                try!(f.write_str(", "));
+            }
+            try!(f.write_str(policy.as_str()));
+        }
+        Ok(())
+    }
+}
+
+define_single_header_marker! {
+    /// The `Referrer-Policy` header.
+    pub REFERRER_POLICY: ReferrerPolicy = "referrer-policy", combine
+}
+
+/// A member of a Permissions Policy allowlist: who may use a feature.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Allowance {
+    /// `*`: any origin.
+    Any,
+    /// `self`: the origin of the document.
+    SelfOrigin,
+    /// A specific origin.
+    Origin(Origin),
+}
+
+impl Allowance {
+    fn from_item(bare_item: &BareItem) -> Option<Allowance> {
+        match *bare_item {
+            BareItem::Token(ref token) if token == "*" => Some(Allowance::Any),
+            BareItem::Token(ref token) if token == "self" => Some(Allowance::SelfOrigin),
+            BareItem::String(ref origin) => {
+                Origin::parse(origin.as_bytes()).map(Allowance::Origin)
+            },
+            _ => None,
+        }
+    }
+
+    fn to_item(&self) -> BareItem {
+        match *self {
+            Allowance::Any => BareItem::Token("*".to_owned()),
+            Allowance::SelfOrigin => BareItem::Token("self".to_owned()),
+            Allowance::Origin(ref origin) => {
+                BareItem::String(format!("{}", HeaderDisplayAdapter(origin)))
+            },
+        }
+    }
+}
+
+/// The `Permissions-Policy` header (Permissions Policy, W3C, section 5.2): which origins may use
+/// each feature, such as `geolocation` or `camera`.
+///
+/// This is a structured field dictionary, each member an inner list of allowances (or a single
+/// one), so `geolocation=(self "https://example.com"), camera=()` permits geolocation to the
+/// document’s own origin and `https://example.com`, and the camera to nobody. As the
+/// specification requires, anything in an allowlist which isn’t an allowance is ignored, as are
+/// parameters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PermissionsPolicy {
+    features: Vec<(String, Vec<Allowance>)>,
+}
+
+impl PermissionsPolicy {
+    /// Construct a policy from features and their allowlists.
+    pub fn new(features: Vec<(String, Vec<Allowance>)>) -> PermissionsPolicy {
+        PermissionsPolicy { features: features }
+    }
+
+    /// The features and their allowlists, in the order given.
+    pub fn features(&self) -> &[(String, Vec<Allowance>)] {
+        &self.features
+    }
+
+    /// The allowlist of a feature, if the policy mentions it. An empty allowlist means that the
+    /// feature is disabled; a feature not mentioned has its default allowlist.
+    pub fn allowlist(&self, feature: &str) -> Option<&[Allowance]> {
+        self.features.iter().find(|&&(ref name, _)| name == feature)
+                     .map(|&(_, ref allowlist)| &allowlist[..])
+    }
+}
+
+impl ToHeader for PermissionsPolicy {
+    fn parse(raw: &[u8]) -> Option<PermissionsPolicy> {
+        sf::parse_dictionary(raw).map(|dictionary| PermissionsPolicy {
+            features: dictionary.into_iter().map(|(feature, member)| {
+                let allowlist = match member {
+                    Member::Item(ref bare_item, _) => {
+                        Allowance::from_item(bare_item).into_iter().collect()
+                    },
+                    Member::InnerList(ref items, _) => {
+                        items.iter().filter_map(|&(ref bare_item, _)| {
+                            Allowance::from_item(bare_item)
+                        }).collect()
+                    },
+                };
+                (feature, allowlist)
+            }).collect(),
+        })
+    }
+}
+
+impl Header for PermissionsPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dictionary = self.features.iter().map(|&(ref feature, ref allowlist)| {
+            let items = allowlist.iter().map(|allowance| (allowance.to_item(), vec![])).collect();
+            (feature.clone(), Member::InnerList(items, vec![]))
+        }).collect();
+        sf::fmt_dictionary(f, &dictionary)
+    }
+}
+
+define_single_header_marker! {
+    /// The `Permissions-Policy` header.
+    pub PERMISSIONS_POLICY: PermissionsPolicy = "permissions-policy", combine
+}
+
+/// The `Upgrade-Insecure-Requests` header (Upgrade Insecure Requests, W3C, section 3.2.1), which
+/// the user agent sends to say that it would prefer a response which upgrades it to HTTPS, as by
+/// redirecting there.
+///
+/// Grammar:
+///
+/// ```abnf
+/// Upgrade-Insecure-Requests = "1"
+/// ```
+///
+/// As `1` is the only valid value, the header’s presence is all that matters.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UpgradeInsecureRequests;
+
+impl UpgradeInsecureRequests {
+    /// Whether the user agent prefers an upgraded response. Always true, given that the header
+    /// has parsed; it exists to make code testing for the header read well.
+    pub fn prefers_upgrade(&self) -> bool {
+        true
+    }
+}
+
+impl ToHeader for UpgradeInsecureRequests {
+    fn parse(raw: &[u8]) -> Option<UpgradeInsecureRequests> {
+        if raw == b"1" {
+            Some(UpgradeInsecureRequests)
+        } else {
+            None
+        }
+    }
+}
+
+impl Header for UpgradeInsecureRequests {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("1")
+    }
+}
+
+define_single_header_marker! {
+    /// The `Upgrade-Insecure-Requests` header.
+    pub UPGRADE_INSECURE_REQUESTS: UpgradeInsecureRequests = "upgrade-insecure-requests"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, Headers, ToHeader, HeaderDisplayAdapter};
+    use headers::origin::Origin;
+    use super::{XContentTypeOptions, XFrameOptions, Policy, ReferrerPolicy, REFERRER_POLICY};
+    use super::{Allowance, PermissionsPolicy, PERMISSIONS_POLICY, UpgradeInsecureRequests};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_x_content_type_options() {
+        eq(b"nosniff", XContentTypeOptions::NoSniff);
+        assert_eq!(XContentTypeOptions::parse(b"NoSniff"), Some(XContentTypeOptions::NoSniff));
+        assert!(XContentTypeOptions::NoSniff.is_nosniff());
+        bad::<XContentTypeOptions>(b"");
+        bad::<XContentTypeOptions>(b"sniff");
+        bad::<XContentTypeOptions>(b"nosniff, nosniff");
+        bad::<XContentTypeOptions>(b"\"nosniff\"");
+    }
+
+    enum_header! {
+        /// A header with a fallback.
+        pub XMood {
+            /// `happy`.
+            Happy = "happy",
+        } else {
+            /// Anything else.
+            Other
+        }
+        /// The `X-Mood` header.
+        pub X_MOOD = "x-mood"
+    }
+
+    #[test]
+    fn test_enum_header_fallback() {
+        eq(b"happy", XMood::Happy);
+        assert_eq!(XMood::parse(b"HAPPY"), Some(XMood::Happy));
+        eq(b"Grumpy", XMood::Other("Grumpy".to_owned()));
+        assert_eq!(XMood::Other("Grumpy".to_owned()).as_str(), "Grumpy");
+        bad::<XMood>(b"");
+        bad::<XMood>(b"very happy");
+    }
+
+    #[test]
+    fn test_x_frame_options() {
+        eq(b"DENY", XFrameOptions::Deny);
+        eq(b"SAMEORIGIN", XFrameOptions::SameOrigin);
+        eq(b"ALLOW-FROM https://example.com",
+           XFrameOptions::AllowFrom(Origin::new("https", "example.com", None)));
+        assert_eq!(XFrameOptions::parse(b"deny"), Some(XFrameOptions::Deny));
+        assert_eq!(XFrameOptions::parse(b"SameOrigin"), Some(XFrameOptions::SameOrigin));
+        assert_eq!(XFrameOptions::parse(b"allow-from  HTTPS://Example.com:443"),
+                   Some(XFrameOptions::AllowFrom(Origin::new("https", "example.com", None))));
+        bad::<XFrameOptions>(b"");
+        bad::<XFrameOptions>(b"ALLOWALL");
+        bad::<XFrameOptions>(b"DENY, SAMEORIGIN");
+        bad::<XFrameOptions>(b"ALLOW-FROM");
+        bad::<XFrameOptions>(b"ALLOW-FROMhttps://example.com");
+        bad::<XFrameOptions>(b"ALLOW-FROM example.com");
+    }
+
+    #[test]
+    fn test_referrer_policy() {
+        eq(b"no-referrer", ReferrerPolicy::new(vec![Policy::NoReferrer]));
+        eq(b"origin, strict-origin-when-cross-origin",
+           ReferrerPolicy::new(vec![Policy::Origin, Policy::StrictOriginWhenCrossOrigin]));
+        let policy = ReferrerPolicy::parse(b"same-origin, unknown-future-policy").unwrap();
+        assert_eq!(policy.policies(), &[Policy::SameOrigin]);
+        assert_eq!(policy.effective(), Some(Policy::SameOrigin));
+        let policy = ReferrerPolicy::parse(b"no-referrer, Unsafe-URL, unknown-future-policy")
+                                    .unwrap();
+        assert_eq!(policy.effective(), Some(Policy::UnsafeUrl));
+        assert_eq!(ReferrerPolicy::parse(b"unknown").unwrap().effective(), None);
+        bad::<ReferrerPolicy>(b"");
+        bad::<ReferrerPolicy>(b" , ,");
+        bad::<ReferrerPolicy>(b"origin, \"same-origin\"");
+        bad::<ReferrerPolicy>(b"origin same-origin");
+    }
+
+    #[test]
+    fn test_referrer_policy_several_field_lines() {
+        // A fallback policy may come in a field line of its own; the last one still wins.
+        let headers = Headers::parse_block(b"Referrer-Policy: no-referrer\r\n\
+                                             Referrer-Policy: strict-origin\r\n").unwrap();
+        let policy = headers.get(REFERRER_POLICY).unwrap();
+        assert_eq!(policy.policies(), &[Policy::NoReferrer, Policy::StrictOrigin]);
+        assert_eq!(policy.effective(), Some(Policy::StrictOrigin));
+    }
+
+    #[test]
+    fn test_empty_referrer_policy_is_not_written() {
+        let mut headers = Headers::new();
+        headers.set(REFERRER_POLICY, ReferrerPolicy::new(vec![]));
+        assert!(headers.pairs().is_empty());
+        let mut out = vec![];
+        headers.write_to(&mut out).unwrap();
+        assert_eq!(out, b"");
+        let mut formatted = String::new();
+        let empty = ReferrerPolicy::new(vec![]);
+        assert!(fmt::write(&mut formatted, format_args!("{}", HeaderDisplayAdapter(&empty)))
+                    .is_err());
+    }
+
+    #[test]
+    fn test_permissions_policy() {
+        let example = Origin::new("https", "ex.com", None);
+        let policy = PermissionsPolicy::new(vec![
+            ("geolocation".to_owned(), vec![Allowance::SelfOrigin,
+                                            Allowance::Origin(example.clone())]),
+            ("camera".to_owned(), vec![]),
+        ]);
+        eq(b"geolocation=(self \"https://ex.com\"), camera=()", policy.clone());
+        assert_eq!(policy.allowlist("geolocation"),
+                   Some(&[Allowance::SelfOrigin, Allowance::Origin(example)][..]));
+        assert_eq!(policy.allowlist("camera"), Some(&[][..]));
+        assert_eq!(policy.allowlist("microphone"), None);
+
+        let policy = PermissionsPolicy::parse(b"fullscreen=*, usb=self, gyroscope=(\"bogus\" 1 \
+                                                 *;a)").unwrap();
+        assert_eq!(policy.allowlist("fullscreen"), Some(&[Allowance::Any][..]));
+        assert_eq!(policy.allowlist("usb"), Some(&[Allowance::SelfOrigin][..]));
+        assert_eq!(policy.allowlist("gyroscope"), Some(&[Allowance::Any][..]));
+        bad::<PermissionsPolicy>(b"camera=(");
+        bad::<PermissionsPolicy>(b"Camera=()");
+    }
+
+    #[test]
+    fn test_permissions_policy_several_field_lines() {
+        let headers = Headers::parse_block(b"Permissions-Policy: camera=(), usb=self\r\n\
+                                             Permissions-Policy: geolocation=*, camera=self\r\n")
+                              .unwrap();
+        let policy = headers.get(PERMISSIONS_POLICY).unwrap();
+        assert_eq!(policy.allowlist("usb"), Some(&[Allowance::SelfOrigin][..]));
+        assert_eq!(policy.allowlist("geolocation"), Some(&[Allowance::Any][..]));
+        // A later member overrides an earlier one, across lines as within them.
+        assert_eq!(policy.allowlist("camera"), Some(&[Allowance::SelfOrigin][..]));
+        // A bad line spoils the whole header.
+        let headers = Headers::parse_block(b"Permissions-Policy: camera=()\r\n\
+                                             Permissions-Policy: usb=(\r\n").unwrap();
+        assert!(headers.get(PERMISSIONS_POLICY).is_none());
+    }
+
+    #[test]
+    fn test_upgrade_insecure_requests() {
+        eq(b"1", UpgradeInsecureRequests);
+        assert!(UpgradeInsecureRequests.prefers_upgrade());
+        bad::<UpgradeInsecureRequests>(b"0");
+        bad::<UpgradeInsecureRequests>(b"2");
+        bad::<UpgradeInsecureRequests>(b"");
+        bad::<UpgradeInsecureRequests>(b"1, 1");
+        bad::<UpgradeInsecureRequests>(b"true");
+    }
+}
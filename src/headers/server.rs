@@ -0,0 +1,86 @@
+//! The `Server` header (RFC 7231, section 7.4.2).
+
+text_header! {
+    /// The `Server` header: information about the software used by the origin server.
+    ///
+    /// RFC 7231 grammar:
+    ///
+    /// ```abnf
+    /// Server = product *( RWS ( product / comment ) )
+    /// ```
+    ///
+    /// This is kept as text rather than being parsed into products and comments, as servers are
+    /// not particularly careful about following the grammar and there is little to be done with
+    /// the parts anyway.
+    pub Server
+}
+
+define_single_header_marker! {
+    /// The `Server` header, as text.
+    pub SERVER: Server = "server"
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::{Headers, ToHeader, Header, HeaderDisplayAdapter, ParseMode};
+    use super::{Server, SERVER};
+
+    text_header! {
+        XCustom
+    }
+
+    #[test]
+    fn test_server() {
+        let server = Server::new("Apache/2.4.1 (Unix)").unwrap();
+        assert_eq!(Server::parse(b"Apache/2.4.1 (Unix)"), Some(server.clone()));
+        assert_eq!(server.as_str(), "Apache/2.4.1 (Unix)");
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&server)), "Apache/2.4.1 (Unix)");
+    }
+
+    #[test]
+    fn test_rejects_newlines() {
+        assert_eq!(Server::parse(b"Apache\r\nX-Injected: 1"), None);
+        assert_eq!(Server::new("Apache\nX-Injected: 1"), None);
+        assert_eq!(XCustom::parse(b"a\nb"), None);
+        assert_eq!(XCustom::parse(b"a\x00b"), None);
+        assert_eq!(XCustom::parse(b"\xff"), None);
+        assert_eq!(XCustom::parse(b"some value").map(|x| x.as_str().to_owned()),
+                   Some("some value".to_owned()));
+    }
+
+    #[test]
+    fn test_latin1() {
+        let raw = b"Apache/2.4.1 (Caf\xe9)";
+        assert_eq!(Server::parse(raw), None);
+        assert_eq!(Server::parse_with_mode(raw, ParseMode::Strict), None);
+        assert_eq!(Server::parse_with_mode(raw, ParseMode::Lenient),
+                   Some(Server::new("Apache/2.4.1 (Caf\u{e9})").unwrap()));
+        // UTF-8 is still UTF-8, and control characters are still rejected.
+        assert_eq!(Server::parse_with_mode(b"Caf\xc3\xa9", ParseMode::Lenient),
+                   Some(Server::new("Caf\u{e9}").unwrap()));
+        assert_eq!(Server::parse_with_mode(b"\xe9\r\nX-Injected: 1", ParseMode::Lenient), None);
+        // Written back as it came, not as UTF-8.
+        let server = Server::parse_with_mode(raw, ParseMode::Lenient).unwrap();
+        assert_eq!(&server.to_raw()[..], &raw[..]);
+        assert_eq!(&Server::new("Caf\u{e9}").unwrap().to_raw()[..], b"Caf\xc3\xa9");
+    }
+
+    #[test]
+    fn test_latin1_through_headers() {
+        let mut headers = Headers::parse_block(b"Server: Apache/2.4.1 (Caf\xe9)\r\n").unwrap();
+        assert_eq!(headers.parse_mode(), ParseMode::Strict);
+        assert!(headers.get(SERVER).is_none());
+        headers.set_parse_mode(ParseMode::Lenient);
+        assert_eq!(headers.get(SERVER).unwrap().as_str(), "Apache/2.4.1 (Caf\u{e9})");
+        // A value parsed leniently is parsed again, strictly, when the mode changes back.
+        headers.set_parse_mode(ParseMode::Strict);
+        assert!(headers.get(SERVER).is_none());
+
+        // Taking the value mutably drops the raw form; what is written is still ISO-8859-1.
+        headers.set_parse_mode(ParseMode::Lenient);
+        assert!(headers.get_mut(SERVER).is_some());
+        let mut out = vec![];
+        headers.write_to(&mut out).unwrap();
+        assert_eq!(out, b"server: Apache/2.4.1 (Caf\xe9)\r\n");
+    }
+}
@@ -0,0 +1,720 @@
+//! Structured Field Values (RFC 8941), the common syntax of many newer headers.
+//!
+//! RFC 8941 defines three top-level types: items, lists and dictionaries. A header defined as a
+//! structured field is parsed with the function for its top-level type, and the header type then
+//! interprets the result; structured field parsing is strict, and any error means the whole
+//! field is to be ignored.
+//!
+//! The parsing functions here take one field value; RFC 8941 specifies that multiple field lines
+//! be combined, with `,`, before parsing lists and dictionaries.
+
+use std::fmt;
+use std::str;
+
+use grammar::base64;
+use grammar::token::is_tchar;
+
+/// A bare item: a value without parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BareItem {
+    /// An integer, which has at most fifteen digits.
+    Integer(i64),
+    /// A decimal, which has at most twelve integer and three fractional digits.
+    Decimal(f64),
+    /// A string, which may only contain printable ASCII.
+    String(String),
+    /// A token, which is a little more permissive than an HTTP token.
+    Token(String),
+    /// A byte sequence, written in base 64 between colons.
+    ByteSequence(Vec<u8>),
+    /// A boolean, written `?1` or `?0`.
+    Boolean(bool),
+}
+
+impl BareItem {
+    /// If the item is an integer, get it.
+    pub fn as_integer(&self) -> Option<i64> {
+        match *self {
+            BareItem::Integer(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// If the item is a boolean, get it.
+    pub fn as_boolean(&self) -> Option<bool> {
+        match *self {
+            BareItem::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// If the item is a token, get it.
+    pub fn as_token(&self) -> Option<&str> {
+        match *self {
+            BareItem::Token(ref token) => Some(token),
+            _ => None,
+        }
+    }
+
+    /// If the item is a string, get it.
+    pub fn as_string(&self) -> Option<&str> {
+        match *self {
+            BareItem::String(ref string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+/// The largest magnitude of an integer: fifteen digits.
+const MAX_INTEGER: i64 = 999_999_999_999_999;
+
+/// Items are serialised as RFC 8941, section 4.1 describes. A decimal is rounded to three
+/// fractional digits (and is written with at least one); an integer or decimal which is out of
+/// range, or a string, token or byte sequence which is invalid, is an error.
+impl fmt::Display for BareItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BareItem::Integer(i) if i >= -MAX_INTEGER && i <= MAX_INTEGER => write!(f, "{}", i),
+            BareItem::Decimal(d) if d.abs() < 1e12 => {
+                let formatted = format!("{:.3}", d);
+                let trimmed = formatted.trim_right_matches('0');
+                if trimmed.ends_with('.') {
+                    write!(f, "{}0", trimmed)
+                } else {
+                    f.write_str(trimmed)
+                }
+            },
+            BareItem::String(ref s) if s.bytes().all(|c| c >= 0x20 && c <= 0x7e) => {
+                try!(f.write_str("\""));
+                for c in s.chars() {
+                    if c == '"' || c == '\\' {
+                        try!(f.write_str("\\"));
+                    }
+                    try!(write!(f, "{}", c));
+                }
+                f.write_str("\"")
+            },
+            BareItem::Token(ref token) if is_token(token.as_bytes()) => f.write_str(token),
+            BareItem::ByteSequence(ref bytes) => write!(f, ":{}:", base64::encode(bytes)),
+            BareItem::Boolean(b) => f.write_str(if b { "?1" } else { "?0" }),
+            _ => Err(fmt::Error),
+        }
+    }
+}
+
+/// An item’s parameters, in order. Keys are unique.
+pub type Parameters = Vec<(String, BareItem)>;
+
+/// Write parameters, as RFC 8941, section 4.1.1.2 describes: `;key=value`, or just `;key` when
+/// the value is true.
+pub fn fmt_parameters(f: &mut fmt::Formatter, parameters: &Parameters) -> fmt::Result {
+    for &(ref key, ref value) in parameters {
+        if !is_key(key.as_bytes()) {
+            return Err(fmt::Error);
+        }
+        try!(write!(f, ";{}", key));
+        if *value != BareItem::Boolean(true) {
+            try!(write!(f, "={}", value));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the slice is a valid token: `( ALPHA / "*" ) *( tchar / ":" / "/" )`.
+fn is_token(raw: &[u8]) -> bool {
+    match raw.first() {
+        Some(&c) if (c >= b'A' && c <= b'Z') || (c >= b'a' && c <= b'z') || c == b'*' => {
+            raw[1..].iter().all(|&c| is_tchar(c) || c == b':' || c == b'/')
+        },
+        _ => false,
+    }
+}
+
+fn is_key_char(c: u8) -> bool {
+    (c >= b'a' && c <= b'z') || (c >= b'0' && c <= b'9') || c == b'_' || c == b'-' ||
+    c == b'.' || c == b'*'
+}
+
+/// Whether the slice is a valid key: `( lcalpha / "*" ) *( lcalpha / DIGIT / "_" / "-" / "." /
+/// "*" )`.
+pub fn is_key(raw: &[u8]) -> bool {
+    match raw.first() {
+        Some(&c) if (c >= b'a' && c <= b'z') || c == b'*' => raw.iter().all(|&c| is_key_char(c)),
+        _ => false,
+    }
+}
+
+fn skip_sp(raw: &[u8]) -> &[u8] {
+    let start = raw.iter().position(|&c| c != b' ').unwrap_or(raw.len());
+    &raw[start..]
+}
+
+/// Parse a number: an integer or a decimal.
+fn parse_number(raw: &[u8]) -> Option<(BareItem, &[u8])> {
+    let (negative, digits) = match raw.first() {
+        Some(&b'-') => (true, &raw[1..]),
+        _ => (false, raw),
+    };
+    let integer_length = digits.iter().position(|&c| c < b'0' || c > b'9')
+                                      .unwrap_or(digits.len());
+    if integer_length == 0 {
+        return None;
+    }
+    let (number, rest) = if digits.get(integer_length) == Some(&b'.') {
+        let fraction = &digits[integer_length + 1..];
+        let fraction_length = fraction.iter().position(|&c| c < b'0' || c > b'9')
+                                      .unwrap_or(fraction.len());
+        if integer_length > 12 || fraction_length == 0 || fraction_length > 3 {
+            return None;
+        }
+        // All ASCII, so this can’t fail.
+        let text = unsafe { str::from_utf8_unchecked(&digits[..integer_length + 1 +
+                                                              fraction_length]) };
+        let number: f64 = text.parse().unwrap();
+        (BareItem::Decimal(if negative { -number } else { number }),
+         &fraction[fraction_length..])
+    } else {
+        if integer_length > 15 {
+            return None;
+        }
+        let text = unsafe { str::from_utf8_unchecked(&digits[..integer_length]) };
+        let number: i64 = text.parse().unwrap();
+        (BareItem::Integer(if negative { -number } else { number }), &digits[integer_length..])
+    };
+    Some((number, rest))
+}
+
+fn parse_string(raw: &[u8]) -> Option<(BareItem, &[u8])> {
+    let mut out = String::new();
+    let mut i = 1;
+    while i < raw.len() {
+        match raw[i] {
+            b'\\' => match raw.get(i + 1) {
+                Some(&c) if c == b'"' || c == b'\\' => {
+                    out.push(c as char);
+                    i += 2;
+                },
+                _ => return None,
+            },
+            b'"' => return Some((BareItem::String(out), &raw[i + 1..])),
+            c if c >= 0x20 && c <= 0x7e => {
+                out.push(c as char);
+                i += 1;
+            },
+            _ => return None,
+        }
+    }
+    None
+}
+
+fn parse_token(raw: &[u8]) -> Option<(BareItem, &[u8])> {
+    let end = 1 + raw[1..].iter().position(|&c| !is_tchar(c) && c != b':' && c != b'/')
+                                 .unwrap_or(raw.len() - 1);
+    // All ASCII, so this can’t fail.
+    let token = unsafe { str::from_utf8_unchecked(&raw[..end]) }.to_owned();
+    Some((BareItem::Token(token), &raw[end..]))
+}
+
+fn parse_byte_sequence(raw: &[u8]) -> Option<(BareItem, &[u8])> {
+    let end = match raw[1..].iter().position(|&c| c == b':') {
+        Some(end) => end + 1,
+        None => return None,
+    };
+    base64::decode(&raw[1..end]).map(|bytes| (BareItem::ByteSequence(bytes), &raw[end + 1..]))
+}
+
+fn parse_boolean(raw: &[u8]) -> Option<(BareItem, &[u8])> {
+    match raw.get(1) {
+        Some(&b'1') => Some((BareItem::Boolean(true), &raw[2..])),
+        Some(&b'0') => Some((BareItem::Boolean(false), &raw[2..])),
+        _ => None,
+    }
+}
+
+/// Parse a bare item off the start of a slice, returning it and the remainder.
+pub fn parse_bare_item(raw: &[u8]) -> Option<(BareItem, &[u8])> {
+    match raw.first() {
+        Some(&c) if c == b'-' || (c >= b'0' && c <= b'9') => parse_number(raw),
+        Some(&b'"') => parse_string(raw),
+        Some(&c) if (c >= b'A' && c <= b'Z') || (c >= b'a' && c <= b'z') || c == b'*' => {
+            parse_token(raw)
+        },
+        Some(&b':') => parse_byte_sequence(raw),
+        Some(&b'?') => parse_boolean(raw),
+        _ => None,
+    }
+}
+
+/// Parse a key off the start of a slice, returning it and the remainder.
+pub fn parse_key(raw: &[u8]) -> Option<(String, &[u8])> {
+    match raw.first() {
+        Some(&c) if (c >= b'a' && c <= b'z') || c == b'*' => (),
+        _ => return None,
+    }
+    let end = raw.iter().position(|&c| !is_key_char(c)).unwrap_or(raw.len());
+    // All ASCII, so this can’t fail.
+    Some((unsafe { str::from_utf8_unchecked(&raw[..end]) }.to_owned(), &raw[end..]))
+}
+
+/// Parse parameters off the start of a slice, returning them and the remainder.
+///
+/// Where a key is repeated, the last value wins, but the key keeps its first position.
+pub fn parse_parameters(mut raw: &[u8]) -> Option<(Parameters, &[u8])> {
+    let mut parameters: Parameters = vec![];
+    while raw.first() == Some(&b';') {
+        let (key, rest) = match parse_key(skip_sp(&raw[1..])) {
+            Some(key) => key,
+            None => return None,
+        };
+        let (value, rest) = if rest.first() == Some(&b'=') {
+            match parse_bare_item(&rest[1..]) {
+                Some(value) => value,
+                None => return None,
+            }
+        } else {
+            (BareItem::Boolean(true), rest)
+        };
+        match parameters.iter().position(|&(ref k, _)| *k == key) {
+            Some(i) => parameters[i].1 = value,
+            None => parameters.push((key, value)),
+        }
+        raw = rest;
+    }
+    Some((parameters, raw))
+}
+
+/// Parse an item, with its parameters, off the start of a slice, returning it and the remainder.
+pub fn parse_item_prefix(raw: &[u8]) -> Option<(BareItem, Parameters, &[u8])> {
+    let (bare_item, rest) = match parse_bare_item(raw) {
+        Some(bare_item) => bare_item,
+        None => return None,
+    };
+    parse_parameters(rest).map(|(parameters, rest)| (bare_item, parameters, rest))
+}
+
+/// Parse a field value as an item (RFC 8941, section 4.2.3).
+///
+/// RFC 8941 grammar:
+///
+/// ```abnf
+/// sf-item   = bare-item parameters
+/// bare-item = sf-integer / sf-decimal / sf-string / sf-token / sf-binary / sf-boolean
+/// ```
+pub fn parse_item(raw: &[u8]) -> Option<(BareItem, Parameters)> {
+    match parse_item_prefix(skip_sp(raw)) {
+        Some((bare_item, parameters, rest)) if skip_sp(rest).is_empty() => {
+            Some((bare_item, parameters))
+        },
+        _ => None,
+    }
+}
+
+/// The `Display` form of an item with parameters.
+pub struct ItemDisplay<'a>(pub &'a BareItem, pub &'a Parameters);
+
+impl<'a> fmt::Display for ItemDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{}", self.0));
+        fmt_parameters(f, self.1)
+    }
+}
+
+/// A member of a list or dictionary: an item, or an inner list of items, with parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Member {
+    /// An item, with its parameters.
+    Item(BareItem, Parameters),
+    /// An inner list of items, each with its parameters, and the parameters of the inner list.
+    InnerList(Vec<(BareItem, Parameters)>, Parameters),
+}
+
+impl Member {
+    /// If the member is an item, get it.
+    pub fn as_item(&self) -> Option<&BareItem> {
+        match *self {
+            Member::Item(ref bare_item, _) => Some(bare_item),
+            Member::InnerList(..) => None,
+        }
+    }
+
+    /// The parameters of the member.
+    pub fn parameters(&self) -> &Parameters {
+        match *self {
+            Member::Item(_, ref parameters) | Member::InnerList(_, ref parameters) => parameters,
+        }
+    }
+}
+
+impl fmt::Display for Member {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Member::Item(ref bare_item, ref parameters) => {
+                write!(f, "{}", ItemDisplay(bare_item, parameters))
+            },
+            Member::InnerList(ref items, ref parameters) => {
+                try!(f.write_str("("));
+                for (i, &(ref bare_item, ref item_parameters)) in items.iter().enumerate() {
+                    if i > 0 {
+                        try!(f.write_str(" "));
+                    }
+                    try!(write!(f, "{}", ItemDisplay(bare_item, item_parameters)));
+                }
+                try!(f.write_str(")"));
+                fmt_parameters(f, parameters)
+            },
+        }
+    }
+}
+
+/// A list: its members, in order.
+pub type List = Vec<Member>;
+
+/// A dictionary: its members, in order, keyed. Keys are unique.
+pub type Dictionary = Vec<(String, Member)>;
+
+fn skip_ows(raw: &[u8]) -> &[u8] {
+    let start = raw.iter().position(|&c| c != b' ' && c != b'\t').unwrap_or(raw.len());
+    &raw[start..]
+}
+
+/// Parse an inner list off the start of a slice, returning it and the remainder.
+fn parse_inner_list(raw: &[u8]) -> Option<(Member, &[u8])> {
+    let mut items = vec![];
+    let mut raw = &raw[1..];
+    loop {
+        raw = skip_sp(raw);
+        if raw.first() == Some(&b')') {
+            return parse_parameters(&raw[1..]).map(|(parameters, rest)| {
+                (Member::InnerList(items, parameters), rest)
+            });
+        }
+        let (bare_item, parameters, rest) = match parse_item_prefix(raw) {
+            Some(item) => item,
+            None => return None,
+        };
+        items.push((bare_item, parameters));
+        match rest.first() {
+            Some(&b' ') | Some(&b')') => raw = rest,
+            _ => return None,
+        }
+    }
+}
+
+/// Parse a list or dictionary member off the start of a slice, returning it and the remainder.
+fn parse_member(raw: &[u8]) -> Option<(Member, &[u8])> {
+    if raw.first() == Some(&b'(') {
+        parse_inner_list(raw)
+    } else {
+        parse_item_prefix(raw).map(|(bare_item, parameters, rest)| {
+            (Member::Item(bare_item, parameters), rest)
+        })
+    }
+}
+
+/// Parse comma-separated members until the input is exhausted, given a function to parse each.
+fn parse_members<'a, T, F>(raw: &'a [u8], mut parse: F) -> Option<Vec<T>>
+        where F: FnMut(&'a [u8]) -> Option<(T, &'a [u8])> {
+    let mut members = vec![];
+    let mut raw = skip_sp(raw);
+    while !raw.is_empty() {
+        let (member, rest) = match parse(raw) {
+            Some(member) => member,
+            None => return None,
+        };
+        members.push(member);
+        raw = skip_ows(rest);
+        if raw.is_empty() {
+            break;
+        }
+        if raw[0] != b',' {
+            return None;
+        }
+        raw = skip_ows(&raw[1..]);
+        if raw.is_empty() {
+            // A trailing comma.
+            return None;
+        }
+    }
+    Some(members)
+}
+
+/// Parse a field value as a list (RFC 8941, section 4.2.1).
+///
+/// RFC 8941 grammar:
+///
+/// ```abnf
+/// sf-list       = list-member *( OWS "," OWS list-member )
+/// list-member   = sf-item / inner-list
+/// inner-list    = "(" *SP [ sf-item *( 1*SP sf-item ) *SP ] ")" parameters
+/// ```
+///
+/// An empty (or all-space) field value is an empty list.
+pub fn parse_list(raw: &[u8]) -> Option<List> {
+    parse_members(raw, parse_member)
+}
+
+/// Parse a field value as a dictionary (RFC 8941, section 4.2.2).
+///
+/// RFC 8941 grammar:
+///
+/// ```abnf
+/// sf-dictionary  = dict-member *( OWS "," OWS dict-member )
+/// dict-member    = member-key ( parameters / ( "=" member-value ))
+/// member-key     = key
+/// member-value   = sf-item / inner-list
+/// ```
+///
+/// A member without a value is the boolean true, with the parameters given. Where a key is
+/// repeated, the last value wins, but the key keeps its first position. An empty (or all-space)
+/// field value is an empty dictionary.
+pub fn parse_dictionary(raw: &[u8]) -> Option<Dictionary> {
+    let members = match parse_members(raw, |raw| {
+        let (key, rest) = match parse_key(raw) {
+            Some(key) => key,
+            None => return None,
+        };
+        if rest.first() == Some(&b'=') {
+            parse_member(&rest[1..]).map(|(member, rest)| ((key, member), rest))
+        } else {
+            parse_parameters(rest).map(|(parameters, rest)| {
+                ((key, Member::Item(BareItem::Boolean(true), parameters)), rest)
+            })
+        }
+    }) {
+        Some(members) => members,
+        None => return None,
+    };
+    let mut dictionary: Dictionary = vec![];
+    for (key, member) in members {
+        match dictionary.iter().position(|&(ref k, _)| *k == key) {
+            Some(i) => dictionary[i].1 = member,
+            None => dictionary.push((key, member)),
+        }
+    }
+    Some(dictionary)
+}
+
+/// Write a list, as RFC 8941, section 4.1.1 describes.
+pub fn fmt_list(f: &mut fmt::Formatter, list: &List) -> fmt::Result {
+    for (i, member) in list.iter().enumerate() {
+        if i > 0 {
+            try!(f.write_str(", "));
+        }
+        try!(write!(f, "{}", member));
+    }
+    Ok(())
+}
+
+/// Write a dictionary, as RFC 8941, section 4.1.2 describes: where a member is the boolean true,
+/// only its key and parameters are written.
+pub fn fmt_dictionary(f: &mut fmt::Formatter, dictionary: &Dictionary) -> fmt::Result {
+    for (i, &(ref key, ref member)) in dictionary.iter().enumerate() {
+        if !is_key(key.as_bytes()) {
+            return Err(fmt::Error);
+        }
+        if i > 0 {
+            try!(f.write_str(", "));
+        }
+        try!(f.write_str(key));
+        match *member {
+            Member::Item(BareItem::Boolean(true), ref parameters) => {
+                try!(fmt_parameters(f, parameters));
+            },
+            _ => try!(write!(f, "={}", member)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use super::{BareItem, Parameters, ItemDisplay, Member, List, Dictionary, parse_item,
+                parse_list, parse_dictionary, fmt_list, fmt_dictionary};
+    use super::BareItem::{Integer, Decimal, String, Token, ByteSequence, Boolean};
+
+    fn item(raw: &[u8]) -> Option<BareItem> {
+        match parse_item(raw) {
+            Some((bare_item, ref parameters)) if parameters.is_empty() => Some(bare_item),
+            _ => None,
+        }
+    }
+
+    fn display(bare_item: BareItem, parameters: Parameters) -> ::std::string::String {
+        format!("{}", ItemDisplay(&bare_item, &parameters))
+    }
+
+    fn string(s: &str) -> BareItem {
+        String(s.to_owned())
+    }
+
+    #[test]
+    fn test_integer() {
+        assert_eq!(item(b"42"), Some(Integer(42)));
+        assert_eq!(item(b"-42"), Some(Integer(-42)));
+        assert_eq!(item(b"0"), Some(Integer(0)));
+        assert_eq!(item(b"00"), Some(Integer(0)));
+        assert_eq!(item(b"999999999999999"), Some(Integer(999999999999999)));
+        assert_eq!(item(b"1000000000000000"), None);
+        assert_eq!(item(b"- 1"), None);
+        assert_eq!(item(b"1a"), None);
+        assert_eq!(display(Integer(-42), vec![]), "-42");
+    }
+
+    #[test]
+    fn test_decimal() {
+        assert_eq!(item(b"4.5"), Some(Decimal(4.5)));
+        assert_eq!(item(b"-0.125"), Some(Decimal(-0.125)));
+        assert_eq!(item(b"123456789012.123"), Some(Decimal(123456789012.123)));
+        assert_eq!(item(b"1234567890123.0"), None);
+        assert_eq!(item(b"1.1234"), None);
+        assert_eq!(item(b"1."), None);
+        assert_eq!(display(Decimal(4.5), vec![]), "4.5");
+        assert_eq!(display(Decimal(2.0), vec![]), "2.0");
+        assert_eq!(display(Decimal(0.0), vec![]), "0.0");
+        assert_eq!(display(Decimal(1.00049), vec![]), "1.0");
+        assert_eq!(display(Decimal(-0.125), vec![]), "-0.125");
+    }
+
+    #[test]
+    fn test_string() {
+        assert_eq!(item(b"\"hello world\""), Some(string("hello world")));
+        assert_eq!(item(b"\"a \\\"b\\\" \\\\c\""), Some(string("a \"b\" \\c")));
+        assert_eq!(item(b"\"\""), Some(string("")));
+        assert_eq!(item(b"\"a\\b\""), None);
+        assert_eq!(item(b"\"caf\xc3\xa9\""), None);
+        assert_eq!(item(b"\"unterminated"), None);
+        assert_eq!(display(string("a \"b\""), vec![]), "\"a \\\"b\\\"\"");
+    }
+
+    #[test]
+    fn test_token() {
+        assert_eq!(item(b"foo123/456"), Some(Token("foo123/456".to_owned())));
+        assert_eq!(item(b"*"), Some(Token("*".to_owned())));
+        assert_eq!(item(b"text/html:x"), Some(Token("text/html:x".to_owned())));
+        assert_eq!(item(b"1foo"), None);
+        assert_eq!(item(b"foo bar"), None);
+        assert_eq!(display(Token("*foo".to_owned()), vec![]), "*foo");
+    }
+
+    #[test]
+    fn test_byte_sequence() {
+        assert_eq!(item(b":aGVsbG8=:"), Some(ByteSequence(b"hello".to_vec())));
+        assert_eq!(item(b"::"), Some(ByteSequence(vec![])));
+        assert_eq!(item(b":aGVsbG8=="), None);
+        assert_eq!(item(b":aGVs bG8=:"), None);
+        assert_eq!(display(ByteSequence(b"hello".to_vec()), vec![]), ":aGVsbG8=:");
+    }
+
+    #[test]
+    fn test_boolean() {
+        assert_eq!(item(b"?1"), Some(Boolean(true)));
+        assert_eq!(item(b"?0"), Some(Boolean(false)));
+        assert_eq!(item(b"?2"), None);
+        assert_eq!(item(b"?"), None);
+        assert_eq!(display(Boolean(false), vec![]), "?0");
+    }
+
+    #[test]
+    fn test_parameters() {
+        let parameters = vec![("a".to_owned(), Integer(1)), ("b".to_owned(), Boolean(true)),
+                              ("c-d".to_owned(), string("x"))];
+        assert_eq!(parse_item(b"  text/html;a=1;b; c-d=\"x\" "),
+                   Some((Token("text/html".to_owned()), parameters.clone())));
+        assert_eq!(display(Token("text/html".to_owned()), parameters), "text/html;a=1;b;c-d=\"x\"");
+        assert_eq!(parse_item(b"1;a=1;a=2;b=?0"),
+                   Some((Integer(1), vec![("a".to_owned(), Integer(2)),
+                                          ("b".to_owned(), Boolean(false))])));
+        assert_eq!(parse_item(b"1;A=1"), None);
+        assert_eq!(parse_item(b"1;a="), None);
+        assert_eq!(parse_item(b"1 ;a=1"), None);
+        assert_eq!(parse_item(b"1;a=1;"), None);
+    }
+
+    struct ListDisplay(List);
+
+    impl fmt::Display for ListDisplay {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt_list(f, &self.0)
+        }
+    }
+
+    struct DictionaryDisplay(Dictionary);
+
+    impl fmt::Display for DictionaryDisplay {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt_dictionary(f, &self.0)
+        }
+    }
+
+    fn token(s: &str) -> BareItem {
+        Token(s.to_owned())
+    }
+
+    fn parameter(key: &str, value: BareItem) -> (::std::string::String, BareItem) {
+        (key.to_owned(), value)
+    }
+
+    #[test]
+    fn test_list() {
+        let list = vec![Member::Item(token("sugar"), vec![]),
+                        Member::Item(token("tea"), vec![parameter("q", Decimal(0.5))]),
+                        Member::Item(token("rum"), vec![])];
+        assert_eq!(parse_list(b"sugar, tea;q=0.5,rum"), Some(list.clone()));
+        assert_eq!(format!("{}", ListDisplay(list)), "sugar, tea;q=0.5, rum");
+        assert_eq!(parse_list(b""), Some(vec![]));
+        assert_eq!(parse_list(b"  "), Some(vec![]));
+        assert_eq!(parse_list(b"1 ,\t2"), Some(vec![Member::Item(Integer(1), vec![]),
+                                                    Member::Item(Integer(2), vec![])]));
+        assert_eq!(parse_list(b"1,"), None);
+        assert_eq!(parse_list(b"1,,2"), None);
+        assert_eq!(parse_list(b"1 2"), None);
+    }
+
+    #[test]
+    fn test_inner_lists() {
+        let list = vec![
+            Member::InnerList(vec![(string("foo"), vec![]),
+                                   (string("bar"), vec![parameter("a", Integer(1))])],
+                              vec![parameter("lvl", Integer(5))]),
+            Member::InnerList(vec![(token("baz"), vec![])], vec![]),
+            Member::InnerList(vec![], vec![]),
+            Member::Item(Integer(3), vec![]),
+        ];
+        assert_eq!(parse_list(b"(\"foo\" \"bar\";a=1);lvl=5, ( baz ), (), 3"),
+                   Some(list.clone()));
+        assert_eq!(format!("{}", ListDisplay(list)),
+                   "(\"foo\" \"bar\";a=1);lvl=5, (baz), (), 3");
+        assert_eq!(list_member_parameters(b"(1 2);x"), Some(vec![parameter("x", Boolean(true))]));
+        assert_eq!(parse_list(b"(1 2"), None);
+        assert_eq!(parse_list(b"(1,2)"), None);
+        assert_eq!(parse_list(b"(1)(2)"), None);
+        assert_eq!(parse_list(b"((1))"), None);
+    }
+
+    fn list_member_parameters(raw: &[u8]) -> Option<Parameters> {
+        parse_list(raw).and_then(|list| list.first().map(|member| member.parameters().clone()))
+    }
+
+    #[test]
+    fn test_dictionary() {
+        let dictionary = vec![
+            ("a".to_owned(), Member::Item(Integer(1), vec![])),
+            ("b".to_owned(), Member::Item(Boolean(true), vec![parameter("x", token("y"))])),
+            ("c".to_owned(), Member::InnerList(vec![(Integer(1), vec![]), (Integer(2), vec![])],
+                                               vec![parameter("p", Boolean(false))])),
+            ("d".to_owned(), Member::Item(Boolean(false), vec![])),
+        ];
+        assert_eq!(parse_dictionary(b"a=1, b;x=y, c=(1 2);p=?0, d=?0"), Some(dictionary.clone()));
+        assert_eq!(format!("{}", DictionaryDisplay(dictionary)), "a=1, b;x=y, c=(1 2);p=?0, d=?0");
+        assert_eq!(parse_dictionary(b"a=1, b=2, a=3"),
+                   Some(vec![("a".to_owned(), Member::Item(Integer(3), vec![])),
+                             ("b".to_owned(), Member::Item(Integer(2), vec![]))]));
+        assert_eq!(parse_dictionary(b""), Some(vec![]));
+        assert_eq!(parse_dictionary(b"a=?1").unwrap()[0].1.as_item(), Some(&Boolean(true)));
+        assert_eq!(parse_dictionary(b"A=1"), None);
+        assert_eq!(parse_dictionary(b"a=1,"), None);
+        assert_eq!(parse_dictionary(b"a="), None);
+        assert_eq!(parse_dictionary(b"1=1"), None);
+    }
+}
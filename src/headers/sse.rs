@@ -0,0 +1,33 @@
+//! The `Last-Event-ID` header (HTML Living Standard, section 9.2), with which a client of
+//! server-sent events resumes an event stream.
+
+text_header! {
+    /// The `Last-Event-ID` header: the ID of the last event the client received, an opaque
+    /// string chosen by the server.
+    ///
+    /// The event stream format permits anything but NUL, CR and LF in an ID; as a header value,
+    /// other control characters are rejected too.
+    pub LastEventId
+}
+
+define_single_header_marker! {
+    /// The `Last-Event-ID` header.
+    pub LAST_EVENT_ID: LastEventId = "last-event-id"
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::{ToHeader, HeaderDisplayAdapter};
+    use super::LastEventId;
+
+    #[test]
+    fn test_last_event_id() {
+        let id = LastEventId::parse(b"event 42/\xc3\xa9").unwrap();
+        assert_eq!(id.as_str(), "event 42/é");
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&id)), "event 42/é");
+        assert_eq!(LastEventId::new("event 42/é"), Some(id));
+        assert_eq!(LastEventId::new("42\n43"), None);
+        assert_eq!(LastEventId::new("42\r"), None);
+        assert_eq!(LastEventId::parse(b"42\x0043"), None);
+    }
+}
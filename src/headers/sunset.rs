@@ -0,0 +1,44 @@
+//! The `Sunset` header (RFC 8594).
+
+use super::date_based::HttpDate;
+
+define_single_header_marker! {
+    /// The `Sunset` header: the HTTP-date at which the resource is expected to become
+    /// unresponsive.
+    ///
+    /// Unlike `Retry-After`, there is no delta-seconds form; it is a date and only a date.
+    pub SUNSET: HttpDate = "sunset"
+}
+
+#[cfg(test)]
+mod tests {
+    use smallvec::SmallVec;
+    use tendril::ByteTendril;
+    use time::{self, Timespec};
+    use headers::Headers;
+    use headers::date_based::HttpDate;
+    use super::SUNSET;
+
+    fn headers_with_sunset(raw: &[u8]) -> Headers {
+        let mut headers = Headers::new();
+        let mut value = SmallVec::new();
+        value.push(ByteTendril::from_slice(raw));
+        headers.set_raw(SUNSET, value);
+        headers
+    }
+
+    #[test]
+    fn test_sunset() {
+        let date = HttpDate(time::at_utc(Timespec::new(1542412800, 0)));
+        let mut headers = headers_with_sunset(b"Sat, 17 Nov 2018 00:00:00 GMT");
+        assert_eq!(*headers.get(SUNSET).unwrap(), date);
+
+        headers.set(SUNSET, date);
+        assert_eq!(&headers.get_raw(SUNSET).unwrap()[0][..], b"Sat, 17 Nov 2018 00:00:00 GMT");
+    }
+
+    #[test]
+    fn test_sunset_is_not_a_delta() {
+        assert!(headers_with_sunset(b"3600").get(SUNSET).is_none());
+    }
+}
@@ -0,0 +1,136 @@
+//! The `Timing-Allow-Origin` header (Resource Timing, W3C).
+
+use std::fmt;
+
+use grammar::trim_ows;
+use super::origin::Origin;
+use super::{ToHeader, Header};
+
+/// The `Timing-Allow-Origin` header: the origins which may see the detailed timing information
+/// of a resource.
+///
+/// Grammar:
+///
+/// ```abnf
+/// Timing-Allow-Origin = 1#( origin-or-null / wildcard )
+/// ```
+///
+/// A `*` anywhere in the list allows all origins.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TimingAllowOrigin {
+    /// `*`: all origins are allowed.
+    Any,
+    /// Only the listed origins are allowed. Never empty.
+    Origins(Vec<Origin>),
+}
+
+impl TimingAllowOrigin {
+    /// Whether the given origin is allowed to see the timing information.
+    pub fn allows(&self, origin: &Origin) -> bool {
+        match *self {
+            TimingAllowOrigin::Any => true,
+            TimingAllowOrigin::Origins(ref origins) => origins.contains(origin),
+        }
+    }
+}
+
+impl ToHeader for TimingAllowOrigin {
+    fn parse(raw: &[u8]) -> Option<TimingAllowOrigin> {
+        let mut origins = vec![];
+        let mut any = false;
+        for value in raw.split(|&c| c == b',').map(trim_ows).filter(|value| !value.is_empty()) {
+            if value == b"*" {
+                any = true;
+            } else {
+                match Origin::parse(value) {
+                    Some(origin) => origins.push(origin),
+                    None => return None,
+                }
+            }
+        }
+        if any {
+            Some(TimingAllowOrigin::Any)
+        } else if origins.is_empty() {
+            None
+        } else {
+            Some(TimingAllowOrigin::Origins(origins))
+        }
+    }
+}
+
+impl Header for TimingAllowOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TimingAllowOrigin::Any => f.write_str("*"),
+            TimingAllowOrigin::Origins(ref origins) => {
+                for (i, origin) in origins.iter().enumerate() {
+                    if i > 0 {
+                        try!(f.write_str(", "));
+                    }
+                    try!(Header::fmt(origin, f));
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+define_single_header_marker! {
+    /// The `Timing-Allow-Origin` header. Being a list, it may be split across field lines, which
+    /// are combined.
+    pub TIMING_ALLOW_ORIGIN: TimingAllowOrigin = "timing-allow-origin", combine
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use headers::origin::Origin;
+    use super::{TimingAllowOrigin, TIMING_ALLOW_ORIGIN};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_any() {
+        eq(b"*", TimingAllowOrigin::Any);
+        assert_eq!(TimingAllowOrigin::parse(b"https://a.example, *"),
+                   Some(TimingAllowOrigin::Any));
+        assert!(TimingAllowOrigin::Any.allows(&Origin::new("https", "example.com", None)));
+        assert!(TimingAllowOrigin::Any.allows(&Origin::Null));
+    }
+
+    #[test]
+    fn test_origins() {
+        let a = Origin::new("https", "a.example", None);
+        let b = Origin::new("http", "b.example", Some(8080));
+        let tao = TimingAllowOrigin::Origins(vec![a.clone(), b.clone()]);
+        eq(b"https://a.example, http://b.example:8080", tao.clone());
+        assert!(tao.allows(&a));
+        assert!(tao.allows(&b));
+        assert!(!tao.allows(&Origin::new("http", "b.example", None)));
+        assert!(!tao.allows(&Origin::Null));
+        bad::<TimingAllowOrigin>(b"");
+        bad::<TimingAllowOrigin>(b",");
+        bad::<TimingAllowOrigin>(b"https://a.example, b.example");
+    }
+
+    #[test]
+    fn test_several_field_lines() {
+        let headers = Headers::parse_block(b"Timing-Allow-Origin: https://a.example\r\n\
+                                             Timing-Allow-Origin: http://b.example:8080\r\n")
+                             .unwrap();
+        let tao = TimingAllowOrigin::Origins(vec![Origin::new("https", "a.example", None),
+                                                  Origin::new("http", "b.example", Some(8080))]);
+        assert_eq!(**headers.get(TIMING_ALLOW_ORIGIN).unwrap(), tao);
+        let headers = Headers::parse_block(b"Timing-Allow-Origin: https://a.example\r\n\
+                                             Timing-Allow-Origin: *\r\n").unwrap();
+        assert_eq!(**headers.get(TIMING_ALLOW_ORIGIN).unwrap(), TimingAllowOrigin::Any);
+    }
+}
@@ -0,0 +1,187 @@
+//! The `Trailer` header (RFC 7230, section 4.4), and which fields may be sent as trailers.
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+
+use grammar::token::is_tchar;
+use grammar::trim_ows;
+use super::{ToHeader, Header};
+
+/// The headers which must not be sent in a trailer section (RFC 7230, section 4.1.2), as they are
+/// needed for message framing, routing, request modifiers, authentication, response control or
+/// determining how to process the payload, all of which must be known before the body is.
+const FORBIDDEN_TRAILERS: &'static [&'static str] = &[
+    // Message framing.
+    "content-length",
+    "transfer-encoding",
+    "trailer",
+    // Routing.
+    "host",
+    // Request modifiers.
+    "cache-control",
+    "expect",
+    "max-forwards",
+    "pragma",
+    "range",
+    "te",
+    "if-match",
+    "if-none-match",
+    "if-modified-since",
+    "if-unmodified-since",
+    "if-range",
+    // Authentication.
+    "authorization",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "set-cookie",
+    "www-authenticate",
+    // Response control.
+    "age",
+    "date",
+    "expires",
+    "location",
+    "retry-after",
+    "vary",
+    "warning",
+    // Payload processing.
+    "content-encoding",
+    "content-range",
+    "content-type",
+];
+
+/// Whether the named header must not be sent in a trailer section. The name is case-insensitive.
+pub fn is_forbidden_trailer(name: &str) -> bool {
+    FORBIDDEN_TRAILERS.iter().any(|forbidden| forbidden.eq_ignore_ascii_case(name))
+}
+
+/// The `Trailer` header: the header fields which the sender will send in the trailer section of
+/// a chunked message.
+///
+/// RFC 7230 grammar:
+///
+/// ```abnf
+/// Trailer = 1#field-name
+/// ```
+///
+/// Field names are lowercased, and duplicates are discarded.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Trailer {
+    names: Vec<String>,
+}
+
+impl Trailer {
+    /// Construct a `Trailer` announcing the given fields. Returns `None` if there are none or any
+    /// is not a valid field name.
+    pub fn new<I: IntoIterator<Item = S>, S: AsRef<str>>(names: I) -> Option<Trailer> {
+        let mut trailer = Trailer { names: vec![] };
+        for name in names {
+            let name = name.as_ref();
+            if name.is_empty() || !name.bytes().all(is_tchar) {
+                return None;
+            }
+            trailer.push(name);
+        }
+        if trailer.names.is_empty() {
+            None
+        } else {
+            Some(trailer)
+        }
+    }
+
+    fn push(&mut self, name: &str) {
+        if !self.announces(name) {
+            self.names.push(name.to_ascii_lowercase());
+        }
+    }
+
+    /// Whether the named field, in any case, is announced.
+    pub fn announces(&self, name: &str) -> bool {
+        self.names.iter().any(|announced| announced.eq_ignore_ascii_case(name))
+    }
+
+    /// The field names announced, lowercase.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+}
+
+impl ToHeader for Trailer {
+    fn parse(raw: &[u8]) -> Option<Trailer> {
+        let mut names = vec![];
+        for name in raw.split(|&c| c == b',').map(trim_ows).filter(|name| !name.is_empty()) {
+            match str::from_utf8(name) {
+                Ok(name) => names.push(name),
+                Err(_) => return None,
+            }
+        }
+        Trailer::new(names)
+    }
+}
+
+impl Header for Trailer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, name) in self.names.iter().enumerate() {
+            if i > 0 {
+                try!(f.write_str(", "));
+            }
+            try!(f.write_str(name));
+        }
+        Ok(())
+    }
+}
+
+define_single_header_marker! {
+    /// The `Trailer` header.
+    pub TRAILER: Trailer = "trailer", combine
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, Headers, ToHeader, HeaderDisplayAdapter};
+    use super::{Trailer, TRAILER, is_forbidden_trailer};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_trailer() {
+        eq(b"server-timing, content-digest",
+           Trailer::new(&["Server-Timing", "content-digest", "SERVER-TIMING"]).unwrap());
+        let trailer = Trailer::parse(b"X-Checksum ,, Expires").unwrap();
+        assert_eq!(trailer.names(), &["x-checksum".to_owned(), "expires".to_owned()][..]);
+        assert!(trailer.announces("x-CHECKSUM"));
+        assert!(!trailer.announces("x-other"));
+        assert_eq!(Trailer::new(&[] as &[&str]), None);
+        bad::<Trailer>(b"");
+        bad::<Trailer>(b",");
+        bad::<Trailer>(b"x-checksum x-other");
+        bad::<Trailer>(b"x-\xff");
+    }
+
+    #[test]
+    fn test_several_field_lines() {
+        let headers = Headers::parse_block(b"Trailer: Server-Timing\r\n\
+                                             Trailer: Content-Digest, server-timing\r\n").unwrap();
+        assert_eq!(headers.get(TRAILER).unwrap().names(),
+                   &["server-timing".to_owned(), "content-digest".to_owned()][..]);
+        // An empty line adds nothing, but doesn't spoil the others either.
+        let headers = Headers::parse_block(b"Trailer: \r\nTrailer: Expires\r\n").unwrap();
+        assert_eq!(headers.get(TRAILER).unwrap().names(), &["expires".to_owned()][..]);
+    }
+
+    #[test]
+    fn test_is_forbidden_trailer() {
+        assert!(is_forbidden_trailer("Content-Length"));
+        assert!(is_forbidden_trailer("set-cookie"));
+        assert!(!is_forbidden_trailer("server-timing"));
+        assert!(!is_forbidden_trailer("content-digest"));
+    }
+}
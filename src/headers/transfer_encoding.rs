@@ -0,0 +1,249 @@
+//! The `Transfer-Encoding` header (RFC 7230, section 3.3.1), and working out how a message body
+//! is delimited (RFC 7230, section 3.3.3).
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+
+use grammar::parameters::{self, split_token};
+use grammar::trim_ows;
+use super::{ToHeader, Header, Headers};
+use super::block::{HeaderParseError, HeaderParseErrorKind};
+use super::trailer::{is_forbidden_trailer, TRAILER};
+
+/// A transfer coding, being one item of the `Transfer-Encoding` header.
+///
+/// RFC 7230 grammar:
+///
+/// ```abnf
+/// Transfer-Encoding  = 1#transfer-coding
+/// transfer-coding    = "chunked" / "compress" / "deflate" / "gzip" / transfer-extension
+/// transfer-extension = token *( OWS ";" OWS transfer-parameter )
+/// ```
+///
+/// Transfer coding names are case-insensitive; extension names are lowercased.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TransferCoding {
+    /// `chunked`.
+    Chunked,
+    /// `compress` (or `x-compress`).
+    Compress,
+    /// `deflate`.
+    Deflate,
+    /// `gzip` (or `x-gzip`).
+    Gzip,
+    /// Any other transfer coding, with its parameters.
+    Extension(String, Vec<(String, String)>),
+}
+
+impl ToHeader for TransferCoding {
+    fn parse(raw: &[u8]) -> Option<TransferCoding> {
+        let (name, rest) = split_token(raw);
+        if name.is_empty() {
+            return None;
+        }
+        let parameters = match parameters::parse(rest) {
+            Some(parameters) => parameters,
+            None => return None,
+        };
+        // Tokens are ASCII, so this can’t fail.
+        let name = unsafe { str::from_utf8_unchecked(name) }.to_ascii_lowercase();
+        Some(match &name[..] {
+            "chunked" if parameters.is_empty() => TransferCoding::Chunked,
+            "compress" | "x-compress" if parameters.is_empty() => TransferCoding::Compress,
+            "deflate" if parameters.is_empty() => TransferCoding::Deflate,
+            "gzip" | "x-gzip" if parameters.is_empty() => TransferCoding::Gzip,
+            _ => TransferCoding::Extension(name, parameters),
+        })
+    }
+}
+
+impl Header for TransferCoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TransferCoding::Chunked => f.write_str("chunked"),
+            TransferCoding::Compress => f.write_str("compress"),
+            TransferCoding::Deflate => f.write_str("deflate"),
+            TransferCoding::Gzip => f.write_str("gzip"),
+            TransferCoding::Extension(ref name, ref parameters) => {
+                try!(f.write_str(name));
+                for &(ref name, ref value) in parameters {
+                    try!(write!(f, "; {}={}", name, value));
+                }
+                Ok(())
+            },
+        }
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_list_header_marker! {
+    /// The `Transfer-Encoding` header, a list of `TransferCoding`s in the order applied.
+    pub TRANSFER_ENCODING: TransferCoding = "transfer-encoding"
+}
+
+/// How a message body is delimited, as determined by `Headers::framing`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BodyFraming {
+    /// The body uses the chunked transfer coding.
+    Chunked,
+    /// The body is of the given length, from `Content-Length`.
+    Length(u64),
+    /// Neither header is present. For a request this means that there is no body; for a response
+    /// it means that the body continues until the connection is closed (subject to the status
+    /// code and request method, which can mean there is no body at all).
+    None,
+}
+
+impl Headers {
+    /// Work out how the message body is delimited, according to RFC 7230, section 3.3.3.
+    ///
+    /// - If `Transfer-Encoding` is present, its final transfer coding must be `chunked` (and
+    ///   `chunked` must not be applied more than once), and then the body is chunked.
+    /// - If `Content-Length` is present too, the message is an error, being a common technique
+    ///   for request smuggling; the RFC permits recipients to prefer `Transfer-Encoding`, but it
+    ///   is safer to reject it, as this does.
+    /// - Otherwise, if `Content-Length` is present, it must be valid (see `content_length`) and
+    ///   the body is of that length.
+    ///
+    /// A response whose final transfer coding is not `chunked` is technically delimited by the
+    /// connection closing, but this is treated as an error too, as it is for requests.
+    pub fn framing(&self) -> Result<BodyFraming, HeaderParseError> {
+        let content_length = try!(self.content_length());
+        let raw = match self.item_by_name("transfer-encoding").and_then(|item| item.raw()) {
+            Some(raw) => raw,
+            None => return Ok(match content_length {
+                Some(length) => BodyFraming::Length(length),
+                None => BodyFraming::None,
+            }),
+        };
+        if content_length.is_some() {
+            return Err(HeaderParseError::new(HeaderParseErrorKind::Conflicting,
+                                             Some("transfer-encoding"), &raw[0]));
+        }
+        let mut codings = vec![];
+        for line in raw.iter() {
+            for value in line.split(|&c| c == b',').map(trim_ows).filter(|v| !v.is_empty()) {
+                match TransferCoding::parse(value) {
+                    Some(coding) => codings.push(coding),
+                    None => return Err(HeaderParseError::new(HeaderParseErrorKind::Invalid,
+                                                             Some("transfer-encoding"), line)),
+                }
+            }
+        }
+        let chunked_count = codings.iter().filter(|c| **c == TransferCoding::Chunked).count();
+        if chunked_count == 1 && codings.last() == Some(&TransferCoding::Chunked) {
+            Ok(BodyFraming::Chunked)
+        } else {
+            Err(HeaderParseError::new(HeaderParseErrorKind::Invalid, Some("transfer-encoding"),
+                                      raw.last().unwrap()))
+        }
+    }
+}
+
+/// Whether a message with these headers is to have a chunked body: `Transfer-Encoding` ends in
+/// `chunked`, validly, as `Headers::framing` decides.
+///
+/// A body writer should check this rather than looking for `chunked` itself, so that it never
+/// disagrees with a recipient about where the body ends.
+pub fn chunked_framing(headers: &Headers) -> bool {
+    headers.framing() == Ok(BodyFraming::Chunked)
+}
+
+/// The fields a chunked-body writer is to send in the trailer section, after the final chunk:
+/// those announced in the `Trailer` header, lowercase and in order, less any which must not be
+/// sent as trailers (see `trailer::is_forbidden_trailer`).
+///
+/// This is empty unless the body is chunked (see `chunked_framing`), for only a chunked body can
+/// have trailers; a `Trailer` header on any other message is ignored.
+pub fn announced_trailers(headers: &Headers) -> Vec<String> {
+    if !chunked_framing(headers) {
+        return vec![];
+    }
+    match headers.get(TRAILER) {
+        Some(trailer) => trailer.names().iter()
+                                .filter(|name| !is_forbidden_trailer(name))
+                                .cloned()
+                                .collect(),
+        None => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers, HeaderParseErrorKind};
+    use super::{TransferCoding, BodyFraming, chunked_framing, announced_trailers};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn framing(block: &[u8]) -> Result<BodyFraming, HeaderParseErrorKind> {
+        Headers::parse_block(block).ok().unwrap().framing().map_err(|error| error.kind())
+    }
+
+    #[test]
+    fn test_transfer_coding() {
+        eq(b"chunked", TransferCoding::Chunked);
+        eq(b"gzip", TransferCoding::Gzip);
+        eq(b"foo; bar=\"baz\"", TransferCoding::Extension(
+            "foo".to_owned(), vec![("bar".to_owned(), "\"baz\"".to_owned())]));
+        assert_eq!(TransferCoding::parse(b"X-GZIP"), Some(TransferCoding::Gzip));
+        assert_eq!(TransferCoding::parse(b""), None);
+        assert_eq!(TransferCoding::parse(b"gzip;"), Some(TransferCoding::Gzip));
+    }
+
+    #[test]
+    fn test_framing() {
+        assert_eq!(framing(b"Transfer-Encoding: chunked\r\n"), Ok(BodyFraming::Chunked));
+        assert_eq!(framing(b"Transfer-Encoding: gzip\r\nTransfer-Encoding: Chunked\r\n"),
+                   Ok(BodyFraming::Chunked));
+        assert_eq!(framing(b"Content-Length: 42\r\n"), Ok(BodyFraming::Length(42)));
+        assert_eq!(framing(b""), Ok(BodyFraming::None));
+    }
+
+    #[test]
+    fn test_ambiguous_framing() {
+        assert_eq!(framing(b"Content-Length: 42\r\nTransfer-Encoding: chunked\r\n"),
+                   Err(HeaderParseErrorKind::Conflicting));
+        assert_eq!(framing(b"Transfer-Encoding: chunked, gzip\r\n"),
+                   Err(HeaderParseErrorKind::Invalid));
+        assert_eq!(framing(b"Transfer-Encoding: chunked, chunked\r\n"),
+                   Err(HeaderParseErrorKind::Invalid));
+        assert_eq!(framing(b"Transfer-Encoding: chunked;\x01\r\n"),
+                   Err(HeaderParseErrorKind::Invalid));
+        assert_eq!(framing(b"Content-Length: 1, 2\r\n"), Err(HeaderParseErrorKind::Invalid));
+    }
+
+    #[test]
+    fn test_trailers() {
+        let headers = Headers::parse_block(b"Transfer-Encoding: chunked\r\n\
+                                             Trailer: Expires\r\n").unwrap();
+        assert!(chunked_framing(&headers));
+        assert_eq!(announced_trailers(&headers), vec!["expires".to_owned()]);
+
+        // Fields which can’t be trailers are left out.
+        let headers = Headers::parse_block(b"Transfer-Encoding: gzip, chunked\r\n\
+                                             Trailer: Server-Timing, Content-Length, \
+                                             Content-Digest\r\n").unwrap();
+        assert!(chunked_framing(&headers));
+        assert_eq!(announced_trailers(&headers),
+                   vec!["server-timing".to_owned(), "content-digest".to_owned()]);
+
+        // No chunked body, no trailers.
+        for block in &[&b"Content-Length: 5\r\nTrailer: Expires\r\n"[..],
+                       b"Trailer: Expires\r\n",
+                       b"Transfer-Encoding: chunked, gzip\r\nTrailer: Expires\r\n"] {
+            let headers = Headers::parse_block(block).unwrap();
+            assert!(!chunked_framing(&headers));
+            assert_eq!(announced_trailers(&headers), Vec::<String>::new());
+        }
+        let headers = Headers::parse_block(b"Transfer-Encoding: chunked\r\n").unwrap();
+        assert_eq!(announced_trailers(&headers), Vec::<String>::new());
+    }
+}
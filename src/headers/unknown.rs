@@ -0,0 +1,310 @@
+//! What to do with headers this crate knows nothing of, when they are added by name or parsed.
+//!
+//! A proxy must pass on headers it doesn’t understand (RFC 7230, section 3.2.1), but a strict
+//! gateway may prefer to refuse them, or at least to insist that their values be well-formed, for
+//! it can’t otherwise vouch for them.
+
+use std::ascii::AsciiExt;
+use std::collections::hash_map::Entry::{Occupied, Vacant};
+use std::str;
+
+use smallvec::SmallVec;
+use tendril::ByteTendril;
+
+use grammar::is_field_content;
+use super::{Headers, check_field_name};
+use super::block::{HeaderParseError, HeaderParseErrorKind};
+use super::internals::Item;
+
+/// The headers which are known: those defined by RFC 7230 to RFC 7235, and those which this crate
+/// has markers for. Sorted, for binary search.
+const KNOWN_HEADERS: &'static [&'static str] = &[
+    "accept",
+    "accept-ch",
+    "accept-charset",
+    "accept-encoding",
+    "accept-language",
+    "accept-patch",
+    "accept-ranges",
+    "age",
+    "allow",
+    "authorization",
+    "cache-control",
+    "clear-site-data",
+    "connection",
+    "content-digest",
+    "content-encoding",
+    "content-language",
+    "content-length",
+    "content-location",
+    "content-range",
+    "content-type",
+    "cookie",
+    "cross-origin-embedder-policy",
+    "cross-origin-opener-policy",
+    "cross-origin-resource-policy",
+    "date",
+    "dnt",
+    "early-data",
+    "etag",
+    "expect",
+    "expect-ct",
+    "expires",
+    "forwarded",
+    "from",
+    "host",
+    "if-match",
+    "if-modified-since",
+    "if-none-match",
+    "if-range",
+    "if-unmodified-since",
+    "last-event-id",
+    "last-modified",
+    "location",
+    "max-forwards",
+    "nel",
+    "origin",
+    "permissions-policy",
+    "pragma",
+    "priority",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "range",
+    "referer",
+    "referrer-policy",
+    "report-to",
+    "retry-after",
+    "save-data",
+    "server",
+    "set-cookie",
+    "sunset",
+    "te",
+    "timing-allow-origin",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "upgrade-insecure-requests",
+    "user-agent",
+    "vary",
+    "via",
+    "want-content-digest",
+    "warning",
+    "www-authenticate",
+    "x-content-type-options",
+    "x-forwarded-for",
+    "x-frame-options",
+];
+
+/// Whether the named header (case-insensitive) is one that is known; see `UnknownPolicy`.
+pub fn is_known_header(name: &str) -> bool {
+    KNOWN_HEADERS.binary_search(&&*name.to_ascii_lowercase()).is_ok()
+}
+
+/// What `Headers::append_raw` and `Headers::parse_block_with_policy` do with a header which is not
+/// known (by `is_known_header`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UnknownPolicy {
+    /// Accept it, whatever its value. This is the default, and what a proxy needs.
+    Accept,
+    /// Accept it only if its value is field-content (RFC 7230, section 3.2), so that it is at
+    /// least well-formed; otherwise it is an `Invalid` error.
+    Validate,
+    /// Refuse it, as a `Forbidden` error.
+    Reject,
+}
+
+impl Default for UnknownPolicy {
+    fn default() -> UnknownPolicy {
+        UnknownPolicy::Accept
+    }
+}
+
+/// Check a field line against the policy on unknown headers; `name` must be lowercase.
+pub fn check_unknown(policy: UnknownPolicy, name: &str, value: &[u8])
+                     -> Result<(), HeaderParseError> {
+    if policy == UnknownPolicy::Accept || is_known_header(name) {
+        return Ok(());
+    }
+    if policy == UnknownPolicy::Reject {
+        return Err(HeaderParseError::new(HeaderParseErrorKind::Forbidden, Some(name), value));
+    }
+    if !is_field_content(value) {
+        return Err(HeaderParseError::new(HeaderParseErrorKind::Invalid, Some(name), value));
+    }
+    Ok(())
+}
+
+impl Headers {
+    /// The policy on unknown headers added with `append_raw`.
+    pub fn unknown_policy(&self) -> UnknownPolicy {
+        self.unknown_policy
+    }
+
+    /// Set the policy on unknown headers added with `append_raw`.
+    ///
+    /// Headers already present are not affected, nor are headers set through a marker, for the
+    /// program which defined the marker evidently knows of the header.
+    pub fn set_unknown_policy(&mut self, policy: UnknownPolicy) {
+        self.unknown_policy = policy;
+    }
+
+    /// Add a raw field line, by name (case-insensitive), after any existing ones.
+    ///
+    /// An invalid name is a `Malformed` error, and a value containing CR, LF or NUL (which could
+    /// be used to inject headers, and which `write_to` would refuse) is an `Invalid` error,
+    /// whatever the policy; beyond that, an unknown header is subject to the `unknown_policy`.
+    /// Nothing is added on error.
+    pub fn append_raw(&mut self, name: &str, value: &[u8]) -> Result<(), HeaderParseError> {
+        try!(check_field_name(name));
+        let name = name.to_ascii_lowercase();
+        if value.iter().any(|&c| c == b'\r' || c == b'\n' || c == b'\0') {
+            return Err(HeaderParseError::new(HeaderParseErrorKind::Invalid, Some(&name), value));
+        }
+        try!(check_unknown(self.unknown_policy, &name, value));
+        let value = ByteTendril::from_slice(value);
+        match self.data_mut().entry((&name[..]).into()) {
+            Vacant(entry) => {
+                let mut values = SmallVec::new();
+                values.push(value);
+                let _ = entry.insert(Item::from_raw(values, false));
+            },
+            Occupied(entry) => entry.into_mut().raw_mut().push(value),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::{Headers, HeaderParseErrorKind, Limits};
+    use headers::{Marker, accept, accept_encoding, accept_patch, allow, authorization,
+                  cache_control, clear_site_data, client_hints, conditional, connection,
+                  content_digest, content_encoding, content_length, content_type, cookie,
+                  cross_origin, date_based, dnt, early_data, etag, expect_ct, expires, forwarded,
+                  nel, origin, priority, range, report_to, retry_after, save_data, security, server,
+                  sse, sunset, timing_allow_origin, trailer, transfer_encoding, upgrade, vary, via,
+                  warning};
+    use super::{UnknownPolicy, KNOWN_HEADERS, is_known_header};
+
+    fn append(policy: UnknownPolicy, name: &str, value: &[u8])
+              -> (Option<HeaderParseErrorKind>, Option<Vec<String>>) {
+        let mut headers = Headers::new();
+        headers.set_unknown_policy(policy);
+        let kind = headers.append_raw(name, value).err().map(|error| error.kind());
+        (kind, headers.get_raw_str(name, false))
+    }
+
+    #[test]
+    fn test_known_headers() {
+        // Binary search depends on this, so a name added out of place would go unrecognised.
+        assert!(KNOWN_HEADERS.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(KNOWN_HEADERS.iter().all(|name| is_known_header(name)));
+        assert!(is_known_header("content-type"));
+        assert!(is_known_header("Content-Type"));
+        assert!(!is_known_header("x-whatever"));
+    }
+
+    fn name<M: Marker<'static>>(_: M) -> &'static str {
+        M::header_name()
+    }
+
+    #[test]
+    fn test_markers_are_known() {
+        // Or `UnknownPolicy::Reject` would refuse a header which the crate supports.
+        let names = [name(accept::ACCEPT), name(accept_encoding::ACCEPT_ENCODING),
+                     name(accept_patch::ACCEPT_PATCH), name(allow::ALLOW),
+                     name(authorization::AUTHORIZATION), name(authorization::PROXY_AUTHORIZATION),
+                     name(cache_control::CACHE_CONTROL), name(cache_control::AGE),
+                     name(clear_site_data::CLEAR_SITE_DATA), name(client_hints::ACCEPT_CH),
+                     name(conditional::IF_MODIFIED_SINCE), name(conditional::IF_UNMODIFIED_SINCE),
+                     name(connection::CONNECTION), name(content_digest::CONTENT_DIGEST),
+                     name(content_digest::WANT_CONTENT_DIGEST),
+                     name(content_encoding::CONTENT_ENCODING), name(content_length::CONTENT_LENGTH),
+                     name(content_type::CONTENT_TYPE), name(cookie::COOKIE),
+                     name(cookie::SET_COOKIE), name(cross_origin::CROSS_ORIGIN_RESOURCE_POLICY),
+                     name(cross_origin::CROSS_ORIGIN_OPENER_POLICY),
+                     name(cross_origin::CROSS_ORIGIN_EMBEDDER_POLICY), name(date_based::DATE),
+                     name(dnt::DNT), name(early_data::EARLY_DATA), name(etag::ETAG),
+                     name(etag::IF_MATCH), name(etag::IF_NONE_MATCH), name(expect_ct::EXPECT_CT),
+                     name(expires::EXPIRES), name(forwarded::X_FORWARDED_FOR),
+                     name(forwarded::FORWARDED), name(nel::NEL), name(origin::ORIGIN),
+                     name(priority::PRIORITY), name(range::RANGE), name(report_to::REPORT_TO),
+                     name(retry_after::RETRY_AFTER), name(save_data::SAVE_DATA),
+                     name(security::X_FRAME_OPTIONS), name(security::REFERRER_POLICY),
+                     name(security::PERMISSIONS_POLICY), name(security::UPGRADE_INSECURE_REQUESTS),
+                     name(server::SERVER), name(sse::LAST_EVENT_ID), name(sunset::SUNSET),
+                     name(timing_allow_origin::TIMING_ALLOW_ORIGIN), name(trailer::TRAILER),
+                     name(transfer_encoding::TRANSFER_ENCODING), name(upgrade::UPGRADE),
+                     name(vary::VARY), name(via::VIA), name(warning::WARNING)];
+        for name in &names {
+            assert!(is_known_header(name), "{} is missing from KNOWN_HEADERS", name);
+        }
+    }
+
+    #[test]
+    fn test_accept() {
+        assert_eq!(Headers::new().unknown_policy(), UnknownPolicy::Accept);
+        assert_eq!(append(UnknownPolicy::Accept, "X-Whatever", b"1"),
+                   (None, Some(vec!["1".to_owned()])));
+        assert_eq!(append(UnknownPolicy::Accept, "X-Whatever", b"\x01"),
+                   (None, Some(vec!["\x01".to_owned()])));
+        assert_eq!(append(UnknownPolicy::Accept, "X-Whatever", b"1\r\nHost: evil"),
+                   (Some(HeaderParseErrorKind::Invalid), None));
+        assert_eq!(append(UnknownPolicy::Accept, "X-Whatever", b"1\0"),
+                   (Some(HeaderParseErrorKind::Invalid), None));
+        assert_eq!(append(UnknownPolicy::Accept, "X Whatever", b"1"),
+                   (Some(HeaderParseErrorKind::Malformed), None));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(append(UnknownPolicy::Validate, "X-Whatever", b"a b"),
+                   (None, Some(vec!["a b".to_owned()])));
+        assert_eq!(append(UnknownPolicy::Validate, "X-Whatever", b"\x01"),
+                   (Some(HeaderParseErrorKind::Invalid), None));
+        assert_eq!(append(UnknownPolicy::Validate, "X-Whatever", b" a"),
+                   (Some(HeaderParseErrorKind::Invalid), None));
+        // Known headers are left to their typed parsing.
+        assert_eq!(append(UnknownPolicy::Validate, "Server", b"\x01"),
+                   (None, Some(vec!["\x01".to_owned()])));
+    }
+
+    #[test]
+    fn test_reject() {
+        assert_eq!(append(UnknownPolicy::Reject, "X-Whatever", b"1"),
+                   (Some(HeaderParseErrorKind::Forbidden), None));
+        assert_eq!(append(UnknownPolicy::Reject, "Vary", b"accept"),
+                   (None, Some(vec!["accept".to_owned()])));
+
+        let mut headers = Headers::new();
+        headers.set_unknown_policy(UnknownPolicy::Reject);
+        headers.append_raw("vary", b"accept").unwrap();
+        headers.append_raw("VARY", b"origin").unwrap();
+        let error = headers.append_raw("X-Whatever", b"1").unwrap_err();
+        assert_eq!(error.name(), Some("x-whatever"));
+        assert_eq!(headers.get_raw_str("vary", false),
+                   Some(vec!["accept".to_owned(), "origin".to_owned()]));
+    }
+
+    #[test]
+    fn test_parse_block() {
+        let block = b"Vary: accept\r\nX-Whatever: \x01\r\n";
+        let parse = |policy| Headers::parse_block_with_policy(block, Limits::default(), policy);
+        let headers = parse(UnknownPolicy::Accept).unwrap();
+        assert_eq!(headers.get_raw_str("x-whatever", false), Some(vec!["\x01".to_owned()]));
+        let error = parse(UnknownPolicy::Validate).unwrap_err();
+        assert_eq!(error.kind(), HeaderParseErrorKind::Invalid);
+        assert_eq!(error.name(), Some("x-whatever"));
+        let error = parse(UnknownPolicy::Reject).unwrap_err();
+        assert_eq!(error.kind(), HeaderParseErrorKind::Forbidden);
+        assert_eq!(error.name(), Some("x-whatever"));
+
+        // The policy stays with the headers, for later additions.
+        let headers = Headers::parse_block_with_policy(b"Vary: accept\r\nX-Whatever: 1\r\n",
+                                                       Limits::default(), UnknownPolicy::Validate)
+                             .unwrap();
+        assert_eq!(headers.unknown_policy(), UnknownPolicy::Validate);
+        // The plain parse_block accepts anything, as before.
+        assert!(Headers::parse_block(block).is_ok());
+    }
+}
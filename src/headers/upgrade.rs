@@ -0,0 +1,168 @@
+//! The `Upgrade` header (RFC 7230, section 6.7).
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+
+use grammar::token::is_tchar;
+use super::{ToHeader, Header, Headers};
+
+/// A protocol, being one item of the `Upgrade` header: a name and perhaps a version.
+///
+/// RFC 7230 grammar:
+///
+/// ```abnf
+/// Upgrade          = 1#protocol
+/// protocol         = protocol-name ["/" protocol-version]
+/// protocol-name    = token
+/// protocol-version = token
+/// ```
+///
+/// Protocol names are case-insensitive, but are kept with their case preserved.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProtocolName {
+    /// The protocol name, such as `websocket` or `HTTP`.
+    pub name: String,
+    /// The protocol version, such as `2.0`, if given.
+    pub version: Option<String>,
+}
+
+impl ProtocolName {
+    /// Whether this is the protocol written `protocol`, which is a protocol name with an optional
+    /// version. Without a version, any version of the named protocol matches.
+    pub fn matches(&self, protocol: &str) -> bool {
+        let (name, version) = match protocol.find('/') {
+            Some(slash) => (&protocol[..slash], Some(&protocol[slash + 1..])),
+            None => (protocol, None),
+        };
+        self.name.eq_ignore_ascii_case(name) &&
+            (version.is_none() || self.version.as_ref().map(|v| &v[..]) == version)
+    }
+}
+
+impl ToHeader for ProtocolName {
+    fn parse(raw: &[u8]) -> Option<ProtocolName> {
+        let (name, version) = match raw.iter().position(|&c| c == b'/') {
+            Some(slash) => (&raw[..slash], Some(&raw[slash + 1..])),
+            None => (raw, None),
+        };
+        let is_token = |raw: &[u8]| !raw.is_empty() && raw.iter().all(|&c| is_tchar(c));
+        if !is_token(name) || !version.map_or(true, is_token) {
+            return None;
+        }
+        // Tokens are ASCII, so these can’t fail.
+        Some(ProtocolName {
+            name: unsafe { str::from_utf8_unchecked(name) }.to_owned(),
+            version: version.map(|version| unsafe { str::from_utf8_unchecked(version) }.to_owned()),
+        })
+    }
+}
+
+impl Header for ProtocolName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str(&self.name));
+        match self.version {
+            Some(ref version) => write!(f, "/{}", version),
+            None => Ok(()),
+        }
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_list_header_marker! {
+    /// The `Upgrade` header: the protocols to which the sender would like to switch, in
+    /// descending order of preference.
+    pub UPGRADE: ProtocolName = "upgrade"
+}
+
+/// The protocols of an `Upgrade` header, in the client’s order of preference.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Upgrade {
+    protocols: Vec<ProtocolName>,
+}
+
+impl Upgrade {
+    /// Construct a list of protocols, most preferred first.
+    pub fn new<I: IntoIterator<Item = ProtocolName>>(protocols: I) -> Upgrade {
+        Upgrade { protocols: protocols.into_iter().collect() }
+    }
+
+    /// Read the protocols from a request’s `Upgrade` header.
+    ///
+    /// Items which aren’t valid protocols are skipped. If there is no `Upgrade` header, the list
+    /// will be empty.
+    pub fn from_headers(headers: &Headers) -> Upgrade {
+        Upgrade::new(headers.get(UPGRADE).into_owned())
+    }
+
+    /// The protocols, most preferred first.
+    pub fn protocols(&self) -> &[ProtocolName] {
+        &self.protocols
+    }
+
+    /// Choose the protocol to switch to: the first in the client’s list which the server
+    /// supports, per `ProtocolName::matches`. The client’s order is what counts, not the order
+    /// of `supported`.
+    pub fn preferred(&self, supported: &[&str]) -> Option<ProtocolName> {
+        self.protocols.iter().find(|protocol| {
+            supported.iter().any(|supported| protocol.matches(supported))
+        }).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use super::{ProtocolName, Upgrade};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    fn protocol(raw: &str) -> ProtocolName {
+        ProtocolName::parse(raw.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_protocol_name() {
+        eq(b"websocket", ProtocolName { name: "websocket".to_owned(), version: None });
+        eq(b"HTTP/2.0", ProtocolName { name: "HTTP".to_owned(), version: Some("2.0".to_owned()) });
+        bad::<ProtocolName>(b"");
+        bad::<ProtocolName>(b"HTTP/");
+        bad::<ProtocolName>(b"/2.0");
+        bad::<ProtocolName>(b"HTTP/2.0/1");
+        bad::<ProtocolName>(b"web socket");
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(protocol("WebSocket").matches("websocket"));
+        assert!(protocol("HTTP/2.0").matches("http"));
+        assert!(protocol("HTTP/2.0").matches("HTTP/2.0"));
+        assert!(!protocol("HTTP/2.0").matches("HTTP/3.0"));
+        assert!(!protocol("HTTP").matches("HTTP/2.0"));
+    }
+
+    #[test]
+    fn test_preferred() {
+        let headers = Headers::parse_block(b"Upgrade: HTTP/2.0, SHTTP/1.3, IRC/6.9, RTA/x11\r\n")
+                          .unwrap();
+        let upgrade = Upgrade::from_headers(&headers);
+        assert_eq!(upgrade.protocols().len(), 4);
+        assert_eq!(upgrade.preferred(&["websocket", "shttp"]), Some(protocol("SHTTP/1.3")));
+        // The client’s order wins over the server’s.
+        assert_eq!(upgrade.preferred(&["IRC", "SHTTP/1.3"]), Some(protocol("SHTTP/1.3")));
+        assert_eq!(upgrade.preferred(&["HTTP/3.0"]), None);
+        assert_eq!(upgrade.preferred(&[]), None);
+        assert_eq!(Upgrade::from_headers(&Headers::new()).preferred(&["websocket"]), None);
+    }
+}
@@ -0,0 +1,134 @@
+//! Checks on a header collection as a whole, for rejecting messages which are invalid in ways
+//! that matter for security, such as request smuggling.
+
+use std::str;
+
+use grammar::trim_ows;
+use super::Headers;
+use super::block::{HeaderParseError, HeaderParseErrorKind};
+
+impl Headers {
+    /// Get the value of the `Content-Length` header, if it is present.
+    ///
+    /// A recipient must be much fussier about `Content-Length` than about other headers, for it
+    /// determines where a message ends; RFC 7230, section 3.3.2 permits a list of identical values
+    /// (as caused by some intermediaries combining fields), but anything else must be treated as
+    /// an error rather than guessed at, which is what this does.
+    pub fn content_length(&self) -> Result<Option<u64>, HeaderParseError> {
+        let raw = match self.item_by_name("content-length").and_then(|item| item.raw()) {
+            Some(raw) => raw,
+            None => return Ok(None),
+        };
+        let mut length = None;
+        for line in raw.iter() {
+            for value in line.split(|&c| c == b',').map(trim_ows) {
+                let invalid = || HeaderParseError::new(HeaderParseErrorKind::Invalid,
+                                                       Some("content-length"), line);
+                if value.is_empty() || !value.iter().all(|&c| c >= b'0' && c <= b'9') {
+                    return Err(invalid());
+                }
+                // All ASCII digits, so this can’t fail.
+                let value = match unsafe { str::from_utf8_unchecked(value) }.parse() {
+                    Ok(value) => value,
+                    Err(_) => return Err(invalid()),
+                };
+                match length {
+                    Some(length) if length != value => return Err(invalid()),
+                    _ => length = Some(value),
+                }
+            }
+        }
+        Ok(length)
+    }
+
+    /// Check that the headers of an HTTP/1.1 request are acceptable, as a server should before
+    /// doing anything else with a request.
+    ///
+    /// This rejects:
+    ///
+    /// - multiple `Host` fields, or a missing `Host` (RFC 7230, section 5.4; HTTP/1.0 requests are
+    ///   permitted to omit it, so don’t use this on them);
+    /// - an invalid `Content-Length`, as described at `content_length`;
+    /// - both `Content-Length` and `Transfer-Encoding`, which is a request smuggling attempt
+    ///   (RFC 7230, section 3.3.3).
+    ///
+    /// RFC 7230 requires that such requests be responded to with `400 Bad Request`.
+    pub fn validate_request(&self) -> Result<(), HeaderParseError> {
+        match self.item_by_name("host").and_then(|item| item.raw()) {
+            Some(ref raw) if raw.len() > 1 => {
+                return Err(HeaderParseError::new(HeaderParseErrorKind::Duplicate, Some("host"),
+                                                 &raw[1]));
+            },
+            Some(_) => (),
+            None => {
+                return Err(HeaderParseError::new(HeaderParseErrorKind::Missing, Some("host"),
+                                                 b""));
+            },
+        }
+        let content_length = try!(self.content_length());
+        if content_length.is_some() && self.item_by_name("transfer-encoding").is_some() {
+            let raw = self.item_by_name("content-length").and_then(|item| item.raw()).unwrap();
+            return Err(HeaderParseError::new(HeaderParseErrorKind::Conflicting,
+                                             Some("content-length"), &raw[0]));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::{Headers, HeaderParseErrorKind};
+
+    fn headers(block: &[u8]) -> Headers {
+        Headers::parse_block(block).ok().unwrap()
+    }
+
+    fn kind(block: &[u8]) -> Option<HeaderParseErrorKind> {
+        headers(block).validate_request().err().map(|error| error.kind())
+    }
+
+    #[test]
+    fn test_valid_request() {
+        assert_eq!(kind(b"Host: example.com\r\nContent-Length: 5\r\n"), None);
+        assert_eq!(kind(b"Host: example.com\r\nTransfer-Encoding: chunked\r\n"), None);
+        assert_eq!(kind(b"Host: example.com\r\n"), None);
+        assert_eq!(kind(b"Host: example.com\r\nContent-Length: 5, 5\r\nContent-Length: 5\r\n"),
+                   None);
+    }
+
+    #[test]
+    fn test_duplicate_host() {
+        assert_eq!(kind(b"Host: example.com\r\nHost: evil.example\r\n"),
+                   Some(HeaderParseErrorKind::Duplicate));
+        let error = headers(b"Host: example.com\r\nHost: evil.example\r\n").validate_request()
+                                                                         .err().unwrap();
+        assert_eq!(error.name(), Some("host"));
+        assert_eq!(error.snippet(), b"evil.example");
+    }
+
+    #[test]
+    fn test_missing_host() {
+        assert_eq!(kind(b"Content-Length: 0\r\n"), Some(HeaderParseErrorKind::Missing));
+        assert_eq!(kind(b""), Some(HeaderParseErrorKind::Missing));
+    }
+
+    #[test]
+    fn test_smuggling() {
+        assert_eq!(kind(b"Host: a\r\nContent-Length: 5\r\nTransfer-Encoding: chunked\r\n"),
+                   Some(HeaderParseErrorKind::Conflicting));
+        assert_eq!(kind(b"Host: a\r\nContent-Length: 5\r\nContent-Length: 6\r\n"),
+                   Some(HeaderParseErrorKind::Invalid));
+        assert_eq!(kind(b"Host: a\r\nContent-Length: 5, 6\r\n"),
+                   Some(HeaderParseErrorKind::Invalid));
+        assert_eq!(kind(b"Host: a\r\nContent-Length: +5\r\n"),
+                   Some(HeaderParseErrorKind::Invalid));
+        assert_eq!(kind(b"Host: a\r\nContent-Length: 99999999999999999999\r\n"),
+                   Some(HeaderParseErrorKind::Invalid));
+    }
+
+    #[test]
+    fn test_content_length() {
+        assert_eq!(headers(b"Content-Length: 1234\r\n").content_length(), Ok(Some(1234)));
+        assert_eq!(headers(b"").content_length(), Ok(None));
+    }
+}
@@ -0,0 +1,223 @@
+//! The `Vary` header (RFC 7231, section 7.1.4), and its use in keying a cache (RFC 7234,
+//! section 4.1).
+
+use std::ascii::AsciiExt;
+use std::borrow::Cow;
+use std::fmt;
+use std::str;
+
+use grammar::token::is_tchar;
+use grammar::trim_ows;
+use super::{ToHeader, Header, Headers};
+
+/// The `Vary` header: the request header fields which, besides the method and target, selected
+/// the representation.
+///
+/// RFC 7231 grammar:
+///
+/// ```abnf
+/// Vary = "*" / 1#field-name
+/// ```
+///
+/// Field names are lowercased, and duplicates are discarded.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Vary {
+    /// `*`: the representation was selected by things other than request header fields, such as
+    /// the client’s network address, so a cache can’t reuse it without revalidating it.
+    Any,
+    /// The field names, lowercase. Never empty.
+    Fields(Vec<String>),
+}
+
+impl Vary {
+    /// The selecting header names in a canonical form, for storing a cached response by: sorted,
+    /// lowercase and without duplicates, so that `Vary: Accept-Encoding, accept-encoding` and
+    /// `Vary: accept-encoding` give the same list. `Any` gives just `*`, which can’t be confused
+    /// with a field name, as no field name can be `*`.
+    ///
+    /// Parsing already lowercases and de-duplicates, but a `Fields` constructed directly needn’t
+    /// be; the order of the names doesn’t matter to which representation is selected, so it is
+    /// normalised too.
+    pub fn normalized_selectors(&self) -> Vec<Cow<'static, str>> {
+        match *self {
+            Vary::Any => vec![Cow::Borrowed("*")],
+            Vary::Fields(ref fields) => {
+                let mut names = fields.iter().map(|name| name.to_ascii_lowercase())
+                                      .collect::<Vec<String>>();
+                names.sort();
+                names.dedup();
+                names.into_iter().map(Cow::Owned).collect()
+            },
+        }
+    }
+}
+
+impl ToHeader for Vary {
+    fn parse(raw: &[u8]) -> Option<Vary> {
+        let mut fields: Vec<String> = vec![];
+        let mut any = false;
+        for value in raw.split(|&c| c == b',').map(trim_ows).filter(|value| !value.is_empty()) {
+            if value == b"*" {
+                any = true;
+            } else if value.iter().all(|&c| is_tchar(c)) {
+                // Tokens are ASCII, so this can’t fail.
+                let name = unsafe { str::from_utf8_unchecked(value) }.to_ascii_lowercase();
+                if !fields.contains(&name) {
+                    fields.push(name);
+                }
+            } else {
+                return None;
+            }
+        }
+        // RFC 7231 doesn’t permit `*` in a list, but it’s clear enough what it means.
+        if any {
+            Some(Vary::Any)
+        } else if fields.is_empty() {
+            None
+        } else {
+            Some(Vary::Fields(fields))
+        }
+    }
+}
+
+impl Header for Vary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Vary::Any => f.write_str("*"),
+            Vary::Fields(ref fields) => {
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        try!(f.write_str(", "));
+                    }
+                    try!(f.write_str(field));
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+define_single_header_marker! {
+    /// The `Vary` header. Being a list, it may be split across field lines, which are combined.
+    pub VARY: Vary = "vary", combine
+}
+
+/// Produce the secondary cache key for a request, given the `Vary` header of a stored response.
+///
+/// The key is the value of each field which `Vary` names, paired with its name, in the order
+/// `Vary` names them; a field absent from the request has the value `None`, which RFC 7234
+/// requires be distinguished from an empty value. Multiple field lines are combined with `, `, as
+/// RFC 7230 permits; no other normalisation is done, so equivalent values with different spacing
+/// or case will produce different keys, which is merely less efficient.
+///
+/// A stored response may only be used for a request with the same key as the request which
+/// produced it. `None` is returned for `Vary: *`, for such a response can never be selected by
+/// key.
+pub fn cache_key(request: &Headers, vary: &Vary) -> Option<Vec<(String, Option<String>)>> {
+    let fields = match *vary {
+        Vary::Any => return None,
+        Vary::Fields(ref fields) => fields,
+    };
+    Some(fields.iter().map(|name| {
+        let value = request.item_by_name(name).and_then(|item| item.raw()).map(|raw| {
+            let lines = raw.iter().map(|line| String::from_utf8_lossy(line).into_owned())
+                                  .collect::<Vec<String>>();
+            lines.join(", ")
+        });
+        (name.clone(), value)
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use super::{Vary, VARY, cache_key};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    fn fields(names: &[&str]) -> Vary {
+        Vary::Fields(names.iter().map(|name| (*name).to_owned()).collect())
+    }
+
+    fn headers_vary(headers: &Headers) -> Vary {
+        (**headers.get(VARY).unwrap()).clone()
+    }
+
+    #[test]
+    fn test_vary() {
+        eq(b"*", Vary::Any);
+        eq(b"accept-encoding", fields(&["accept-encoding"]));
+        eq(b"accept, accept-language", fields(&["accept", "accept-language"]));
+        assert_eq!(Vary::parse(b"Accept,ACCEPT, Cookie"), Some(fields(&["accept", "cookie"])));
+        assert_eq!(Vary::parse(b"accept, *"), Some(Vary::Any));
+        bad::<Vary>(b"");
+        bad::<Vary>(b"accept encoding");
+    }
+
+    #[test]
+    fn test_several_field_lines() {
+        let response = Headers::parse_block(b"Vary: Accept-Encoding\r\nVary: origin\r\n").unwrap();
+        let vary = headers_vary(&response);
+        assert_eq!(vary, fields(&["accept-encoding", "origin"]));
+        let mut request = Headers::new();
+        request.append_raw("Origin", b"https://a.example").unwrap();
+        assert_eq!(cache_key(&request, &vary),
+                   Some(vec![("accept-encoding".to_owned(), None),
+                             ("origin".to_owned(), Some("https://a.example".to_owned()))]));
+
+        let response = Headers::parse_block(b"Vary: origin\r\nVary: *\r\n").unwrap();
+        assert_eq!(headers_vary(&response), Vary::Any);
+    }
+
+    #[test]
+    fn test_normalized_selectors() {
+        let one = Vary::parse(b"Accept-Encoding, accept-encoding").unwrap();
+        let other = Vary::parse(b"accept-encoding").unwrap();
+        assert_eq!(one.normalized_selectors(), vec!["accept-encoding"]);
+        assert_eq!(one.normalized_selectors(), other.normalized_selectors());
+
+        assert_eq!(Vary::parse(b"User-Agent, Accept, accept-language, ACCEPT").unwrap()
+                       .normalized_selectors(),
+                   vec!["accept", "accept-language", "user-agent"]);
+        assert_eq!(fields(&["Cookie", "accept", "cookie"]).normalized_selectors(),
+                   vec!["accept", "cookie"]);
+
+        assert_eq!(Vary::Any.normalized_selectors(), vec!["*"]);
+        assert_eq!(Vary::parse(b"accept, *").unwrap().normalized_selectors(), vec!["*"]);
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let vary = fields(&["accept-encoding"]);
+        let gzip = Headers::parse_block(b"Accept-Encoding: gzip\r\nUser-Agent: a\r\n").unwrap();
+        assert_eq!(cache_key(&gzip, &vary),
+                   Some(vec![("accept-encoding".to_owned(), Some("gzip".to_owned()))]));
+        let gzip_too = Headers::parse_block(b"Accept-Encoding: gzip\r\nUser-Agent: b\r\n").unwrap();
+        assert_eq!(cache_key(&gzip, &vary), cache_key(&gzip_too, &vary));
+        let neither = Headers::parse_block(b"User-Agent: a\r\n").unwrap();
+        assert_eq!(cache_key(&neither, &vary),
+                   Some(vec![("accept-encoding".to_owned(), None)]));
+
+        let vary = fields(&["accept", "accept-encoding"]);
+        let both = Headers::parse_block(b"Accept: text/html\r\nAccept-Encoding: gzip\r\n\
+                                          accept-encoding: br\r\n").unwrap();
+        assert_eq!(cache_key(&both, &vary),
+                   Some(vec![("accept".to_owned(), Some("text/html".to_owned())),
+                             ("accept-encoding".to_owned(), Some("gzip, br".to_owned()))]));
+    }
+
+    #[test]
+    fn test_cache_key_any() {
+        assert_eq!(cache_key(&Headers::new(), &Vary::Any), None);
+        let gzip = Headers::parse_block(b"Accept-Encoding: gzip\r\n").unwrap();
+        assert_eq!(cache_key(&gzip, &Vary::Any), None);
+    }
+}
@@ -0,0 +1,202 @@
+//! The `Via` header (RFC 7230, section 5.7.1).
+
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::str;
+
+use grammar::{is_ows, trim_ows};
+use grammar::token::is_tchar;
+use super::{ToHeader, Header, Headers};
+
+/// One intermediary, being one item of the `Via` header.
+///
+/// RFC 7230 grammar:
+///
+/// ```abnf
+/// Via               = 1#( received-protocol RWS received-by [ RWS comment ] )
+/// received-protocol = [ protocol-name "/" ] protocol-version
+/// received-by       = ( uri-host [ ":" port ] ) / pseudonym
+/// pseudonym         = token
+/// ```
+///
+/// The comment is kept verbatim, parentheses and all. As the list is split on commas, a comment
+/// containing a comma makes the item invalid.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ViaEntry {
+    /// The protocol name, if given; `HTTP` is implied if not.
+    pub protocol: Option<String>,
+    /// The protocol version, such as `1.1`.
+    pub version: String,
+    /// The host and perhaps port, or pseudonym, of the intermediary, such as `proxy.example:8080`
+    /// or `fred`.
+    pub received_by: String,
+    /// The comment, such as `(Apache/1.1)`, if given.
+    pub comment: Option<String>,
+}
+
+impl ViaEntry {
+    /// Construct an entry for an intermediary which received the message over HTTP of the given
+    /// version, with no comment.
+    pub fn new(version: &str, received_by: &str) -> ViaEntry {
+        ViaEntry {
+            protocol: None,
+            version: version.to_owned(),
+            received_by: received_by.to_owned(),
+            comment: None,
+        }
+    }
+}
+
+/// Split off the leading run of non-whitespace, and the whitespace which follows it.
+fn split_word(raw: &[u8]) -> (&[u8], &[u8]) {
+    let end = raw.iter().position(|&c| is_ows(c)).unwrap_or(raw.len());
+    (&raw[..end], trim_ows(&raw[end..]))
+}
+
+impl ToHeader for ViaEntry {
+    fn parse(raw: &[u8]) -> Option<ViaEntry> {
+        let is_token = |raw: &[u8]| !raw.is_empty() && raw.iter().all(|&c| is_tchar(c));
+        let (received_protocol, rest) = split_word(raw);
+        let (protocol, version) = match received_protocol.iter().position(|&c| c == b'/') {
+            Some(slash) => (Some(&received_protocol[..slash]), &received_protocol[slash + 1..]),
+            None => (None, received_protocol),
+        };
+        if !protocol.map_or(true, is_token) || !is_token(version) {
+            return None;
+        }
+        let (received_by, comment) = split_word(rest);
+        // Close enough to uri-host [ ":" port ], including IP literals, without being lax.
+        if received_by.is_empty() ||
+                !received_by.iter().all(|&c| is_tchar(c) || c == b':' || c == b'[' || c == b']') {
+            return None;
+        }
+        let comment = if comment.is_empty() {
+            None
+        } else if comment.len() >= 2 && comment[0] == b'(' && comment[comment.len() - 1] == b')' &&
+                comment.iter().all(|&c| (c >= 0x20 && c != 0x7f) || c == b'\t') {
+            match str::from_utf8(comment) {
+                Ok(comment) => Some(comment.to_owned()),
+                Err(_) => return None,
+            }
+        } else {
+            return None;
+        };
+        // Tokens and the like are ASCII, so these can’t fail.
+        Some(ViaEntry {
+            protocol: protocol.map(|protocol| {
+                unsafe { str::from_utf8_unchecked(protocol) }.to_owned()
+            }),
+            version: unsafe { str::from_utf8_unchecked(version) }.to_owned(),
+            received_by: unsafe { str::from_utf8_unchecked(received_by) }.to_owned(),
+            comment: comment,
+        })
+    }
+}
+
+impl Header for ViaEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref protocol) = self.protocol {
+            try!(write!(f, "{}/", protocol));
+        }
+        try!(write!(f, "{} {}", self.version, self.received_by));
+        match self.comment {
+            Some(ref comment) => write!(f, " {}", comment),
+            None => Ok(()),
+        }
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_list_header_marker! {
+    /// The `Via` header: the intermediaries which the message has passed through, in the order
+    /// that it did.
+    pub VIA: ViaEntry = "via"
+}
+
+/// The intermediaries of a `Via` header, the first being the nearest to the origin of the
+/// message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Via {
+    entries: Vec<ViaEntry>,
+}
+
+impl Via {
+    /// Construct a chain of intermediaries.
+    pub fn new<I: IntoIterator<Item = ViaEntry>>(entries: I) -> Via {
+        Via { entries: entries.into_iter().collect() }
+    }
+
+    /// Read the intermediaries from a message’s `Via` header.
+    ///
+    /// Items which aren’t valid are skipped. If there is no `Via` header, the chain will be
+    /// empty.
+    pub fn from_headers(headers: &Headers) -> Via {
+        Via::new(headers.get(VIA).into_owned())
+    }
+
+    /// The intermediaries, in the order that the message passed through them.
+    pub fn entries(&self) -> &[ViaEntry] {
+        &self.entries
+    }
+
+    /// Whether an intermediary with the given pseudonym (or host) has already handled the
+    /// message, compared case-insensitively with `received-by`.
+    ///
+    /// A proxy which finds itself here is in a loop, and should refuse the request rather than
+    /// forward it yet again.
+    pub fn contains_self(&self, my_pseudonym: &str) -> bool {
+        self.entries.iter().any(|entry| entry.received_by.eq_ignore_ascii_case(my_pseudonym))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use super::{ViaEntry, Via};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    #[test]
+    fn test_via_entry() {
+        eq(b"1.0 fred", ViaEntry::new("1.0", "fred"));
+        eq(b"HTTP/1.1 p.example.net:8080 (Apache/1.1)", ViaEntry {
+            protocol: Some("HTTP".to_owned()),
+            version: "1.1".to_owned(),
+            received_by: "p.example.net:8080".to_owned(),
+            comment: Some("(Apache/1.1)".to_owned()),
+        });
+        eq(b"2 [2001:db8::1]:443", ViaEntry::new("2", "[2001:db8::1]:443"));
+        assert_eq!(ViaEntry::parse(b"1.1  fred\t"), Some(ViaEntry::new("1.1", "fred")));
+        bad::<ViaEntry>(b"");
+        bad::<ViaEntry>(b"1.1");
+        bad::<ViaEntry>(b"/1.1 fred");
+        bad::<ViaEntry>(b"1.1 fred comment");
+        bad::<ViaEntry>(b"1.1 fred (unclosed");
+        bad::<ViaEntry>(b"1.1 fr\"ed");
+    }
+
+    #[test]
+    fn test_contains_self() {
+        let headers = Headers::parse_block(b"Via: 1.0 fred, 1.1 p.example.net (Apache/1.1)\r\n\
+                                             Via: HTTP/1.1 GoodProxy\r\n").unwrap();
+        let via = Via::from_headers(&headers);
+        assert_eq!(via.entries().len(), 3);
+        assert!(via.contains_self("goodproxy"));
+        assert!(via.contains_self("FRED"));
+        assert!(via.contains_self("p.example.net"));
+        assert!(!via.contains_self("badproxy"));
+        assert!(!via.contains_self("good"));
+        assert!(!Via::from_headers(&Headers::new()).contains_self("fred"));
+    }
+}
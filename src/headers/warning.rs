@@ -0,0 +1,178 @@
+//! The `Warning` header (RFC 7234, section 5.5).
+
+use std::fmt;
+use std::str;
+
+use time::Tm;
+
+use grammar::quoted_string;
+use grammar::token::is_tchar;
+use super::{ToHeader, Header};
+use super::date_based::{HttpDate, parse_http_date};
+
+/// A warning, being one item of the `Warning` header.
+///
+/// RFC 7234 grammar:
+///
+/// ```abnf
+/// Warning       = 1#warning-value
+/// warning-value = warn-code SP warn-agent SP warn-text [ SP warn-date ]
+/// warn-code     = 3DIGIT
+/// warn-agent    = ( uri-host [ ":" port ] ) / pseudonym
+/// warn-text     = quoted-string
+/// warn-date     = DQUOTE HTTP-date DQUOTE
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Warning {
+    /// The warn-code, such as 110 for “Response is Stale”. Always three digits.
+    pub code: u16,
+    /// The host and perhaps port, or pseudonym, of the agent adding the warning, or `-` if
+    /// unknown.
+    pub agent: String,
+    /// The warning text, unquoted.
+    pub text: String,
+    /// The date of the warning, if given.
+    pub date: Option<Tm>,
+}
+
+impl Warning {
+    /// Whether the warning must be discarded because its date is not the `Date` of the message
+    /// it came in, as happens when a cache passes on a warning that has since been superseded
+    /// (RFC 7234, section 5.5). A warning without a date is never stale.
+    pub fn is_stale_against(&self, response_date: &Tm) -> bool {
+        match self.date {
+            Some(ref date) => date.to_timespec() != response_date.to_timespec(),
+            None => false,
+        }
+    }
+}
+
+impl ToHeader for Warning {
+    fn parse(raw: &[u8]) -> Option<Warning> {
+        if raw.len() < 4 || !raw[..3].iter().all(|&c| c >= b'0' && c <= b'9') || raw[3] != b' ' {
+            return None;
+        }
+        // All ASCII digits, so this can’t fail.
+        let code = unsafe { str::from_utf8_unchecked(&raw[..3]) }.parse().unwrap();
+        let rest = &raw[4..];
+        let space = match rest.iter().position(|&c| c == b' ') {
+            Some(space) => space,
+            None => return None,
+        };
+        let agent = &rest[..space];
+        // Close enough to uri-host [ ":" port ], including IP literals, without being lax.
+        if agent.is_empty() ||
+                !agent.iter().all(|&c| is_tchar(c) || c == b':' || c == b'[' || c == b']') {
+            return None;
+        }
+        let (text, rest) = match quoted_string::split(&rest[space + 1..]) {
+            Some((text, rest)) => (text, rest),
+            None => return None,
+        };
+        let text = match quoted_string::unquote(text).map(String::from_utf8) {
+            Some(Ok(text)) => text,
+            _ => return None,
+        };
+        let date = if rest.is_empty() {
+            None
+        } else if rest.len() > 3 && rest[0] == b' ' && rest[1] == b'"' &&
+                rest[rest.len() - 1] == b'"' {
+            match parse_http_date(&rest[2..rest.len() - 1]) {
+                Some(date) => Some(date),
+                None => return None,
+            }
+        } else {
+            return None;
+        };
+        // Checked above, so this can’t fail.
+        let agent = unsafe { str::from_utf8_unchecked(agent) }.to_owned();
+        Some(Warning { code: code, agent: agent, text: text, date: date })
+    }
+}
+
+impl Header for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.code > 999 {
+            return Err(fmt::Error);
+        }
+        try!(write!(f, "{:03} {} ", self.code, self.agent));
+        match quoted_string::quote(self.text.as_bytes()) {
+            // quote only adds ASCII to what was already UTF-8, so this can’t fail.
+            Some(quoted) => try!(f.write_str(unsafe { str::from_utf8_unchecked(&quoted) })),
+            None => return Err(fmt::Error),
+        }
+        match self.date {
+            Some(date) => {
+                try!(f.write_str(" \""));
+                try!(HttpDate(date).fmt(f));
+                f.write_str("\"")
+            },
+            None => Ok(()),
+        }
+    }
+
+    fn is_list_combinable() -> bool {
+        true
+    }
+}
+
+define_list_header_marker! {
+    /// The `Warning` header.
+    pub WARNING: Warning = "warning"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use time::{self, Timespec};
+    use headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+    use headers::date_based::DATE;
+    use super::{Warning, WARNING};
+
+    fn eq<H: Header + ToHeader + Eq + fmt::Debug>(raw: &[u8], typed: H) {
+        assert_eq!(format!("{}", HeaderDisplayAdapter(&typed)).as_bytes(), raw);
+        assert_eq!(H::parse(raw), Some(typed));
+    }
+
+    fn bad<H: ToHeader + Eq + fmt::Debug>(raw: &[u8]) {
+        assert_eq!(H::parse(raw), None);
+    }
+
+    fn warning(code: u16, agent: &str, text: &str, date: Option<i64>) -> Warning {
+        Warning {
+            code: code,
+            agent: agent.to_owned(),
+            text: text.to_owned(),
+            date: date.map(|date| time::at_utc(Timespec::new(date, 0))),
+        }
+    }
+
+    #[test]
+    fn test_warning() {
+        eq(b"110 - \"Response is Stale\"", warning(110, "-", "Response is Stale", None));
+        eq(b"299 proxy.example:8080 \"say \\\"hi\\\"\" \"Sun, 06 Nov 1994 08:49:37 GMT\"",
+           warning(299, "proxy.example:8080", "say \"hi\"", Some(784111777)));
+        eq(b"112 fred \"\"", warning(112, "fred", "", None));
+        bad::<Warning>(b"");
+        bad::<Warning>(b"11 - \"x\"");
+        bad::<Warning>(b"1100 - \"x\"");
+        bad::<Warning>(b"110 -");
+        bad::<Warning>(b"110 - x");
+        bad::<Warning>(b"110  - \"x\"");
+        bad::<Warning>(b"110 - \"x\" \"yesterday\"");
+        bad::<Warning>(b"110 - \"x\" Sun, 06 Nov 1994 08:49:37 GMT");
+    }
+
+    #[test]
+    fn test_is_stale_against() {
+        let headers = Headers::parse_block(b"Date: Sun, 06 Nov 1994 08:49:37 GMT\r\n\
+            Warning: 110 a \"kept\" \"Sun, 06 Nov 1994 08:49:37 GMT\", 112 b \"undated\"\r\n\
+            Warning: 110 c \"discarded\" \"Sat, 05 Nov 1994 08:49:37 GMT\"\r\n").unwrap();
+        let date = headers.get(DATE).unwrap().0;
+        let warnings = headers.get(WARNING);
+        assert_eq!(warnings.len(), 3);
+        assert!(!warnings[0].is_stale_against(&date));
+        assert!(!warnings[1].is_stale_against(&date));
+        assert!(warnings[2].is_stale_against(&date));
+    }
+}
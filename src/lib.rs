@@ -35,6 +35,7 @@ extern crate mopa;
 extern crate lazy_static;
 
 extern crate tendril;
+extern crate time;
 extern crate smallvec;
 
 pub mod method;
@@ -0,0 +1,167 @@
+//! Round-trip tests for the header types: formatting a canonical value and parsing the result
+//! must produce the same value again.
+//!
+//! Where a header type legitimately doesn’t round-trip (e.g. because formatting normalises the
+//! value), the first round trip is allowed to change the value, but a second round trip must then
+//! be idempotent.
+//!
+//! A list header received as several field lines must likewise re-parse to the same list when the
+//! collection is written out, whether its lines are combined or not.
+
+#[macro_use] extern crate teepee;
+extern crate time;
+
+use std::fmt::Debug;
+
+use time::Timespec;
+
+use teepee::headers::{Header, ToHeader, HeaderDisplayAdapter, Headers};
+use teepee::headers::accept_encoding::{AcceptCoding, ACCEPT_ENCODING};
+use teepee::headers::cache_control::{CacheDirective, Age};
+use teepee::headers::content_type::MediaType;
+use teepee::headers::date_based::HttpDate;
+use teepee::headers::dnt::Dnt;
+use teepee::headers::early_data::EarlyData;
+use teepee::headers::expires::Expires;
+use teepee::headers::nel::Nel;
+use teepee::headers::origin::Origin;
+use teepee::headers::report_to::{ReportTo, EndpointGroup, Endpoint};
+use teepee::headers::save_data::SaveData;
+use teepee::headers::timing_allow_origin::TimingAllowOrigin;
+
+fn format<H: Header>(value: &H) -> Vec<u8> {
+    format!("{}", HeaderDisplayAdapter(value)).into_bytes()
+}
+
+/// Assert that formatting and then parsing `value` produces `value`.
+fn roundtrip<H: Header + ToHeader + PartialEq + Debug>(value: H) {
+    let raw = format(&value);
+    assert_eq!(H::parse(&raw), Some(value), "parsing {:?}", String::from_utf8_lossy(&raw));
+}
+
+/// Assert that formatting and parsing `raw` produces the same bytes again: the canonical form.
+fn canonical<H: Header + ToHeader + PartialEq + Debug>(raw: &[u8]) {
+    let value = H::parse(raw).expect("canonical example failed to parse");
+    assert_eq!(format(&value), raw);
+    roundtrip(value);
+}
+
+/// For values which don’t survive the first round trip: assert that the second is idempotent.
+fn idempotent<H: Header + ToHeader + PartialEq + Debug>(raw: &[u8]) {
+    let first = H::parse(raw).expect("example failed to parse");
+    let formatted = format(&first);
+    let second = H::parse(&formatted).expect("formatted example failed to parse");
+    assert_eq!(format(&second), formatted);
+}
+
+#[test]
+fn date_based() {
+    roundtrip(HttpDate(time::at_utc(Timespec::new(0, 0))));
+    roundtrip(HttpDate(time::at_utc(Timespec::new(784111777, 0))));
+    roundtrip(HttpDate(time::at_utc(Timespec::new(1542412800, 0))));
+    roundtrip(HttpDate(time::at_utc(Timespec::new(253402300799, 0))));
+    canonical::<HttpDate>(b"Sun, 06 Nov 1994 08:49:37 GMT");
+    // The obsolete formats are always written as IMF-fixdates.
+    idempotent::<HttpDate>(b"Sunday, 06-Nov-94 08:49:37 GMT");
+    idempotent::<HttpDate>(b"Sun Nov  6 08:49:37 1994");
+    roundtrip(Expires::At(time::at_utc(Timespec::new(784111777, 0))));
+    // Any invalid date is in the past, and the past is written as 0.
+    canonical::<Expires>(b"0");
+    idempotent::<Expires>(b"-1");
+}
+
+#[test]
+fn caching() {
+    roundtrip(CacheDirective::MaxAge(3600));
+    roundtrip(CacheDirective::NoCache(Some("set-cookie".to_owned())));
+    roundtrip(CacheDirective::Extension("foo".to_owned(), Some("a, b".to_owned())));
+    canonical::<CacheDirective>(b"private");
+    // Names are lowercased, and quotes are dropped where a token will do.
+    idempotent::<CacheDirective>(b"Max-Age=\"60\"");
+    idempotent::<CacheDirective>(b"Ext=\"x\"");
+    roundtrip(Age(0));
+    roundtrip(Age(2147483648));
+}
+
+#[test]
+fn media_types() {
+    roundtrip(MediaType::new("text", "html"));
+    roundtrip(MediaType::new("application", "vnd.api+json"));
+    canonical::<MediaType>(b"multipart/form-data; boundary=\"a b\"");
+    // Type and subtype are lowercased and parameters are respaced.
+    idempotent::<MediaType>(b"Text/HTML;charset=UTF-8");
+}
+
+#[test]
+fn origins() {
+    roundtrip(Origin::Null);
+    roundtrip(Origin::new("https", "example.com", None));
+    roundtrip(Origin::new("http", "[::1]", Some(8080)));
+    // Default ports are dropped.
+    idempotent::<Origin>(b"https://example.com:443");
+    roundtrip(TimingAllowOrigin::Any);
+    roundtrip(TimingAllowOrigin::Origins(vec![Origin::new("https", "a.example", None),
+                                              Origin::new("http", "b.example", Some(81))]));
+}
+
+#[test]
+fn simple_values() {
+    roundtrip(EarlyData);
+    roundtrip(Dnt::OptIn);
+    roundtrip(Dnt::OptOut);
+    roundtrip(SaveData::On);
+    roundtrip(SaveData::Off);
+    // Anything that isn’t `on` is off.
+    idempotent::<SaveData>(b"yes");
+    roundtrip(17389usize);
+}
+
+#[test]
+fn json_values() {
+    roundtrip(Nel::new("network-errors", 2592000));
+    roundtrip(Nel {
+        report_to: "caf\u{e9} \"errors\"".to_owned(),
+        max_age: 0,
+        include_subdomains: true,
+        success_fraction: 0.125,
+        failure_fraction: 0.0,
+    });
+    idempotent::<Nel>(b"{\"max_age\":1,\"report_to\":\"x\",\"unknown\":null}");
+    roundtrip(ReportTo {
+        groups: vec![EndpointGroup {
+            group: "default".into(),
+            max_age: 86400,
+            include_subdomains: false,
+            endpoints: vec![Endpoint::new("https://example.com/reports")],
+        }],
+    });
+}
+
+// A list header this crate doesn’t define, as a user of it might.
+define_list_header_marker!(X_CODINGS: AcceptCoding = "x-codings");
+
+#[test]
+fn list_folding() {
+    let raw = b"Accept-Encoding: gzip;q=0.8, br\r\nAccept-Encoding: identity;q=0\r\n\
+                X-Codings: gzip\r\nX-Codings: br;q=0.5, *;q=0\r\n";
+    let headers = Headers::parse_block(raw).unwrap();
+    // Written before the lists are parsed, the lines are kept as they came.
+    let mut separate = vec![];
+    headers.write_to(&mut separate).unwrap();
+    let accept_encoding = headers.get(ACCEPT_ENCODING).into_owned();
+    let x_codings = headers.get(X_CODINGS).into_owned();
+    assert_eq!(accept_encoding.len(), 3);
+    assert_eq!(x_codings.len(), 3);
+
+    // Once they have been, their markers say that they may be combined, and they are.
+    let mut combined = vec![];
+    headers.write_to(&mut combined).unwrap();
+    assert_eq!(combined, &b"accept-encoding: gzip;q=0.8, br, identity;q=0\r\n\
+                            x-codings: gzip, br;q=0.5, *;q=0\r\n"[..]);
+
+    for out in &[separate, combined] {
+        let reparsed = Headers::parse_block(out).unwrap();
+        assert_eq!(reparsed.get(ACCEPT_ENCODING).into_owned(), accept_encoding);
+        assert_eq!(reparsed.get(X_CODINGS).into_owned(), x_codings);
+    }
+}